@@ -0,0 +1,13 @@
+CREATE TABLE sso_key_webauthn (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "updated_at" TIMESTAMPTZ NOT NULL,
+    "key_id"     UUID        NOT NULL,
+    "credential" JSONB       NOT NULL,
+    "counter"    BIGINT      NOT NULL,
+    "aaguid"     UUID        NOT NULL,
+    PRIMARY KEY ("key_id"),
+    CONSTRAINT fk_sso_key_webauthn_key
+        FOREIGN KEY ("key_id")
+        REFERENCES sso_key("id")
+        ON DELETE CASCADE
+);
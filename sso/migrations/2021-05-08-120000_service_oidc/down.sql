@@ -0,0 +1,2 @@
+ALTER TABLE sso_service
+    DROP COLUMN "oidc_redirect_uris";
@@ -0,0 +1,2 @@
+ALTER TABLE sso_service
+    ADD COLUMN "oidc_redirect_uris" VARCHAR[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,4 @@
+ALTER TABLE sso_service
+    DROP COLUMN "jwt_algorithm";
+
+DROP TABLE sso_jwt_key;
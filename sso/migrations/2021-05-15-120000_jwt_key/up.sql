@@ -0,0 +1,13 @@
+CREATE TABLE sso_jwt_key (
+    "created_at"  TIMESTAMPTZ NOT NULL,
+    "id"          UUID        NOT NULL,
+    "algorithm"   VARCHAR     NOT NULL,
+    "private_key" VARCHAR     NOT NULL,
+    "public_key"  VARCHAR     NOT NULL,
+    "expires_at"  TIMESTAMPTZ,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX idx_sso_jwt_key_algorithm ON sso_jwt_key("algorithm", "created_at");
+
+ALTER TABLE sso_service
+    ADD COLUMN "jwt_algorithm" VARCHAR NOT NULL DEFAULT 'HS256';
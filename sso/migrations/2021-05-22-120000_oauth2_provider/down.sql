@@ -0,0 +1,4 @@
+ALTER TABLE sso_service
+    DROP COLUMN "provider_oauth2_url";
+
+DROP TABLE sso_oauth2_provider;
@@ -0,0 +1,16 @@
+CREATE TABLE sso_oauth2_provider (
+    "created_at"    TIMESTAMPTZ NOT NULL,
+    "updated_at"    TIMESTAMPTZ NOT NULL,
+    "id"            UUID        NOT NULL,
+    "is_enabled"    BOOLEAN     NOT NULL,
+    "name"          VARCHAR     NOT NULL,
+    "discovery_url" VARCHAR     NOT NULL,
+    "client_id"     VARCHAR     NOT NULL,
+    "client_secret" VARCHAR     NOT NULL,
+    "scopes"        VARCHAR[]   NOT NULL,
+    "email_claim"   VARCHAR     NOT NULL,
+    PRIMARY KEY ("id")
+);
+
+ALTER TABLE sso_service
+    ADD COLUMN "provider_oauth2_url" VARCHAR;
@@ -0,0 +1,4 @@
+ALTER TABLE sso_service
+    DROP COLUMN "provider_saml_url",
+    DROP COLUMN "saml_idp_metadata",
+    DROP COLUMN "saml_email_attribute";
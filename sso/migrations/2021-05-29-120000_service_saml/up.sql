@@ -0,0 +1,4 @@
+ALTER TABLE sso_service
+    ADD COLUMN "provider_saml_url" VARCHAR,
+    ADD COLUMN "saml_idp_metadata" VARCHAR,
+    ADD COLUMN "saml_email_attribute" VARCHAR;
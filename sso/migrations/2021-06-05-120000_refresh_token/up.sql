@@ -0,0 +1,20 @@
+CREATE TABLE sso_refresh_token (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "key"        VARCHAR     NOT NULL,
+    "family_id"  UUID        NOT NULL,
+    "ttl"        TIMESTAMPTZ NOT NULL,
+    "used_at"    TIMESTAMPTZ,
+    "is_revoked" BOOLEAN     NOT NULL,
+    "service_id" UUID        NOT NULL,
+    "user_id"    UUID        NOT NULL,
+    PRIMARY KEY ("key"),
+    CONSTRAINT fk_sso_refresh_token_service
+        FOREIGN KEY ("service_id")
+        REFERENCES sso_service("id")
+        ON DELETE CASCADE,
+    CONSTRAINT fk_sso_refresh_token_user
+        FOREIGN KEY ("user_id")
+        REFERENCES sso_user("id")
+        ON DELETE CASCADE
+);
+CREATE INDEX idx_sso_refresh_token_family_id ON sso_refresh_token("family_id");
@@ -0,0 +1 @@
+DROP TABLE sso_jwt_denylist;
@@ -0,0 +1,16 @@
+CREATE TABLE sso_jwt_denylist (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "jti"        VARCHAR     NOT NULL,
+    "exp"        TIMESTAMPTZ NOT NULL,
+    "service_id" UUID        NOT NULL,
+    "user_id"    UUID        NOT NULL,
+    PRIMARY KEY ("jti"),
+    CONSTRAINT fk_sso_jwt_denylist_service
+        FOREIGN KEY ("service_id")
+        REFERENCES sso_service("id")
+        ON DELETE CASCADE,
+    CONSTRAINT fk_sso_jwt_denylist_user
+        FOREIGN KEY ("user_id")
+        REFERENCES sso_user("id")
+        ON DELETE CASCADE
+);
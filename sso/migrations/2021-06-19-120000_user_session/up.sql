@@ -0,0 +1,22 @@
+CREATE TABLE sso_user_session (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "updated_at" TIMESTAMPTZ NOT NULL,
+    "id"         UUID        NOT NULL,
+    "user_agent" VARCHAR     NOT NULL,
+    "remote"     VARCHAR     NOT NULL,
+    "forwarded"  VARCHAR,
+    "expires_at" TIMESTAMPTZ NOT NULL,
+    "is_revoked" BOOLEAN     NOT NULL,
+    "service_id" UUID        NOT NULL,
+    "user_id"    UUID        NOT NULL,
+    PRIMARY KEY ("id"),
+    CONSTRAINT fk_sso_user_session_service
+        FOREIGN KEY ("service_id")
+        REFERENCES sso_service("id")
+        ON DELETE CASCADE,
+    CONSTRAINT fk_sso_user_session_user
+        FOREIGN KEY ("user_id")
+        REFERENCES sso_user("id")
+        ON DELETE CASCADE
+);
+CREATE INDEX idx_sso_user_session_user_id ON sso_user_session("user_id", "updated_at");
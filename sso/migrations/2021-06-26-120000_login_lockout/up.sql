@@ -0,0 +1,8 @@
+CREATE TABLE sso_login_lockout (
+    "created_at"    TIMESTAMPTZ NOT NULL,
+    "updated_at"    TIMESTAMPTZ NOT NULL,
+    "key"           VARCHAR     NOT NULL,
+    "failure_count" INTEGER     NOT NULL,
+    "locked_until"  TIMESTAMPTZ,
+    PRIMARY KEY ("key")
+);
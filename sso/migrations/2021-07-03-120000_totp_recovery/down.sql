@@ -0,0 +1 @@
+DROP TABLE sso_totp_recovery;
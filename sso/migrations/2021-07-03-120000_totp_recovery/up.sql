@@ -0,0 +1,12 @@
+CREATE TABLE sso_totp_recovery (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "hash"       VARCHAR     NOT NULL,
+    "used_at"    TIMESTAMPTZ,
+    "key_id"     UUID        NOT NULL,
+    PRIMARY KEY ("hash"),
+    CONSTRAINT fk_sso_totp_recovery_key
+        FOREIGN KEY ("key_id")
+        REFERENCES sso_key("id")
+        ON DELETE CASCADE
+);
+CREATE INDEX idx_sso_totp_recovery_key ON sso_totp_recovery ("key_id");
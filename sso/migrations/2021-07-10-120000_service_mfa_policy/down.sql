@@ -0,0 +1,2 @@
+ALTER TABLE sso_service
+    DROP COLUMN "mfa_policy";
@@ -0,0 +1,2 @@
+ALTER TABLE sso_service
+    ADD COLUMN "mfa_policy" VARCHAR NOT NULL DEFAULT 'Off';
@@ -0,0 +1,15 @@
+CREATE TABLE sso_key_totp (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "updated_at" TIMESTAMPTZ NOT NULL,
+    "key_id"     UUID        NOT NULL,
+    "period"     INTEGER     NOT NULL,
+    "digits"     INTEGER     NOT NULL,
+    "algorithm"  VARCHAR     NOT NULL,
+    "skew"       INTEGER     NOT NULL,
+    "last_step"  BIGINT,
+    PRIMARY KEY ("key_id"),
+    CONSTRAINT fk_sso_key_totp_key
+        FOREIGN KEY ("key_id")
+        REFERENCES sso_key("id")
+        ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+ALTER TABLE sso_user
+    DROP COLUMN "phone";
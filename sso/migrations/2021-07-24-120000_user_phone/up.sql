@@ -0,0 +1,2 @@
+ALTER TABLE sso_user
+    ADD COLUMN "phone" VARCHAR NULL;
@@ -0,0 +1,8 @@
+ALTER TABLE sso_user
+    DROP COLUMN "password_updated_at";
+
+ALTER TABLE sso_service
+    DROP COLUMN "password_min_strength",
+    DROP COLUMN "password_reject_pwned",
+    DROP COLUMN "password_history",
+    DROP COLUMN "password_max_age_days";
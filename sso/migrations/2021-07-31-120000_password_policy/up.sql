@@ -0,0 +1,10 @@
+ALTER TABLE sso_service
+    ADD COLUMN "password_min_strength" INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN "password_reject_pwned" BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN "password_history" INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN "password_max_age_days" INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE sso_user
+    ADD COLUMN "password_updated_at" TIMESTAMPTZ NULL;
+
+UPDATE sso_user SET "password_updated_at" = "updated_at" WHERE "password_hash" IS NOT NULL;
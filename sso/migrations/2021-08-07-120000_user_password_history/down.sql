@@ -0,0 +1 @@
+DROP TABLE sso_user_password_history;
@@ -0,0 +1,12 @@
+CREATE TABLE sso_user_password_history (
+    "created_at"    TIMESTAMPTZ NOT NULL,
+    "id"            UUID        NOT NULL,
+    "user_id"       UUID        NOT NULL,
+    "password_hash" VARCHAR     NOT NULL,
+    PRIMARY KEY ("id"),
+    CONSTRAINT fk_sso_user_password_history_user
+        FOREIGN KEY ("user_id")
+        REFERENCES sso_user("id")
+        ON DELETE CASCADE
+);
+CREATE INDEX idx_sso_user_password_history_user ON sso_user_password_history ("user_id", "created_at");
@@ -0,0 +1,3 @@
+DROP INDEX idx_sso_key_value_prefix;
+ALTER TABLE sso_key
+    DROP COLUMN "value_prefix";
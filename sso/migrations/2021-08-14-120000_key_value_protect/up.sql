@@ -0,0 +1,3 @@
+ALTER TABLE sso_key
+    ADD COLUMN "value_prefix" VARCHAR NULL;
+CREATE INDEX idx_sso_key_value_prefix ON sso_key ("value_prefix");
@@ -0,0 +1,8 @@
+CREATE TABLE sso_key_data_key (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "updated_at" TIMESTAMPTZ NOT NULL,
+    "id"         UUID        NOT NULL,
+    "wrapped"    VARCHAR     NOT NULL,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX idx_sso_key_data_key_created_at ON sso_key_data_key ("created_at");
@@ -0,0 +1,7 @@
+DROP INDEX idx_sso_key_last_used_at;
+DROP INDEX idx_sso_key_expires_at;
+ALTER TABLE sso_key
+    DROP COLUMN "last_used_remote",
+    DROP COLUMN "last_used_at",
+    DROP COLUMN "scopes",
+    DROP COLUMN "expires_at";
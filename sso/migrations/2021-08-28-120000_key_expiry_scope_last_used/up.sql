@@ -0,0 +1,7 @@
+ALTER TABLE sso_key
+    ADD COLUMN "expires_at" TIMESTAMPTZ NULL,
+    ADD COLUMN "scopes" VARCHAR[] NOT NULL DEFAULT '{}',
+    ADD COLUMN "last_used_at" TIMESTAMPTZ NULL,
+    ADD COLUMN "last_used_remote" VARCHAR NULL;
+CREATE INDEX idx_sso_key_expires_at ON sso_key ("expires_at");
+CREATE INDEX idx_sso_key_last_used_at ON sso_key ("last_used_at");
@@ -0,0 +1,4 @@
+ALTER TABLE sso_key
+    DROP CONSTRAINT fk_sso_key_role,
+    DROP COLUMN "role";
+DROP TABLE sso_role;
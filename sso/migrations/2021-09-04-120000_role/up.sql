@@ -0,0 +1,25 @@
+CREATE TABLE sso_role (
+    "created_at" TIMESTAMPTZ NOT NULL,
+    "updated_at" TIMESTAMPTZ NOT NULL,
+    "name" VARCHAR NOT NULL,
+    "permissions" VARCHAR[] NOT NULL,
+    PRIMARY KEY ("name")
+);
+INSERT INTO sso_role ("created_at", "updated_at", "name", "permissions") VALUES
+    (now(), now(), 'admin', ARRAY[
+        'audit:read', 'audit:write', 'key:read', 'key:create', 'key:write',
+        'service:read', 'service:write', 'oauth2_provider:read', 'oauth2_provider:write',
+        'user:read', 'user:write', 'user:auth'
+    ]),
+    (now(), now(), 'read_only', ARRAY[
+        'audit:read', 'key:read', 'service:read', 'oauth2_provider:read', 'user:read'
+    ]),
+    (now(), now(), 'key_manager', ARRAY[
+        'key:read', 'key:create', 'key:write'
+    ]);
+ALTER TABLE sso_key
+    ADD COLUMN "role" VARCHAR NULL,
+    ADD CONSTRAINT fk_sso_key_role
+        FOREIGN KEY ("role")
+        REFERENCES sso_role("name")
+        ON DELETE RESTRICT ON UPDATE CASCADE;
@@ -0,0 +1,2 @@
+ALTER TABLE sso_oauth2_provider
+    DROP COLUMN "trust_email";
@@ -0,0 +1,2 @@
+ALTER TABLE sso_oauth2_provider
+    ADD COLUMN "trust_email" BOOLEAN NOT NULL DEFAULT FALSE;
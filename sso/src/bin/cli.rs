@@ -18,13 +18,30 @@
 //!
 //! Postgres connections, optional.
 //!
+//! ### SSO_KEY_SECRET
+//!
+//! Base64 encoded 32 byte master key which wraps the data keys used to hash and
+//! encrypt key values and signing keys stored in the database, required unless
+//! `SSO_KEY_SECRET_FILE` is defined. Generate with `openssl rand -base64 32`,
+//! keys are unusable if this is lost. Use `sso-cli rotate-key-secret` to change it.
+//!
+//! ### SSO_KEY_SECRET_FILE
+//!
+//! Path to file containing base64 encoded master key, alternative to `SSO_KEY_SECRET`.
+//!
 #[macro_use]
 extern crate clap;
 #[macro_use]
 extern crate log;
 
 use clap::{App, Arg, SubCommand};
-use sso::{log_init, KeyCreate, Postgres, ServiceCreate};
+use sso::{
+    log_init, AuditBuilder, AuditMeta, AuditSubject, AuditType, DriverError, JwtAlgorithm,
+    JwtKeyCreate, KeyCreate, KeyRotate, KeyRotateLineage, KeySecret, Postgres, PwnedPasswordsBloom,
+    RoleCreate, RolePermission, SamlIdpMetadata, ServiceCreate, ServiceMfaPolicy, UserPasswordHash,
+    DEFAULT_KEY_ROTATE_GRACE_PERIOD_HOURS,
+};
+use std::{fs, str::FromStr, time::Duration};
 
 const CRATE_NAME: &str = crate_name!();
 const CRATE_VERSION: &str = crate_version!();
@@ -33,7 +50,14 @@ const CRATE_AUTHORS: &str = "Sam Ward <git@mojzu.net>";
 
 const CMD_CREATE_ROOT_KEY: &str = "create-root-key";
 const CMD_CREATE_SERVICE_WITH_KEY: &str = "create-service-with-key";
+const CMD_CREATE_JWT_KEY: &str = "create-jwt-key";
 const CMD_TASK_RETENTION: &str = "task-retention";
+const CMD_CREATE_PWNED_PASSWORDS_BLOOM: &str = "create-pwned-passwords-bloom";
+const CMD_BENCHMARK_PASSWORD_HASH: &str = "benchmark-password-hash";
+const CMD_ROTATE_KEY_SECRET: &str = "rotate-key-secret";
+const CMD_ROTATE_KEY: &str = "rotate-key";
+const CMD_CREATE_ROLE: &str = "create-role";
+const CMD_REVOKE_EXPIRED_KEYS: &str = "revoke-expired-keys";
 
 const ARG_NAME: &str = "NAME";
 const ARG_URL: &str = "URL";
@@ -42,7 +66,25 @@ const ARG_EMAIL_TEXT: &str = "EMAIL_TEXT";
 const ARG_LOCAL_URL: &str = "LOCAL_URL";
 const ARG_GITHUB_OAUTH2_URL: &str = "GITHUB_OAUTH2_URL";
 const ARG_MICROSOFT_OAUTH2_URL: &str = "MICROSOFT_OAUTH2_URL";
+const ARG_OAUTH2_URL: &str = "OAUTH2_URL";
+const ARG_SAML_URL: &str = "SAML_URL";
+const ARG_SAML_METADATA: &str = "SAML_METADATA";
+const ARG_SAML_EMAIL_ATTRIBUTE: &str = "SAML_EMAIL_ATTRIBUTE";
+const ARG_OIDC_REDIRECT_URI: &str = "OIDC_REDIRECT_URI";
+const ARG_JWT_ALGORITHM: &str = "JWT_ALGORITHM";
+const ARG_MFA_POLICY: &str = "MFA_POLICY";
 const ARG_WEEKS: &str = "WEEKS";
+const ARG_INPUT: &str = "INPUT";
+const ARG_OUTPUT: &str = "OUTPUT";
+const ARG_FALSE_POSITIVE_RATE: &str = "FALSE_POSITIVE_RATE";
+const ARG_TARGET_MS: &str = "TARGET_MS";
+const ARG_PARALLELISM: &str = "PARALLELISM";
+const ARG_MAX_MEMORY: &str = "MAX_MEMORY";
+const ARG_SECRET_FILE: &str = "SECRET_FILE";
+const ARG_KEY_ID: &str = "KEY_ID";
+const ARG_GRACE_PERIOD_HOURS: &str = "GRACE_PERIOD_HOURS";
+const ARG_ROLE: &str = "ROLE";
+const ARG_PERMISSION: &str = "PERMISSION";
 
 fn main() {
     // Logging, error handling.
@@ -58,12 +100,17 @@ fn main() {
                 .version(CRATE_VERSION)
                 .about("Create a root key")
                 .author(CRATE_AUTHORS)
-                .arg(
+                .args(&[
                     Arg::with_name(ARG_NAME)
                         .help("Key name")
                         .required(true)
                         .index(1),
-                ),
+                    Arg::with_name(ARG_ROLE)
+                        .long("role")
+                        .help("Key role name, keys without a role have all permissions")
+                        .takes_value(true)
+                        .required(false),
+                ]),
             SubCommand::with_name(CMD_CREATE_SERVICE_WITH_KEY)
                 .version(CRATE_VERSION)
                 .about("Create service with service key")
@@ -102,7 +149,56 @@ fn main() {
                         .help("Microsoft OAuth2 provider callback URL")
                         .takes_value(true)
                         .required(false),
+                    Arg::with_name(ARG_OAUTH2_URL)
+                        .long("oauth2-url")
+                        .help("OAuth2 provider registry callback URL")
+                        .takes_value(true)
+                        .required(false),
+                    Arg::with_name(ARG_SAML_URL)
+                        .long("saml-url")
+                        .help("SAML provider assertion consumer service URL")
+                        .takes_value(true)
+                        .requires(ARG_SAML_METADATA)
+                        .required(false),
+                    Arg::with_name(ARG_SAML_METADATA)
+                        .long("saml-metadata")
+                        .help("SAML identity provider metadata XML file")
+                        .takes_value(true)
+                        .requires(ARG_SAML_URL)
+                        .required(false),
+                    Arg::with_name(ARG_SAML_EMAIL_ATTRIBUTE)
+                        .long("saml-email-attribute")
+                        .help("SAML assertion attribute containing user email")
+                        .takes_value(true)
+                        .required(false),
+                    Arg::with_name(ARG_OIDC_REDIRECT_URI)
+                        .long("oidc-redirect-uri")
+                        .help("OpenID Connect client redirect URI")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required(false),
+                    Arg::with_name(ARG_JWT_ALGORITHM)
+                        .long("jwt-algorithm")
+                        .help("JWT signing algorithm (HS256, RS256, ES256, EdDSA)")
+                        .takes_value(true)
+                        .required(false),
+                    Arg::with_name(ARG_MFA_POLICY)
+                        .long("mfa-policy")
+                        .help("Local login MFA policy (Off, Optional, Required)")
+                        .takes_value(true)
+                        .required(false),
                 ]),
+            SubCommand::with_name(CMD_CREATE_JWT_KEY)
+                .version(CRATE_VERSION)
+                .about("Create a JWT signing key, expires existing keys for algorithm")
+                .author(CRATE_AUTHORS)
+                .arg(
+                    Arg::with_name(ARG_JWT_ALGORITHM)
+                        .help("JWT signing algorithm (RS256, ES256, EdDSA)")
+                        .required(true)
+                        .index(1),
+                ),
             SubCommand::with_name(CMD_TASK_RETENTION)
                 .version(CRATE_VERSION)
                 .about("Run retention task")
@@ -114,17 +210,111 @@ fn main() {
                         .takes_value(true)
                         .required(false),
                 ),
+            SubCommand::with_name(CMD_CREATE_PWNED_PASSWORDS_BLOOM)
+                .version(CRATE_VERSION)
+                .about("Create Pwned Passwords bloom filter from sorted SHA-1 hash file")
+                .author(CRATE_AUTHORS)
+                .args(&[
+                    Arg::with_name(ARG_INPUT)
+                        .help("Sorted SHA-1 hash file")
+                        .required(true)
+                        .index(1),
+                    Arg::with_name(ARG_OUTPUT)
+                        .help("Bloom filter file")
+                        .required(true)
+                        .index(2),
+                    Arg::with_name(ARG_FALSE_POSITIVE_RATE)
+                        .long("false-positive-rate")
+                        .help("False positive rate")
+                        .takes_value(true)
+                        .required(false),
+                ]),
+            SubCommand::with_name(CMD_BENCHMARK_PASSWORD_HASH)
+                .version(CRATE_VERSION)
+                .about("Find Argon2id password hash parameters for target latency on host")
+                .author(CRATE_AUTHORS)
+                .args(&[
+                    Arg::with_name(ARG_TARGET_MS)
+                        .long("target-ms")
+                        .help("Target hash latency in milliseconds")
+                        .takes_value(true)
+                        .required(false)
+                        .validator(positive_u64_validator),
+                    Arg::with_name(ARG_PARALLELISM)
+                        .long("parallelism")
+                        .help("Parallelism")
+                        .takes_value(true)
+                        .required(false)
+                        .validator(positive_u32_validator),
+                    Arg::with_name(ARG_MAX_MEMORY)
+                        .long("max-memory")
+                        .help("Maximum memory cost in KiB")
+                        .takes_value(true)
+                        .required(false)
+                        .validator(positive_u32_validator),
+                ]),
+            SubCommand::with_name(CMD_ROTATE_KEY_SECRET)
+                .version(CRATE_VERSION)
+                .about("Wrap key data keys with new master key")
+                .author(CRATE_AUTHORS)
+                .arg(
+                    Arg::with_name(ARG_SECRET_FILE)
+                        .help("File containing base64 encoded new master key")
+                        .required(true)
+                        .index(1),
+                ),
+            SubCommand::with_name(CMD_ROTATE_KEY)
+                .version(CRATE_VERSION)
+                .about("Create successor key, rotated key expires after grace period")
+                .author(CRATE_AUTHORS)
+                .args(&[
+                    Arg::with_name(ARG_KEY_ID)
+                        .help("Key UUID")
+                        .required(true)
+                        .index(1),
+                    Arg::with_name(ARG_GRACE_PERIOD_HOURS)
+                        .long("grace-period-hours")
+                        .help("Rotated key grace period in hours")
+                        .takes_value(true)
+                        .required(false),
+                ]),
+            SubCommand::with_name(CMD_CREATE_ROLE)
+                .version(CRATE_VERSION)
+                .about("Create a role, permissions of existing role are replaced")
+                .author(CRATE_AUTHORS)
+                .args(&[
+                    Arg::with_name(ARG_NAME)
+                        .help("Role name")
+                        .required(true)
+                        .index(1),
+                    Arg::with_name(ARG_PERMISSION)
+                        .help("Role permissions (for example user:read, audit:read)")
+                        .required(false)
+                        .multiple(true)
+                        .index(2)
+                        .validator(permission_validator),
+                ]),
+            SubCommand::with_name(CMD_REVOKE_EXPIRED_KEYS)
+                .version(CRATE_VERSION)
+                .about("Disable and revoke expired keys, expired keys already fail authentication")
+                .author(CRATE_AUTHORS),
         ])
         .get_matches();
 
     // Build driver from environment variables.
-    let driver = Postgres::from_env("SSO_POSTGRES_URL", "SSO_POSTGRES_CONNECTIONS");
+    let driver = Postgres::from_env(
+        "SSO_POSTGRES_URL",
+        "SSO_POSTGRES_CONNECTIONS",
+        "SSO_KEY_SECRET",
+        "SSO_KEY_SECRET_FILE",
+    );
     let result = Ok(driver).and_then(|driver| {
         // Call library functions with command line arguments.
         match matches.subcommand() {
             (CMD_CREATE_ROOT_KEY, Some(submatches)) => {
                 let name = submatches.value_of(ARG_NAME).unwrap();
-                let create = KeyCreate::root(true, name);
+                let role = submatches.value_of(ARG_ROLE);
+                let create = KeyCreate::root(true, name).role(role.map(|x| x.to_owned()));
                 driver.key_create(&create).map(|key| {
                     println!("{}", key);
                     0
@@ -138,11 +328,29 @@ fn main() {
                 let provider_local_url = submatches.value_of(ARG_LOCAL_URL);
                 let provider_github_oauth2_url = submatches.value_of(ARG_GITHUB_OAUTH2_URL);
                 let provider_microsoft_oauth2_url = submatches.value_of(ARG_MICROSOFT_OAUTH2_URL);
+                let provider_oauth2_url = submatches.value_of(ARG_OAUTH2_URL);
+                let provider_saml_url = submatches.value_of(ARG_SAML_URL);
+                let saml_idp_metadata = submatches.value_of(ARG_SAML_METADATA);
+                let saml_email_attribute = submatches.value_of(ARG_SAML_EMAIL_ATTRIBUTE);
+                let oidc_redirect_uris = submatches.values_of(ARG_OIDC_REDIRECT_URI);
+                let jwt_algorithm = submatches.value_of(ARG_JWT_ALGORITHM);
+                let mfa_policy = submatches.value_of(ARG_MFA_POLICY);
 
                 let user_allow_register = user_allow_register
                     .unwrap_or("false")
                     .parse::<bool>()
                     .unwrap();
+                let jwt_algorithm =
+                    JwtAlgorithm::from_str(jwt_algorithm.unwrap_or("HS256")).unwrap();
+                let mfa_policy = ServiceMfaPolicy::from_str(mfa_policy.unwrap_or("Off")).unwrap();
+                let saml_idp_metadata = match saml_idp_metadata {
+                    Some(path) => {
+                        let metadata = fs::read_to_string(path).map_err(DriverError::StdIo)?;
+                        SamlIdpMetadata::parse(&metadata)?;
+                        Some(metadata)
+                    }
+                    None => None,
+                };
                 let service_create = ServiceCreate {
                     is_enabled: true,
                     name: name.to_owned(),
@@ -153,6 +361,19 @@ fn main() {
                     provider_github_oauth2_url: provider_github_oauth2_url.map(|x| x.to_owned()),
                     provider_microsoft_oauth2_url: provider_microsoft_oauth2_url
                         .map(|x| x.to_owned()),
+                    oidc_redirect_uris: oidc_redirect_uris
+                        .map(|x| x.map(|x| x.to_owned()).collect())
+                        .unwrap_or_default(),
+                    jwt_algorithm,
+                    provider_oauth2_url: provider_oauth2_url.map(|x| x.to_owned()),
+                    provider_saml_url: provider_saml_url.map(|x| x.to_owned()),
+                    saml_idp_metadata,
+                    saml_email_attribute: saml_email_attribute.map(|x| x.to_owned()),
+                    mfa_policy,
+                    password_min_strength: 0,
+                    password_reject_pwned: false,
+                    password_history: 0,
+                    password_max_age_days: 0,
                 };
                 let service = driver.service_create(&service_create)?;
                 let key_create = KeyCreate::service(true, name, service.id);
@@ -163,6 +384,15 @@ fn main() {
                     0
                 })
             }
+            (CMD_CREATE_JWT_KEY, Some(submatches)) => {
+                let jwt_algorithm = submatches.value_of(ARG_JWT_ALGORITHM).unwrap();
+                let jwt_algorithm = JwtAlgorithm::from_str(jwt_algorithm).unwrap();
+                let conn = driver.conn()?;
+                JwtKeyCreate::generate(&conn, driver.data_keys(), jwt_algorithm).map(|key| {
+                    println!("{}", key);
+                    0
+                })
+            }
             (CMD_TASK_RETENTION, Some(submatches)) => {
                 let weeks = submatches.value_of(ARG_WEEKS).unwrap_or("12");
                 let weeks: i64 = weeks.parse().unwrap();
@@ -173,6 +403,93 @@ fn main() {
                     0
                 })
             }
+            (CMD_CREATE_PWNED_PASSWORDS_BLOOM, Some(submatches)) => {
+                let input = submatches.value_of(ARG_INPUT).unwrap();
+                let output = submatches.value_of(ARG_OUTPUT).unwrap();
+                let false_positive_rate = submatches
+                    .value_of(ARG_FALSE_POSITIVE_RATE)
+                    .unwrap_or("0.001");
+                let false_positive_rate: f64 = false_positive_rate.parse().unwrap();
+                let bloom = PwnedPasswordsBloom::from_file(input, false_positive_rate)?;
+                bloom.write(output).map(|_| 0)
+            }
+            (CMD_BENCHMARK_PASSWORD_HASH, Some(submatches)) => {
+                let target_ms = submatches.value_of(ARG_TARGET_MS).unwrap_or("500");
+                let target_ms: u64 = target_ms.parse().unwrap();
+                let parallelism = submatches.value_of(ARG_PARALLELISM).unwrap_or("1");
+                let parallelism: u32 = parallelism.parse().unwrap();
+                let max_memory = submatches.value_of(ARG_MAX_MEMORY).unwrap_or("262144");
+                let max_memory: u32 = max_memory.parse().unwrap();
+                let target = Duration::from_millis(target_ms);
+                UserPasswordHash::argon2id_benchmark(target, parallelism, max_memory).map(
+                    |(hash, elapsed)| {
+                        if let UserPasswordHash::Argon2id {
+                            memory,
+                            iterations,
+                            parallelism,
+                        } = hash
+                        {
+                            println!("SSO_PASSWORD_ARGON2ID=true");
+                            println!("SSO_PASSWORD_ARGON2ID_MEMORY={}", memory);
+                            println!("SSO_PASSWORD_ARGON2ID_ITERATIONS={}", iterations);
+                            println!("SSO_PASSWORD_ARGON2ID_PARALLELISM={}", parallelism);
+                        }
+                        println!("# Password hash latency {}ms", elapsed.as_millis());
+                        0
+                    },
+                )
+            }
+            (CMD_ROTATE_KEY_SECRET, Some(submatches)) => {
+                let secret_file = submatches.value_of(ARG_SECRET_FILE).unwrap();
+                let secret = KeySecret::from_file(secret_file)?;
+                driver.key_secret_rotate(&secret).map(|count| {
+                    println!("{}", count);
+                    0
+                })
+            }
+            (CMD_ROTATE_KEY, Some(submatches)) => {
+                let id = submatches.value_of(ARG_KEY_ID).unwrap();
+                let grace_period_hours = submatches
+                    .value_of(ARG_GRACE_PERIOD_HOURS)
+                    .map(|x| x.parse().unwrap())
+                    .unwrap_or(DEFAULT_KEY_ROTATE_GRACE_PERIOD_HOURS);
+                let rotate = KeyRotate {
+                    id: id.parse().unwrap(),
+                    grace_period_hours,
+                };
+                let (previous, key) = driver.key_rotate(&rotate, None)?;
+
+                let audit = AuditBuilder::new(
+                    AuditMeta::new(CRATE_NAME, "", None, None),
+                    AuditType::KeyRotate,
+                );
+                let lineage = KeyRotateLineage::new(&previous, &key);
+                audit.create_data(&driver, 0, Some(key.subject()), Some(lineage))?;
+                println!("{}", previous);
+                println!("{}", key);
+                Ok(0)
+            }
+            (CMD_CREATE_ROLE, Some(submatches)) => {
+                let name = submatches.value_of(ARG_NAME).unwrap();
+                let permissions = submatches
+                    .values_of(ARG_PERMISSION)
+                    .map(|x| x.filter_map(|x| RolePermission::from_str(x).ok()).collect())
+                    .unwrap_or_default();
+                let create = RoleCreate {
+                    name: name.to_owned(),
+                    permissions,
+                };
+                driver.role_create(&create).map(|role| {
+                    println!("{}", role);
+                    0
+                })
+            }
+            (CMD_REVOKE_EXPIRED_KEYS, Some(_submatches)) => {
+                driver.key_revoke_expired().map(|revoked| {
+                    println!("{}", revoked);
+                    0
+                })
+            }
             _ => {
                 println!("{}", matches.usage());
                 Ok(1)
@@ -189,3 +506,24 @@ fn main() {
         }
     }
 }
+
+fn positive_u32_validator(value: String) -> Result<(), String> {
+    match value.parse::<u32>() {
+        Ok(x) if x > 0 => Ok(()),
+        _ => Err(format!("must be an integer from 1 to {}", u32::MAX)),
+    }
+}
+
+fn positive_u64_validator(value: String) -> Result<(), String> {
+    match value.parse::<u64>() {
+        Ok(x) if x > 0 => Ok(()),
+        _ => Err(format!("must be an integer from 1 to {}", u64::MAX)),
+    }
+}
+
+fn permission_validator(value: String) -> Result<(), String> {
+    RolePermission::from_str(&value).map(|_| ()).map_err(|_| {
+        let permissions: Vec<String> = RolePermission::ALL.iter().map(|x| x.to_string()).collect();
+        format!("valid permissions are {}", permissions.join(", "))
+    })
+}
@@ -18,6 +18,17 @@
 //!
 //! Postgres connections, optional.
 //!
+//! ### SSO_KEY_SECRET
+//!
+//! Base64 encoded 32 byte master key which wraps the data keys used to hash and
+//! encrypt key values stored in the database, required unless
+//! `SSO_KEY_SECRET_FILE` is defined. Generate with `openssl rand -base64 32`,
+//! keys are unusable if this is lost. Use `sso-cli rotate-key-secret` to change it.
+//!
+//! ### SSO_KEY_SECRET_FILE
+//!
+//! Path to file containing base64 encoded master key, alternative to `SSO_KEY_SECRET`.
+//!
 //! ### SSO_USER_AGENT
 //!
 //! HTTP client user agent header, optional, defaults to `sso`.
@@ -26,10 +37,76 @@
 //!
 //! Pwned Passwords integration enabled, optional, defaults to false.
 //!
+//! ### SSO_PWNED_PASSWORDS_FILE
+//!
+//! Path to sorted SHA-1 hash file (downloadable Pwned Passwords `HASH:COUNT` format ordered
+//! by hash), optional, checked instead of Pwned Passwords API if defined.
+//!
+//! ### SSO_PWNED_PASSWORDS_BLOOM
+//!
+//! Path to bloom filter file created by `sso-cli create-pwned-passwords-bloom`, optional,
+//! checked instead of Pwned Passwords API if defined. Cannot be used with `SSO_PWNED_PASSWORDS_FILE`.
+//!
 //! ### SSO_TRAEFIK
 //!
 //! Traefik forward authentcation integration enabled, optional, defaults to false.
 //!
+//! ### SSO_LOCKOUT_USER_ATTEMPTS
+//!
+//! Failed login attempts per user email before lockout, optional, defaults to 5, 0 disables.
+//!
+//! ### SSO_LOCKOUT_REMOTE_ATTEMPTS
+//!
+//! Failed login attempts per remote IP address before lockout, optional, defaults to 0 (disabled).
+//! Remote is the connecting address, enable only if server is not behind a shared proxy.
+//!
+//! ### SSO_LOCKOUT_DELAY
+//!
+//! Lockout duration in seconds, doubled for each further failed attempt, optional, defaults to 60.
+//!
+//! ### SSO_LOCKOUT_MAX_DELAY
+//!
+//! Maximum lockout duration in seconds, optional, defaults to 3600.
+//! Failed attempt counts are reset after this duration without failures.
+//!
+//! ### SSO_PASSWORD_MIN_STRENGTH
+//!
+//! Minimum password `zxcvbn` strength score (0 to 4), optional, defaults to 0 (disabled).
+//!
+//! ### SSO_PASSWORD_REJECT_PWNED
+//!
+//! Reject passwords present in Pwned Passwords index, optional, defaults to false.
+//! Requires Pwned Passwords integration enabled.
+//!
+//! ### SSO_PASSWORD_HISTORY
+//!
+//! Number of previous passwords which cannot be reused, optional, defaults to 0.
+//! The current password can never be reused.
+//!
+//! ### SSO_PASSWORD_MAX_AGE_DAYS
+//!
+//! Maximum password age in days before user must update password, optional,
+//! defaults to 0 (disabled).
+//! Service password policies can only make these values stricter.
+//!
+//! ### SSO_PASSWORD_ARGON2ID
+//!
+//! Hash passwords with Argon2id, optional, defaults to false (libreauth default algorithm).
+//! Existing hashes are verified and rehashed with the configured algorithm and parameters on login.
+//!
+//! ### SSO_PASSWORD_ARGON2ID_MEMORY
+//!
+//! Argon2id memory cost in KiB, optional, defaults to 19456.
+//!
+//! ### SSO_PASSWORD_ARGON2ID_ITERATIONS
+//!
+//! Argon2id iterations, optional, defaults to 2.
+//!
+//! ### SSO_PASSWORD_ARGON2ID_PARALLELISM
+//!
+//! Argon2id parallelism, optional, defaults to 1.
+//! Use `sso-cli benchmark-password-hash` to find parameters for a target latency on host.
+//!
 //! ### SSO_TLS_CERT
 //!
 //! Path to TLS certificate in PEM format, optional.
@@ -62,6 +139,10 @@
 //!
 //! SMTP file transport directory path, optional, defaults to `./tmp`.
 //!
+//! ### SSO_NOTIFY_WEBHOOK_URL
+//!
+//! Notification HTTP webhook URL, optional, notifications are sent as JSON POST requests.
+//!
 //! ### SSO_GITHUB_CLIENT_ID
 //!
 //! GitHub OAuth2 provider client ID, optional.
@@ -78,6 +159,10 @@
 //!
 //! Microsoft OAuth2 provider client secret, optional.
 //!
+//! ### SSO_OIDC_ISSUER
+//!
+//! OpenID Connect provider issuer URL, optional, endpoints are disabled if undefined.
+//!
 #[macro_use]
 extern crate log;
 
@@ -103,12 +188,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let _guard = log_init("SSO_SENTRY_DSN", "SSO_LOG_PRETTY");
 
     // Postgres connection.
-    let driver = Postgres::from_env("SSO_POSTGRES_URL", "SSO_POSTGRES_CONNECTIONS");
+    let driver = Postgres::from_env(
+        "SSO_POSTGRES_URL",
+        "SSO_POSTGRES_CONNECTIONS",
+        "SSO_KEY_SECRET",
+        "SSO_KEY_SECRET_FILE",
+    );
 
     // gRPC, HTTP server options.
     let grpc_options =
         GrpcServerOptions::from_env("SSO_USER_AGENT", "SSO_PWNED_PASSWORDS", "SSO_TRAEFIK")
+            .pwned_passwords_from_env("SSO_PWNED_PASSWORDS_FILE", "SSO_PWNED_PASSWORDS_BLOOM")
             .tls_from_env("SSO_TLS_CERT", "SSO_TLS_KEY", "SSO_TLS_CLIENT_CA_CERT")
+            .lockout_from_env(
+                "SSO_LOCKOUT_USER_ATTEMPTS",
+                "SSO_LOCKOUT_REMOTE_ATTEMPTS",
+                "SSO_LOCKOUT_DELAY",
+                "SSO_LOCKOUT_MAX_DELAY",
+            )
+            .password_policy_from_env(
+                "SSO_PASSWORD_MIN_STRENGTH",
+                "SSO_PASSWORD_REJECT_PWNED",
+                "SSO_PASSWORD_HISTORY",
+                "SSO_PASSWORD_MAX_AGE_DAYS",
+            )
+            .password_hash_from_env(
+                "SSO_PASSWORD_ARGON2ID",
+                "SSO_PASSWORD_ARGON2ID_MEMORY",
+                "SSO_PASSWORD_ARGON2ID_ITERATIONS",
+                "SSO_PASSWORD_ARGON2ID_PARALLELISM",
+            )
             .smtp_transport_from_env(
                 "SSO_SMTP_HOST",
                 "SSO_SMTP_PORT",
@@ -116,8 +225,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "SSO_SMTP_PASSWORD",
             )
             .smtp_file_transport_from_env("SSO_SMTP_FILE")
+            .notify_webhook_from_env("SSO_NOTIFY_WEBHOOK_URL")
             .github_from_env("SSO_GITHUB_CLIENT_ID", "SSO_GITHUB_CLIENT_SECRET")
-            .microsoft_from_env("SSO_MICROSOFT_CLIENT_ID", "SSO_MICROSOFT_CLIENT_SECRET");
+            .microsoft_from_env("SSO_MICROSOFT_CLIENT_ID", "SSO_MICROSOFT_CLIENT_SECRET")
+            .oidc_issuer_from_env("SSO_OIDC_ISSUER");
     let grpc_tls_config = grpc_options.tls_config();
     let http_options = Arc::new(grpc_options.clone());
 
@@ -114,6 +114,23 @@ impl CsrfCreate {
         Self::create(conn, &key, &key, ttl, service_id)
     }
 
+    /// Generate random CSRF key for value with time to live for service.
+    pub fn generate_value<V>(
+        conn: &PgConnection,
+        value: V,
+        ttl: Duration,
+        service_id: Uuid,
+    ) -> DriverResult<Csrf>
+    where
+        V: Into<String>,
+    {
+        let key = KeyBuilder::new()
+            .size(CSRF_KEY_BYTES)
+            .generate()
+            .as_base32();
+        Self::create(conn, key, value, ttl, service_id)
+    }
+
     /// Create CSRF key/value with time to live for service. Key must be unique.
     pub fn create<K, V>(
         conn: &PgConnection,
@@ -191,6 +208,35 @@ impl CsrfRead {
         Ok(csrf)
     }
 
+    /// Read CSRF token without deleting it, expired tokens are not returned.
+    pub fn peek<T: AsRef<str>>(conn: &PgConnection, key: T) -> DriverResult<Option<Csrf>> {
+        Self::delete_by_ttl(conn)?;
+
+        sso_csrf::table
+            .filter(sso_csrf::dsl::key.eq(key.as_ref()))
+            .get_result::<Csrf>(conn)
+            .optional()
+            .map_err(DriverError::DieselResult)
+    }
+
+    /// Delete CSRF token if value matches, returns true if token was deleted.
+    pub fn delete_by_value<K, V>(conn: &PgConnection, key: K, value: V) -> DriverResult<bool>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        diesel::delete(
+            sso_csrf::table.filter(
+                sso_csrf::dsl::key
+                    .eq(key.as_ref())
+                    .and(sso_csrf::dsl::value.eq(value.as_ref())),
+            ),
+        )
+        .execute(conn)
+        .map_err(Into::into)
+        .map(|count| count > 0)
+    }
+
     fn delete_by_key<T: AsRef<str>>(conn: &PgConnection, key: T) -> DriverResult<()> {
         diesel::delete(sso_csrf::table.filter(sso_csrf::dsl::key.eq(key.as_ref())))
             .execute(conn)
@@ -23,18 +23,34 @@ pub enum AuditType {
     KeyCreate,
     KeyRead,
     KeyUpdate,
+    KeyRotate,
     KeyDelete,
     ServiceList,
     ServiceCreate,
     ServiceRead,
     ServiceUpdate,
     ServiceDelete,
+    Oauth2ProviderList,
+    Oauth2ProviderCreate,
+    Oauth2ProviderRead,
+    Oauth2ProviderUpdate,
+    Oauth2ProviderDelete,
     UserList,
     UserCreate,
     UserRead,
     UserUpdate,
     UserDelete,
+    UserSessionList,
+    UserSessionRevoke,
+    UserLoginUnlock,
+    UserPasswordRehash,
     AuthLocalLogin,
+    AuthLocalLoginLockout,
+    AuthLocalLoginMfa,
+    AuthLocalMagicLink,
+    AuthLocalMagicLinkConfirm,
+    AuthLocalEmailCode,
+    AuthLocalEmailCodeConfirm,
     AuthLocalRegister,
     AuthLocalRegisterConfirm,
     AuthLocalRegisterRevoke,
@@ -49,15 +65,30 @@ pub enum AuditType {
     AuthGithubOauth2Callback,
     AuthMicrosoftOauth2Url,
     AuthMicrosoftOauth2Callback,
+    AuthOauth2Url,
+    AuthOauth2Callback,
+    AuthSamlUrl,
+    AuthSamlCallback,
     AuthOauth2Login,
     AuthKeyVerify,
     AuthKeyRevoke,
     AuthTokenVerify,
     AuthTokenRefresh,
+    AuthTokenRefreshReuse,
     AuthTokenRevoke,
     AuthTotp,
+    AuthTotpRegisterBegin,
+    AuthTotpRegisterFinish,
+    AuthTotpRecovery,
     AuthCsrfCreate,
     AuthCsrfVerify,
+    AuthWebauthnRegisterBegin,
+    AuthWebauthnRegisterFinish,
+    AuthWebauthnLoginBegin,
+    AuthWebauthnLoginFinish,
+    AuthOidcAuthorize,
+    AuthOidcToken,
+    AuthOidcUserinfo,
 }
 
 impl_enum_to_from_string!(AuditType, "sso:");
@@ -353,6 +384,10 @@ impl AuditBuilder {
         &self.meta
     }
 
+    pub fn type_(&self) -> AuditType {
+        self.type_
+    }
+
     pub fn get_key_id(&self) -> Option<Uuid> {
         self.key
     }
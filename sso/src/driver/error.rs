@@ -19,12 +19,39 @@ pub enum DriverError {
     #[fail(display = "KeyRevoked")]
     KeyRevoked,
 
+    #[fail(display = "KeyExpired")]
+    KeyExpired,
+
+    #[fail(display = "KeyRotateTypeInvalid")]
+    KeyRotateTypeInvalid,
+
+    #[fail(display = "KeyPermissionDenied")]
+    KeyPermissionDenied,
+
+    #[fail(display = "RoleNotFound")]
+    RoleNotFound,
+
     #[fail(display = "KeyUserTokenConstraint")]
     KeyUserTokenConstraint,
 
     #[fail(display = "KeyUserTotpConstraint")]
     KeyUserTotpConstraint,
 
+    #[fail(display = "KeyUserWebauthnCredentialRequired")]
+    KeyUserWebauthnCredentialRequired,
+
+    #[fail(display = "KeySecretInvalid")]
+    KeySecretInvalid,
+
+    #[fail(display = "KeyValueInvalid")]
+    KeyValueInvalid,
+
+    #[fail(display = "KeyUserWebauthnNotFound")]
+    KeyUserWebauthnNotFound,
+
+    #[fail(display = "KeyUserWebauthnAaguidInvalid")]
+    KeyUserWebauthnAaguidInvalid,
+
     #[fail(display = "ServiceNotFound")]
     ServiceNotFound,
 
@@ -43,6 +70,15 @@ pub enum DriverError {
     #[fail(display = "ServiceProviderGithubOauth2Disabled")]
     ServiceProviderGithubOauth2Disabled,
 
+    #[fail(display = "ServiceProviderOauth2Disabled")]
+    ServiceProviderOauth2Disabled,
+
+    #[fail(display = "ServiceProviderSamlDisabled")]
+    ServiceProviderSamlDisabled,
+
+    #[fail(display = "ServiceOidcRedirectUriInvalid")]
+    ServiceOidcRedirectUriInvalid,
+
     #[fail(display = "ServiceCannotCreateServiceKey")]
     ServiceCannotCreateServiceKey,
 
@@ -64,6 +100,18 @@ pub enum DriverError {
     #[fail(display = "UserPasswordIncorrect")]
     UserPasswordIncorrect,
 
+    #[fail(display = "UserLoginLocked")]
+    UserLoginLocked,
+
+    #[fail(display = "UserMfaRequired")]
+    UserMfaRequired,
+
+    #[fail(display = "UserMfaUndefined")]
+    UserMfaUndefined,
+
+    #[fail(display = "UserMfaMismatch")]
+    UserMfaMismatch,
+
     #[fail(display = "UserPasswordUndefined")]
     UserPasswordUndefined,
 
@@ -76,21 +124,117 @@ pub enum DriverError {
     #[fail(display = "JwtServiceMismatch")]
     JwtServiceMismatch,
 
+    #[fail(display = "JwtAlgorithmInvalid")]
+    JwtAlgorithmInvalid,
+
+    #[fail(display = "JwtAlgorithmMismatch")]
+    JwtAlgorithmMismatch,
+
+    #[fail(display = "JwtKeyNotFound")]
+    JwtKeyNotFound,
+
+    #[fail(display = "UserSessionNotFound")]
+    UserSessionNotFound,
+
+    #[fail(display = "JwtRevoked")]
+    JwtRevoked,
+
+    #[fail(display = "JwtRefreshTokenRevoked")]
+    JwtRefreshTokenRevoked,
+
+    #[fail(display = "JwtRefreshTokenReused {}", _0)]
+    JwtRefreshTokenReused(uuid::Uuid),
+
     #[fail(display = "CsrfNotFoundOrUsed")]
     CsrfNotFoundOrUsed,
 
     #[fail(display = "CsrfServiceMismatch")]
     CsrfServiceMismatch,
 
+    #[fail(display = "CsrfProviderMismatch")]
+    CsrfProviderMismatch,
+
+    #[fail(display = "CsrfSessionMismatch")]
+    CsrfSessionMismatch,
+
+    #[fail(display = "Oauth2ProviderNotFound")]
+    Oauth2ProviderNotFound,
+
+    #[fail(display = "Oauth2ProviderDisabled")]
+    Oauth2ProviderDisabled,
+
+    #[fail(display = "Oauth2ProviderDiscovery {}", _0)]
+    Oauth2ProviderDiscovery(u16),
+
+    #[fail(display = "Oauth2ProviderEmailUnverified")]
+    Oauth2ProviderEmailUnverified,
+
+    #[fail(display = "Oauth2ProviderEmailClaimNotFound")]
+    Oauth2ProviderEmailClaimNotFound,
+
+    #[fail(display = "SamlMetadataInvalid")]
+    SamlMetadataInvalid,
+
+    #[fail(display = "SamlResponseInvalid")]
+    SamlResponseInvalid,
+
+    #[fail(display = "SamlStatusUnsuccessful")]
+    SamlStatusUnsuccessful,
+
+    #[fail(display = "SamlSignatureInvalid")]
+    SamlSignatureInvalid,
+
+    #[fail(display = "SamlIssuerMismatch")]
+    SamlIssuerMismatch,
+
+    #[fail(display = "SamlAudienceMismatch")]
+    SamlAudienceMismatch,
+
+    #[fail(display = "SamlRecipientMismatch")]
+    SamlRecipientMismatch,
+
+    #[fail(display = "SamlInResponseToMismatch")]
+    SamlInResponseToMismatch,
+
+    #[fail(display = "SamlExpired")]
+    SamlExpired,
+
+    #[fail(display = "SamlEmailNotFound")]
+    SamlEmailNotFound,
+
+    #[fail(display = "OidcClientInvalid")]
+    OidcClientInvalid,
+
+    #[fail(display = "OidcResponseTypeUnsupported")]
+    OidcResponseTypeUnsupported,
+
+    #[fail(display = "OidcScopeInvalid")]
+    OidcScopeInvalid,
+
+    #[fail(display = "OidcGrantTypeUnsupported")]
+    OidcGrantTypeUnsupported,
+
     #[fail(display = "TotpInvalid")]
     TotpInvalid,
 
-    #[fail(display = "SmtpDisabled")]
-    SmtpDisabled,
+    #[fail(display = "TotpReused")]
+    TotpReused,
+
+    #[fail(display = "TotpRecoveryInvalid")]
+    TotpRecoveryInvalid,
+
+    #[fail(display = "EmailCodeInvalid")]
+    EmailCodeInvalid,
+
+    #[fail(display = "NotifyDisabled")]
+    NotifyDisabled,
 
     #[fail(display = "PwnedPasswordsDisabled")]
     PwnedPasswordsDisabled,
 
+    #[fail(display = "PwnedPasswordsBloomInvalid")]
+    PwnedPasswordsBloomInvalid,
+
     #[fail(display = "AuthenticateKeyOrTokenUndefined")]
     AuthenticateKeyOrTokenUndefined,
 
@@ -115,6 +259,9 @@ pub enum DriverError {
     #[fail(display = "LibreauthPass {}", _0)]
     LibreauthPass(usize),
 
+    #[fail(display = "Argon2 {}", _0)]
+    Argon2(#[fail(cause)] argon2::Error),
+
     #[fail(display = "LibreauthOath {}", _0)]
     LibreauthOath(usize),
 
@@ -127,6 +274,9 @@ pub enum DriverError {
     #[fail(display = "Jsonwebtoken {}", _0)]
     Jsonwebtoken(#[fail(cause)] jsonwebtoken::errors::Error),
 
+    #[fail(display = "Openssl {}", _0)]
+    Openssl(#[fail(cause)] openssl::error::ErrorStack),
+
     #[fail(display = "StdIo {}", _0)]
     StdIo(#[fail(cause)] std::io::Error),
 
@@ -183,6 +333,18 @@ pub enum DriverError {
 
     #[fail(display = "HttpUri {}", _0)]
     HttpUri(#[fail(cause)] http::uri::InvalidUri),
+
+    #[fail(display = "SerdeJson {}", _0)]
+    SerdeJson(#[fail(cause)] serde_json::Error),
+
+    #[fail(display = "SerdeCbor {}", _0)]
+    SerdeCbor(#[fail(cause)] serde_cbor::Error),
+
+    #[fail(display = "QrCode {}", _0)]
+    QrCode(#[fail(cause)] qrcode::types::QrError),
+
+    #[fail(display = "Webauthn {}", _0)]
+    Webauthn(#[fail(cause)] webauthn_rs::error::WebauthnError),
 }
 
 impl From<libreauth::pass::ErrorCode> for DriverError {
@@ -9,12 +9,25 @@ use uuid::Uuid;
 /// Key value size in bytes.
 pub const BYTES_KEY_VALUE: usize = 21;
 
+/// Maximum key list filter days.
+pub const MAX_KEY_LIST_DAYS: u32 = 3650;
+
+/// Default key rotate grace period hours.
+pub const DEFAULT_KEY_ROTATE_GRACE_PERIOD_HOURS: u32 = 24;
+
+/// Maximum key rotate grace period hours.
+pub const MAX_KEY_ROTATE_GRACE_PERIOD_HOURS: u32 = 720;
+
+/// Key last used update interval seconds, limits writes for frequently used keys.
+pub const KEY_LAST_USED_INTERVAL_S: i64 = 60;
+
 /// Key types.
 #[derive(Debug, Copy, PartialEq, Clone, Serialize, Deserialize)]
 pub enum KeyType {
     Key,
     Token,
     Totp,
+    WebAuthn,
 }
 
 impl_enum_to_from_string!(KeyType, "");
@@ -25,6 +38,7 @@ impl KeyType {
             0 => Self::Key,
             1 => Self::Token,
             2 => Self::Totp,
+            3 => Self::WebAuthn,
             _ => unimplemented!(),
         }
     }
@@ -38,6 +52,7 @@ impl TryFrom<i32> for KeyType {
             0 => Ok(Self::Key),
             1 => Ok(Self::Token),
             2 => Ok(Self::Totp),
+            3 => Ok(Self::WebAuthn),
             _ => Err(()),
         }
     }
@@ -55,6 +70,11 @@ pub struct Key {
     pub name: String,
     pub service_id: Option<Uuid>,
     pub user_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scopes: Vec<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_remote: Option<String>,
+    pub role: Option<String>,
 }
 
 impl fmt::Display for Key {
@@ -72,6 +92,21 @@ impl fmt::Display for Key {
         if let Some(user_id) = &self.user_id {
             write!(f, "\n\tuser_id {}", user_id)?;
         }
+        if let Some(expires_at) = &self.expires_at {
+            write!(f, "\n\texpires_at {}", expires_at)?;
+        }
+        if !self.scopes.is_empty() {
+            write!(f, "\n\tscopes {}", self.scopes.join(", "))?;
+        }
+        if let Some(last_used_at) = &self.last_used_at {
+            write!(f, "\n\tlast_used_at {}", last_used_at)?;
+        }
+        if let Some(last_used_remote) = &self.last_used_remote {
+            write!(f, "\n\tlast_used_remote {}", last_used_remote)?;
+        }
+        if let Some(role) = &self.role {
+            write!(f, "\n\trole {}", role)?;
+        }
         Ok(())
     }
 }
@@ -88,6 +123,13 @@ impl AuditDiff for Key {
             .compare("is_enabled", &self.is_enabled, &previous.is_enabled)
             .compare("is_revoked", &self.is_revoked, &previous.is_revoked)
             .compare("name", &self.name, &previous.name)
+            .compare_opt(
+                "expires_at",
+                self.expires_at.as_ref(),
+                previous.expires_at.as_ref(),
+            )
+            .compare_vec("scopes", &self.scopes, &previous.scopes)
+            .compare_opt("role", self.role.as_ref(), previous.role.as_ref())
             .into_value()
     }
 }
@@ -108,6 +150,11 @@ pub struct KeyWithValue {
     pub value: String,
     pub service_id: Option<Uuid>,
     pub user_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scopes: Vec<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_remote: Option<String>,
+    pub role: Option<String>,
 }
 
 impl fmt::Display for KeyWithValue {
@@ -126,6 +173,21 @@ impl fmt::Display for KeyWithValue {
         if let Some(user_id) = &self.user_id {
             write!(f, "\n\tuser_id {}", user_id)?;
         }
+        if let Some(expires_at) = &self.expires_at {
+            write!(f, "\n\texpires_at {}", expires_at)?;
+        }
+        if !self.scopes.is_empty() {
+            write!(f, "\n\tscopes {}", self.scopes.join(", "))?;
+        }
+        if let Some(last_used_at) = &self.last_used_at {
+            write!(f, "\n\tlast_used_at {}", last_used_at)?;
+        }
+        if let Some(last_used_remote) = &self.last_used_remote {
+            write!(f, "\n\tlast_used_remote {}", last_used_remote)?;
+        }
+        if let Some(role) = &self.role {
+            write!(f, "\n\trole {}", role)?;
+        }
         Ok(())
     }
 }
@@ -148,10 +210,22 @@ impl From<KeyWithValue> for Key {
             name: k.name,
             service_id: k.service_id,
             user_id: k.user_id,
+            expires_at: k.expires_at,
+            scopes: k.scopes,
+            last_used_at: k.last_used_at,
+            last_used_remote: k.last_used_remote,
+            role: k.role,
         }
     }
 }
 
+impl KeyWithValue {
+    /// Returns true if key has expired.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.map(|x| x <= Utc::now()).unwrap_or(false)
+    }
+}
+
 /// Key list query.
 #[derive(Debug)]
 pub enum KeyListQuery {
@@ -169,6 +243,8 @@ pub struct KeyListFilter {
     pub type_: Option<Vec<KeyType>>,
     pub service_id: Option<Vec<Uuid>>,
     pub user_id: Option<Vec<Uuid>>,
+    pub expires_within_days: Option<u32>,
+    pub unused_for_days: Option<u32>,
     pub limit: i64,
 }
 
@@ -196,6 +272,9 @@ pub struct KeyCreate {
     pub value: String,
     pub service_id: Option<Uuid>,
     pub user_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scopes: Vec<String>,
+    pub role: Option<String>,
 }
 
 impl KeyCreate {
@@ -213,6 +292,9 @@ impl KeyCreate {
             value,
             service_id: None,
             user_id: None,
+            expires_at: None,
+            scopes: Vec::new(),
+            role: None,
         }
     }
 
@@ -230,6 +312,9 @@ impl KeyCreate {
             value,
             service_id: Some(service_id),
             user_id: None,
+            expires_at: None,
+            scopes: Vec::new(),
+            role: None,
         }
     }
 
@@ -253,8 +338,29 @@ impl KeyCreate {
             value,
             service_id: Some(service_id),
             user_id: Some(user_id),
+            expires_at: None,
+            scopes: Vec::new(),
+            role: None,
         }
     }
+
+    /// Set key expiry date and time.
+    pub fn expires_at(mut self, expires_at: Option<DateTime<Utc>>) -> Self {
+        self.expires_at = expires_at;
+        self
+    }
+
+    /// Set key permission scopes.
+    pub fn scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Set key role.
+    pub fn role(mut self, role: Option<String>) -> Self {
+        self.role = role;
+        self
+    }
 }
 
 /// Key read by service ID and user ID.
@@ -333,6 +439,107 @@ pub struct KeyUpdate {
     pub is_enabled: Option<bool>,
     pub is_revoked: Option<bool>,
     pub name: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scopes: Option<Vec<String>>,
+    pub role: Option<String>,
+}
+
+/// Key rotate data.
+#[derive(Debug)]
+pub struct KeyRotate {
+    pub id: Uuid,
+    pub grace_period_hours: u32,
+}
+
+/// Key rotate lineage, recorded in audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRotateLineage {
+    pub previous_key_id: Uuid,
+    pub previous_key_expires_at: Option<DateTime<Utc>>,
+    pub key_id: Uuid,
+}
+
+impl KeyRotateLineage {
+    pub fn new(previous: &Key, key: &KeyWithValue) -> Self {
+        Self {
+            previous_key_id: previous.id,
+            previous_key_expires_at: previous.expires_at,
+            key_id: key.id,
+        }
+    }
+}
+
+/// Key WebAuthn credential.
+///
+/// Stored alongside a user key of type `WebAuthn`, where the key value
+/// is the base64 encoded credential ID.
+#[derive(Debug, Clone)]
+pub struct KeyWebauthn {
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub key_id: Uuid,
+    pub credential: Value,
+    pub counter: i64,
+    pub aaguid: Uuid,
+}
+
+/// Key WebAuthn list by service ID and user ID, enabled and not revoked keys only.
+#[derive(Debug)]
+pub struct KeyWebauthnList {
+    pub service_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// Key WebAuthn create data.
+#[derive(Debug)]
+pub struct KeyWebauthnCreate {
+    pub key: KeyCreate,
+    pub credential: Value,
+    pub counter: i64,
+    pub aaguid: Uuid,
+}
+
+impl KeyWebauthnCreate {
+    /// Create user WebAuthn key.
+    pub fn user<N, V>(
+        name: N,
+        value: V,
+        service_id: Uuid,
+        user_id: Uuid,
+        credential: Value,
+        counter: i64,
+        aaguid: Uuid,
+    ) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            key: KeyCreate {
+                is_enabled: true,
+                is_revoked: false,
+                type_: KeyType::WebAuthn,
+                name: name.into(),
+                value: value.into(),
+                service_id: Some(service_id),
+                user_id: Some(user_id),
+                expires_at: None,
+                scopes: Vec::new(),
+                role: None,
+            },
+            credential,
+            counter,
+            aaguid,
+        }
+    }
+}
+
+/// Key WebAuthn update data.
+#[derive(Debug)]
+pub struct KeyWebauthnUpdate {
+    pub key_id: Uuid,
+    pub credential: Value,
+    pub counter: i64,
 }
 
 /// Generate new key value from random bytes.
@@ -0,0 +1,593 @@
+use crate::{DriverError, DriverResult, KeyType};
+use openssl::{
+    hash::MessageDigest,
+    memcmp,
+    pkey::PKey,
+    rand::rand_bytes,
+    sign::Signer,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use std::{fmt, fs, path::Path, str::FromStr};
+use uuid::Uuid;
+
+/// Key secret size in bytes.
+pub const BYTES_KEY_SECRET: usize = 32;
+
+/// Key value public prefix length.
+pub const KEY_VALUE_PREFIX_LEN: usize = 8;
+
+/// Key value HMAC-SHA256 hash format.
+const KEY_VALUE_HASH_FORMAT: &str = "$hmac-sha256$";
+
+/// Key value AES-256-GCM ciphertext format.
+const KEY_VALUE_ENCRYPT_FORMAT: &str = "$aes-256-gcm$";
+
+/// JSON web token signing key private key ciphertext additional authenticated data.
+const JWT_KEY_ENCRYPT_AAD: &str = "sso-jwt-key";
+
+/// Wrapped hash only data key format.
+const KEY_DATA_KEY_HASH_ONLY_FORMAT: &str = "$hash-only$";
+
+/// AES-256-GCM nonce size in bytes.
+const BYTES_NONCE: usize = 12;
+
+/// AES-256-GCM tag size in bytes.
+const BYTES_TAG: usize = 16;
+
+/// Key secret.
+///
+/// Server master key loaded from file or environment, used to wrap data keys
+/// which are stored in the database.
+#[derive(Clone)]
+pub struct KeySecret {
+    secret: Vec<u8>,
+    wrap_key: Vec<u8>,
+}
+
+impl fmt::Debug for KeySecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeySecret {{ ... }}")
+    }
+}
+
+impl KeySecret {
+    /// Returns key secret from master key bytes.
+    pub fn new(secret: &[u8]) -> DriverResult<Self> {
+        if secret.len() != BYTES_KEY_SECRET {
+            return Err(DriverError::KeySecretInvalid);
+        }
+        Ok(Self {
+            secret: secret.to_vec(),
+            wrap_key: hmac_sha256(secret, b"sso-key-data-key-wrap")?,
+        })
+    }
+
+    /// Returns key secret from base64 encoded master key.
+    pub fn from_base64(secret: &str) -> DriverResult<Self> {
+        let secret = base64::decode(secret.trim()).map_err(|_| DriverError::KeySecretInvalid)?;
+        Self::new(&secret)
+    }
+
+    /// Returns key secret from file containing base64 encoded master key.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> DriverResult<Self> {
+        let secret = fs::read_to_string(path).map_err(DriverError::StdIo)?;
+        Self::from_base64(&secret)
+    }
+
+    /// Returns random base64 encoded master key.
+    pub fn generate() -> DriverResult<String> {
+        let mut secret = [0u8; BYTES_KEY_SECRET];
+        rand_bytes(&mut secret).map_err(DriverError::Openssl)?;
+        Ok(base64::encode(secret))
+    }
+
+    /// Returns data key wrapped by master key.
+    pub fn wrap(&self, key: &KeyDataKey) -> DriverResult<String> {
+        let wrapped = encrypt(&self.wrap_key, key.id.as_bytes(), &key.data)?;
+        if key.is_hash_only() {
+            Ok(format!("{}{}", KEY_DATA_KEY_HASH_ONLY_FORMAT, wrapped))
+        } else {
+            Ok(wrapped)
+        }
+    }
+
+    /// Returns data key unwrapped by master key, or error if data key was
+    /// not wrapped by this master key.
+    pub fn unwrap(&self, id: Uuid, wrapped: &str) -> DriverResult<KeyDataKey> {
+        let (hash_only, wrapped) = match wrapped.strip_prefix(KEY_DATA_KEY_HASH_ONLY_FORMAT) {
+            Some(wrapped) => (true, wrapped),
+            None => (false, wrapped),
+        };
+        let data = decrypt(&self.wrap_key, id.as_bytes(), wrapped)
+            .map_err(|_| DriverError::KeySecretInvalid)?;
+        if hash_only {
+            KeyDataKey::new_hash_only(id, &data)
+        } else {
+            KeyDataKey::new(id, &data)
+        }
+    }
+
+    /// Returns data key equal to master key, which protected key values
+    /// stored before data keys were introduced. This key is never stored.
+    pub fn legacy_data_key(&self) -> DriverResult<KeyDataKey> {
+        KeyDataKey::new(Uuid::new_v4(), &self.secret)
+    }
+}
+
+/// Key data key.
+///
+/// Data encryption key used to hash `Key` type values and encrypt `Token` and
+/// `Totp` type values and signing key private keys before they are stored.
+/// Hash only data keys verify hashes stored before data keys were introduced
+/// and cannot encrypt values.
+#[derive(Clone)]
+pub struct KeyDataKey {
+    id: Uuid,
+    data: Vec<u8>,
+    hash_key: Vec<u8>,
+    encrypt_key: Option<Vec<u8>>,
+}
+
+impl fmt::Debug for KeyDataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyDataKey {{ id: {}, ... }}", self.id)
+    }
+}
+
+impl KeyDataKey {
+    /// Returns data key from bytes.
+    pub fn new(id: Uuid, data: &[u8]) -> DriverResult<Self> {
+        if data.len() != BYTES_KEY_SECRET {
+            return Err(DriverError::KeySecretInvalid);
+        }
+        Ok(Self {
+            id,
+            data: data.to_vec(),
+            hash_key: hmac_sha256(data, b"sso-key-value-hash")?,
+            encrypt_key: Some(hmac_sha256(data, b"sso-key-value-encrypt")?),
+        })
+    }
+
+    /// Returns hash only data key from hash key bytes.
+    fn new_hash_only(id: Uuid, hash_key: &[u8]) -> DriverResult<Self> {
+        if hash_key.len() != BYTES_KEY_SECRET {
+            return Err(DriverError::KeySecretInvalid);
+        }
+        Ok(Self {
+            id,
+            data: hash_key.to_vec(),
+            hash_key: hash_key.to_vec(),
+            encrypt_key: None,
+        })
+    }
+
+    /// Returns hash only data key with same ID and hash key.
+    pub fn hash_only(&self) -> DriverResult<Self> {
+        Self::new_hash_only(self.id, &self.hash_key)
+    }
+
+    /// Returns random data key.
+    pub fn generate() -> DriverResult<Self> {
+        let mut data = [0u8; BYTES_KEY_SECRET];
+        rand_bytes(&mut data).map_err(DriverError::Openssl)?;
+        Self::new(Uuid::new_v4(), &data)
+    }
+
+    /// Returns data key ID.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns true if data key only verifies hashes.
+    pub fn is_hash_only(&self) -> bool {
+        self.encrypt_key.is_none()
+    }
+
+    fn value_hash(&self, value: &str) -> DriverResult<String> {
+        let hash = hmac_sha256(&self.hash_key, value.as_bytes())?;
+        Ok(format!(
+            "{}{}${}",
+            KEY_VALUE_HASH_FORMAT,
+            self.id,
+            base64::encode(hash)
+        ))
+    }
+
+    /// Encrypt value of key, ciphertext is bound to key ID so it cannot be
+    /// moved to another key.
+    fn value_encrypt(&self, key_id: Uuid, value: &str) -> DriverResult<String> {
+        self.encrypt_aad(&value_encrypt_aad(key_id), value)
+    }
+
+    fn value_decrypt(&self, key_id: Uuid, data: &str) -> DriverResult<String> {
+        self.decrypt_aad(&value_encrypt_aad(key_id), data)
+    }
+
+    fn encrypt_aad(&self, aad: &[u8], value: &str) -> DriverResult<String> {
+        let data = encrypt(self.encrypt_key()?, aad, value.as_bytes())?;
+        Ok(format!("{}{}${}", KEY_VALUE_ENCRYPT_FORMAT, self.id, data))
+    }
+
+    fn decrypt_aad(&self, aad: &[u8], data: &str) -> DriverResult<String> {
+        let value = decrypt(self.encrypt_key()?, aad, data)?;
+        String::from_utf8(value).map_err(|_| DriverError::KeyValueInvalid)
+    }
+
+    /// Decrypt value encrypted before data keys were introduced.
+    fn value_decrypt_legacy(&self, data: &str) -> DriverResult<String> {
+        let value = decrypt(
+            self.encrypt_key()?,
+            KEY_VALUE_ENCRYPT_FORMAT.as_bytes(),
+            data,
+        )?;
+        String::from_utf8(value).map_err(|_| DriverError::KeyValueInvalid)
+    }
+
+    fn encrypt_key(&self) -> DriverResult<&[u8]> {
+        self.encrypt_key
+            .as_deref()
+            .ok_or(DriverError::KeyValueInvalid)
+    }
+}
+
+/// Key data keys.
+///
+/// Values are protected by the newest data key, and recovered or verified
+/// by the data key whose ID is part of the stored value.
+#[derive(Debug, Clone)]
+pub struct KeyDataKeys {
+    keys: Vec<KeyDataKey>,
+}
+
+impl KeyDataKeys {
+    /// Returns data keys ordered from oldest to newest, at least one data key
+    /// which is not hash only is required.
+    pub fn new(keys: Vec<KeyDataKey>) -> DriverResult<Self> {
+        if keys.iter().all(KeyDataKey::is_hash_only) {
+            return Err(DriverError::KeySecretInvalid);
+        }
+        Ok(Self { keys })
+    }
+
+    /// Returns data keys.
+    pub fn keys(&self) -> &[KeyDataKey] {
+        &self.keys
+    }
+
+    /// Returns public prefix of key value used for lookup.
+    pub fn value_prefix(value: &str) -> &str {
+        value.get(..KEY_VALUE_PREFIX_LEN).unwrap_or(value)
+    }
+
+    /// Returns stored value of key protected before data keys were introduced,
+    /// upgraded to be protected by data keys.
+    ///
+    /// Hashes are verified by hash only data key with ID of legacy data key,
+    /// ciphertexts are decrypted by legacy data key and encrypted by newest data key.
+    pub fn value_legacy_upgrade(
+        &self,
+        legacy: &KeyDataKey,
+        key_id: Uuid,
+        stored: &str,
+    ) -> DriverResult<String> {
+        let legacy_data = |format: &str| {
+            stored
+                .strip_prefix(format)
+                .filter(|data| !data.contains('$'))
+        };
+        if let Some(data) = legacy_data(KEY_VALUE_HASH_FORMAT) {
+            Ok(format!("{}{}${}", KEY_VALUE_HASH_FORMAT, legacy.id, data))
+        } else if let Some(data) = legacy_data(KEY_VALUE_ENCRYPT_FORMAT) {
+            let value = legacy.value_decrypt_legacy(data)?;
+            self.newest().value_encrypt(key_id, &value)
+        } else {
+            Err(DriverError::KeyValueInvalid)
+        }
+    }
+
+    /// Returns stored value and lookup prefix for key type, ID and plaintext value.
+    pub fn value_protect(
+        &self,
+        type_: KeyType,
+        key_id: Uuid,
+        value: &str,
+    ) -> DriverResult<(String, Option<String>)> {
+        let key = self.newest();
+        match type_ {
+            KeyType::Key => Ok((
+                key.value_hash(value)?,
+                Some(Self::value_prefix(value).to_owned()),
+            )),
+            KeyType::Token | KeyType::Totp => Ok((key.value_encrypt(key_id, value)?, None)),
+            KeyType::WebAuthn => Ok((value.to_owned(), None)),
+        }
+    }
+
+    /// Returns plaintext value for key type, ID and stored value.
+    /// `Key` type values are hashed and cannot be recovered, returns empty string.
+    pub fn value_recover(
+        &self,
+        type_: KeyType,
+        key_id: Uuid,
+        stored: &str,
+    ) -> DriverResult<String> {
+        match type_ {
+            KeyType::Key => Ok(String::new()),
+            KeyType::Token | KeyType::Totp => {
+                let (key, data) = self.value_parse(KEY_VALUE_ENCRYPT_FORMAT, stored)?;
+                key.value_decrypt(key_id, data)
+            }
+            KeyType::WebAuthn => Ok(stored.to_owned()),
+        }
+    }
+
+    /// Returns true if value matches stored hash, compared in constant time.
+    pub fn value_hash_verify(&self, value: &str, stored: &str) -> DriverResult<bool> {
+        let key = match self.value_parse(KEY_VALUE_HASH_FORMAT, stored) {
+            Ok((key, _)) => key,
+            Err(_) => return Ok(false),
+        };
+        let check = key.value_hash(value)?;
+        Ok(check.len() == stored.len() && memcmp::eq(check.as_bytes(), stored.as_bytes()))
+    }
+
+    /// Returns private key of JSON web token signing key encrypted by newest
+    /// data key, ciphertext is bound to signing key ID.
+    pub fn jwt_key_encrypt(&self, jwt_key_id: Uuid, private_key: &str) -> DriverResult<String> {
+        self.newest()
+            .encrypt_aad(&jwt_key_encrypt_aad(jwt_key_id), private_key)
+    }
+
+    /// Returns private key of JSON web token signing key for ID and stored value.
+    pub fn jwt_key_decrypt(&self, jwt_key_id: Uuid, stored: &str) -> DriverResult<String> {
+        let (key, data) = self.value_parse(KEY_VALUE_ENCRYPT_FORMAT, stored)?;
+        key.decrypt_aad(&jwt_key_encrypt_aad(jwt_key_id), data)
+    }
+
+    /// Returns newest data key which is not hash only.
+    fn newest(&self) -> &KeyDataKey {
+        self.keys.iter().rev().find(|x| !x.is_hash_only()).unwrap()
+    }
+
+    /// Returns data key and data of stored value.
+    fn value_parse<'a>(
+        &self,
+        format: &str,
+        stored: &'a str,
+    ) -> DriverResult<(&KeyDataKey, &'a str)> {
+        let mut parts = stored
+            .strip_prefix(format)
+            .ok_or(DriverError::KeyValueInvalid)?
+            .splitn(2, '$');
+        let id = parts
+            .next()
+            .and_then(|x| Uuid::from_str(x).ok())
+            .ok_or(DriverError::KeyValueInvalid)?;
+        let data = parts.next().ok_or(DriverError::KeyValueInvalid)?;
+        let key = self
+            .keys
+            .iter()
+            .find(|x| x.id == id)
+            .ok_or(DriverError::KeyValueInvalid)?;
+        Ok((key, data))
+    }
+}
+
+/// Returns additional authenticated data of value encrypted for key.
+fn value_encrypt_aad(key_id: Uuid) -> Vec<u8> {
+    let mut aad = KEY_VALUE_ENCRYPT_FORMAT.as_bytes().to_vec();
+    aad.extend_from_slice(key_id.as_bytes());
+    aad
+}
+
+/// Returns additional authenticated data of private key encrypted for signing key.
+fn jwt_key_encrypt_aad(jwt_key_id: Uuid) -> Vec<u8> {
+    let mut aad = JWT_KEY_ENCRYPT_AAD.as_bytes().to_vec();
+    aad.extend_from_slice(jwt_key_id.as_bytes());
+    aad
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> DriverResult<Vec<u8>> {
+    let key = PKey::hmac(key).map_err(DriverError::Openssl)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(DriverError::Openssl)?;
+    signer.update(data).map_err(DriverError::Openssl)?;
+    signer.sign_to_vec().map_err(DriverError::Openssl)
+}
+
+/// Returns base64 encoded nonce, ciphertext and tag.
+fn encrypt(key: &[u8], aad: &[u8], data: &[u8]) -> DriverResult<String> {
+    let mut nonce = [0u8; BYTES_NONCE];
+    rand_bytes(&mut nonce).map_err(DriverError::Openssl)?;
+    let mut tag = [0u8; BYTES_TAG];
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        aad,
+        data,
+        &mut tag,
+    )
+    .map_err(DriverError::Openssl)?;
+
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&ciphertext);
+    out.extend_from_slice(&tag);
+    Ok(base64::encode(&out))
+}
+
+fn decrypt(key: &[u8], aad: &[u8], data: &str) -> DriverResult<Vec<u8>> {
+    let data = base64::decode(data)
+        .ok()
+        .filter(|x| x.len() >= BYTES_NONCE + BYTES_TAG)
+        .ok_or(DriverError::KeyValueInvalid)?;
+    let (nonce, data) = data.split_at(BYTES_NONCE);
+    let (ciphertext, tag) = data.split_at(data.len() - BYTES_TAG);
+    decrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(nonce),
+        aad,
+        ciphertext,
+        tag,
+    )
+    .map_err(|_| DriverError::KeyValueInvalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567AB";
+
+    fn key_secret() -> KeySecret {
+        KeySecret::from_base64(&KeySecret::generate().unwrap()).unwrap()
+    }
+
+    fn data_keys() -> KeyDataKeys {
+        KeyDataKeys::new(vec![KeyDataKey::generate().unwrap()]).unwrap()
+    }
+
+    #[test]
+    fn key_secret_invalid() {
+        assert!(KeySecret::new(&[0u8; 16]).is_err());
+        assert!(KeySecret::from_base64("not base64!").is_err());
+        assert!(KeySecret::new(&[0u8; BYTES_KEY_SECRET]).is_ok());
+        assert!(KeyDataKeys::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn key_secret_wrap() {
+        let secret = key_secret();
+        let key = KeyDataKey::generate().unwrap();
+        let wrapped = secret.wrap(&key).unwrap();
+        let unwrapped = secret.unwrap(key.id(), &wrapped).unwrap();
+        assert_eq!(unwrapped.data, key.data);
+        assert!(secret.unwrap(Uuid::new_v4(), &wrapped).is_err());
+        assert!(key_secret().unwrap(key.id(), &wrapped).is_err());
+    }
+
+    #[test]
+    fn key_secret_wrap_hash_only() {
+        let secret = key_secret();
+        let key = secret.legacy_data_key().unwrap().hash_only().unwrap();
+        assert!(key.is_hash_only());
+        let wrapped = secret.wrap(&key).unwrap();
+        let unwrapped = secret.unwrap(key.id(), &wrapped).unwrap();
+        assert!(unwrapped.is_hash_only());
+        assert_eq!(unwrapped.hash_key, key.hash_key);
+        assert!(KeyDataKeys::new(vec![unwrapped]).is_err());
+    }
+
+    #[test]
+    fn key_data_keys_value_hash() {
+        let keys = data_keys();
+        let id = Uuid::new_v4();
+        let (hash, prefix) = keys.value_protect(KeyType::Key, id, VALUE).unwrap();
+        assert_eq!(prefix.unwrap(), "ABCDEFGH");
+        assert!(!hash.contains(VALUE));
+        assert!(keys.value_hash_verify(VALUE, &hash).unwrap());
+        assert!(!keys.value_hash_verify("ABCDEFGH", &hash).unwrap());
+        assert!(!data_keys().value_hash_verify(VALUE, &hash).unwrap());
+        assert_eq!(keys.value_recover(KeyType::Key, id, &hash).unwrap(), "");
+    }
+
+    #[test]
+    fn key_data_keys_value_encrypt() {
+        let id = Uuid::new_v4();
+        let old = KeyDataKey::generate().unwrap();
+        let keys = KeyDataKeys::new(vec![old.clone()]).unwrap();
+        let (stored, prefix) = keys.value_protect(KeyType::Totp, id, VALUE).unwrap();
+        assert!(prefix.is_none());
+        assert!(!stored.contains(VALUE));
+        assert_eq!(
+            keys.value_recover(KeyType::Totp, id, &stored).unwrap(),
+            VALUE
+        );
+
+        // Values protected by older data keys remain recoverable.
+        let new = KeyDataKey::generate().unwrap();
+        let keys = KeyDataKeys::new(vec![old, new.clone()]).unwrap();
+        let (stored_new, _) = keys.value_protect(KeyType::Token, id, VALUE).unwrap();
+        assert!(stored_new.contains(&new.id().to_string()));
+        assert_eq!(
+            keys.value_recover(KeyType::Token, id, &stored).unwrap(),
+            VALUE
+        );
+        assert_eq!(
+            keys.value_recover(KeyType::Token, id, &stored_new).unwrap(),
+            VALUE
+        );
+
+        let mut tampered = stored.clone();
+        tampered.pop();
+        tampered.push(if stored.ends_with('A') { 'B' } else { 'A' });
+        assert!(keys.value_recover(KeyType::Totp, id, &tampered).is_err());
+        assert!(data_keys()
+            .value_recover(KeyType::Totp, id, &stored)
+            .is_err());
+        assert!(keys.value_recover(KeyType::Totp, id, VALUE).is_err());
+    }
+
+    #[test]
+    fn key_data_keys_value_encrypt_key_id_bound() {
+        let keys = data_keys();
+        let id = Uuid::new_v4();
+        let (stored, _) = keys.value_protect(KeyType::Token, id, VALUE).unwrap();
+        assert!(keys
+            .value_recover(KeyType::Token, Uuid::new_v4(), &stored)
+            .is_err());
+    }
+
+    #[test]
+    fn key_data_keys_jwt_key_encrypt() {
+        let keys = data_keys();
+        let id = Uuid::new_v4();
+        let stored = keys.jwt_key_encrypt(id, VALUE).unwrap();
+        assert!(!stored.contains(VALUE));
+        assert_eq!(keys.jwt_key_decrypt(id, &stored).unwrap(), VALUE);
+        assert!(keys.jwt_key_decrypt(Uuid::new_v4(), &stored).is_err());
+        assert!(data_keys().jwt_key_decrypt(id, &stored).is_err());
+        // Signing key ciphertext cannot be recovered as a key value.
+        assert!(keys.value_recover(KeyType::Token, id, &stored).is_err());
+    }
+
+    #[test]
+    fn key_data_keys_value_legacy_upgrade() {
+        let id = Uuid::new_v4();
+        let secret = key_secret();
+        let legacy = secret.legacy_data_key().unwrap();
+        let new = KeyDataKey::generate().unwrap();
+        let keys = KeyDataKeys::new(vec![legacy.hash_only().unwrap(), new.clone()]).unwrap();
+        let legacy_id = format!("{}$", legacy.id());
+
+        let legacy_hash = legacy.value_hash(VALUE).unwrap().replace(&legacy_id, "");
+        let upgraded = keys
+            .value_legacy_upgrade(&legacy, id, &legacy_hash)
+            .unwrap();
+        assert!(keys.value_hash_verify(VALUE, &upgraded).unwrap());
+
+        let legacy_data = encrypt(
+            legacy.encrypt_key().unwrap(),
+            KEY_VALUE_ENCRYPT_FORMAT.as_bytes(),
+            VALUE.as_bytes(),
+        )
+        .unwrap();
+        let legacy_encrypt = format!("{}{}", KEY_VALUE_ENCRYPT_FORMAT, legacy_data);
+        let upgraded = keys
+            .value_legacy_upgrade(&legacy, id, &legacy_encrypt)
+            .unwrap();
+        assert!(upgraded.contains(&new.id().to_string()));
+        assert_eq!(
+            keys.value_recover(KeyType::Totp, id, &upgraded).unwrap(),
+            VALUE
+        );
+        // Master key does not recover upgraded ciphertext.
+        let legacy_keys = KeyDataKeys::new(vec![legacy.clone()]).unwrap();
+        assert!(legacy_keys
+            .value_recover(KeyType::Totp, id, &upgraded)
+            .is_err());
+
+        assert!(keys.value_legacy_upgrade(&legacy, id, &upgraded).is_err());
+        assert!(keys.value_legacy_upgrade(&legacy, id, VALUE).is_err());
+    }
+}
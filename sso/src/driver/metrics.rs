@@ -1,7 +1,7 @@
-use crate::{DriverResult, Postgres};
-use chrono::{DateTime, Utc};
+use crate::{DriverResult, Postgres, UserPasswordHash};
+use chrono::{DateTime, Duration, Utc};
 use prometheus::{
-    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
 };
 use std::{fmt, sync::Mutex};
 
@@ -23,6 +23,17 @@ pub const METRICS_NAME_GRPC_LATENCY: &str = "grpc_latency";
 /// Metrics gRPC latency help.
 pub const METRICS_HELP_GRPC_LATENCY: &str = "gRPC request latency (ms)";
 
+/// Metrics user password legacy count name.
+pub const METRICS_NAME_USER_PASSWORD_LEGACY_COUNT: &str = "user_password_legacy_count";
+
+/// Metrics user password legacy count help.
+pub const METRICS_HELP_USER_PASSWORD_LEGACY_COUNT: &str =
+    "User passwords not hashed with configured hash algorithm and parameters";
+
+/// Metrics user password legacy count interval in seconds, count requires a table scan
+/// so it is not updated on every read.
+pub const METRICS_USER_PASSWORD_LEGACY_COUNT_INTERVAL_S: i64 = 3600;
+
 /// Metrics.
 pub struct Metrics {
     pub registry: Registry,
@@ -30,6 +41,8 @@ pub struct Metrics {
     pub audit_count: IntCounterVec,
     pub grpc_count: IntCounterVec,
     pub grpc_latency: HistogramVec,
+    pub user_password_legacy_count: IntGauge,
+    pub user_password_legacy_from: Option<DateTime<Utc>>,
 }
 
 impl fmt::Debug for Metrics {
@@ -52,9 +65,18 @@ lazy_static! {
             HistogramOpts::new(METRICS_NAME_GRPC_LATENCY, METRICS_HELP_GRPC_LATENCY);
         let grpc_latency = HistogramVec::new(grpc_latency_opts, &["path"]).unwrap();
 
+        let user_password_legacy_count = IntGauge::new(
+            METRICS_NAME_USER_PASSWORD_LEGACY_COUNT,
+            METRICS_HELP_USER_PASSWORD_LEGACY_COUNT,
+        )
+        .unwrap();
+
         registry.register(Box::new(audit_count.clone())).unwrap();
         registry.register(Box::new(grpc_count.clone())).unwrap();
         registry.register(Box::new(grpc_latency.clone())).unwrap();
+        registry
+            .register(Box::new(user_password_legacy_count.clone()))
+            .unwrap();
 
         Mutex::new(Metrics {
             registry,
@@ -62,6 +84,8 @@ lazy_static! {
             audit_count,
             grpc_count,
             grpc_latency,
+            user_password_legacy_count,
+            user_password_legacy_from: None,
         })
     };
 }
@@ -72,7 +96,7 @@ impl Metrics {
         (metrics.grpc_count.clone(), metrics.grpc_latency.clone())
     }
 
-    pub fn read(driver: &Postgres) -> DriverResult<String> {
+    pub fn read(driver: &Postgres, password_hash: &UserPasswordHash) -> DriverResult<String> {
         let mut metrics = METRICS.lock().unwrap();
         let audit_metrics = driver.audit_read_metrics(&metrics.audit_from, None)?;
 
@@ -85,6 +109,20 @@ impl Metrics {
                 .inc_by(*count);
         }
 
+        let now = Utc::now();
+        let interval = Duration::seconds(METRICS_USER_PASSWORD_LEGACY_COUNT_INTERVAL_S);
+        let update = match metrics.user_password_legacy_from {
+            Some(from) => now - from >= interval,
+            None => true,
+        };
+        if update {
+            let user_password_legacy_count = driver.user_password_legacy_count(password_hash)?;
+            metrics
+                .user_password_legacy_count
+                .set(user_password_legacy_count);
+            metrics.user_password_legacy_from = Some(now);
+        }
+
         Self::registry_encode(&metrics.registry)
     }
 
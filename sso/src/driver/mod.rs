@@ -1,18 +1,29 @@
 mod audit;
 mod error;
 mod key;
+mod key_secret;
 mod metrics;
 pub(crate) mod pattern;
 mod postgres;
+mod role;
 mod service;
 mod template;
 mod user;
 
 pub use crate::driver::postgres::{Postgres, PostgresLockFn};
-pub use crate::driver::{audit::*, error::*, key::*, metrics::*, service::*, template::*, user::*};
+pub use crate::driver::{
+    audit::*, error::*, key::*, key_secret::*, metrics::*, role::*, service::*, template::*,
+    user::*,
+};
 
 /// Default limit.
 pub const DEFAULT_LIMIT: i64 = 50;
 
 /// Default CSRF expires seconds.
 pub const DEFAULT_CSRF_EXPIRES_S: i64 = 1000;
+
+/// Default MFA token expires seconds.
+pub const DEFAULT_MFA_TOKEN_EXPIRES_S: i64 = 300;
+
+/// Default email code and magic link token expires seconds.
+pub const DEFAULT_EMAIL_CODE_EXPIRES_S: i64 = 600;
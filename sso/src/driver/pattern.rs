@@ -1,23 +1,14 @@
 //! # Pattern functions.
 use crate::prelude::*;
-use libreauth::oath::TOTPBuilder;
 use reqwest::Client;
-use sha1::{Digest, Sha1};
 use url::Url;
 use uuid::Uuid;
 
-/// Verify TOTP code using key.
-pub fn totp_verify(key: &str, code: &str) -> DriverResult<()> {
-    let totp = TOTPBuilder::new()
-        .base32_key(key)
-        .finalize()
-        .map_err::<DriverError, _>(Into::into)?;
-
-    if !totp.is_valid(&code) {
-        Err(DriverError::TotpInvalid)
-    } else {
-        Ok(())
-    }
+/// Verify TOTP code using key parameters.
+/// Codes are single use, codes for the last accepted time step or earlier are rejected.
+pub fn totp_verify(driver: &Postgres, key: &KeyWithValue, code: &str) -> DriverResult<()> {
+    let conn = driver.conn()?;
+    KeyTotp::verify(&conn, key.id, &key.value, code)
 }
 
 /// Authenticate root key.
@@ -43,9 +34,15 @@ pub fn key_root_authenticate(
         },
         HeaderAuth::None => Err(DriverError::KeyUndefined),
     }?;
-    key.ok_or_else(|| DriverError::KeyNotFound).map(|key| {
-        audit.key(Some(&key));
-    })
+    key.ok_or_else(|| DriverError::KeyNotFound)
+        .and_then(|key| {
+            audit.key(Some(&key));
+            key_check_expired(key)
+        })
+        .and_then(|key| {
+            key_check_permission(driver, audit, &key)?;
+            key_last_used(driver, audit, &key)
+        })
 }
 
 /// Authenticate service key.
@@ -97,7 +94,8 @@ pub fn user_key_token_authenticate(
                             key_read_user_checked(driver, &service, audit, &user, KeyType::Token)?;
 
                         // Safely decode token with user key.
-                        Jwt::decode_access(&service, &user, &key, x)?;
+                        let conn = driver.conn()?;
+                        Jwt::decode_access(&conn, &service, &user, &key, x)?;
                         Ok(user)
                     }
                 },
@@ -123,7 +121,10 @@ pub fn key_authenticate(
             Ok(service)
         })
         .map(Some)
-        .or_else(|_err| key_root_authenticate(driver, audit, auth).map(|_| None))?;
+        .or_else(|err| match err {
+            DriverError::KeyExpired | DriverError::KeyPermissionDenied => Err(err),
+            _ => key_root_authenticate(driver, audit, auth).map(|_| None),
+        })?;
     Ok(service)
 }
 
@@ -152,23 +153,130 @@ fn key_service_authenticate_try(
     key.ok_or_else(|| DriverError::KeyNotFound)
         .and_then(|key| {
             audit.key(Some(&key));
+            let key = key_check_expired(key)?;
+            key_check_permission(driver, audit, &key)?;
+            key_last_used(driver, audit, &key)?;
             key.service_id
                 .ok_or_else(|| DriverError::KeyServiceUndefined)
         })
-        .and_then(|service_id| key_service_authenticate_inner(driver, audit, service_id))
+        .and_then(|service_id| service_read_id_checked(driver, audit, service_id))
 }
 
-fn key_service_authenticate_inner(
+/// Returns error if key has expired.
+fn key_check_expired(key: KeyWithValue) -> DriverResult<KeyWithValue> {
+    if key.is_expired() {
+        Err(DriverError::KeyExpired)
+    } else {
+        Ok(key)
+    }
+}
+
+/// Returns error if key does not have permission required by audit type, keys
+/// without a role have all permissions and key scopes limit the permissions of
+/// their role.
+fn key_check_permission(
     driver: &Postgres,
-    audit: &mut AuditBuilder,
-    service_id: Uuid,
-) -> DriverResult<Service> {
-    let service = driver
-        .service_read(&ServiceRead::new(service_id), None)?
-        .ok_or_else(|| DriverError::ServiceNotFound)?
-        .check()?;
-    audit.service(Some(&service));
-    Ok(service)
+    audit: &AuditBuilder,
+    key: &KeyWithValue,
+) -> DriverResult<()> {
+    let permission = match RolePermission::from_audit_type(audit.type_()) {
+        Some(permission) => permission,
+        None => return Ok(()),
+    };
+    if key.role.is_none() && key.scopes.is_empty() {
+        return Ok(());
+    }
+    let permissions = key_permissions_read(driver, key.role.as_deref(), &key.scopes)?;
+    if permissions.contains(&permission) {
+        Ok(())
+    } else {
+        Err(DriverError::KeyPermissionDenied)
+    }
+}
+
+/// Returns role and scopes assigned to key created or updated by authenticated key.
+///
+/// Keys with a role or scopes can only assign a role and scopes with a subset of
+/// their permissions, keys they create inherit their role and scopes if none are
+/// requested.
+pub fn key_role_assign(
+    driver: &Postgres,
+    audit: &AuditBuilder,
+    role: Option<String>,
+    scopes: Vec<String>,
+) -> DriverResult<(Option<String>, Vec<String>)> {
+    let key = match key_audit_read(driver, audit)? {
+        Some(key) if key.role.is_some() || !key.scopes.is_empty() => key,
+        _ => return Ok((role, scopes)),
+    };
+    let role = role.or_else(|| key.role.clone());
+    let scopes = if scopes.is_empty() {
+        key.scopes.clone()
+    } else {
+        scopes
+    };
+    let key_permissions = key_permissions_read(driver, key.role.as_deref(), &key.scopes)?;
+    let assign = key_permissions_read(driver, role.as_deref(), &scopes)?;
+    if RolePermission::contains_all(&key_permissions, &assign) {
+        Ok((role, scopes))
+    } else {
+        Err(DriverError::KeyPermissionDenied)
+    }
+}
+
+/// Returns error if authenticated key does not have all permissions of key it
+/// updates, rotates or deletes.
+pub fn key_role_check(
+    driver: &Postgres,
+    audit: &AuditBuilder,
+    role: Option<&str>,
+    scopes: &[String],
+) -> DriverResult<()> {
+    let key = match key_audit_read(driver, audit)? {
+        Some(key) if key.role.is_some() || !key.scopes.is_empty() => key,
+        _ => return Ok(()),
+    };
+    let key_permissions = key_permissions_read(driver, key.role.as_deref(), &key.scopes)?;
+    let permissions = key_permissions_read(driver, role, scopes)?;
+    if RolePermission::contains_all(&key_permissions, &permissions) {
+        Ok(())
+    } else {
+        Err(DriverError::KeyPermissionDenied)
+    }
+}
+
+/// Read authenticated key.
+fn key_audit_read(driver: &Postgres, audit: &AuditBuilder) -> DriverResult<Option<KeyWithValue>> {
+    match audit.get_key_id() {
+        Some(id) => driver.key_read(&KeyRead::IdUser(id, None), None),
+        None => Ok(None),
+    }
+}
+
+/// Read role and returns permissions of key with role and scopes.
+fn key_permissions_read(
+    driver: &Postgres,
+    role: Option<&str>,
+    scopes: &[String],
+) -> DriverResult<Vec<RolePermission>> {
+    let role = match role {
+        Some(role) => Some(driver.role_read(role)?.ok_or(DriverError::RoleNotFound)?),
+        None => None,
+    };
+    Ok(RolePermission::key_permissions(role.as_ref(), scopes))
+}
+
+/// Update key last used date and time and audit remote.
+/// Update key last used, skipped if key was read with a recent use from the same remote.
+fn key_last_used(driver: &Postgres, audit: &AuditBuilder, key: &KeyWithValue) -> DriverResult<()> {
+    let remote = audit.meta().remote();
+    let used_before = Utc::now() - Duration::seconds(KEY_LAST_USED_INTERVAL_S);
+    let used_recently = matches!(key.last_used_at, Some(x) if x >= used_before)
+        && key.last_used_remote.as_deref() == Some(remote);
+    if !used_recently {
+        driver.key_update_last_used(&key.id, remote)?;
+    }
+    Ok(())
 }
 
 fn check_audit_user(
@@ -195,7 +303,8 @@ fn check_audit_user(
                 let key = key_read_user_checked(driver, &service, audit, &user, KeyType::Token)?;
 
                 // Safely decode token with user key.
-                Jwt::decode_access(&service, &user, &key, &token)?;
+                let conn = driver.conn()?;
+                Jwt::decode_access(&conn, &service, &user, &key, &token)?;
                 Ok(())
             }
         },
@@ -203,6 +312,21 @@ fn check_audit_user(
     }
 }
 
+/// Read service by ID.
+/// Checks service is enabled, returns bad request if disabled.
+pub fn service_read_id_checked(
+    driver: &Postgres,
+    audit: &mut AuditBuilder,
+    id: Uuid,
+) -> DriverResult<Service> {
+    let service = driver
+        .service_read(&ServiceRead::new(id), None)?
+        .ok_or_else(|| DriverError::ServiceNotFound)?
+        .check()?;
+    audit.service(Some(&service));
+    Ok(service)
+}
+
 /// Read user by ID.
 /// Checks user is enabled, returns bad request if disabled.
 pub fn user_read_id_checked(
@@ -257,6 +381,184 @@ pub fn user_read_email_checked(
     Ok(user)
 }
 
+/// Check user password.
+/// If password hash was not created by hash algorithm and parameters,
+/// rehash password and create audit log.
+pub fn user_password_check(
+    driver: &Postgres,
+    audit: &mut AuditBuilder,
+    hash: &UserPasswordHash,
+    user: &User,
+    password: &str,
+) -> DriverResult<()> {
+    user.password_check(password)?;
+    if user.password_needs_update(hash)? && driver.user_password_rehash(user, password, hash)? {
+        audit.create(
+            driver,
+            AuditType::UserPasswordRehash.to_string(),
+            Some(user.id.to_string()),
+            None,
+        )?;
+    }
+    Ok(())
+}
+
+/// Check password against service and global user password policy.
+/// Password is reused if it matches the current user password or one of
+/// the policy number of previous passwords.
+pub fn user_password_policy_check(
+    driver: &Postgres,
+    policy: &UserPasswordPolicy,
+    service: Option<&Service>,
+    user: Option<&User>,
+    field: &'static str,
+    password: &str,
+    meta: &UserPasswordMeta,
+) -> DriverResult<()> {
+    let policy = match service {
+        Some(service) => policy.merge(&service.password_policy()),
+        None => *policy,
+    };
+    let reused = match user {
+        Some(user) => {
+            if user.password_check(password).is_ok() {
+                true
+            } else {
+                let conn = driver.conn()?;
+                UserPasswordHistory::reused(&conn, user.id, password, policy.history)?
+            }
+        }
+        None => false,
+    };
+    policy.check(field, meta, reused)
+}
+
+/// Check user password age against service and global user password policy.
+/// If password has expired, sets user password update required flag and returns error.
+pub fn user_password_age_check(
+    driver: &Postgres,
+    policy: &UserPasswordPolicy,
+    service: &Service,
+    user: &User,
+) -> DriverResult<()> {
+    let policy = policy.merge(&service.password_policy());
+    if policy.password_expired(user, Utc::now()) {
+        let update = UserUpdate::new_id(user.id).set_password_require_update(true);
+        driver.user_update(&update)?;
+        return Err(DriverError::UserPasswordUpdateRequired);
+    }
+    Ok(())
+}
+
+/// Read user by email address if email and audit remote are not locked out by
+/// failed logins, unknown emails are counted as failed logins so lockout does
+/// not reveal whether user exists.
+pub fn user_read_email_lockout(
+    driver: &Postgres,
+    audit: &mut AuditBuilder,
+    options: &GrpcServerOptionsLockout,
+    service: &Service,
+    email: &str,
+) -> DriverResult<User> {
+    let conn = driver.conn()?;
+    LoginLockout::check(&conn, email, audit.meta().remote())?;
+    match user_read_email_checked(driver, Some(service), audit, email) {
+        Err(DriverError::UserNotFound) => {
+            user_login_failure(driver, audit, options, email)?;
+            Err(DriverError::UserNotFound)
+        }
+        res => res,
+    }
+}
+
+/// Check user password, incorrect passwords are counted as failed logins.
+pub fn user_password_check_lockout(
+    driver: &Postgres,
+    audit: &mut AuditBuilder,
+    options: &GrpcServerOptionsLockout,
+    hash: &UserPasswordHash,
+    user: &User,
+    password: &str,
+) -> DriverResult<()> {
+    let res = user_password_check(driver, audit, hash, user, password);
+    user_login_lockout(driver, audit, options, user, res)
+}
+
+/// Returns result of user login check, errors are counted as failed logins.
+pub fn user_login_lockout<T>(
+    driver: &Postgres,
+    audit: &mut AuditBuilder,
+    options: &GrpcServerOptionsLockout,
+    user: &User,
+    res: DriverResult<T>,
+) -> DriverResult<T> {
+    if res.is_err() {
+        user_login_failure(driver, audit, options, &user.email)?;
+    }
+    res
+}
+
+/// Reset failed logins for user email after login succeeds.
+pub fn user_login_success(driver: &Postgres, user: &User) -> DriverResult<()> {
+    let conn = driver.conn()?;
+    LoginLockout::success(&conn, &user.email)
+}
+
+/// Count failed login for user email and audit remote.
+/// If login is now locked out, also creates audit log.
+pub fn user_login_failure(
+    driver: &Postgres,
+    audit: &mut AuditBuilder,
+    options: &GrpcServerOptionsLockout,
+    email: &str,
+) -> DriverResult<()> {
+    let conn = driver.conn()?;
+    if LoginLockout::failure(&conn, options, email, audit.meta().remote())? {
+        audit.create(
+            driver,
+            AuditType::AuthLocalLoginLockout.to_string(),
+            Some(email.to_owned()),
+            None,
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns second factor key types required to login user with service MFA policy,
+/// or none if login does not require second factor.
+/// Forbidden if policy is required and user has no second factor keys.
+pub fn user_login_mfa(
+    driver: &Postgres,
+    service: &Service,
+    user: &User,
+) -> DriverResult<Option<Vec<KeyType>>> {
+    if service.mfa_policy == ServiceMfaPolicy::Off {
+        return Ok(None);
+    }
+
+    let mut key_types = Vec::new();
+    let totp = driver.key_read(
+        &KeyRead::user_id(service.id, user.id, true, false, KeyType::Totp),
+        None,
+    )?;
+    if totp.is_some() {
+        key_types.push(KeyType::Totp);
+    }
+    let webauthn = driver.key_webauthn_list(&KeyWebauthnList {
+        service_id: service.id,
+        user_id: user.id,
+    })?;
+    if !webauthn.is_empty() {
+        key_types.push(KeyType::WebAuthn);
+    }
+
+    match (service.mfa_policy, key_types.is_empty()) {
+        (ServiceMfaPolicy::Required, true) => Err(DriverError::UserMfaRequired),
+        (_, true) => Ok(None),
+        (_, false) => Ok(Some(key_types)),
+    }
+}
+
 /// Read key by user reference and key type.
 /// Also checks key is enabled and not revoked, returns bad request if disabled.
 pub fn key_read_user_checked(
@@ -278,7 +580,7 @@ pub fn key_read_user_checked(
     } else if key.is_revoked {
         Err(DriverError::KeyRevoked)
     } else {
-        Ok(key)
+        key_check_expired(key)
     }
 }
 
@@ -325,6 +627,8 @@ where
     } else if key.is_revoked {
         Err(DriverError::KeyRevoked)
     } else {
+        let key = key_check_expired(key)?;
+        key_last_used(driver, audit, &key)?;
         Ok(key)
     }
 }
@@ -348,13 +652,66 @@ pub fn key_read_user_value_unchecked(
     Ok(key)
 }
 
+/// Safely decode refresh token for user with key, returns token family ID.
+/// If refresh token was reused its family is revoked, also creates audit log.
+pub fn user_token_refresh_decode(
+    driver: &Postgres,
+    service: &Service,
+    audit: &mut AuditBuilder,
+    user: &User,
+    key: &KeyWithValue,
+    token: &str,
+) -> DriverResult<Uuid> {
+    let conn = driver.conn()?;
+    match Jwt::decode_refresh(&conn, service, user, key, token) {
+        Err(DriverError::JwtRefreshTokenReused(family_id)) => {
+            audit.create(
+                driver,
+                AuditType::AuthTokenRefreshReuse.to_string(),
+                Some(family_id.to_string()),
+                None,
+            )?;
+            Err(DriverError::JwtRefreshTokenReused(family_id))
+        }
+        res => res,
+    }
+}
+
+/// Create CSRF token with serialised state value, returns CSRF key.
+pub fn csrf_create<T: serde::Serialize>(
+    driver: &Postgres,
+    service: &Service,
+    state: &T,
+) -> DriverResult<String> {
+    let value = serde_json::to_string(state).map_err(DriverError::SerdeJson)?;
+    let conn = driver.conn()?;
+    let csrf = CsrfCreate::generate_value(
+        &conn,
+        value,
+        Duration::seconds(DEFAULT_CSRF_EXPIRES_S),
+        service.id,
+    )?;
+    Ok(csrf.key().to_owned())
+}
+
+/// Verify CSRF token and deserialise state value.
+pub fn csrf_read<T>(driver: &Postgres, service: &Service, csrf_key: &str) -> DriverResult<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let conn = driver.conn()?;
+    let csrf = CsrfVerify::verify(&conn, service.id, Some(csrf_key.to_owned()))?;
+    serde_json::from_str(csrf.value()).map_err(DriverError::SerdeJson)
+}
+
 /// Password strength and pwned checks.
 ///
 /// If password is empty, returns 0 for strength and true for pwned.
 /// If password is none, returns none for strength and pwned.
+/// If pwned passwords backend is none, returns none for pwned.
 pub async fn password_meta(
     client: &Client,
-    enabled: bool,
+    pwned_passwords: Option<PwnedPasswords>,
     password: Option<String>,
 ) -> DriverResult<UserPasswordMeta> {
     match password.as_ref().map(|x| &**x) {
@@ -367,7 +724,8 @@ pub async fn password_meta(
                     None
                 }
             };
-            let password_pwned = match password_meta_pwned(client, enabled, password).await {
+            let password_pwned = match password_meta_pwned(client, pwned_passwords, password).await
+            {
                 Ok(password_pwned) => Some(password_pwned),
                 Err(err) => {
                     warn!("{}", err);
@@ -391,31 +749,31 @@ fn password_meta_strength(password: &str) -> DriverResult<zxcvbn::Entropy> {
 
 /// Returns true if password is present in `Pwned Passwords` index, else false.
 /// <https://haveibeenpwned.com/Passwords>
-async fn password_meta_pwned(client: &Client, enabled: bool, password: &str) -> DriverResult<bool> {
-    if enabled {
-        // Make request to API using first 5 characters of SHA1 password hash.
-        let mut hash = Sha1::new();
-        hash.update(password);
-        let hash = format!("{:X}", hash.finalize());
-        let url = format!("https://api.pwnedpasswords.com/range/{:.5}", hash);
-
-        match Url::parse(&url).map_err(DriverError::UrlParse) {
-            Ok(url) => {
-                let res = client.get(url).send().await.map_err(DriverError::Reqwest)?;
-                let res = res.error_for_status().map_err(DriverError::Reqwest)?;
-                let text = res.text().await.map_err(DriverError::Reqwest)?;
-
-                // Compare suffix of hash to lines to determine if password is pwned.
-                for line in text.lines() {
-                    if hash[5..] == line[..35] {
-                        return Ok(true);
-                    }
+async fn password_meta_pwned(
+    client: &Client,
+    pwned_passwords: Option<PwnedPasswords>,
+    password: &str,
+) -> DriverResult<bool> {
+    let hash = PwnedPasswords::hash(password);
+    match pwned_passwords {
+        Some(PwnedPasswords::Api) => {
+            // Make request to API using first 5 characters of SHA1 password hash.
+            let url = format!("https://api.pwnedpasswords.com/range/{:.5}", hash);
+            let url = Url::parse(&url).map_err(DriverError::UrlParse)?;
+            let res = client.get(url).send().await.map_err(DriverError::Reqwest)?;
+            let res = res.error_for_status().map_err(DriverError::Reqwest)?;
+            let text = res.text().await.map_err(DriverError::Reqwest)?;
+
+            // Compare suffix of hash to lines to determine if password is pwned.
+            for line in text.lines() {
+                if hash[5..] == line[..35] {
+                    return Ok(true);
                 }
-                Ok(false)
             }
-            Err(e) => Err(e),
+            Ok(false)
         }
-    } else {
-        Err(DriverError::PwnedPasswordsDisabled)
+        Some(PwnedPasswords::File(file)) => blocking(move || file.contains(&hash)).await,
+        Some(PwnedPasswords::Bloom(bloom)) => bloom.contains(&hash),
+        None => Err(DriverError::PwnedPasswordsDisabled),
     }
 }
@@ -2,7 +2,9 @@ mod diesel_admin;
 mod model;
 
 use crate::{
-    driver::postgres::model::{ModelAudit, ModelKey, ModelService, ModelUser},
+    driver::postgres::model::{
+        ModelAudit, ModelKey, ModelKeyDataKey, ModelKeyWebauthn, ModelRole, ModelService, ModelUser,
+    },
     prelude::*,
 };
 use chrono::{DateTime, Utc};
@@ -18,11 +20,13 @@ type PooledConnection = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
 #[derive(Clone)]
 pub struct Postgres {
     pool: r2d2::Pool<ConnectionManager<PgConnection>>,
+    secret: KeySecret,
+    data_keys: KeyDataKeys,
 }
 
 impl fmt::Debug for Postgres {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Postgres {{ pool }}")
+        write!(f, "Postgres {{ pool, secret, data_keys }}")
     }
 }
 
@@ -30,20 +34,39 @@ impl fmt::Debug for Postgres {
 pub type PostgresLockFn = Box<dyn FnOnce(&PgConnection) -> DriverResult<bool>>;
 
 impl Postgres {
-    /// Initialise driver with connection URL and number of pooled connections.
-    pub fn initialise(url: &str, connections: Option<u32>) -> DriverResult<Self> {
+    /// Initialise driver with connection URL, number of pooled connections
+    /// and master key which wraps data keys used to protect key values at rest.
+    pub fn initialise(
+        url: &str,
+        connections: Option<u32>,
+        secret: KeySecret,
+    ) -> DriverResult<Self> {
         let manager = ConnectionManager::<PgConnection>::new(url);
         let mut pool = r2d2::Pool::builder();
         if let Some(connections) = connections {
             pool = pool.max_size(connections);
         }
         let pool = pool.build(manager).map_err(DriverError::R2d2)?;
-        let driver = Postgres { pool };
-        driver.run_migrations()?;
-        Ok(driver)
+        let data_keys = {
+            let connection = pool.get().map_err(DriverError::R2d2)?;
+            Self::run_migrations(&connection)?;
+            Self::run_key_migrations(&connection, &secret)?
+        };
+        Ok(Postgres {
+            pool,
+            secret,
+            data_keys,
+        })
     }
 
-    pub fn from_env<T>(url_name: T, connections_name: T) -> Self
+    /// Initialise driver from environment, master key is read from
+    /// environment variable or file.
+    pub fn from_env<T>(
+        url_name: T,
+        connections_name: T,
+        secret_name: T,
+        secret_file_name: T,
+    ) -> Self
     where
         T: AsRef<str>,
     {
@@ -51,16 +74,66 @@ impl Postgres {
             .expect("Failed to read postgres URL environment variable.");
         let connections = env::value_opt::<u32>(connections_name.as_ref())
             .expect("Failed to read postgres connections environment variable.");
-        Self::initialise(&url, connections).expect("Failed to initialise postgres connection.")
+        let secret = env::string_opt(secret_name.as_ref());
+        let secret_file = env::string_opt(secret_file_name.as_ref());
+        let secret = match (secret, secret_file) {
+            (Some(secret), None) => KeySecret::from_base64(&secret),
+            (None, Some(secret_file)) => KeySecret::from_file(secret_file),
+            (Some(_), Some(_)) => panic!("Key secret and key secret file are both defined."),
+            (None, None) => panic!("Failed to read key secret environment variable."),
+        }
+        .expect("Failed to read key secret.");
+        Self::initialise(&url, connections, secret)
+            .expect("Failed to initialise postgres connection.")
     }
 
     pub fn conn(&self) -> DriverResult<PooledConnection> {
         self.pool.get().map_err(DriverError::R2d2)
     }
 
-    fn run_migrations(&self) -> DriverResult<()> {
-        let connection = self.conn()?;
-        embedded_migrations::run(&connection).map_err(DriverError::DieselMigrations)
+    /// Returns data keys which protect key values and signing keys at rest.
+    pub fn data_keys(&self) -> &KeyDataKeys {
+        &self.data_keys
+    }
+
+    /// Wrap data keys with new master key, returns number of data keys.
+    ///
+    /// Plaintext signing key private keys are encrypted by data keys first,
+    /// so they are protected by the new master key.
+    /// Servers must be restarted with the new master key, data keys wrapped
+    /// by the previous master key cannot be unwrapped.
+    pub fn key_secret_rotate(&self, secret: &KeySecret) -> DriverResult<usize> {
+        let conn = self.conn()?;
+        Self::run_jwt_key_migrations(&conn, &self.data_keys)?;
+        ModelKeyDataKey::rewrap(&conn, &self.secret, secret)
+    }
+
+    fn run_migrations(connection: &PgConnection) -> DriverResult<()> {
+        embedded_migrations::run(connection).map_err(DriverError::DieselMigrations)
+    }
+
+    fn run_key_migrations(
+        connection: &PgConnection,
+        secret: &KeySecret,
+    ) -> DriverResult<KeyDataKeys> {
+        let data_keys = ModelKeyDataKey::initialise(connection, secret)?;
+        let count = ModelKey::value_migrate(connection, &data_keys)?;
+        if count > 0 {
+            info!("Protected {} plaintext key values", count);
+        }
+        Self::run_jwt_key_migrations(connection, &data_keys)?;
+        Ok(data_keys)
+    }
+
+    fn run_jwt_key_migrations(
+        connection: &PgConnection,
+        data_keys: &KeyDataKeys,
+    ) -> DriverResult<()> {
+        let count = JwtKey::private_key_migrate(connection, data_keys)?;
+        if count > 0 {
+            info!("Protected {} plaintext signing keys", count);
+        }
+        Ok(())
     }
 }
 
@@ -171,9 +244,10 @@ impl Postgres {
     ///
     /// Returns error if more than one `Token` or `Totp` type would be enabled for user keys.
     /// Returns error if related service or user does not exist.
+    /// Returns error if key type is `WebAuthn`, use `key_webauthn_create` instead.
     pub fn key_create(&self, create: &KeyCreate) -> DriverResult<KeyWithValue> {
         let conn = self.conn()?;
-        ModelKey::create(&conn, create)
+        ModelKey::create(&conn, &self.data_keys, create)
     }
 
     /// Read key.
@@ -183,7 +257,7 @@ impl Postgres {
         service_id: Option<Uuid>,
     ) -> DriverResult<Option<KeyWithValue>> {
         let conn = self.conn()?;
-        ModelKey::read(&conn, read, service_id)
+        ModelKey::read(&conn, &self.data_keys, read, service_id)
     }
 
     /// Update key.
@@ -198,12 +272,114 @@ impl Postgres {
         ModelKey::update_many(&conn, user_id, update)
     }
 
+    /// Rotate key, creates successor key and previous key expires when grace period ends.
+    ///
+    /// Returns previous key and successor key.
+    pub fn key_rotate(
+        &self,
+        rotate: &KeyRotate,
+        service_id: Option<Uuid>,
+    ) -> DriverResult<(Key, KeyWithValue)> {
+        let conn = self.conn()?;
+        ModelKey::rotate(&conn, &self.data_keys, rotate, service_id)
+    }
+
+    /// Disable and revoke expired keys, returns number of keys revoked.
+    /// Expired keys already fail authentication, `expires_at` enforces rotation grace periods.
+    pub fn key_revoke_expired(&self) -> DriverResult<usize> {
+        let conn = self.conn()?;
+        ModelKey::revoke_expired(&conn)
+    }
+
+    /// Update key last used date and time and remote, returns number of keys updated.
+    pub fn key_update_last_used(&self, id: &Uuid, remote: &str) -> DriverResult<usize> {
+        let conn = self.conn()?;
+        ModelKey::update_last_used(&conn, id, remote)
+    }
+
     /// Delete key.
     pub fn key_delete(&self, id: &Uuid) -> DriverResult<usize> {
         let conn = self.conn()?;
         ModelKey::delete(&conn, id)
     }
 
+    /// Create TOTP user key with parameters and recovery codes, existing enabled
+    /// TOTP key of user is revoked.
+    ///
+    /// Returns key and recovery codes.
+    pub fn key_totp_create(
+        &self,
+        create: &KeyCreate,
+        params: &TotpParams,
+        last_step: Option<i64>,
+    ) -> DriverResult<(KeyWithValue, Vec<String>)> {
+        let conn = self.conn()?;
+        conn.transaction(|| {
+            if let (Some(service_id), Some(user_id)) = (create.service_id, create.user_id) {
+                let read = KeyRead::user_id(service_id, user_id, true, false, KeyType::Totp);
+                if let Some(existing) = ModelKey::read(&conn, &self.data_keys, &read, None)? {
+                    ModelKey::update(
+                        &conn,
+                        &KeyUpdate {
+                            id: existing.id,
+                            is_enabled: Some(false),
+                            is_revoked: Some(true),
+                            name: None,
+                            expires_at: None,
+                            scopes: None,
+                            role: None,
+                        },
+                    )?;
+                }
+            }
+
+            let key = ModelKey::create(&conn, &self.data_keys, create)?;
+            KeyTotp::create(&conn, key.id, params, last_step)?;
+            let recovery_codes = TotpRecovery::generate(&conn, key.id)?;
+            Ok((key, recovery_codes))
+        })
+    }
+
+    /// List WebAuthn credentials of enabled and not revoked user keys.
+    pub fn key_webauthn_list(&self, list: &KeyWebauthnList) -> DriverResult<Vec<KeyWebauthn>> {
+        let conn = self.conn()?;
+        ModelKeyWebauthn::list(&conn, list)
+    }
+
+    /// Create WebAuthn user key with credential.
+    pub fn key_webauthn_create(&self, create: &KeyWebauthnCreate) -> DriverResult<Key> {
+        let conn = self.conn()?;
+        ModelKeyWebauthn::create(&conn, &self.data_keys, create)
+    }
+
+    /// Update WebAuthn credential and signature counter.
+    pub fn key_webauthn_update(&self, update: &KeyWebauthnUpdate) -> DriverResult<KeyWebauthn> {
+        let conn = self.conn()?;
+        ModelKeyWebauthn::update(&conn, update)
+    }
+
+    // --------------
+    // Role Functions
+    // --------------
+
+    /// List roles.
+    pub fn role_list(&self) -> DriverResult<Vec<Role>> {
+        let conn = self.conn()?;
+        ModelRole::list(&conn)
+    }
+
+    /// Create role, permissions of existing role are replaced.
+    pub fn role_create(&self, create: &RoleCreate) -> DriverResult<Role> {
+        let conn = self.conn()?;
+        ModelRole::create(&conn, create)
+    }
+
+    /// Read role.
+    pub fn role_read(&self, name: &str) -> DriverResult<Option<Role>> {
+        let conn = self.conn()?;
+        ModelRole::read(&conn, name)
+    }
+
     // -----------------
     // Service Functions
     // -----------------
@@ -276,6 +452,29 @@ impl Postgres {
         ModelUser::update(&conn, update)
     }
 
+    /// Rehash user password with hash algorithm and parameters.
+    /// Returns false if user password hash has changed since user was read.
+    pub fn user_password_rehash(
+        &self,
+        user: &User,
+        password: &str,
+        hash: &UserPasswordHash,
+    ) -> DriverResult<bool> {
+        let password_hash = user
+            .password_hash
+            .as_ref()
+            .ok_or(DriverError::UserPasswordUndefined)?;
+        let new_password_hash = hash.hash(password)?;
+        let conn = self.conn()?;
+        ModelUser::update_password_hash(&conn, &user.id, password_hash, &new_password_hash)
+    }
+
+    /// Count users with password hash not created by hash algorithm and parameters.
+    pub fn user_password_legacy_count(&self, hash: &UserPasswordHash) -> DriverResult<i64> {
+        let conn = self.conn()?;
+        ModelUser::count_password_legacy(&conn, hash)
+    }
+
     /// Delete user.
     pub fn user_delete(&self, id: &Uuid) -> DriverResult<usize> {
         let conn = self.conn()?;
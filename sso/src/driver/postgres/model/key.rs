@@ -1,11 +1,12 @@
 use crate::{
-    driver::postgres::model::{ModelService, ModelUser},
+    driver::postgres::model::{ModelRole, ModelService, ModelUser},
     prelude::*,
     schema::sso_key,
 };
 use diesel::{dsl::sql, prelude::*, sql_types::BigInt, PgConnection};
+use std::fmt;
 
-#[derive(Debug, Identifiable, Queryable)]
+#[derive(Identifiable, Queryable)]
 #[table_name = "sso_key"]
 #[primary_key(id)]
 pub struct ModelKey {
@@ -19,6 +20,34 @@ pub struct ModelKey {
     value: String,
     service_id: Option<Uuid>,
     user_id: Option<Uuid>,
+    value_prefix: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    scopes: Vec<String>,
+    last_used_at: Option<DateTime<Utc>>,
+    last_used_remote: Option<String>,
+    role: Option<String>,
+}
+
+impl fmt::Debug for ModelKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModelKey")
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .field("id", &self.id)
+            .field("is_enabled", &self.is_enabled)
+            .field("is_revoked", &self.is_revoked)
+            .field("type_", &self.type_)
+            .field("name", &self.name)
+            .field("service_id", &self.service_id)
+            .field("user_id", &self.user_id)
+            .field("value_prefix", &self.value_prefix)
+            .field("expires_at", &self.expires_at)
+            .field("scopes", &self.scopes)
+            .field("last_used_at", &self.last_used_at)
+            .field("last_used_remote", &self.last_used_remote)
+            .field("role", &self.role)
+            .finish()
+    }
 }
 
 impl From<ModelKey> for Key {
@@ -33,23 +62,41 @@ impl From<ModelKey> for Key {
             name: key.name,
             service_id: key.service_id,
             user_id: key.user_id,
+            expires_at: key.expires_at,
+            scopes: key.scopes,
+            last_used_at: key.last_used_at,
+            last_used_remote: key.last_used_remote,
+            role: key.role,
         }
     }
 }
 
-impl From<ModelKey> for KeyWithValue {
-    fn from(key: ModelKey) -> Self {
-        Self {
-            created_at: key.created_at,
-            updated_at: key.updated_at,
-            id: key.id,
-            is_enabled: key.is_enabled,
-            is_revoked: key.is_revoked,
-            type_: KeyType::from_str(&key.type_).unwrap(),
-            name: key.name,
-            value: key.value,
-            service_id: key.service_id,
-            user_id: key.user_id,
+impl ModelKey {
+    /// Returns key with value recovered from stored value.
+    fn into_key_with_value(self, data_keys: &KeyDataKeys) -> DriverResult<KeyWithValue> {
+        let type_ = KeyType::from_str(&self.type_).unwrap();
+        let value = data_keys.value_recover(type_, self.id, &self.value)?;
+        Ok(self.key_with_value(value))
+    }
+
+    /// Returns key with known plaintext value.
+    fn key_with_value(self, value: String) -> KeyWithValue {
+        KeyWithValue {
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            id: self.id,
+            is_enabled: self.is_enabled,
+            is_revoked: self.is_revoked,
+            type_: KeyType::from_str(&self.type_).unwrap(),
+            name: self.name,
+            value,
+            service_id: self.service_id,
+            user_id: self.user_id,
+            expires_at: self.expires_at,
+            scopes: self.scopes,
+            last_used_at: self.last_used_at,
+            last_used_remote: self.last_used_remote,
+            role: self.role,
         }
     }
 }
@@ -67,6 +114,10 @@ struct ModelKeyInsert<'a> {
     value: &'a str,
     service_id: Option<&'a Uuid>,
     user_id: Option<&'a Uuid>,
+    value_prefix: Option<&'a str>,
+    expires_at: Option<&'a DateTime<Utc>>,
+    scopes: &'a [String],
+    role: Option<&'a str>,
 }
 
 #[derive(AsChangeset)]
@@ -76,6 +127,9 @@ struct ModelKeyUpdate<'a> {
     is_enabled: Option<bool>,
     is_revoked: Option<bool>,
     name: Option<&'a str>,
+    expires_at: Option<&'a DateTime<Utc>>,
+    scopes: Option<&'a [String]>,
+    role: Option<&'a str>,
 }
 
 impl<'a> ModelKeyUpdate<'a> {
@@ -85,6 +139,9 @@ impl<'a> ModelKeyUpdate<'a> {
             is_enabled: update.is_enabled,
             is_revoked: update.is_revoked,
             name: update.name.as_ref().map(|x| &**x),
+            expires_at: update.expires_at.as_ref(),
+            scopes: update.scopes.as_deref(),
+            role: update.role.as_deref(),
         }
     }
 }
@@ -121,6 +178,21 @@ impl ModelKey {
             let user_id: Vec<Uuid> = user_id.iter().copied().collect();
             query = query.filter(sso_key::dsl::user_id.eq(any(user_id)));
         }
+        if let Some(days) = list.filter.expires_within_days {
+            let expires_before = Utc::now() + Duration::days(days as i64);
+            query = query.filter(sso_key::dsl::expires_at.le(expires_before));
+        }
+        if let Some(days) = list.filter.unused_for_days {
+            // Keys never used are unused since they were created.
+            let used_before = Utc::now() - Duration::days(days as i64);
+            query = query.filter(
+                sso_key::dsl::last_used_at
+                    .le(used_before)
+                    .or(sso_key::dsl::last_used_at
+                        .is_null()
+                        .and(sso_key::dsl::created_at.le(used_before))),
+            );
+        }
         if let Some(service_id_mask) = service_id {
             query = query.filter(sso_key::dsl::service_id.eq(service_id_mask));
         }
@@ -161,7 +233,23 @@ impl ModelKey {
         .map(|x| x as usize)
     }
 
-    pub fn create(conn: &PgConnection, create: &KeyCreate) -> DriverResult<KeyWithValue> {
+    pub fn create(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        create: &KeyCreate,
+    ) -> DriverResult<KeyWithValue> {
+        // WebAuthn keys are created with a credential by `ModelKeyWebauthn`.
+        if create.type_ == KeyType::WebAuthn {
+            return Err(DriverError::KeyUserWebauthnCredentialRequired);
+        }
+        Self::create_inner(conn, data_keys, create)
+    }
+
+    pub fn create_inner(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        create: &KeyCreate,
+    ) -> DriverResult<KeyWithValue> {
         if create.is_enabled {
             if create.type_ == KeyType::Token {
                 let count = Self::count_token(
@@ -191,9 +279,14 @@ impl ModelKey {
         if let Some(user_id) = &create.user_id {
             ModelUser::read(conn, &UserRead::Id(*user_id))?.ok_or(DriverError::UserNotFound)?;
         }
+        if let Some(role) = &create.role {
+            ModelRole::read(conn, role)?.ok_or(DriverError::RoleNotFound)?;
+        }
 
-        let now = Utc::now();
+        // Plaintext value is returned once and never stored.
         let id = Uuid::new_v4();
+        let (stored, prefix) = data_keys.value_protect(create.type_, id, &create.value)?;
+        let now = Utc::now();
         let value = ModelKeyInsert {
             created_at: &now,
             updated_at: &now,
@@ -202,34 +295,190 @@ impl ModelKey {
             is_revoked: create.is_revoked,
             type_: create.type_.to_string(),
             name: &create.name,
-            value: &create.value,
+            value: &stored,
             service_id: create.service_id.as_ref(),
             user_id: create.user_id.as_ref(),
+            value_prefix: prefix.as_deref(),
+            expires_at: create.expires_at.as_ref(),
+            scopes: &create.scopes,
+            role: create.role.as_deref(),
         };
         diesel::insert_into(sso_key::table)
             .values(&value)
             .get_result::<ModelKey>(conn)
             .map_err(Into::into)
-            .map(Into::into)
+            .map(|x| x.key_with_value(create.value.clone()))
     }
 
     pub fn read(
         conn: &PgConnection,
+        data_keys: &KeyDataKeys,
         read: &KeyRead,
         service_id: Option<Uuid>,
     ) -> DriverResult<Option<KeyWithValue>> {
-        match read {
-            KeyRead::IdUser(id, user_id) => Self::read_by_id(conn, id, &service_id, user_id),
-            KeyRead::RootId(id) => Self::read_by_root_id(conn, *id),
-            KeyRead::RootValue(value) => Self::read_by_root_value(conn, value),
-            KeyRead::ServiceId(service_id, id) => Self::read_by_service_id(conn, *service_id, *id),
-            KeyRead::ServiceValue(value) => Self::read_by_service_value(conn, value),
-            KeyRead::UserId(r) => Self::read_by_user_id(conn, r),
-            KeyRead::UserValue(r) => Self::read_by_user_value(conn, r),
+        let key = match read {
+            KeyRead::IdUser(id, user_id) => Self::read_by_id(conn, id, &service_id, user_id)?,
+            KeyRead::RootId(id) => Self::read_by_root_id(conn, *id)?,
+            KeyRead::ServiceId(service_id, id) => Self::read_by_service_id(conn, *service_id, *id)?,
+            KeyRead::UserId(r) => Self::read_by_user_id(conn, r)?,
+            // Values are hashed, keys are read by prefix and compared.
+            KeyRead::RootValue(value) => return Self::read_by_root_value(conn, data_keys, value),
+            KeyRead::ServiceValue(value) => {
+                return Self::read_by_service_value(conn, data_keys, value)
+            }
+            KeyRead::UserValue(r) => return Self::read_by_user_value(conn, data_keys, r),
+        };
+        key.map(|x| x.into_key_with_value(data_keys)).transpose()
+    }
+
+    /// Create successor of key, previous key expires when grace period ends.
+    pub fn rotate(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        rotate: &KeyRotate,
+        service_id_mask: Option<Uuid>,
+    ) -> DriverResult<(Key, KeyWithValue)> {
+        conn.transaction(|| {
+            let previous = sso_key::table
+                .filter(sso_key::dsl::id.eq(rotate.id))
+                .for_update()
+                .get_result::<ModelKey>(conn)
+                .optional()?
+                .filter(|x| service_id_mask.is_none() || x.service_id == service_id_mask)
+                .ok_or(DriverError::KeyNotFound)?;
+            let previous: Key = previous.into();
+            if previous.type_ != KeyType::Key {
+                return Err(DriverError::KeyRotateTypeInvalid);
+            } else if !previous.is_enabled {
+                return Err(DriverError::KeyDisabled);
+            } else if previous.is_revoked {
+                return Err(DriverError::KeyRevoked);
+            }
+
+            let now = Utc::now();
+            let expires_at = now + Duration::hours(rotate.grace_period_hours as i64);
+            let expires_at = match previous.expires_at {
+                Some(x) if x <= now => return Err(DriverError::KeyExpired),
+                Some(x) if x < expires_at => x,
+                _ => expires_at,
+            };
+
+            let create = match (previous.service_id, previous.user_id) {
+                (Some(service_id), Some(user_id)) => {
+                    KeyCreate::user(true, KeyType::Key, &previous.name, service_id, user_id)
+                }
+                (Some(service_id), None) => KeyCreate::service(true, &previous.name, service_id),
+                _ => KeyCreate::root(true, &previous.name),
+            }
+            .scopes(previous.scopes.clone())
+            .role(previous.role.clone());
+            let key = Self::create_inner(conn, data_keys, &create)?;
+
+            let previous = diesel::update(sso_key::table.filter(sso_key::dsl::id.eq(previous.id)))
+                .set((
+                    sso_key::dsl::updated_at.eq(now),
+                    sso_key::dsl::expires_at.eq(expires_at),
+                ))
+                .get_result::<ModelKey>(conn)?;
+            Ok((previous.into(), key))
+        })
+    }
+
+    /// Disable and revoke keys which have expired and are not revoked,
+    /// returns number of keys updated.
+    pub fn revoke_expired(conn: &PgConnection) -> DriverResult<usize> {
+        let now = Utc::now();
+        diesel::update(
+            sso_key::table.filter(
+                sso_key::dsl::expires_at
+                    .le(now)
+                    .and(sso_key::dsl::is_revoked.eq(false)),
+            ),
+        )
+        .set((
+            sso_key::dsl::updated_at.eq(now),
+            sso_key::dsl::is_enabled.eq(false),
+            sso_key::dsl::is_revoked.eq(true),
+        ))
+        .execute(conn)
+        .map_err(Into::into)
+    }
+
+    /// Protect plaintext values of keys created before values were hashed or
+    /// encrypted at rest, returns number of keys updated.
+    pub fn value_migrate(conn: &PgConnection, data_keys: &KeyDataKeys) -> DriverResult<usize> {
+        use diesel::dsl::any;
+
+        conn.transaction(|| {
+            let type_: Vec<String> = vec![KeyType::Key, KeyType::Token, KeyType::Totp]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect();
+            let keys = sso_key::table
+                .filter(
+                    sso_key::dsl::type_
+                        .eq(any(type_))
+                        .and(sso_key::dsl::value.not_like("$%")),
+                )
+                .for_update()
+                .load::<ModelKey>(conn)?;
+
+            for key in keys.iter() {
+                let type_ = KeyType::from_str(&key.type_).unwrap();
+                let (stored, prefix) = data_keys.value_protect(type_, key.id, &key.value)?;
+                diesel::update(sso_key::table.filter(sso_key::dsl::id.eq(key.id)))
+                    .set((
+                        sso_key::dsl::value.eq(stored),
+                        sso_key::dsl::value_prefix.eq(prefix),
+                    ))
+                    .execute(conn)?;
+            }
+            Ok(keys.len())
+        })
+    }
+
+    /// Count keys with values protected before data keys were introduced.
+    pub fn value_legacy_count(conn: &PgConnection) -> DriverResult<i64> {
+        sso_key::table
+            .select(sql::<BigInt>("count(*)"))
+            .filter(
+                sso_key::dsl::value
+                    .like("$%")
+                    .and(sso_key::dsl::value.not_like("$%$%$%")),
+            )
+            .get_result::<i64>(conn)
+            .map_err(Into::into)
+    }
+
+    /// Upgrade values protected before data keys were introduced by legacy
+    /// data key, returns number of keys updated.
+    pub fn value_legacy_upgrade(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        legacy: &KeyDataKey,
+    ) -> DriverResult<usize> {
+        let keys = sso_key::table
+            .filter(
+                sso_key::dsl::value
+                    .like("$%")
+                    .and(sso_key::dsl::value.not_like("$%$%$%")),
+            )
+            .for_update()
+            .load::<ModelKey>(conn)?;
+
+        for key in keys.iter() {
+            let stored = data_keys.value_legacy_upgrade(legacy, key.id, &key.value)?;
+            diesel::update(sso_key::table.filter(sso_key::dsl::id.eq(key.id)))
+                .set(sso_key::dsl::value.eq(stored))
+                .execute(conn)?;
         }
+        Ok(keys.len())
     }
 
     pub fn update(conn: &PgConnection, update: &KeyUpdate) -> DriverResult<Key> {
+        if let Some(role) = &update.role {
+            ModelRole::read(conn, role)?.ok_or(DriverError::RoleNotFound)?;
+        }
         let now = chrono::Utc::now();
         let value = ModelKeyUpdate::from_update(&now, update);
         diesel::update(sso_key::table.filter(sso_key::dsl::id.eq(update.id)))
@@ -252,6 +501,29 @@ impl ModelKey {
             .map_err(Into::into)
     }
 
+    /// Update key last used date and time and remote, writes are skipped if
+    /// key was last used recently from the same remote.
+    pub fn update_last_used(conn: &PgConnection, id: &Uuid, remote: &str) -> DriverResult<usize> {
+        let now = Utc::now();
+        let used_before = now - Duration::seconds(KEY_LAST_USED_INTERVAL_S);
+        diesel::update(
+            sso_key::table.filter(
+                sso_key::dsl::id.eq(id).and(
+                    sso_key::dsl::last_used_at
+                        .is_null()
+                        .or(sso_key::dsl::last_used_at.lt(used_before))
+                        .or(sso_key::dsl::last_used_remote.ne(remote)),
+                ),
+            ),
+        )
+        .set((
+            sso_key::dsl::last_used_at.eq(now),
+            sso_key::dsl::last_used_remote.eq(remote),
+        ))
+        .execute(conn)
+        .map_err(Into::into)
+    }
+
     pub fn delete(conn: &PgConnection, id: &Uuid) -> DriverResult<usize> {
         diesel::delete(sso_key::table.filter(sso_key::dsl::id.eq(id)))
             .execute(conn)
@@ -291,7 +563,7 @@ impl ModelKey {
         id: &Uuid,
         service_id_mask: &Option<Uuid>,
         user_id_mask: &Option<Uuid>,
-    ) -> DriverResult<Option<KeyWithValue>> {
+    ) -> DriverResult<Option<ModelKey>> {
         let mut query = sso_key::table.into_boxed();
 
         if let Some(service_id) = service_id_mask {
@@ -306,10 +578,9 @@ impl ModelKey {
             .get_result::<ModelKey>(conn)
             .optional()
             .map_err(Into::into)
-            .map(|x| x.map(Into::into))
     }
 
-    fn read_by_root_id(conn: &PgConnection, id: Uuid) -> DriverResult<Option<KeyWithValue>> {
+    fn read_by_root_id(conn: &PgConnection, id: Uuid) -> DriverResult<Option<ModelKey>> {
         sso_key::table
             .filter(
                 sso_key::dsl::id
@@ -320,28 +591,29 @@ impl ModelKey {
             .get_result::<ModelKey>(conn)
             .optional()
             .map_err(Into::into)
-            .map(|x| x.map(Into::into))
     }
 
-    fn read_by_root_value(conn: &PgConnection, value: &str) -> DriverResult<Option<KeyWithValue>> {
-        sso_key::table
+    fn read_by_root_value(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        value: &str,
+    ) -> DriverResult<Option<KeyWithValue>> {
+        let keys = sso_key::table
             .filter(
-                sso_key::dsl::value
-                    .eq(value)
+                sso_key::dsl::value_prefix
+                    .eq(KeyDataKeys::value_prefix(value))
                     .and(sso_key::dsl::service_id.is_null())
                     .and(sso_key::dsl::user_id.is_null()),
             )
-            .get_result::<ModelKey>(conn)
-            .optional()
-            .map_err(Into::into)
-            .map(|x| x.map(Into::into))
+            .load::<ModelKey>(conn)?;
+        Self::value_find(keys, data_keys, value)
     }
 
     fn read_by_service_id(
         conn: &PgConnection,
         service_id: Uuid,
         id: Uuid,
-    ) -> DriverResult<Option<KeyWithValue>> {
+    ) -> DriverResult<Option<ModelKey>> {
         sso_key::table
             .filter(
                 sso_key::dsl::id
@@ -352,30 +624,28 @@ impl ModelKey {
             .get_result::<ModelKey>(conn)
             .optional()
             .map_err(Into::into)
-            .map(|x| x.map(Into::into))
     }
 
     fn read_by_service_value(
         conn: &PgConnection,
+        data_keys: &KeyDataKeys,
         value: &str,
     ) -> DriverResult<Option<KeyWithValue>> {
-        sso_key::table
+        let keys = sso_key::table
             .filter(
-                sso_key::dsl::value
-                    .eq(value)
+                sso_key::dsl::value_prefix
+                    .eq(KeyDataKeys::value_prefix(value))
                     .and(sso_key::dsl::service_id.is_not_null())
                     .and(sso_key::dsl::user_id.is_null()),
             )
-            .get_result::<ModelKey>(conn)
-            .optional()
-            .map_err(Into::into)
-            .map(|x| x.map(Into::into))
+            .load::<ModelKey>(conn)?;
+        Self::value_find(keys, data_keys, value)
     }
 
     fn read_by_user_id(
         conn: &PgConnection,
         read: &KeyReadUserId,
-    ) -> DriverResult<Option<KeyWithValue>> {
+    ) -> DriverResult<Option<ModelKey>> {
         let type_ = read.type_.to_string();
         sso_key::table
             .filter(
@@ -390,27 +660,39 @@ impl ModelKey {
             .get_result::<ModelKey>(conn)
             .optional()
             .map_err(Into::into)
-            .map(|x| x.map(Into::into))
     }
 
     fn read_by_user_value(
         conn: &PgConnection,
+        data_keys: &KeyDataKeys,
         read: &KeyReadUserValue,
     ) -> DriverResult<Option<KeyWithValue>> {
         let type_ = read.type_.to_string();
-        sso_key::table
+        let keys = sso_key::table
             .filter(
-                sso_key::dsl::value
-                    .eq(&read.value)
+                sso_key::dsl::value_prefix
+                    .eq(KeyDataKeys::value_prefix(&read.value))
                     .and(sso_key::dsl::service_id.eq(read.service_id))
                     .and(sso_key::dsl::user_id.is_not_null())
                     .and(sso_key::dsl::is_enabled.eq(read.is_enabled))
                     .and(sso_key::dsl::is_revoked.eq(read.is_revoked))
                     .and(sso_key::dsl::type_.eq(type_)),
             )
-            .get_result::<ModelKey>(conn)
-            .optional()
-            .map_err(Into::into)
-            .map(|x| x.map(Into::into))
+            .load::<ModelKey>(conn)?;
+        Self::value_find(keys, data_keys, &read.value)
+    }
+
+    /// Returns key with hash matching value from keys with matching prefix.
+    fn value_find(
+        keys: Vec<ModelKey>,
+        data_keys: &KeyDataKeys,
+        value: &str,
+    ) -> DriverResult<Option<KeyWithValue>> {
+        for key in keys {
+            if data_keys.value_hash_verify(value, &key.value)? {
+                return Ok(Some(key.key_with_value(value.to_owned())));
+            }
+        }
+        Ok(None)
     }
 }
@@ -0,0 +1,126 @@
+use crate::{driver::postgres::model::ModelKey, prelude::*, schema::sso_key_data_key};
+use diesel::{prelude::*, PgConnection};
+use std::fmt;
+
+#[derive(Identifiable, Queryable)]
+#[table_name = "sso_key_data_key"]
+#[primary_key(id)]
+pub struct ModelKeyDataKey {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    id: Uuid,
+    wrapped: String,
+}
+
+impl fmt::Debug for ModelKeyDataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModelKeyDataKey")
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "sso_key_data_key"]
+struct ModelKeyDataKeyInsert<'a> {
+    created_at: &'a DateTime<Utc>,
+    updated_at: &'a DateTime<Utc>,
+    id: &'a Uuid,
+    wrapped: &'a str,
+}
+
+impl ModelKeyDataKey {
+    /// Returns data keys unwrapped by master key, ordered from oldest to newest.
+    ///
+    /// Creates a random data key if none exist. If key values protected before
+    /// data keys were introduced exist, ciphertexts are encrypted with a new
+    /// random data key and hashes are verified by a hash only data key derived
+    /// from the master key, so the master key is never stored as a data key.
+    pub fn initialise(conn: &PgConnection, secret: &KeySecret) -> DriverResult<KeyDataKeys> {
+        conn.transaction(|| {
+            Self::lock(conn)?;
+            let mut keys = Self::list(conn, secret)?;
+
+            let legacy = if ModelKey::value_legacy_count(conn)? > 0 {
+                let legacy = secret.legacy_data_key()?;
+                let key = legacy.hash_only()?;
+                Self::create(conn, secret, &key)?;
+                keys.push(key);
+                let key = KeyDataKey::generate()?;
+                Self::create(conn, secret, &key)?;
+                keys.push(key);
+                Some(legacy)
+            } else {
+                None
+            };
+            if keys.iter().all(KeyDataKey::is_hash_only) {
+                let key = KeyDataKey::generate()?;
+                Self::create(conn, secret, &key)?;
+                keys.push(key);
+            }
+
+            let data_keys = KeyDataKeys::new(keys)?;
+            if let Some(legacy) = legacy {
+                ModelKey::value_legacy_upgrade(conn, &data_keys, &legacy)?;
+            }
+            Ok(data_keys)
+        })
+    }
+
+    /// Wrap data keys with new master key, returns number of data keys.
+    pub fn rewrap(
+        conn: &PgConnection,
+        secret: &KeySecret,
+        secret_new: &KeySecret,
+    ) -> DriverResult<usize> {
+        conn.transaction(|| {
+            Self::lock(conn)?;
+            let keys = Self::list(conn, secret)?;
+
+            let now = Utc::now();
+            for key in keys.iter() {
+                let wrapped = secret_new.wrap(key)?;
+                diesel::update(
+                    sso_key_data_key::table.filter(sso_key_data_key::dsl::id.eq(key.id())),
+                )
+                .set((
+                    sso_key_data_key::dsl::updated_at.eq(now),
+                    sso_key_data_key::dsl::wrapped.eq(wrapped),
+                ))
+                .execute(conn)?;
+            }
+            Ok(keys.len())
+        })
+    }
+
+    fn list(conn: &PgConnection, secret: &KeySecret) -> DriverResult<Vec<KeyDataKey>> {
+        sso_key_data_key::table
+            .order(sso_key_data_key::dsl::created_at.asc())
+            .load::<ModelKeyDataKey>(conn)?
+            .into_iter()
+            .map(|x| secret.unwrap(x.id, &x.wrapped))
+            .collect()
+    }
+
+    fn create(conn: &PgConnection, secret: &KeySecret, key: &KeyDataKey) -> DriverResult<()> {
+        let now = Utc::now();
+        let wrapped = secret.wrap(key)?;
+        let value = ModelKeyDataKeyInsert {
+            created_at: &now,
+            updated_at: &now,
+            id: &key.id(),
+            wrapped: &wrapped,
+        };
+        diesel::insert_into(sso_key_data_key::table)
+            .values(&value)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn lock(conn: &PgConnection) -> DriverResult<()> {
+        diesel::sql_query("LOCK TABLE sso_key_data_key IN EXCLUSIVE MODE").execute(conn)?;
+        Ok(())
+    }
+}
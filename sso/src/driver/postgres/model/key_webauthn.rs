@@ -0,0 +1,112 @@
+use crate::{
+    driver::postgres::model::ModelKey,
+    prelude::*,
+    schema::{sso_key, sso_key_webauthn},
+};
+use diesel::{prelude::*, PgConnection};
+use serde_json::Value;
+
+#[derive(Debug, Identifiable, Queryable)]
+#[table_name = "sso_key_webauthn"]
+#[primary_key(key_id)]
+pub struct ModelKeyWebauthn {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    key_id: Uuid,
+    credential: Value,
+    counter: i64,
+    aaguid: Uuid,
+}
+
+impl From<ModelKeyWebauthn> for KeyWebauthn {
+    fn from(key: ModelKeyWebauthn) -> Self {
+        Self {
+            created_at: key.created_at,
+            updated_at: key.updated_at,
+            key_id: key.key_id,
+            credential: key.credential,
+            counter: key.counter,
+            aaguid: key.aaguid,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "sso_key_webauthn"]
+struct ModelKeyWebauthnInsert<'a> {
+    created_at: &'a DateTime<Utc>,
+    updated_at: &'a DateTime<Utc>,
+    key_id: &'a Uuid,
+    credential: &'a Value,
+    counter: i64,
+    aaguid: &'a Uuid,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "sso_key_webauthn"]
+struct ModelKeyWebauthnUpdate<'a> {
+    updated_at: &'a DateTime<Utc>,
+    credential: &'a Value,
+    counter: i64,
+}
+
+impl ModelKeyWebauthn {
+    pub fn list(conn: &PgConnection, list: &KeyWebauthnList) -> DriverResult<Vec<KeyWebauthn>> {
+        sso_key_webauthn::table
+            .inner_join(sso_key::table)
+            .select(sso_key_webauthn::all_columns)
+            .filter(
+                sso_key::dsl::service_id
+                    .eq(list.service_id)
+                    .and(sso_key::dsl::user_id.eq(list.user_id))
+                    .and(sso_key::dsl::is_enabled.eq(true))
+                    .and(sso_key::dsl::is_revoked.eq(false))
+                    .and(sso_key::dsl::type_.eq(KeyType::WebAuthn.to_string())),
+            )
+            .order(sso_key_webauthn::dsl::created_at.asc())
+            .load::<ModelKeyWebauthn>(conn)
+            .map_err(Into::into)
+            .map(|x| x.into_iter().map(|x| x.into()).collect())
+    }
+
+    pub fn create(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        create: &KeyWebauthnCreate,
+    ) -> DriverResult<Key> {
+        conn.transaction(|| {
+            let key = ModelKey::create_inner(conn, data_keys, &create.key)?;
+
+            let now = Utc::now();
+            let value = ModelKeyWebauthnInsert {
+                created_at: &now,
+                updated_at: &now,
+                key_id: &key.id,
+                credential: &create.credential,
+                counter: create.counter,
+                aaguid: &create.aaguid,
+            };
+            diesel::insert_into(sso_key_webauthn::table)
+                .values(&value)
+                .execute(conn)?;
+
+            Ok(key.into())
+        })
+    }
+
+    pub fn update(conn: &PgConnection, update: &KeyWebauthnUpdate) -> DriverResult<KeyWebauthn> {
+        let now = Utc::now();
+        let value = ModelKeyWebauthnUpdate {
+            updated_at: &now,
+            credential: &update.credential,
+            counter: update.counter,
+        };
+        diesel::update(
+            sso_key_webauthn::table.filter(sso_key_webauthn::dsl::key_id.eq(update.key_id)),
+        )
+        .set(&value)
+        .get_result::<ModelKeyWebauthn>(conn)
+        .map_err(Into::into)
+        .map(Into::into)
+    }
+}
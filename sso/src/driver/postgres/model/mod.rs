@@ -1,6 +1,11 @@
 mod audit;
 mod key;
+mod key_data_key;
+mod key_webauthn;
+mod role;
 mod service;
 mod user;
 
-pub use crate::driver::postgres::model::{audit::*, key::*, service::*, user::*};
+pub use crate::driver::postgres::model::{
+    audit::*, key::*, key_data_key::*, key_webauthn::*, role::*, service::*, user::*,
+};
@@ -0,0 +1,77 @@
+use crate::{prelude::*, schema::sso_role};
+use diesel::{pg::upsert::excluded, prelude::*, PgConnection};
+
+#[derive(Debug, Identifiable, Queryable)]
+#[table_name = "sso_role"]
+#[primary_key(name)]
+pub struct ModelRole {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    name: String,
+    permissions: Vec<String>,
+}
+
+impl From<ModelRole> for Role {
+    fn from(role: ModelRole) -> Self {
+        Self {
+            created_at: role.created_at,
+            updated_at: role.updated_at,
+            name: role.name,
+            permissions: role
+                .permissions
+                .iter()
+                .filter_map(|x| RolePermission::from_str(x).ok())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "sso_role"]
+struct ModelRoleInsert<'a> {
+    created_at: &'a DateTime<Utc>,
+    updated_at: &'a DateTime<Utc>,
+    name: &'a str,
+    permissions: Vec<String>,
+}
+
+impl ModelRole {
+    pub fn list(conn: &PgConnection) -> DriverResult<Vec<Role>> {
+        sso_role::table
+            .order(sso_role::dsl::name.asc())
+            .load::<ModelRole>(conn)
+            .map_err(Into::into)
+            .map(|x| x.into_iter().map(|x| x.into()).collect())
+    }
+
+    /// Create role, permissions of existing role are replaced.
+    pub fn create(conn: &PgConnection, create: &RoleCreate) -> DriverResult<Role> {
+        let now = Utc::now();
+        let value = ModelRoleInsert {
+            created_at: &now,
+            updated_at: &now,
+            name: &create.name,
+            permissions: create.permissions.iter().map(|x| x.to_string()).collect(),
+        };
+        diesel::insert_into(sso_role::table)
+            .values(&value)
+            .on_conflict(sso_role::dsl::name)
+            .do_update()
+            .set((
+                sso_role::dsl::updated_at.eq(excluded(sso_role::dsl::updated_at)),
+                sso_role::dsl::permissions.eq(excluded(sso_role::dsl::permissions)),
+            ))
+            .get_result::<ModelRole>(conn)
+            .map_err(Into::into)
+            .map(Into::into)
+    }
+
+    pub fn read(conn: &PgConnection, name: &str) -> DriverResult<Option<Role>> {
+        sso_role::table
+            .filter(sso_role::dsl::name.eq(name))
+            .get_result::<ModelRole>(conn)
+            .optional()
+            .map_err(Into::into)
+            .map(|x| x.map(Into::into))
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
-    schema::sso_service, DriverResult, Service, ServiceCreate, ServiceList, ServiceListQuery,
-    ServiceRead, ServiceUpdate,
+    schema::sso_service, DriverResult, JwtAlgorithm, Service, ServiceCreate, ServiceList,
+    ServiceListQuery, ServiceMfaPolicy, ServiceRead, ServiceUpdate,
 };
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Debug, Identifiable, Queryable)]
@@ -21,6 +22,17 @@ pub struct ModelService {
     provider_local_url: Option<String>,
     provider_github_oauth2_url: Option<String>,
     provider_microsoft_oauth2_url: Option<String>,
+    oidc_redirect_uris: Vec<String>,
+    jwt_algorithm: String,
+    provider_oauth2_url: Option<String>,
+    provider_saml_url: Option<String>,
+    saml_idp_metadata: Option<String>,
+    saml_email_attribute: Option<String>,
+    mfa_policy: String,
+    password_min_strength: i32,
+    password_reject_pwned: bool,
+    password_history: i32,
+    password_max_age_days: i32,
 }
 
 impl From<ModelService> for Service {
@@ -37,6 +49,17 @@ impl From<ModelService> for Service {
             provider_local_url: service.provider_local_url,
             provider_github_oauth2_url: service.provider_github_oauth2_url,
             provider_microsoft_oauth2_url: service.provider_microsoft_oauth2_url,
+            oidc_redirect_uris: service.oidc_redirect_uris,
+            jwt_algorithm: JwtAlgorithm::from_str(&service.jwt_algorithm).unwrap(),
+            provider_oauth2_url: service.provider_oauth2_url,
+            provider_saml_url: service.provider_saml_url,
+            saml_idp_metadata: service.saml_idp_metadata,
+            saml_email_attribute: service.saml_email_attribute,
+            mfa_policy: ServiceMfaPolicy::from_str(&service.mfa_policy).unwrap(),
+            password_min_strength: service.password_min_strength,
+            password_reject_pwned: service.password_reject_pwned,
+            password_history: service.password_history,
+            password_max_age_days: service.password_max_age_days,
         }
     }
 }
@@ -55,6 +78,17 @@ struct ModelServiceInsert<'a> {
     provider_local_url: Option<&'a str>,
     provider_github_oauth2_url: Option<&'a str>,
     provider_microsoft_oauth2_url: Option<&'a str>,
+    oidc_redirect_uris: &'a [String],
+    jwt_algorithm: String,
+    provider_oauth2_url: Option<&'a str>,
+    provider_saml_url: Option<&'a str>,
+    saml_idp_metadata: Option<&'a str>,
+    saml_email_attribute: Option<&'a str>,
+    mfa_policy: String,
+    password_min_strength: i32,
+    password_reject_pwned: bool,
+    password_history: i32,
+    password_max_age_days: i32,
 }
 
 #[derive(AsChangeset)]
@@ -69,6 +103,17 @@ struct ModelServiceUpdate<'a> {
     provider_local_url: Option<&'a str>,
     provider_github_oauth2_url: Option<&'a str>,
     provider_microsoft_oauth2_url: Option<&'a str>,
+    oidc_redirect_uris: Option<&'a [String]>,
+    jwt_algorithm: Option<String>,
+    provider_oauth2_url: Option<&'a str>,
+    provider_saml_url: Option<&'a str>,
+    saml_idp_metadata: Option<&'a str>,
+    saml_email_attribute: Option<&'a str>,
+    mfa_policy: Option<String>,
+    password_min_strength: Option<i32>,
+    password_reject_pwned: Option<bool>,
+    password_history: Option<i32>,
+    password_max_age_days: Option<i32>,
 }
 
 impl ModelService {
@@ -138,6 +183,17 @@ impl ModelService {
                 .provider_microsoft_oauth2_url
                 .as_ref()
                 .map(|x| &**x),
+            oidc_redirect_uris: &create.oidc_redirect_uris,
+            jwt_algorithm: create.jwt_algorithm.to_string(),
+            provider_oauth2_url: create.provider_oauth2_url.as_ref().map(|x| &**x),
+            provider_saml_url: create.provider_saml_url.as_ref().map(|x| &**x),
+            saml_idp_metadata: create.saml_idp_metadata.as_ref().map(|x| &**x),
+            saml_email_attribute: create.saml_email_attribute.as_ref().map(|x| &**x),
+            mfa_policy: create.mfa_policy.to_string(),
+            password_min_strength: create.password_min_strength,
+            password_reject_pwned: create.password_reject_pwned,
+            password_history: create.password_history,
+            password_max_age_days: create.password_max_age_days,
         };
         diesel::insert_into(sso_service::table)
             .values(value)
@@ -183,6 +239,17 @@ impl ModelService {
                 .provider_microsoft_oauth2_url
                 .as_ref()
                 .map(|x| &**x),
+            oidc_redirect_uris: update.oidc_redirect_uris.as_ref().map(|x| &**x),
+            jwt_algorithm: update.jwt_algorithm.map(|x| x.to_string()),
+            provider_oauth2_url: update.provider_oauth2_url.as_ref().map(|x| &**x),
+            provider_saml_url: update.provider_saml_url.as_ref().map(|x| &**x),
+            saml_idp_metadata: update.saml_idp_metadata.as_ref().map(|x| &**x),
+            saml_email_attribute: update.saml_email_attribute.as_ref().map(|x| &**x),
+            mfa_policy: update.mfa_policy.map(|x| x.to_string()),
+            password_min_strength: update.password_min_strength,
+            password_reject_pwned: update.password_reject_pwned,
+            password_history: update.password_history,
+            password_max_age_days: update.password_max_age_days,
         };
         diesel::update(sso_service::table.filter(sso_service::dsl::id.eq(update.id)))
             .set(value)
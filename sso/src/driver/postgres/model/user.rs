@@ -1,6 +1,7 @@
 use crate::{
     schema::sso_user, DriverError, DriverResult, User, UserCreate, UserList, UserListFilter,
-    UserListQuery, UserRead, UserUpdate,
+    UserListQuery, UserPasswordHash, UserPasswordHistory, UserRead, UserUpdate,
+    USER_PASSWORD_HASH_PHC_VERSION,
 };
 use chrono::{DateTime, Utc};
 use diesel::{pg::Pg, prelude::*};
@@ -22,6 +23,8 @@ pub struct ModelUser {
     password_allow_reset: bool,
     password_require_update: bool,
     password_hash: Option<String>,
+    phone: Option<String>,
+    password_updated_at: Option<DateTime<Utc>>,
 }
 
 impl From<ModelUser> for User {
@@ -35,9 +38,11 @@ impl From<ModelUser> for User {
             email: user.email,
             locale: user.locale,
             timezone: user.timezone,
+            phone: user.phone,
             password_allow_reset: user.password_allow_reset,
             password_require_update: user.password_require_update,
             password_hash: user.password_hash,
+            password_updated_at: user.password_updated_at,
         }
     }
 }
@@ -56,6 +61,8 @@ struct ModelUserInsert<'a> {
     password_allow_reset: bool,
     password_require_update: bool,
     password_hash: Option<&'a str>,
+    phone: Option<&'a str>,
+    password_updated_at: Option<&'a DateTime<Utc>>,
 }
 
 #[derive(AsChangeset)]
@@ -70,6 +77,8 @@ struct ModelUserUpdate<'a> {
     password_allow_reset: Option<bool>,
     password_require_update: Option<bool>,
     password_hash: Option<&'a str>,
+    phone: Option<Option<&'a str>>,
+    password_updated_at: Option<&'a DateTime<Utc>>,
 }
 
 impl ModelUser {
@@ -107,6 +116,8 @@ impl ModelUser {
             password_allow_reset: create.password_allow_reset,
             password_require_update: create.password_require_update,
             password_hash: create.password_hash.as_ref().map(|x| &**x),
+            phone: create.phone.as_ref().map(|x| &**x),
+            password_updated_at: create.password_hash.as_ref().map(|_| &now),
         };
         diesel::insert_into(sso_user::table)
             .values(&value)
@@ -123,7 +134,26 @@ impl ModelUser {
         .map(|r| r.map(|u| u.into()))
     }
 
+    /// Update user, if password is updated previous password hash is written to history.
     pub fn update(conn: &PgConnection, update: &UserUpdate) -> DriverResult<User> {
+        conn.transaction(|| {
+            if update.password_hash.is_some() {
+                let previous = sso_user::table
+                    .select(sso_user::dsl::password_hash)
+                    .filter(sso_user::dsl::id.eq(update.id))
+                    .for_update()
+                    .get_result::<Option<String>>(conn)
+                    .optional()?
+                    .flatten();
+                if let Some(previous) = previous {
+                    UserPasswordHistory::create(conn, update.id, &previous)?;
+                }
+            }
+            Self::update_inner(conn, update)
+        })
+    }
+
+    fn update_inner(conn: &PgConnection, update: &UserUpdate) -> DriverResult<User> {
         let now = Utc::now();
         let value = ModelUserUpdate {
             updated_at: &now,
@@ -135,6 +165,12 @@ impl ModelUser {
             password_allow_reset: update.password_allow_reset,
             password_require_update: update.password_require_update,
             password_hash: update.password_hash.as_ref().map(|x| &**x),
+            // Empty string removes phone number.
+            phone: update
+                .phone
+                .as_ref()
+                .map(|x| if x.is_empty() { None } else { Some(&**x) }),
+            password_updated_at: update.password_hash.as_ref().map(|_| &now),
         };
         diesel::update(sso_user::table.filter(sso_user::dsl::id.eq(update.id)))
             .set(&value)
@@ -143,6 +179,47 @@ impl ModelUser {
             .map(Into::into)
     }
 
+    /// Update user password hash if it has not changed since it was read.
+    pub fn update_password_hash(
+        conn: &PgConnection,
+        id: &Uuid,
+        password_hash: &str,
+        new_password_hash: &str,
+    ) -> DriverResult<bool> {
+        diesel::update(
+            sso_user::table
+                .filter(sso_user::dsl::id.eq(id))
+                .filter(sso_user::dsl::password_hash.eq(password_hash)),
+        )
+        .set((
+            sso_user::dsl::updated_at.eq(Utc::now()),
+            sso_user::dsl::password_hash.eq(new_password_hash),
+        ))
+        .execute(conn)
+        .map_err(Into::into)
+        .map(|count| count == 1)
+    }
+
+    /// Count users with password hash not created by hash algorithm and parameters.
+    pub fn count_password_legacy(
+        conn: &PgConnection,
+        hash: &UserPasswordHash,
+    ) -> DriverResult<i64> {
+        let query = sso_user::table
+            .filter(sso_user::dsl::password_hash.is_not_null())
+            .into_boxed();
+        let query = match hash.argon2id_prefix() {
+            Some(prefix) => {
+                query.filter(sso_user::dsl::password_hash.not_like(format!("{}%", prefix)))
+            }
+            None => query.filter(diesel::dsl::sql::<diesel::sql_types::Bool>(&format!(
+                "password_hash !~ '[$,]{}[,$]'",
+                USER_PASSWORD_HASH_PHC_VERSION
+            ))),
+        };
+        query.count().get_result::<i64>(conn).map_err(Into::into)
+    }
+
     pub fn delete(conn: &PgConnection, id: &Uuid) -> DriverResult<usize> {
         diesel::delete(sso_user::table.filter(sso_user::dsl::id.eq(id)))
             .execute(conn)
@@ -0,0 +1,257 @@
+use crate::{impl_enum_to_from_string, AuditType};
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+/// Role permissions.
+///
+/// Keys with a role are limited to the permissions of that role, keys
+/// without a role have all permissions.
+#[derive(Debug, Copy, PartialEq, Clone, Serialize, Deserialize)]
+pub enum RolePermission {
+    #[serde(rename = "audit:read")]
+    AuditRead,
+    #[serde(rename = "audit:write")]
+    AuditWrite,
+    #[serde(rename = "key:read")]
+    KeyRead,
+    #[serde(rename = "key:create")]
+    KeyCreate,
+    #[serde(rename = "key:write")]
+    KeyWrite,
+    #[serde(rename = "service:read")]
+    ServiceRead,
+    #[serde(rename = "service:write")]
+    ServiceWrite,
+    #[serde(rename = "oauth2_provider:read")]
+    Oauth2ProviderRead,
+    #[serde(rename = "oauth2_provider:write")]
+    Oauth2ProviderWrite,
+    #[serde(rename = "user:read")]
+    UserRead,
+    #[serde(rename = "user:write")]
+    UserWrite,
+    #[serde(rename = "user:auth")]
+    UserAuth,
+}
+
+impl_enum_to_from_string!(RolePermission, "");
+
+impl RolePermission {
+    /// All permissions, keys without a role have all permissions.
+    pub const ALL: [RolePermission; 12] = [
+        Self::AuditRead,
+        Self::AuditWrite,
+        Self::KeyRead,
+        Self::KeyCreate,
+        Self::KeyWrite,
+        Self::ServiceRead,
+        Self::ServiceWrite,
+        Self::Oauth2ProviderRead,
+        Self::Oauth2ProviderWrite,
+        Self::UserRead,
+        Self::UserWrite,
+        Self::UserAuth,
+    ];
+
+    /// Returns permissions of key with role and scopes, keys without a role have all
+    /// permissions and keys with scopes only have the permissions listed in them.
+    pub fn key_permissions(role: Option<&Role>, scopes: &[String]) -> Vec<Self> {
+        let permissions = match role {
+            Some(role) => role.permissions.clone(),
+            None => Self::ALL.to_vec(),
+        };
+        if scopes.is_empty() {
+            permissions
+        } else {
+            permissions
+                .into_iter()
+                .filter(|x| scopes.contains(&x.to_string()))
+                .collect()
+        }
+    }
+
+    /// Returns true if permissions contain all other permissions.
+    pub fn contains_all(permissions: &[Self], other: &[Self]) -> bool {
+        other.iter().all(|x| permissions.contains(x))
+    }
+
+    /// Returns permission required by request of audit type, if any.
+    pub fn from_audit_type(type_: AuditType) -> Option<Self> {
+        match type_ {
+            AuditType::Metrics | AuditType::Traefik => None,
+            AuditType::AuditList | AuditType::AuditRead => Some(Self::AuditRead),
+            AuditType::AuditCreate | AuditType::AuditUpdate => Some(Self::AuditWrite),
+            AuditType::KeyList | AuditType::KeyRead => Some(Self::KeyRead),
+            AuditType::KeyCreate | AuditType::KeyRotate => Some(Self::KeyCreate),
+            AuditType::KeyUpdate | AuditType::KeyDelete => Some(Self::KeyWrite),
+            AuditType::ServiceList | AuditType::ServiceRead => Some(Self::ServiceRead),
+            AuditType::ServiceCreate | AuditType::ServiceUpdate | AuditType::ServiceDelete => {
+                Some(Self::ServiceWrite)
+            }
+            AuditType::Oauth2ProviderList | AuditType::Oauth2ProviderRead => {
+                Some(Self::Oauth2ProviderRead)
+            }
+            AuditType::Oauth2ProviderCreate
+            | AuditType::Oauth2ProviderUpdate
+            | AuditType::Oauth2ProviderDelete => Some(Self::Oauth2ProviderWrite),
+            AuditType::UserList | AuditType::UserRead | AuditType::UserSessionList => {
+                Some(Self::UserRead)
+            }
+            AuditType::UserCreate
+            | AuditType::UserUpdate
+            | AuditType::UserDelete
+            | AuditType::UserSessionRevoke
+            | AuditType::UserLoginUnlock
+            | AuditType::UserPasswordRehash => Some(Self::UserWrite),
+            AuditType::AuthLocalLogin
+            | AuditType::AuthLocalLoginLockout
+            | AuditType::AuthLocalLoginMfa
+            | AuditType::AuthLocalMagicLink
+            | AuditType::AuthLocalMagicLinkConfirm
+            | AuditType::AuthLocalEmailCode
+            | AuditType::AuthLocalEmailCodeConfirm
+            | AuditType::AuthLocalRegister
+            | AuditType::AuthLocalRegisterConfirm
+            | AuditType::AuthLocalRegisterRevoke
+            | AuditType::AuthLocalResetPassword
+            | AuditType::AuthLocalResetPasswordConfirm
+            | AuditType::AuthLocalResetPasswordRevoke
+            | AuditType::AuthLocalUpdateEmail
+            | AuditType::AuthLocalUpdateEmailRevoke
+            | AuditType::AuthLocalUpdatePassword
+            | AuditType::AuthLocalUpdatePasswordRevoke
+            | AuditType::AuthGithubOauth2Url
+            | AuditType::AuthGithubOauth2Callback
+            | AuditType::AuthMicrosoftOauth2Url
+            | AuditType::AuthMicrosoftOauth2Callback
+            | AuditType::AuthOauth2Url
+            | AuditType::AuthOauth2Callback
+            | AuditType::AuthSamlUrl
+            | AuditType::AuthSamlCallback
+            | AuditType::AuthOauth2Login
+            | AuditType::AuthKeyVerify
+            | AuditType::AuthKeyRevoke
+            | AuditType::AuthTokenVerify
+            | AuditType::AuthTokenRefresh
+            | AuditType::AuthTokenRefreshReuse
+            | AuditType::AuthTokenRevoke
+            | AuditType::AuthTotp
+            | AuditType::AuthTotpRegisterBegin
+            | AuditType::AuthTotpRegisterFinish
+            | AuditType::AuthTotpRecovery
+            | AuditType::AuthCsrfCreate
+            | AuditType::AuthCsrfVerify
+            | AuditType::AuthWebauthnRegisterBegin
+            | AuditType::AuthWebauthnRegisterFinish
+            | AuditType::AuthWebauthnLoginBegin
+            | AuditType::AuthWebauthnLoginFinish
+            | AuditType::AuthOidcAuthorize
+            | AuditType::AuthOidcToken
+            | AuditType::AuthOidcUserinfo => Some(Self::UserAuth),
+        }
+    }
+}
+
+/// Role.
+#[derive(Debug, Clone)]
+pub struct Role {
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub permissions: Vec<RolePermission>,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Role {}", self.name)?;
+        write!(f, "\n\tcreated_at {}", self.created_at)?;
+        write!(f, "\n\tupdated_at {}", self.updated_at)?;
+        let permissions: Vec<String> = self.permissions.iter().map(|x| x.to_string()).collect();
+        write!(f, "\n\tpermissions {}", permissions.join(", "))?;
+        Ok(())
+    }
+}
+
+impl Role {
+    /// Returns true if role has permission.
+    pub fn has_permission(&self, permission: RolePermission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    /// Returns true if role has all permissions of other role.
+    pub fn has_permissions_of(&self, other: &Role) -> bool {
+        other.permissions.iter().all(|x| self.has_permission(*x))
+    }
+}
+
+/// Role create or update data.
+#[derive(Debug)]
+pub struct RoleCreate {
+    pub name: String,
+    pub permissions: Vec<RolePermission>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn role(permissions: Vec<RolePermission>) -> Role {
+        Role {
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            name: "role".to_owned(),
+            permissions,
+        }
+    }
+
+    #[test]
+    fn role_permission_to_from_string() {
+        assert_eq!(RolePermission::UserRead.to_string(), "user:read");
+        assert_eq!(
+            RolePermission::from_str("oauth2_provider:write").unwrap(),
+            RolePermission::Oauth2ProviderWrite
+        );
+        assert!(RolePermission::from_str("user:delete").is_err());
+    }
+
+    #[test]
+    fn role_has_permissions_of() {
+        let read = role(vec![RolePermission::UserRead]);
+        let write = role(vec![RolePermission::UserRead, RolePermission::UserWrite]);
+        assert!(write.has_permissions_of(&read));
+        assert!(!read.has_permissions_of(&write));
+        let all = role(RolePermission::ALL.to_vec());
+        assert!(all.has_permissions_of(&write));
+        assert_eq!(
+            RolePermission::from_audit_type(AuditType::UserCreate),
+            Some(RolePermission::UserWrite)
+        );
+        assert_eq!(RolePermission::from_audit_type(AuditType::Traefik), None);
+    }
+
+    #[test]
+    fn role_permission_key_permissions() {
+        let write = role(vec![RolePermission::UserRead, RolePermission::UserWrite]);
+        let scopes = vec!["user:read".to_owned(), "key:read".to_owned()];
+        assert_eq!(
+            RolePermission::key_permissions(Some(&write), &[]),
+            write.permissions
+        );
+        assert_eq!(
+            RolePermission::key_permissions(Some(&write), &scopes),
+            vec![RolePermission::UserRead]
+        );
+        assert_eq!(
+            RolePermission::key_permissions(None, &[]),
+            RolePermission::ALL.to_vec()
+        );
+        assert_eq!(
+            RolePermission::key_permissions(None, &scopes),
+            vec![RolePermission::KeyRead, RolePermission::UserRead]
+        );
+        let all = RolePermission::key_permissions(None, &[]);
+        assert!(RolePermission::contains_all(&all, &write.permissions));
+        assert!(!RolePermission::contains_all(&write.permissions, &all));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{AuditDiff, AuditDiffBuilder, AuditSubject, DriverError, DriverResult};
+use crate::{
+    impl_enum_to_from_string, AuditDiff, AuditDiffBuilder, AuditSubject, DriverError, DriverResult,
+    JwtAlgorithm, SamlIdpMetadata, UserPasswordPolicy,
+};
 use chrono::{DateTime, Utc};
 use serde::ser::Serialize;
 use serde_json::Value;
@@ -6,6 +9,19 @@ use std::fmt;
 use url::Url;
 use uuid::Uuid;
 
+/// Service multi-factor authentication policies.
+#[derive(Debug, Copy, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ServiceMfaPolicy {
+    /// Login does not require second factor.
+    Off,
+    /// Login requires second factor if user has a TOTP or WebAuthn key.
+    Optional,
+    /// Login always requires second factor.
+    Required,
+}
+
+impl_enum_to_from_string!(ServiceMfaPolicy, "");
+
 /// Service.
 #[derive(Debug, Clone)]
 pub struct Service {
@@ -20,6 +36,17 @@ pub struct Service {
     pub provider_local_url: Option<String>,
     pub provider_github_oauth2_url: Option<String>,
     pub provider_microsoft_oauth2_url: Option<String>,
+    pub oidc_redirect_uris: Vec<String>,
+    pub jwt_algorithm: JwtAlgorithm,
+    pub provider_oauth2_url: Option<String>,
+    pub provider_saml_url: Option<String>,
+    pub saml_idp_metadata: Option<String>,
+    pub saml_email_attribute: Option<String>,
+    pub mfa_policy: ServiceMfaPolicy,
+    pub password_min_strength: i32,
+    pub password_reject_pwned: bool,
+    pub password_history: i32,
+    pub password_max_age_days: i32,
 }
 
 impl Service {
@@ -32,6 +59,33 @@ impl Service {
         }
     }
 
+    /// Check OpenID Connect redirect URI is registered for service.
+    pub fn oidc_redirect_uri_check(&self, redirect_uri: &str) -> DriverResult<()> {
+        if self.oidc_redirect_uris.iter().any(|x| x == redirect_uri) {
+            Ok(())
+        } else {
+            Err(DriverError::ServiceOidcRedirectUriInvalid)
+        }
+    }
+
+    /// Returns service user password policy.
+    pub fn password_policy(&self) -> UserPasswordPolicy {
+        UserPasswordPolicy {
+            min_strength: self.password_min_strength,
+            reject_pwned: self.password_reject_pwned,
+            history: self.password_history,
+            max_age_days: self.password_max_age_days,
+        }
+    }
+
+    /// Returns SAML assertion consumer service URL and identity provider metadata.
+    pub fn provider_saml(&self) -> DriverResult<(&str, SamlIdpMetadata)> {
+        match (&self.provider_saml_url, &self.saml_idp_metadata) {
+            (Some(acs_url), Some(metadata)) => Ok((acs_url, SamlIdpMetadata::parse(metadata)?)),
+            _ => Err(DriverError::ServiceProviderSamlDisabled),
+        }
+    }
+
     /// Build a local provider callback URL with type and serialisable data.
     pub fn provider_local_callback_url<T: Into<String>, D: Serialize>(
         &self,
@@ -88,6 +142,36 @@ impl fmt::Display for Service {
                 provider_microsoft_oauth2_url
             )?;
         }
+        for oidc_redirect_uri in &self.oidc_redirect_uris {
+            write!(f, "\n\toidc_redirect_uri {}", oidc_redirect_uri)?;
+        }
+        write!(f, "\n\tjwt_algorithm {}", self.jwt_algorithm)?;
+        if let Some(provider_oauth2_url) = &self.provider_oauth2_url {
+            write!(f, "\n\tprovider_oauth2_url {}", provider_oauth2_url)?;
+        }
+        if let Some(provider_saml_url) = &self.provider_saml_url {
+            write!(f, "\n\tprovider_saml_url {}", provider_saml_url)?;
+        }
+        if let Some(saml_email_attribute) = &self.saml_email_attribute {
+            write!(f, "\n\tsaml_email_attribute {}", saml_email_attribute)?;
+        }
+        write!(f, "\n\tmfa_policy {}", self.mfa_policy)?;
+        write!(
+            f,
+            "\n\tpassword_min_strength {}",
+            self.password_min_strength
+        )?;
+        write!(
+            f,
+            "\n\tpassword_reject_pwned {}",
+            self.password_reject_pwned
+        )?;
+        write!(f, "\n\tpassword_history {}", self.password_history)?;
+        write!(
+            f,
+            "\n\tpassword_max_age_days {}",
+            self.password_max_age_days
+        )?;
         Ok(())
     }
 }
@@ -126,6 +210,33 @@ impl AuditDiff for Service {
             .as_ref()
             .map(|x| &**x)
             .unwrap_or("");
+        let c_provider_oauth2_url = self
+            .provider_oauth2_url
+            .as_ref()
+            .map(|x| &**x)
+            .unwrap_or("");
+        let p_provider_oauth2_url = previous
+            .provider_oauth2_url
+            .as_ref()
+            .map(|x| &**x)
+            .unwrap_or("");
+        let c_provider_saml_url = self.provider_saml_url.as_ref().map(|x| &**x).unwrap_or("");
+        let p_provider_saml_url = previous
+            .provider_saml_url
+            .as_ref()
+            .map(|x| &**x)
+            .unwrap_or("");
+        let c_saml_idp_metadata = self.saml_idp_metadata != previous.saml_idp_metadata;
+        let c_saml_email_attribute = self
+            .saml_email_attribute
+            .as_ref()
+            .map(|x| &**x)
+            .unwrap_or("");
+        let p_saml_email_attribute = previous
+            .saml_email_attribute
+            .as_ref()
+            .map(|x| &**x)
+            .unwrap_or("");
 
         AuditDiffBuilder::default()
             .compare("is_enabled", &self.is_enabled, &previous.is_enabled)
@@ -156,6 +267,53 @@ impl AuditDiff for Service {
                 &c_provider_microsoft_oauth2_url,
                 &p_provider_microsoft_oauth2_url,
             )
+            .compare_vec(
+                "oidc_redirect_uris",
+                &self.oidc_redirect_uris,
+                &previous.oidc_redirect_uris,
+            )
+            .compare(
+                "jwt_algorithm",
+                &self.jwt_algorithm,
+                &previous.jwt_algorithm,
+            )
+            .compare(
+                "provider_oauth2_url",
+                &c_provider_oauth2_url,
+                &p_provider_oauth2_url,
+            )
+            .compare(
+                "provider_saml_url",
+                &c_provider_saml_url,
+                &p_provider_saml_url,
+            )
+            .compare("saml_idp_metadata", &c_saml_idp_metadata, &false)
+            .compare(
+                "saml_email_attribute",
+                &c_saml_email_attribute,
+                &p_saml_email_attribute,
+            )
+            .compare("mfa_policy", &self.mfa_policy, &previous.mfa_policy)
+            .compare(
+                "password_min_strength",
+                &self.password_min_strength,
+                &previous.password_min_strength,
+            )
+            .compare(
+                "password_reject_pwned",
+                &self.password_reject_pwned,
+                &previous.password_reject_pwned,
+            )
+            .compare(
+                "password_history",
+                &self.password_history,
+                &previous.password_history,
+            )
+            .compare(
+                "password_max_age_days",
+                &self.password_max_age_days,
+                &previous.password_max_age_days,
+            )
             .into_value()
     }
 }
@@ -194,6 +352,17 @@ pub struct ServiceCreate {
     pub provider_local_url: Option<String>,
     pub provider_github_oauth2_url: Option<String>,
     pub provider_microsoft_oauth2_url: Option<String>,
+    pub oidc_redirect_uris: Vec<String>,
+    pub jwt_algorithm: JwtAlgorithm,
+    pub provider_oauth2_url: Option<String>,
+    pub provider_saml_url: Option<String>,
+    pub saml_idp_metadata: Option<String>,
+    pub saml_email_attribute: Option<String>,
+    pub mfa_policy: ServiceMfaPolicy,
+    pub password_min_strength: i32,
+    pub password_reject_pwned: bool,
+    pub password_history: i32,
+    pub password_max_age_days: i32,
 }
 
 /// Service read.
@@ -220,6 +389,17 @@ pub struct ServiceUpdate {
     pub provider_local_url: Option<String>,
     pub provider_github_oauth2_url: Option<String>,
     pub provider_microsoft_oauth2_url: Option<String>,
+    pub oidc_redirect_uris: Option<Vec<String>>,
+    pub jwt_algorithm: Option<JwtAlgorithm>,
+    pub provider_oauth2_url: Option<String>,
+    pub provider_saml_url: Option<String>,
+    pub saml_idp_metadata: Option<String>,
+    pub saml_email_attribute: Option<String>,
+    pub mfa_policy: Option<ServiceMfaPolicy>,
+    pub password_min_strength: Option<i32>,
+    pub password_reject_pwned: Option<bool>,
+    pub password_history: Option<i32>,
+    pub password_max_age_days: Option<i32>,
 }
 
 #[cfg(test)]
@@ -233,11 +413,8 @@ mod tests {
         token: String,
     }
 
-    #[test]
-    fn service_provider_local_callback_url() {
-        let id = "6a9c6cfb7e15498b99e057153f0a212b";
-        let id = Uuid::parse_str(id).unwrap();
-        let service = Service {
+    fn service_new(id: Uuid) -> Service {
+        Service {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             id,
@@ -249,7 +426,25 @@ mod tests {
             provider_local_url: Some("http://localhost:9000".to_owned()),
             provider_github_oauth2_url: None,
             provider_microsoft_oauth2_url: None,
-        };
+            oidc_redirect_uris: vec!["http://localhost:9000/callback".to_owned()],
+            jwt_algorithm: JwtAlgorithm::Hs256,
+            provider_oauth2_url: None,
+            provider_saml_url: None,
+            saml_idp_metadata: None,
+            saml_email_attribute: None,
+            mfa_policy: ServiceMfaPolicy::Off,
+            password_min_strength: 0,
+            password_reject_pwned: false,
+            password_history: 0,
+            password_max_age_days: 0,
+        }
+    }
+
+    #[test]
+    fn service_provider_local_callback_url() {
+        let id = "6a9c6cfb7e15498b99e057153f0a212b";
+        let id = Uuid::parse_str(id).unwrap();
+        let service = service_new(id);
         let callback_data = CallbackData {
             email: "user@test.com".to_owned(),
             token: "6a9c6cfb7e15498b99e057153f0a212b".to_owned(),
@@ -262,4 +457,18 @@ mod tests {
             "http://localhost:9000/?type=reset_password&email=user%40test.com&token=6a9c6cfb7e15498b99e057153f0a212b"
         );
     }
+
+    #[test]
+    fn service_oidc_redirect_uri_check() {
+        let service = service_new(Uuid::new_v4());
+        service
+            .oidc_redirect_uri_check("http://localhost:9000/callback")
+            .unwrap();
+        service
+            .oidc_redirect_uri_check("http://localhost:9000/callback/")
+            .unwrap_err();
+        service
+            .oidc_redirect_uri_check("http://localhost:9000")
+            .unwrap_err();
+    }
 }
@@ -8,6 +8,9 @@ const EMAIL_RESET_PASSWORD: &str = "email_reset_password";
 const EMAIL_RESET_PASSWORD_CONFIRM: &str = "email_reset_password_confirm";
 const EMAIL_UPDATE_EMAIL: &str = "email_update_email";
 const EMAIL_UPDATE_PASSWORD: &str = "email_update_password";
+const EMAIL_MAGIC_LINK: &str = "email_magic_link";
+const EMAIL_CODE: &str = "email_code";
+const HTML_OIDC_AUTHORIZE: &str = "html_oidc_authorize";
 
 lazy_static! {
     static ref HANDLEBARS: Handlebars<'static> = {
@@ -43,6 +46,15 @@ lazy_static! {
                 include_str!("email_update_password.hbs"),
             )
             .unwrap();
+        handlebars
+            .register_template_string(EMAIL_MAGIC_LINK, include_str!("email_magic_link.hbs"))
+            .unwrap();
+        handlebars
+            .register_template_string(EMAIL_CODE, include_str!("email_code.hbs"))
+            .unwrap();
+        handlebars
+            .register_template_string(HTML_OIDC_AUTHORIZE, include_str!("html_oidc_authorize.hbs"))
+            .unwrap();
 
         handlebars
     };
@@ -143,25 +155,63 @@ impl TemplateEmailUpdateEmail {
     }
 }
 
+/// Template email code parameters.
+#[derive(Debug, Serialize)]
+struct TemplateEmailCode {
+    user_email: String,
+    code: String,
+    audit: TemplateEmailAudit,
+    service: TemplateEmailService,
+}
+
+impl TemplateEmailCode {
+    pub fn new<UE, C>(user_email: UE, code: C, audit: &AuditMeta, service: &Service) -> Self
+    where
+        UE: Into<String>,
+        C: Into<String>,
+    {
+        Self {
+            user_email: user_email.into(),
+            code: code.into(),
+            audit: TemplateEmailAudit::new(audit),
+            service: TemplateEmailService::new(service),
+        }
+    }
+}
+
+/// Template HTML OpenID Connect authorize parameters.
+#[derive(Debug, Serialize)]
+struct TemplateHtmlOidcAuthorize {
+    action: String,
+    request: String,
+    error: Option<String>,
+    service: TemplateEmailService,
+}
+
 /// Template email.
-#[derive(Debug)]
+///
+/// Sent to user by notification channels, phone number is used by channels
+/// which deliver to phones.
+#[derive(Debug, Serialize)]
 pub struct TemplateEmail {
     pub to_email: String,
     pub to_name: String,
+    pub to_phone: Option<String>,
     pub from_name: String,
     pub subject: String,
     pub text: String,
 }
 
 impl TemplateEmail {
-    fn new<T, S>(to_email: T, to_name: T, from_name: T, subject: S, text: String) -> Self
+    fn new<T, S>(user: &User, from_name: T, subject: S, text: String) -> Self
     where
         T: Into<String>,
         S: Into<String>,
     {
         Self {
-            to_email: to_email.into(),
-            to_name: to_name.into(),
+            to_email: user.email.to_owned(),
+            to_name: user.name.to_owned(),
+            to_phone: user.phone.to_owned(),
             from_name: from_name.into(),
             subject: subject.into(),
             text,
@@ -189,13 +239,7 @@ impl TemplateEmail {
                 &TemplateEmailGeneric::new(&user.email, url.as_str(), audit, service),
             )
             .map_err(DriverError::HandlebarsRender)?;
-        Ok(Self::new(
-            &user.email,
-            &user.name,
-            &service.name,
-            "Registration Request",
-            text,
-        ))
+        Ok(Self::new(user, &service.name, "Registration Request", text))
     }
 
     /// Render register confirm email template.
@@ -220,8 +264,7 @@ impl TemplateEmail {
             )
             .map_err(DriverError::HandlebarsRender)?;
         Ok(Self::new(
-            &user.email,
-            &user.name,
+            user,
             &service.name,
             "Registration Confirmed",
             text,
@@ -250,14 +293,53 @@ impl TemplateEmail {
             )
             .map_err(DriverError::HandlebarsRender)?;
         Ok(Self::new(
-            &user.email,
-            &user.name,
+            user,
             &service.name,
             "Password Reset Request",
             text,
         ))
     }
 
+    /// Render magic link email template.
+    pub fn email_magic_link(
+        service: &Service,
+        user: &User,
+        token: &str,
+        audit: &AuditMeta,
+    ) -> DriverResult<Self> {
+        let url = service.provider_local_callback_url(
+            "magic_link",
+            json!({
+                "email": user.email,
+                "token": token,
+            }),
+        )?;
+
+        let text = HANDLEBARS
+            .render(
+                EMAIL_MAGIC_LINK,
+                &TemplateEmailGeneric::new(&user.email, url.as_str(), audit, service),
+            )
+            .map_err(DriverError::HandlebarsRender)?;
+        Ok(Self::new(user, &service.name, "Login Request", text))
+    }
+
+    /// Render email code email template.
+    pub fn email_code(
+        service: &Service,
+        user: &User,
+        code: &str,
+        audit: &AuditMeta,
+    ) -> DriverResult<Self> {
+        let text = HANDLEBARS
+            .render(
+                EMAIL_CODE,
+                &TemplateEmailCode::new(&user.email, code, audit, service),
+            )
+            .map_err(DriverError::HandlebarsRender)?;
+        Ok(Self::new(user, &service.name, "Login Code", text))
+    }
+
     /// Render reset password confirm email template.
     pub fn email_reset_password_confirm(
         service: &Service,
@@ -280,8 +362,7 @@ impl TemplateEmail {
             )
             .map_err(DriverError::HandlebarsRender)?;
         Ok(Self::new(
-            &user.email,
-            &user.name,
+            user,
             &service.name,
             "Password Reset Confirmed",
             text,
@@ -318,8 +399,7 @@ impl TemplateEmail {
             )
             .map_err(DriverError::HandlebarsRender)?;
         Ok(Self::new(
-            &user.email,
-            &user.name,
+            user,
             &service.name,
             "Email Address Updated",
             text,
@@ -347,12 +427,32 @@ impl TemplateEmail {
                 &TemplateEmailGeneric::new(&user.email, url.as_str(), audit, service),
             )
             .map_err(DriverError::HandlebarsRender)?;
-        Ok(Self::new(
-            &user.email,
-            &user.name,
-            &service.name,
-            "Password Updated",
-            text,
-        ))
+        Ok(Self::new(user, &service.name, "Password Updated", text))
+    }
+}
+
+/// Template HTML.
+#[derive(Debug)]
+pub struct TemplateHtml;
+
+impl TemplateHtml {
+    /// Render OpenID Connect authorize login form template.
+    pub fn oidc_authorize(
+        service: &Service,
+        action: &str,
+        request: &str,
+        error: Option<&str>,
+    ) -> DriverResult<String> {
+        HANDLEBARS
+            .render(
+                HTML_OIDC_AUTHORIZE,
+                &TemplateHtmlOidcAuthorize {
+                    action: action.to_owned(),
+                    request: request.to_owned(),
+                    error: error.map(|x| x.to_owned()),
+                    service: TemplateEmailService::new(service),
+                },
+            )
+            .map_err(DriverError::HandlebarsRender)
     }
 }
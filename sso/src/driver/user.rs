@@ -1,9 +1,14 @@
 use crate::{AuditDiff, AuditDiffBuilder, AuditSubject, DriverError, DriverResult};
-use chrono::{DateTime, Utc};
-use libreauth::pass::HashBuilder;
+use chrono::{DateTime, Duration, Utc};
+use libreauth::{key::KeyBuilder, pass::HashBuilder};
 use serde_json::Value;
-use std::fmt;
+use std::{
+    fmt,
+    time::{Duration as StdDuration, Instant},
+};
+use unicode_normalization::UnicodeNormalization;
 use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
 
 /// Name maximum length.
 pub const MAX_NAME: usize = 100;
@@ -14,9 +19,18 @@ pub const MAX_TEXT: usize = 1000;
 /// TOTP code maximum length.
 pub const MAX_TOTP: usize = 10;
 
+/// Maximum TOTP period in seconds.
+pub const MAX_TOTP_PERIOD: u32 = 300;
+
+/// Maximum TOTP skew in time steps.
+pub const MAX_TOTP_SKEW: u32 = 10;
+
 /// OAuth2 code maximum length.
 pub const MAX_OAUTH2: usize = 1000;
 
+/// SAML metadata and response maximum length.
+pub const MAX_SAML: usize = 100_000;
+
 /// User locale maximum length.
 pub const MAX_USER_LOCALE: usize = 10;
 
@@ -29,17 +43,52 @@ pub const MAX_USER_TIMEZONE: usize = 50;
 /// User default timezone.
 pub const DEFAULT_USER_TIMEZONE: &str = "Etc/UTC";
 
+/// User phone number maximum length, E.164 format.
+pub const MAX_USER_PHONE: usize = 16;
+
 /// User password hash version.
 ///
 /// Passed to libreauth hash builder.
 pub const USER_PASSWORD_HASH_VERSION: usize = 1;
 
+/// User password hash version PHC string parameter.
+///
+/// Libreauth stores hash version offset by its internal version.
+pub const USER_PASSWORD_HASH_PHC_VERSION: &str = "ver=2";
+
+/// User password Argon2id hash PHC string prefix.
+pub const USER_PASSWORD_HASH_ARGON2ID_PREFIX: &str = "$argon2id$";
+
+/// User password Argon2id hash salt length in bytes.
+pub const USER_PASSWORD_HASH_ARGON2ID_SALT_LEN: usize = 16;
+
+/// User password Argon2id hash output length in bytes.
+pub const USER_PASSWORD_HASH_ARGON2ID_LEN: u32 = 32;
+
+/// User password Argon2id default memory cost in KiB.
+pub const DEFAULT_USER_PASSWORD_ARGON2ID_MEMORY: u32 = 19_456;
+
+/// User password Argon2id default iterations.
+pub const DEFAULT_USER_PASSWORD_ARGON2ID_ITERATIONS: u32 = 2;
+
+/// User password Argon2id default parallelism.
+pub const DEFAULT_USER_PASSWORD_ARGON2ID_PARALLELISM: u32 = 1;
+
 /// User password minimum length.
 pub const MIN_USER_PASSWORD: usize = 8;
 
 /// User password maximum length.
 pub const MAX_USER_PASSWORD: usize = 128;
 
+/// User password maximum strength score returned by `zxcvbn`.
+pub const MAX_USER_PASSWORD_STRENGTH: i32 = 4;
+
+/// User password maximum number of previous passwords stored in history.
+pub const MAX_USER_PASSWORD_HISTORY: i32 = 24;
+
+/// User password maximum age in days.
+pub const MAX_USER_PASSWORD_MAX_AGE_DAYS: i32 = 3650;
+
 /// User.
 #[derive(Debug, Clone)]
 pub struct User {
@@ -51,9 +100,11 @@ pub struct User {
     pub email: String,
     pub locale: String,
     pub timezone: String,
+    pub phone: Option<String>,
     pub password_allow_reset: bool,
     pub password_require_update: bool,
     pub password_hash: Option<String>,
+    pub password_updated_at: Option<DateTime<Utc>>,
 }
 
 impl fmt::Display for User {
@@ -66,12 +117,19 @@ impl fmt::Display for User {
         write!(f, "\n\temail {}", self.email)?;
         write!(f, "\n\tlocale {}", self.locale)?;
         write!(f, "\n\ttimezone {}", self.timezone)?;
+        if let Some(phone) = &self.phone {
+            write!(f, "\n\tphone {}", phone)?;
+        }
         write!(f, "\n\tpassword_allow_reset {}", self.password_allow_reset)?;
         write!(
             f,
             "\n\tpassword_require_update {}",
             self.password_require_update
-        )
+        )?;
+        if let Some(password_updated_at) = &self.password_updated_at {
+            write!(f, "\n\tpassword_updated_at {}", password_updated_at)?;
+        }
+        Ok(())
     }
 }
 
@@ -89,6 +147,7 @@ impl AuditDiff for User {
             .compare("email", &self.email, &previous.email)
             .compare("locale", &self.locale, &previous.locale)
             .compare("timezone", &self.timezone, &previous.timezone)
+            .compare_opt("phone", self.phone.as_ref(), previous.phone.as_ref())
             .compare(
                 "password_allow_reset",
                 &self.password_allow_reset,
@@ -128,6 +187,220 @@ impl UserPasswordMeta {
     }
 }
 
+/// User password policy.
+///
+/// Zero values disable the corresponding check.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UserPasswordPolicy {
+    /// Minimum `zxcvbn` strength score.
+    pub min_strength: i32,
+    /// Reject passwords present in `Pwned Passwords` index.
+    pub reject_pwned: bool,
+    /// Number of previous passwords which cannot be reused, in addition to current password.
+    pub history: i32,
+    /// Maximum password age in days, after which user must update password.
+    pub max_age_days: i32,
+}
+
+impl UserPasswordPolicy {
+    /// Returns policy with the stricter value of each check.
+    pub fn merge(&self, other: &Self) -> Self {
+        let max_age_days = match (self.max_age_days, other.max_age_days) {
+            (0, x) | (x, 0) => x,
+            (x, y) => x.min(y),
+        };
+        Self {
+            min_strength: self.min_strength.max(other.min_strength),
+            reject_pwned: self.reject_pwned || other.reject_pwned,
+            history: self.history.max(other.history),
+            max_age_days,
+        }
+    }
+
+    /// Check password metadata against policy, violations are returned as validation errors.
+    ///
+    /// Password strength and pwned checks pass if metadata is unavailable.
+    pub fn check(
+        &self,
+        field: &'static str,
+        meta: &UserPasswordMeta,
+        reused: bool,
+    ) -> DriverResult<()> {
+        let mut errors = ValidationErrors::new();
+        if let Some(password_strength) = meta.password_strength {
+            if i32::from(password_strength) < self.min_strength {
+                let mut e = ValidationError::new("password_strength_invalid");
+                e.add_param("min_strength".into(), &self.min_strength);
+                e.add_param("strength".into(), &password_strength);
+                errors.add(field, e);
+            }
+        }
+        if self.reject_pwned && meta.password_pwned == Some(true) {
+            errors.add(field, ValidationError::new("password_pwned_invalid"));
+        }
+        if reused {
+            let mut e = ValidationError::new("password_reused_invalid");
+            e.add_param("history".into(), &self.history);
+            errors.add(field, e);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(DriverError::Validation(errors))
+        }
+    }
+
+    /// Returns true if user password is older than maximum age.
+    pub fn password_expired(&self, user: &User, now: DateTime<Utc>) -> bool {
+        match (self.max_age_days, user.password_updated_at) {
+            (0, _) | (_, None) => false,
+            (max_age_days, Some(password_updated_at)) => {
+                now - password_updated_at > Duration::days(i64::from(max_age_days))
+            }
+        }
+    }
+}
+
+/// User password hash algorithm.
+///
+/// Hashes of all algorithms are verified, hashes not created by the
+/// configured algorithm and parameters are rehashed on login.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserPasswordHash {
+    /// Libreauth default algorithm and parameters.
+    Libreauth,
+    /// Argon2id with memory cost in KiB, iterations and parallelism.
+    Argon2id {
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Default for UserPasswordHash {
+    fn default() -> Self {
+        Self::Libreauth
+    }
+}
+
+impl UserPasswordHash {
+    /// Returns Argon2id with default parameters.
+    pub fn argon2id() -> Self {
+        Self::Argon2id {
+            memory: DEFAULT_USER_PASSWORD_ARGON2ID_MEMORY,
+            iterations: DEFAULT_USER_PASSWORD_ARGON2ID_ITERATIONS,
+            parallelism: DEFAULT_USER_PASSWORD_ARGON2ID_PARALLELISM,
+        }
+    }
+
+    /// Hash password string.
+    pub fn hash(&self, password: &str) -> DriverResult<String> {
+        match self {
+            Self::Libreauth => hash_password(password),
+            Self::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+            } => {
+                let password = password_normalise(password)?;
+                let salt = KeyBuilder::new()
+                    .size(USER_PASSWORD_HASH_ARGON2ID_SALT_LEN)
+                    .as_vec();
+                let config = argon2::Config {
+                    variant: argon2::Variant::Argon2id,
+                    version: argon2::Version::Version13,
+                    mem_cost: *memory,
+                    time_cost: *iterations,
+                    lanes: *parallelism,
+                    thread_mode: argon2::ThreadMode::from_threads(*parallelism),
+                    hash_length: USER_PASSWORD_HASH_ARGON2ID_LEN,
+                    ..Default::default()
+                };
+                argon2::hash_encoded(password.as_bytes(), &salt, &config)
+                    .map_err(DriverError::Argon2)
+            }
+        }
+    }
+
+    /// Returns PHC string prefix of hashes created by Argon2id parameters.
+    pub fn argon2id_prefix(&self) -> Option<String> {
+        match self {
+            Self::Libreauth => None,
+            Self::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+            } => Some(format!(
+                "{}v=19$m={},t={},p={}$",
+                USER_PASSWORD_HASH_ARGON2ID_PREFIX, memory, iterations, parallelism
+            )),
+        }
+    }
+
+    /// Returns true if password hash was not created by this algorithm and parameters.
+    pub fn needs_update(&self, password_hash: &str) -> DriverResult<bool> {
+        let argon2id = password_hash.starts_with(USER_PASSWORD_HASH_ARGON2ID_PREFIX);
+        match self {
+            Self::Libreauth if argon2id => Ok(true),
+            Self::Libreauth => {
+                let checker =
+                    HashBuilder::from_phc(password_hash).map_err::<DriverError, _>(Into::into)?;
+                Ok(checker.needs_update(Some(USER_PASSWORD_HASH_VERSION)))
+            }
+            Self::Argon2id { .. } => {
+                let prefix = self.argon2id_prefix().unwrap();
+                Ok(!password_hash.starts_with(&prefix))
+            }
+        }
+    }
+
+    /// Returns Argon2id parameters with hash latency of at least target duration.
+    ///
+    /// Memory cost is doubled up to maximum, then iterations are increased.
+    pub fn argon2id_benchmark(
+        target: StdDuration,
+        parallelism: u32,
+        max_memory: u32,
+    ) -> DriverResult<(Self, StdDuration)> {
+        let latency = |hash: &Self| -> DriverResult<StdDuration> {
+            let start = Instant::now();
+            hash.hash("benchmark-password")?;
+            Ok(start.elapsed())
+        };
+
+        let mut memory = DEFAULT_USER_PASSWORD_ARGON2ID_MEMORY.min(max_memory);
+        let mut hash = Self::Argon2id {
+            memory,
+            iterations: 1,
+            parallelism,
+        };
+        let mut elapsed = latency(&hash)?;
+        while matches!(elapsed.checked_mul(2), Some(x) if x <= target)
+            && matches!(memory.checked_mul(2), Some(x) if x <= max_memory)
+        {
+            memory *= 2;
+            hash = Self::Argon2id {
+                memory,
+                iterations: 1,
+                parallelism,
+            };
+            elapsed = latency(&hash)?;
+        }
+
+        let mut iterations: u32 = 1;
+        while elapsed < target && iterations < u32::MAX {
+            iterations += 1;
+            hash = Self::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+            };
+            elapsed = latency(&hash)?;
+        }
+        Ok((hash, elapsed))
+    }
+}
+
 /// User list query.
 #[derive(Debug)]
 pub enum UserListQuery {
@@ -157,13 +430,14 @@ pub struct UserList {
 }
 
 /// User create.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UserCreate {
     pub is_enabled: bool,
     pub name: String,
     pub email: String,
     pub locale: String,
     pub timezone: String,
+    pub phone: Option<String>,
     pub password_allow_reset: bool,
     pub password_require_update: bool,
     pub password_hash: Option<String>,
@@ -181,6 +455,7 @@ impl UserCreate {
             email: email.into(),
             locale: DEFAULT_USER_LOCALE.into(),
             timezone: DEFAULT_USER_TIMEZONE.into(),
+            phone: None,
             password_allow_reset: false,
             password_require_update: false,
             password_hash: None,
@@ -203,6 +478,14 @@ impl UserCreate {
         self
     }
 
+    pub fn phone<P>(mut self, phone: P) -> Self
+    where
+        P: Into<String>,
+    {
+        self.phone = Some(phone.into());
+        self
+    }
+
     pub fn password_allow_reset(mut self, password_allow_reset: bool) -> Self {
         self.password_allow_reset = password_allow_reset;
         self
@@ -213,13 +496,14 @@ impl UserCreate {
         allow_reset: bool,
         require_update: bool,
         password: P,
+        hash: &UserPasswordHash,
     ) -> DriverResult<Self>
     where
         P: AsRef<str>,
     {
         self.password_allow_reset = allow_reset;
         self.password_require_update = require_update;
-        self.password_hash = Some(hash_password(password.as_ref())?);
+        self.password_hash = Some(hash.hash(password.as_ref())?);
         Ok(self)
     }
 }
@@ -240,6 +524,7 @@ pub struct UserUpdate {
     pub email: Option<String>,
     pub locale: Option<String>,
     pub timezone: Option<String>,
+    pub phone: Option<String>,
     pub password_allow_reset: Option<bool>,
     pub password_require_update: Option<bool>,
     pub password_hash: Option<String>,
@@ -262,6 +547,7 @@ impl UserUpdate {
             email: None,
             locale,
             timezone,
+            phone: None,
             password_allow_reset,
             password_require_update,
             password_hash: None,
@@ -276,6 +562,7 @@ impl UserUpdate {
             email: None,
             locale: None,
             timezone: None,
+            phone: None,
             password_allow_reset: None,
             password_require_update: None,
             password_hash: None,
@@ -294,6 +581,7 @@ impl UserUpdate {
             email: Some(email.into()),
             locale: None,
             timezone: None,
+            phone: None,
             password_allow_reset: None,
             password_require_update: None,
             password_hash: None,
@@ -303,7 +591,7 @@ impl UserUpdate {
     /// Update user password.
     ///
     /// This also sets `password_require_update` to false.
-    pub fn new_password<P>(id: Uuid, password: P) -> DriverResult<Self>
+    pub fn new_password<P>(id: Uuid, password: P, hash: &UserPasswordHash) -> DriverResult<Self>
     where
         P: AsRef<str>,
     {
@@ -314,9 +602,10 @@ impl UserUpdate {
             email: None,
             locale: None,
             timezone: None,
+            phone: None,
             password_allow_reset: None,
             password_require_update: Some(false),
-            password_hash: Some(hash_password(password.as_ref())?),
+            password_hash: Some(hash.hash(password.as_ref())?),
         })
     }
 
@@ -325,10 +614,24 @@ impl UserUpdate {
         self
     }
 
+    /// Set user phone number, empty string removes phone number.
+    pub fn set_phone<P>(mut self, phone: P) -> Self
+    where
+        P: Into<String>,
+    {
+        self.phone = Some(phone.into());
+        self
+    }
+
     pub fn set_password_allow_reset(mut self, password_allow_reset: bool) -> Self {
         self.password_allow_reset = Some(password_allow_reset);
         self
     }
+
+    pub fn set_password_require_update(mut self, password_require_update: bool) -> Self {
+        self.password_require_update = Some(password_require_update);
+        self
+    }
 }
 
 /// User token.
@@ -363,18 +666,15 @@ impl User {
     }
 
     /// Checks if password input and password hash match, an error is returned if they do not match
-    /// or the hash is none. Returns true if the hash version does not match the current hash version.
-    pub fn password_check<P>(&self, password: P) -> DriverResult<bool>
+    /// or the hash is none.
+    pub fn password_check<P>(&self, password: P) -> DriverResult<()>
     where
         P: AsRef<str>,
     {
         match self.password_hash() {
             Some(password_hash) => {
-                let checker =
-                    HashBuilder::from_phc(password_hash).map_err::<DriverError, _>(Into::into)?;
-
-                if checker.is_valid(password.as_ref()) {
-                    Ok(checker.needs_update(Some(USER_PASSWORD_HASH_VERSION)))
+                if password_hash_verify(password_hash, password.as_ref())? {
+                    Ok(())
                 } else {
                     Err(DriverError::UserPasswordIncorrect)
                 }
@@ -382,11 +682,43 @@ impl User {
             None => Err(DriverError::UserPasswordUndefined),
         }
     }
+
+    /// Returns true if password hash was not created by hash algorithm and parameters.
+    pub fn password_needs_update(&self, hash: &UserPasswordHash) -> DriverResult<bool> {
+        match self.password_hash() {
+            Some(password_hash) => hash.needs_update(password_hash),
+            None => Err(DriverError::UserPasswordUndefined),
+        }
+    }
+}
+
+/// Returns true if password matches hash, libreauth and Argon2id PHC strings are supported.
+pub(crate) fn password_hash_verify(password_hash: &str, password: &str) -> DriverResult<bool> {
+    if password_hash.starts_with(USER_PASSWORD_HASH_ARGON2ID_PREFIX) {
+        match password_normalise(password) {
+            Ok(password) => argon2::verify_encoded(password_hash, password.as_bytes())
+                .map_err(DriverError::Argon2),
+            Err(_) => Ok(false),
+        }
+    } else {
+        let checker = HashBuilder::from_phc(password_hash).map_err::<DriverError, _>(Into::into)?;
+        Ok(checker.is_valid(password))
+    }
+}
+
+/// Normalise password and check length, as libreauth does before hashing.
+fn password_normalise(password: &str) -> DriverResult<String> {
+    let password: String = password.nfkc().collect();
+    match password.chars().count() {
+        x if x < MIN_USER_PASSWORD => Err(libreauth::pass::ErrorCode::PasswordTooShort.into()),
+        x if x > MAX_USER_PASSWORD => Err(libreauth::pass::ErrorCode::PasswordTooLong.into()),
+        _ => Ok(password),
+    }
 }
 
 /// Hash password string.
 /// <https://github.com/breard-r/libreauth>
-fn hash_password(password: &str) -> DriverResult<String> {
+pub(crate) fn hash_password(password: &str) -> DriverResult<String> {
     let hasher = HashBuilder::new()
         .version(USER_PASSWORD_HASH_VERSION)
         .min_len(MIN_USER_PASSWORD)
@@ -395,3 +727,201 @@ fn hash_password(password: &str) -> DriverResult<String> {
         .map_err::<DriverError, _>(Into::into)?;
     hasher.hash(password).map_err::<DriverError, _>(Into::into)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_new(password_hash: String) -> User {
+        User {
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            id: Uuid::new_v4(),
+            is_enabled: true,
+            name: "user".to_owned(),
+            email: "user@test.com".to_owned(),
+            locale: DEFAULT_USER_LOCALE.to_owned(),
+            timezone: DEFAULT_USER_TIMEZONE.to_owned(),
+            phone: None,
+            password_allow_reset: false,
+            password_require_update: false,
+            password_hash: Some(password_hash),
+            password_updated_at: Some(Utc::now()),
+        }
+    }
+
+    #[test]
+    fn user_password_check_current_version() {
+        let hash = hash_password("guestguest").unwrap();
+        assert!(hash.contains(USER_PASSWORD_HASH_PHC_VERSION));
+
+        let user = user_new(hash);
+        user.password_check("guestguest").unwrap();
+        assert!(user.password_check("guestguests").is_err());
+        assert!(!user
+            .password_needs_update(&UserPasswordHash::Libreauth)
+            .unwrap());
+    }
+
+    #[test]
+    fn user_password_check_legacy_version() {
+        let hash = HashBuilder::new()
+            .version(USER_PASSWORD_HASH_VERSION - 1)
+            .finalize()
+            .unwrap()
+            .hash("guestguest")
+            .unwrap();
+        assert!(!hash.contains(USER_PASSWORD_HASH_PHC_VERSION));
+
+        let user = user_new(hash);
+        user.password_check("guestguest").unwrap();
+        assert!(user
+            .password_needs_update(&UserPasswordHash::Libreauth)
+            .unwrap());
+    }
+
+    #[test]
+    fn user_password_check_argon2id() {
+        let hash = UserPasswordHash::Argon2id {
+            memory: 64,
+            iterations: 1,
+            parallelism: 2,
+        };
+        let password_hash = hash.hash("guestguest").unwrap();
+        assert!(password_hash.starts_with("$argon2id$v=19$m=64,t=1,p=2$"));
+        assert!(hash.hash("guest").is_err());
+
+        let user = user_new(password_hash);
+        user.password_check("guestguest").unwrap();
+        assert!(user.password_check("guestguests").is_err());
+        assert!(user.password_check("guest").is_err());
+        assert!(!user.password_needs_update(&hash).unwrap());
+        assert!(user
+            .password_needs_update(&UserPasswordHash::Argon2id {
+                memory: 128,
+                iterations: 1,
+                parallelism: 2,
+            })
+            .unwrap());
+        assert!(user
+            .password_needs_update(&UserPasswordHash::Libreauth)
+            .unwrap());
+
+        let user = user_new(hash_password("guestguest").unwrap());
+        assert!(user.password_needs_update(&hash).unwrap());
+    }
+
+    #[test]
+    fn user_password_hash_argon2id_benchmark() {
+        let target = StdDuration::from_millis(1);
+        let (hash, elapsed) = UserPasswordHash::argon2id_benchmark(target, 1, 1024).unwrap();
+        assert!(elapsed >= target);
+        match hash {
+            UserPasswordHash::Argon2id {
+                memory,
+                parallelism,
+                ..
+            } => {
+                assert!(memory <= 1024);
+                assert_eq!(parallelism, 1);
+            }
+            _ => panic!("unexpected hash {:?}", hash),
+        }
+    }
+
+    #[test]
+    fn user_password_policy_merge() {
+        let global = UserPasswordPolicy {
+            min_strength: 2,
+            reject_pwned: false,
+            history: 3,
+            max_age_days: 0,
+        };
+        let service = UserPasswordPolicy {
+            min_strength: 3,
+            reject_pwned: true,
+            history: 1,
+            max_age_days: 90,
+        };
+        let policy = global.merge(&service);
+        assert_eq!(
+            policy,
+            UserPasswordPolicy {
+                min_strength: 3,
+                reject_pwned: true,
+                history: 3,
+                max_age_days: 90,
+            }
+        );
+        let policy = policy.merge(&UserPasswordPolicy {
+            max_age_days: 30,
+            ..Default::default()
+        });
+        assert_eq!(policy.max_age_days, 30);
+    }
+
+    #[test]
+    fn user_password_policy_check() {
+        let policy = UserPasswordPolicy {
+            min_strength: 3,
+            reject_pwned: true,
+            history: 1,
+            max_age_days: 0,
+        };
+        let meta = UserPasswordMeta {
+            password_strength: Some(4),
+            password_pwned: Some(false),
+        };
+        policy.check("password", &meta, false).unwrap();
+        policy
+            .check("password", &UserPasswordMeta::default(), false)
+            .unwrap();
+
+        let meta = UserPasswordMeta {
+            password_strength: Some(2),
+            password_pwned: Some(true),
+        };
+        match policy.check("password", &meta, true) {
+            Err(DriverError::Validation(e)) => {
+                let codes: Vec<_> = e.field_errors()["password"]
+                    .iter()
+                    .map(|x| x.code.to_string())
+                    .collect();
+                assert_eq!(
+                    codes,
+                    vec![
+                        "password_strength_invalid",
+                        "password_pwned_invalid",
+                        "password_reused_invalid"
+                    ]
+                );
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+        UserPasswordPolicy::default()
+            .check("password", &meta, false)
+            .unwrap();
+        assert!(UserPasswordPolicy::default()
+            .check("password", &meta, true)
+            .is_err());
+    }
+
+    #[test]
+    fn user_password_policy_password_expired() {
+        let now = Utc::now();
+        let mut user = user_new(hash_password("guestguest").unwrap());
+        user.password_updated_at = Some(now - Duration::days(31));
+
+        let policy = UserPasswordPolicy {
+            max_age_days: 30,
+            ..Default::default()
+        };
+        assert!(policy.password_expired(&user, now));
+        assert!(!UserPasswordPolicy::default().password_expired(&user, now));
+
+        user.password_updated_at = Some(now - Duration::days(29));
+        assert!(!policy.password_expired(&user, now));
+        user.password_updated_at = None;
+        assert!(!policy.password_expired(&user, now));
+    }
+}
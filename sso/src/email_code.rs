@@ -0,0 +1,73 @@
+use crate::prelude::*;
+use diesel::{Connection, PgConnection};
+use openssl::{memcmp, rand::rand_bytes, sha::sha256};
+
+/// Email code number of digits.
+pub const EMAIL_CODE_DIGITS: usize = 6;
+
+/// Email code.
+///
+/// Single use codes sent to users by email for passwordless login. Codes are
+/// stored in the CSRF table keyed by a hash of service and user, so only the
+/// latest code for a service user is valid, the value is a hash of the code.
+#[derive(Debug)]
+pub struct EmailCode;
+
+impl EmailCode {
+    /// Generate code for service user with time to live.
+    /// Previous code for service user is deleted.
+    pub fn generate(
+        conn: &PgConnection,
+        service_id: Uuid,
+        user_id: Uuid,
+        ttl: Duration,
+    ) -> DriverResult<String> {
+        let mut buf = [0u8; 4];
+        rand_bytes(&mut buf).map_err(DriverError::Openssl)?;
+        let code = format!(
+            "{:0width$}",
+            u32::from_be_bytes(buf) % 10u32.pow(EMAIL_CODE_DIGITS as u32),
+            width = EMAIL_CODE_DIGITS
+        );
+        let key = Self::key(service_id, user_id);
+        let value = Self::value(service_id, user_id, &code);
+        conn.transaction(|| {
+            CsrfRead::read(conn, &key)?;
+            CsrfCreate::create(conn, key, value, ttl, service_id)
+        })?;
+        Ok(code)
+    }
+
+    /// Verify code for service user, code is deleted after one successful verification.
+    pub fn verify(
+        conn: &PgConnection,
+        service_id: Uuid,
+        user_id: Uuid,
+        code: &str,
+    ) -> DriverResult<()> {
+        let key = Self::key(service_id, user_id);
+        let value = Self::value(service_id, user_id, code);
+        let csrf = CsrfRead::peek(conn, &key)?
+            .filter(|csrf| csrf.service_id() == service_id)
+            .ok_or(DriverError::EmailCodeInvalid)?;
+        let stored = csrf.value().as_bytes();
+        if stored.len() != value.len() || !memcmp::eq(stored, value.as_bytes()) {
+            return Err(DriverError::EmailCodeInvalid);
+        }
+        // Code is only used once if verified concurrently.
+        if !CsrfRead::delete_by_value(conn, &key, &value)? {
+            return Err(DriverError::EmailCodeInvalid);
+        }
+        Ok(())
+    }
+
+    fn key(service_id: Uuid, user_id: Uuid) -> String {
+        let key = format!("email_code:{}:{}", service_id, user_id);
+        base64::encode(sha256(key.as_bytes()))
+    }
+
+    fn value(service_id: Uuid, user_id: Uuid, code: &str) -> String {
+        let value = format!("{}:{}:{}", service_id, user_id, code);
+        base64::encode(sha256(value.as_bytes()))
+    }
+}
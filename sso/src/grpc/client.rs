@@ -399,6 +399,13 @@ impl GrpcClientBlocking {
         self.rt.block_on(self.client.key_update(request))
     }
 
+    pub fn key_rotate(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::KeyRotateRequest>,
+    ) -> Result<tonic::Response<pb::KeyRotateReply>, tonic::Status> {
+        self.rt.block_on(self.client.key_rotate(request))
+    }
+
     pub fn key_delete(
         &mut self,
         request: impl tonic::IntoRequest<pb::KeyReadRequest>,
@@ -441,6 +448,44 @@ impl GrpcClientBlocking {
         self.rt.block_on(self.client.service_delete(request))
     }
 
+    pub fn oauth2_provider_list(
+        &mut self,
+        request: impl tonic::IntoRequest<()>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderListReply>, tonic::Status> {
+        self.rt.block_on(self.client.oauth2_provider_list(request))
+    }
+
+    pub fn oauth2_provider_create(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::Oauth2ProviderCreateRequest>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderReadReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.oauth2_provider_create(request))
+    }
+
+    pub fn oauth2_provider_read(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::Oauth2ProviderReadRequest>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderReadReply>, tonic::Status> {
+        self.rt.block_on(self.client.oauth2_provider_read(request))
+    }
+
+    pub fn oauth2_provider_update(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::Oauth2ProviderUpdateRequest>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderReadReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.oauth2_provider_update(request))
+    }
+
+    pub fn oauth2_provider_delete(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::Oauth2ProviderReadRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        self.rt
+            .block_on(self.client.oauth2_provider_delete(request))
+    }
+
     pub fn user_list(
         &mut self,
         request: impl tonic::IntoRequest<pb::UserListRequest>,
@@ -476,6 +521,27 @@ impl GrpcClientBlocking {
         self.rt.block_on(self.client.user_delete(request))
     }
 
+    pub fn user_session_list(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::UserSessionListRequest>,
+    ) -> Result<tonic::Response<pb::UserSessionListReply>, tonic::Status> {
+        self.rt.block_on(self.client.user_session_list(request))
+    }
+
+    pub fn user_session_revoke(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::UserSessionRevokeRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        self.rt.block_on(self.client.user_session_revoke(request))
+    }
+
+    pub fn user_login_unlock(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::UserLoginUnlockRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        self.rt.block_on(self.client.user_login_unlock(request))
+    }
+
     pub fn auth_key_verify(
         &mut self,
         request: impl tonic::IntoRequest<pb::AuthKeyRequest>,
@@ -518,6 +584,30 @@ impl GrpcClientBlocking {
         self.rt.block_on(self.client.auth_totp_verify(request))
     }
 
+    pub fn auth_totp_register_begin(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthTotpRegisterBeginRequest>,
+    ) -> Result<tonic::Response<pb::AuthTotpRegisterBeginReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_totp_register_begin(request))
+    }
+
+    pub fn auth_totp_register_finish(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthTotpRegisterFinishRequest>,
+    ) -> Result<tonic::Response<pb::AuthTotpRegisterFinishReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_totp_register_finish(request))
+    }
+
+    pub fn auth_totp_recovery_verify(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthTotpRecoveryRequest>,
+    ) -> Result<tonic::Response<pb::AuthAuditReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_totp_recovery_verify(request))
+    }
+
     pub fn auth_csrf_create(
         &mut self,
         request: impl tonic::IntoRequest<pb::AuthCsrfCreateRequest>,
@@ -539,6 +629,43 @@ impl GrpcClientBlocking {
         self.rt.block_on(self.client.auth_local_login(request))
     }
 
+    pub fn auth_local_login_mfa(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthLoginMfaRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        self.rt.block_on(self.client.auth_local_login_mfa(request))
+    }
+
+    pub fn auth_local_magic_link(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthMagicLinkRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        self.rt.block_on(self.client.auth_local_magic_link(request))
+    }
+
+    pub fn auth_local_magic_link_confirm(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthMagicLinkConfirmRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_local_magic_link_confirm(request))
+    }
+
+    pub fn auth_local_email_code(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthEmailCodeRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        self.rt.block_on(self.client.auth_local_email_code(request))
+    }
+
+    pub fn auth_local_email_code_confirm(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthEmailCodeConfirmRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_local_email_code_confirm(request))
+    }
+
     pub fn auth_local_register(
         &mut self,
         request: impl tonic::IntoRequest<pb::AuthRegisterRequest>,
@@ -649,4 +776,64 @@ impl GrpcClientBlocking {
         self.rt
             .block_on(self.client.auth_microsoft_oauth2_callback(request))
     }
+
+    pub fn auth_oauth2_url(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthOauth2UrlRequest>,
+    ) -> Result<tonic::Response<pb::AuthOauth2UrlReply>, tonic::Status> {
+        self.rt.block_on(self.client.auth_oauth2_url(request))
+    }
+
+    pub fn auth_oauth2_callback(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthOauth2ProviderCallbackRequest>,
+    ) -> Result<tonic::Response<pb::AuthTokenReply>, tonic::Status> {
+        self.rt.block_on(self.client.auth_oauth2_callback(request))
+    }
+
+    pub fn auth_saml_url(
+        &mut self,
+        request: impl tonic::IntoRequest<()>,
+    ) -> Result<tonic::Response<pb::AuthSamlUrlReply>, tonic::Status> {
+        self.rt.block_on(self.client.auth_saml_url(request))
+    }
+
+    pub fn auth_saml_callback(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthSamlCallbackRequest>,
+    ) -> Result<tonic::Response<pb::AuthTokenReply>, tonic::Status> {
+        self.rt.block_on(self.client.auth_saml_callback(request))
+    }
+
+    pub fn auth_webauthn_register_begin(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthWebauthnRegisterBeginRequest>,
+    ) -> Result<tonic::Response<pb::AuthWebauthnChallengeReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_webauthn_register_begin(request))
+    }
+
+    pub fn auth_webauthn_register_finish(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthWebauthnFinishRequest>,
+    ) -> Result<tonic::Response<pb::AuthWebauthnRegisterFinishReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_webauthn_register_finish(request))
+    }
+
+    pub fn auth_webauthn_login_begin(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthWebauthnLoginBeginRequest>,
+    ) -> Result<tonic::Response<pb::AuthWebauthnChallengeReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_webauthn_login_begin(request))
+    }
+
+    pub fn auth_webauthn_login_finish(
+        &mut self,
+        request: impl tonic::IntoRequest<pb::AuthWebauthnFinishRequest>,
+    ) -> Result<tonic::Response<pb::AuthTokenReply>, tonic::Status> {
+        self.rt
+            .block_on(self.client.auth_webauthn_login_finish(request))
+    }
 }
@@ -18,7 +18,7 @@ pub async fn oauth2_url(
         .map_err(Into::into)
     })
     .await
-    .map(|url| pb::AuthOauth2UrlReply { url })
+    .map(|(url, session)| pb::AuthOauth2UrlReply { url, session })
 }
 
 impl validator::Validate for pb::AuthOauth2CallbackRequest {
@@ -26,6 +26,7 @@ impl validator::Validate for pb::AuthOauth2CallbackRequest {
         validate::wrap(|e| {
             validate::oauth2_token(e, "code", &self.code);
             validate::oauth2_token(e, "state", &self.state);
+            validate::key(e, "session", &self.session);
         })
     }
 }
@@ -85,10 +86,11 @@ pub async fn oauth2_callback(
 }
 
 mod provider_github {
-    use crate::{pattern::*, prelude::*};
+    use crate::{grpc::method::auth::Oauth2State, pattern::*, prelude::*};
     use oauth2::{
         basic::BasicClient, reqwest::http_client, AuthUrl, AuthorizationCode, ClientId,
-        ClientSecret, CsrfToken, RedirectUrl, Scope, TokenResponse, TokenUrl,
+        ClientSecret, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+        TokenResponse, TokenUrl,
     };
     use reqwest::Client;
 
@@ -97,30 +99,35 @@ mod provider_github {
         audit: &mut AuditBuilder,
         auth: &HeaderAuth,
         args: &ServerProviderOauth2Args,
-    ) -> GrpcMethodResult<String> {
+    ) -> GrpcMethodResult<(String, String)> {
         let service =
             key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
 
+        // Create a PKCE code verifier and SHA-256 encode it as a code challenge.
+        let (pkce_code_challenge, pkce_code_verifier) = PkceCodeChallenge::new_random_sha256();
+
         // Generate the authorisation URL to which we'll redirect the user.
         let client = new_client(&service, &args.provider).map_err(GrpcMethodError::BadRequest)?;
         let (authorise_url, csrf_state) = client
             .authorize_url(CsrfToken::new_random)
             .add_scope(Scope::new("user:email".to_string()))
+            .set_pkce_challenge(pkce_code_challenge)
             .url();
 
-        // Save the state and code verifier secrets as a CSRF key, value.
-        let csrf_key = csrf_state.secret();
+        // Save the state as a CSRF key, code verifier and browser session as value.
+        let (state, session) = Oauth2State::new(None, Some(pkce_code_verifier.secret().to_owned()));
+        let csrf_value = state.to_value().map_err(GrpcMethodError::BadRequest)?;
         let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
         CsrfCreate::create(
             &conn,
-            csrf_key,
-            csrf_key,
+            csrf_state.secret(),
+            csrf_value,
             args.access_token_expires,
             service.id,
         )
         .map_err(GrpcMethodError::BadRequest)?;
 
-        Ok(authorise_url.to_string())
+        Ok((authorise_url.to_string(), session))
     }
 
     pub(crate) fn oauth2_callback(
@@ -133,18 +140,24 @@ mod provider_github {
         let service =
             key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
 
-        // Read the CSRF key using state value, rebuild code verifier from value.
+        // Read the CSRF key using state value, check browser session matches URL request.
         let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
         let csrf = CsrfRead::read(&conn, &request.state)
             .map_err(GrpcMethodError::BadRequest)?
             .ok_or_else(|| DriverError::CsrfNotFoundOrUsed)
             .map_err(GrpcMethodError::BadRequest)?;
+        let state =
+            Oauth2State::from_csrf(&csrf, &request.session).map_err(GrpcMethodError::BadRequest)?;
 
         // Exchange the code with a token.
         let client = new_client(&service, &args.provider).map_err(GrpcMethodError::BadRequest)?;
         let code = AuthorizationCode::new(request.code.clone());
-        let token = client
-            .exchange_code(code)
+        let mut token_request = client.exchange_code(code);
+        if let Some(pkce_code_verifier) = state.pkce_code_verifier {
+            token_request =
+                token_request.set_pkce_verifier(PkceCodeVerifier::new(pkce_code_verifier));
+        }
+        let token = token_request
             .request(http_client)
             .map_err(|err| DriverError::Oauth2Request(err.into()))
             .map_err(GrpcMethodError::BadRequest)?;
@@ -98,6 +98,9 @@ pub async fn revoke(
                         is_enabled: Some(false),
                         is_revoked: Some(true),
                         name: None,
+                        expires_at: None,
+                        scopes: None,
+                        role: None,
                     })
                     .map_err(GrpcMethodError::BadRequest)?;
 
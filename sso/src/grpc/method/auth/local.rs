@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use diesel::PgConnection;
 
 impl validator::Validate for pb::AuthLoginRequest {
     fn validate(&self) -> Result<(), validator::ValidationErrors> {
@@ -16,7 +17,7 @@ pub async fn login(
     let (audit_meta, auth, req) = request.into_inner();
 
     let client = server.client();
-    let pwned_passwords = server.options().pwned_passwords_enabled();
+    let pwned_passwords = server.options().pwned_passwords_options();
     let password_meta =
         pattern::password_meta(client.as_ref(), pwned_passwords, Some(req.password.clone()))
             .await
@@ -25,8 +26,12 @@ pub async fn login(
     let driver = server.driver();
     let access_token_expires = server.options().access_token_expires();
     let refresh_token_expires = server.options().refresh_token_expires();
+    let lockout = server.options().lockout_options();
+    let password_policy = server.options().password_policy_options();
+    let password_hash = server.options().password_hash_options();
+    let mfa_token_expires = Duration::seconds(DEFAULT_MFA_TOKEN_EXPIRES_S);
     blocking_method(move || {
-        let user_token = audit_result(
+        let reply = audit_result(
             driver.as_ref(),
             audit_meta,
             AuditType::AuthLocalLogin,
@@ -34,9 +39,12 @@ pub async fn login(
                 let service = pattern::key_service_authenticate(driver, audit, &auth)
                     .map_err(GrpcMethodError::Unauthorised)?;
 
+                // Forbidden if email or remote locked out by failed logins.
                 // Login requires token key type.
+                // Unknown emails are counted as failed logins, so lockout
+                // does not reveal whether user exists.
                 let user =
-                    pattern::user_read_email_checked(driver, Some(&service), audit, &req.email)
+                    pattern::user_read_email_lockout(driver, audit, &lockout, &service, &req.email)
                         .map_err(GrpcMethodError::BadRequest)?;
                 let key =
                     pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
@@ -50,13 +58,140 @@ pub async fn login(
                 }
 
                 // Check user password.
-                user.password_check(&req.password)
+                pattern::user_password_check_lockout(
+                    driver,
+                    audit,
+                    &lockout,
+                    &password_hash,
+                    &user,
+                    &req.password,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+
+                // Forbidden if user password is older than password policy maximum age.
+                pattern::user_password_age_check(driver, &password_policy, &service, &user)
+                    .map_err(GrpcMethodError::Forbidden)?;
+
+                login_reply(
+                    driver,
+                    &conn,
+                    audit,
+                    &service,
+                    user,
+                    &key,
+                    mfa_token_expires,
+                    access_token_expires,
+                    refresh_token_expires,
+                )
+            },
+        )?;
+        Ok(pb::AuthLoginReply {
+            meta: Some(password_meta.into()),
+            ..reply
+        })
+    })
+    .await
+}
+
+impl validator::Validate for pb::AuthLoginMfaRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::token(e, "token", &self.token);
+            validate::totp_opt(e, "totp", self.totp.as_ref().map(|x| &**x));
+            if let Some(recovery_code) = &self.recovery_code {
+                validate::key(e, "recovery_code", recovery_code);
+            }
+            if let Some(webauthn) = &self.webauthn {
+                validate::csrf_token(e, "csrf", &webauthn.csrf);
+                validate::webauthn_credential(e, "credential", webauthn.credential.as_ref());
+            }
+        })
+    }
+}
+
+pub async fn login_mfa(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthLoginMfaRequest>,
+) -> GrpcMethodResult<pb::AuthLoginReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+    let refresh_token_expires = server.options().refresh_token_expires();
+    let lockout = server.options().lockout_options();
+    blocking_method(move || {
+        audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthLocalLoginMfa,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Unsafely decode token to get user identifier, used to read key for safe token decode.
+                let (user_id, _) = Jwt::decode_unsafe_user(&req.token, service.id)
                     .map_err(GrpcMethodError::BadRequest)?;
 
-                // Encode user token.
+                // Login requires token key type.
+                let user = pattern::user_read_id_checked(driver, Some(&service), audit, user_id)
+                    .map_err(GrpcMethodError::BadRequest)?;
+                let key =
+                    pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                // Safely decode token with user key, MFA token can only be used once
+                // so an incorrect second factor requires login with password again.
                 let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                Jwt::decode_mfa(&conn, &service, &user, &key, &req.token)
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                // Forbidden if email or remote locked out by failed logins.
+                LoginLockout::check(&conn, &user.email, audit.meta().remote())
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                // Verify second factor, TOTP code, WebAuthn assertion for same user or
+                // TOTP recovery code. Incorrect second factors are counted as failed logins.
+                let res = match (&req.totp, &req.webauthn, &req.recovery_code) {
+                    (Some(totp), _, _) => pattern::key_read_user_checked(
+                        driver,
+                        &service,
+                        audit,
+                        &user,
+                        KeyType::Totp,
+                    )
+                    .and_then(|totp_key| pattern::totp_verify(driver, &totp_key, totp)),
+                    (None, Some(webauthn), _) => super::webauthn::provider_webauthn::login_finish(
+                        driver, audit, &service, webauthn,
+                    )
+                    .and_then(|webauthn_user| {
+                        if webauthn_user.id != user.id {
+                            Err(DriverError::UserMfaMismatch)
+                        } else {
+                            Ok(())
+                        }
+                    }),
+                    (None, None, Some(recovery_code)) => pattern::key_read_user_checked(
+                        driver,
+                        &service,
+                        audit,
+                        &user,
+                        KeyType::Totp,
+                    )
+                    .and_then(|totp_key| TotpRecovery::use_code(&conn, totp_key.id, recovery_code)),
+                    (None, None, None) => {
+                        return Err(GrpcMethodError::BadRequest(DriverError::UserMfaUndefined));
+                    }
+                };
+                pattern::user_login_lockout(driver, audit, &lockout, &user, res)
+                    .map_err(GrpcMethodError::BadRequest)?;
+                pattern::user_login_success(driver, &user).map_err(GrpcMethodError::BadRequest)?;
+
+                // Encode user token.
                 Jwt::encode_user(
                     &conn,
+                    driver.data_keys(),
+                    audit.meta(),
                     &service,
                     user,
                     &key,
@@ -65,16 +200,250 @@ pub async fn login(
                 )
                 .map_err(GrpcMethodError::BadRequest)
             },
-        )?;
-        Ok((password_meta, user_token))
+        )
     })
     .await
-    .map(|(password_meta, user_token)| pb::AuthLoginReply {
-        meta: Some(password_meta.into()),
-        user: Some(user_token.user.clone().into()),
-        access: Some(user_token.access_token()),
-        refresh: Some(user_token.refresh_token()),
+    .map(Into::into)
+}
+
+impl validator::Validate for pb::AuthMagicLinkRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::email(e, "email", &self.email);
+        })
+    }
+}
+
+pub async fn magic_link(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthMagicLinkRequest>,
+) -> GrpcMethodResult<()> {
+    let (audit_meta, auth, req) = request.into_inner();
+    let driver = server.driver();
+    let token_expires = Duration::seconds(DEFAULT_EMAIL_CODE_EXPIRES_S);
+    let notify = server.notify();
+
+    blocking_method(move || {
+        let template = audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthLocalMagicLink,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Magic link requires token key type.
+                let user =
+                    pattern::user_read_email_checked(driver, Some(&service), audit, &req.email)
+                        .map_err(GrpcMethodError::BadRequest)?;
+                let key =
+                    pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                // Encode magic link token.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let token = Jwt::encode_magic_link(
+                    &conn,
+                    driver.data_keys(),
+                    &service,
+                    &user,
+                    &key,
+                    token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                // Send magic link email.
+                TemplateEmail::email_magic_link(&service, &user, &token, audit.meta())
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        );
+        // Catch Err result so this function returns Ok to prevent the caller
+        // from inferring a users existence.
+        match template {
+            Ok(template) => notify(template)
+                .map_err::<DriverError, _>(Into::into)
+                .map_err(GrpcMethodError::BadRequest)
+                .or_else(|_| Ok(())),
+            Err(_e) => Ok(()),
+        }
+    })
+    .await
+}
+
+impl validator::Validate for pb::AuthMagicLinkConfirmRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::token(e, "token", &self.token);
+        })
+    }
+}
+
+pub async fn magic_link_confirm(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthMagicLinkConfirmRequest>,
+) -> GrpcMethodResult<pb::AuthLoginReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+    let refresh_token_expires = server.options().refresh_token_expires();
+    let mfa_token_expires = Duration::seconds(DEFAULT_MFA_TOKEN_EXPIRES_S);
+    blocking_method(move || {
+        audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthLocalMagicLinkConfirm,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Unsafely decode token to get user identifier, used to read key for safe token decode.
+                let (user_id, _) = Jwt::decode_unsafe_user(&req.token, service.id)
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                // Magic link confirm requires token key type.
+                let user = pattern::user_read_id_checked(driver, Some(&service), audit, user_id)
+                    .map_err(GrpcMethodError::BadRequest)?;
+                let key =
+                    pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                // Safely decode token with user key, magic link token can only be used once.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                Jwt::decode_magic_link(&conn, &service, &user, &key, &req.token)
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                login_reply(
+                    driver,
+                    &conn,
+                    audit,
+                    &service,
+                    user,
+                    &key,
+                    mfa_token_expires,
+                    access_token_expires,
+                    refresh_token_expires,
+                )
+            },
+        )
+    })
+    .await
+}
+
+impl validator::Validate for pb::AuthEmailCodeRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::email(e, "email", &self.email);
+        })
+    }
+}
+
+pub async fn email_code(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthEmailCodeRequest>,
+) -> GrpcMethodResult<()> {
+    let (audit_meta, auth, req) = request.into_inner();
+    let driver = server.driver();
+    let code_expires = Duration::seconds(DEFAULT_EMAIL_CODE_EXPIRES_S);
+    let notify = server.notify();
+
+    blocking_method(move || {
+        let template = audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthLocalEmailCode,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Email code requires token key type.
+                let user =
+                    pattern::user_read_email_checked(driver, Some(&service), audit, &req.email)
+                        .map_err(GrpcMethodError::BadRequest)?;
+                pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                // Generate email code.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let code = EmailCode::generate(&conn, service.id, user.id, code_expires)
+                    .map_err(GrpcMethodError::BadRequest)?;
+                // Send email code email.
+                TemplateEmail::email_code(&service, &user, &code, audit.meta())
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        );
+        // Catch Err result so this function returns Ok to prevent the caller
+        // from inferring a users existence.
+        match template {
+            Ok(template) => notify(template)
+                .map_err::<DriverError, _>(Into::into)
+                .map_err(GrpcMethodError::BadRequest)
+                .or_else(|_| Ok(())),
+            Err(_e) => Ok(()),
+        }
+    })
+    .await
+}
+
+impl validator::Validate for pb::AuthEmailCodeConfirmRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::email(e, "email", &self.email);
+            validate::email_code(e, "code", &self.code);
+        })
+    }
+}
+
+pub async fn email_code_confirm(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthEmailCodeConfirmRequest>,
+) -> GrpcMethodResult<pb::AuthLoginReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+    let refresh_token_expires = server.options().refresh_token_expires();
+    let lockout = server.options().lockout_options();
+    let mfa_token_expires = Duration::seconds(DEFAULT_MFA_TOKEN_EXPIRES_S);
+    blocking_method(move || {
+        audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthLocalEmailCodeConfirm,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Forbidden if email or remote locked out by failed logins.
+                // Email code confirm requires token key type.
+                // Unknown emails and incorrect codes are counted as failed logins.
+                let user =
+                    pattern::user_read_email_lockout(driver, audit, &lockout, &service, &req.email)
+                        .map_err(GrpcMethodError::BadRequest)?;
+                let key =
+                    pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                // Check email code, code can only be used once.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let res = EmailCode::verify(&conn, service.id, user.id, &req.code);
+                pattern::user_login_lockout(driver, audit, &lockout, &user, res)
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                login_reply(
+                    driver,
+                    &conn,
+                    audit,
+                    &service,
+                    user,
+                    &key,
+                    mfa_token_expires,
+                    access_token_expires,
+                    refresh_token_expires,
+                )
+            },
+        )
     })
+    .await
 }
 
 impl validator::Validate for pb::AuthRegisterRequest {
@@ -95,7 +464,7 @@ pub async fn register(
     let (audit_meta, auth, req) = request.into_inner();
     let driver = server.driver();
     let access_token_expires = server.options().access_token_expires();
-    let email = server.smtp_email();
+    let notify = server.notify();
 
     blocking_method(move || {
         let template = audit_result(
@@ -159,15 +528,21 @@ pub async fn register(
                 };
                 // Encode register token.
                 let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
-                let token =
-                    Jwt::encode_register(&conn, &service, &user, &key, access_token_expires)
-                        .map_err(GrpcMethodError::BadRequest)?;
+                let token = Jwt::encode_register(
+                    &conn,
+                    driver.data_keys(),
+                    &service,
+                    &user,
+                    &key,
+                    access_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
                 // Send register email.
                 TemplateEmail::email_register(&service, &user, &token, audit.meta())
                     .map_err(GrpcMethodError::BadRequest)
             },
         )?;
-        email(template)
+        notify(template)
             .map_err::<DriverError, _>(Into::into)
             .map_err(GrpcMethodError::BadRequest)?;
         Ok(())
@@ -191,15 +566,17 @@ pub async fn register_confirm(
     let (audit_meta, auth, req) = request.into_inner();
 
     let client = server.client();
-    let pwned_passwords = server.options().pwned_passwords_enabled();
+    let pwned_passwords = server.options().pwned_passwords_options();
     let password_meta =
         pattern::password_meta(client.as_ref(), pwned_passwords, req.password.clone())
             .await
             .map_err(GrpcMethodError::BadRequest)?;
 
     let driver = server.driver();
+    let password_policy = server.options().password_policy_options();
+    let password_hash = server.options().password_hash_options();
     let revoke_token_expires = server.options().revoke_token_expires();
-    let email = server.smtp_email();
+    let notify = server.notify();
     blocking_method(move || {
         let template = audit_result(
             driver.as_ref(),
@@ -232,13 +609,31 @@ pub async fn register_confirm(
                     .map_err(GrpcMethodError::BadRequest)?;
 
                 // Encode revoke token.
-                let token = Jwt::encode_revoke(&conn, &service, &user, &key, revoke_token_expires)
-                    .map_err(GrpcMethodError::BadRequest)?;
+                let token = Jwt::encode_revoke(
+                    &conn,
+                    driver.data_keys(),
+                    &service,
+                    &user,
+                    &key,
+                    revoke_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
 
                 // Update user password and allow reset flag if provided.
                 if let Some(password) = &req.password {
-                    let mut user_update = UserUpdate::new_password(user.id, password)
-                        .map_err(GrpcMethodError::BadRequest)?;
+                    pattern::user_password_policy_check(
+                        driver,
+                        &password_policy,
+                        Some(&service),
+                        Some(&user),
+                        "password",
+                        password,
+                        &password_meta,
+                    )
+                    .map_err(GrpcMethodError::BadRequest)?;
+                    let mut user_update =
+                        UserUpdate::new_password(user.id, password, &password_hash)
+                            .map_err(GrpcMethodError::BadRequest)?;
                     if let Some(password_allow_reset) = req.password_allow_reset {
                         user_update = user_update.set_password_allow_reset(password_allow_reset);
                     }
@@ -251,7 +646,7 @@ pub async fn register_confirm(
                     .map_err(GrpcMethodError::BadRequest)
             },
         )?;
-        email(template)
+        notify(template)
             .map_err::<DriverError, _>(Into::into)
             .map_err(GrpcMethodError::BadRequest)?;
         Ok(password_meta)
@@ -299,7 +694,7 @@ pub async fn reset_password(
     let (audit_meta, auth, req) = request.into_inner();
     let driver = server.driver();
     let access_token_expires = server.options().access_token_expires();
-    let email = server.smtp_email();
+    let notify = server.notify();
 
     blocking_method(move || {
         let template = audit_result(
@@ -327,9 +722,15 @@ pub async fn reset_password(
 
                 // Encode reset token.
                 let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
-                let token =
-                    Jwt::encode_reset_password(&conn, &service, &user, &key, access_token_expires)
-                        .map_err(GrpcMethodError::BadRequest)?;
+                let token = Jwt::encode_reset_password(
+                    &conn,
+                    driver.data_keys(),
+                    &service,
+                    &user,
+                    &key,
+                    access_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
                 // Send reset password email.
                 TemplateEmail::email_reset_password(&service, &user, &token, audit.meta())
                     .map_err(GrpcMethodError::BadRequest)
@@ -338,7 +739,7 @@ pub async fn reset_password(
         // Catch Err result so this function returns Ok to prevent the caller
         // from inferring a users existence.
         match template {
-            Ok(template) => email(template)
+            Ok(template) => notify(template)
                 .map_err::<DriverError, _>(Into::into)
                 .map_err(GrpcMethodError::BadRequest)
                 .or_else(|_| Ok(())),
@@ -364,15 +765,17 @@ pub async fn reset_password_confirm(
     let (audit_meta, auth, req) = request.into_inner();
 
     let client = server.client();
-    let pwned_passwords = server.options().pwned_passwords_enabled();
+    let pwned_passwords = server.options().pwned_passwords_options();
     let password_meta =
         pattern::password_meta(client.as_ref(), pwned_passwords, Some(req.password.clone()))
             .await
             .map_err(GrpcMethodError::BadRequest)?;
 
     let driver = server.driver();
+    let password_policy = server.options().password_policy_options();
+    let password_hash = server.options().password_hash_options();
     let revoke_token_expires = server.options().revoke_token_expires();
-    let email = server.smtp_email();
+    let notify = server.notify();
     blocking_method(move || {
         let template = audit_result(
             driver.as_ref(),
@@ -406,11 +809,28 @@ pub async fn reset_password_confirm(
                     .map_err(GrpcMethodError::BadRequest)?;
 
                 // Encode revoke token.
-                let token = Jwt::encode_revoke(&conn, &service, &user, &key, revoke_token_expires)
-                    .map_err(GrpcMethodError::BadRequest)?;
-
-                // Update user password.
-                let user_update = UserUpdate::new_password(user.id, &req.password)
+                let token = Jwt::encode_revoke(
+                    &conn,
+                    driver.data_keys(),
+                    &service,
+                    &user,
+                    &key,
+                    revoke_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                // Update user password, if allowed by password policy.
+                pattern::user_password_policy_check(
+                    driver,
+                    &password_policy,
+                    Some(&service),
+                    Some(&user),
+                    "password",
+                    &req.password,
+                    &password_meta,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                let user_update = UserUpdate::new_password(user.id, &req.password, &password_hash)
                     .map_err(GrpcMethodError::BadRequest)?;
                 driver
                     .user_update(&user_update)
@@ -421,7 +841,7 @@ pub async fn reset_password_confirm(
                     .map_err(GrpcMethodError::BadRequest)
             },
         )?;
-        email(template)
+        notify(template)
             .map_err::<DriverError, _>(Into::into)
             .map_err(GrpcMethodError::BadRequest)?;
         Ok(password_meta)
@@ -472,7 +892,9 @@ pub async fn update_email(
 
     let driver = server.driver();
     let revoke_token_expires = server.options().revoke_token_expires();
-    let email = server.smtp_email();
+    let password_hash = server.options().password_hash_options();
+    let lockout = server.options().lockout_options();
+    let notify = server.notify();
     blocking_method(move || {
         let template = audit_result(
             driver.as_ref(),
@@ -482,9 +904,11 @@ pub async fn update_email(
                 let service = pattern::key_service_authenticate(driver, audit, &auth)
                     .map_err(GrpcMethodError::Unauthorised)?;
 
+                // Forbidden if email or remote locked out by failed logins.
                 // Update email requires token key type.
+                // Unknown emails are counted as failed logins.
                 let user =
-                    pattern::user_read_email_checked(driver, Some(&service), audit, &req.email)
+                    pattern::user_read_email_lockout(driver, audit, &lockout, &service, &req.email)
                         .map_err(GrpcMethodError::BadRequest)?;
                 let key =
                     pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
@@ -497,12 +921,27 @@ pub async fn update_email(
                     ));
                 }
                 // Check user password.
-                user.password_check(&req.password)
-                    .map_err(GrpcMethodError::BadRequest)?;
-                // Encode revoke token.
+                pattern::user_password_check_lockout(
+                    driver,
+                    audit,
+                    &lockout,
+                    &password_hash,
+                    &user,
+                    &req.password,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                pattern::user_login_success(driver, &user).map_err(GrpcMethodError::BadRequest)?;
                 let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
-                let token = Jwt::encode_revoke(&conn, &service, &user, &key, revoke_token_expires)
-                    .map_err(GrpcMethodError::BadRequest)?;
+                // Encode revoke token.
+                let token = Jwt::encode_revoke(
+                    &conn,
+                    driver.data_keys(),
+                    &service,
+                    &user,
+                    &key,
+                    revoke_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
 
                 // Update user email.
                 let old_email = user.email.to_owned();
@@ -517,7 +956,7 @@ pub async fn update_email(
                     .map_err(GrpcMethodError::BadRequest)
             },
         )?;
-        email(template)
+        notify(template)
             .map_err::<DriverError, _>(Into::into)
             .map_err(GrpcMethodError::BadRequest)?;
         Ok(())
@@ -564,7 +1003,7 @@ pub async fn update_password(
     let (audit_meta, auth, req) = request.into_inner();
 
     let client = server.client();
-    let pwned_passwords = server.options().pwned_passwords_enabled();
+    let pwned_passwords = server.options().pwned_passwords_options();
     let password_meta = pattern::password_meta(
         client.as_ref(),
         pwned_passwords,
@@ -574,8 +1013,11 @@ pub async fn update_password(
     .map_err(GrpcMethodError::BadRequest)?;
 
     let driver = server.driver();
+    let password_policy = server.options().password_policy_options();
+    let password_hash = server.options().password_hash_options();
     let revoke_token_expires = server.options().revoke_token_expires();
-    let email = server.smtp_email();
+    let lockout = server.options().lockout_options();
+    let notify = server.notify();
     blocking_method(move || {
         let template = audit_result(
             driver.as_ref(),
@@ -585,27 +1027,54 @@ pub async fn update_password(
                 let service = pattern::key_service_authenticate(driver, audit, &auth)
                     .map_err(GrpcMethodError::Unauthorised)?;
 
+                // Forbidden if email or remote locked out by failed logins.
                 // Update password requires token key type.
+                // Unknown emails are counted as failed logins.
                 let user =
-                    pattern::user_read_email_checked(driver, Some(&service), audit, &req.email)
+                    pattern::user_read_email_lockout(driver, audit, &lockout, &service, &req.email)
                         .map_err(GrpcMethodError::BadRequest)?;
                 let key =
                     pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
                         .map_err(GrpcMethodError::BadRequest)?;
 
                 // User is allowed to update password if `password_require_update` is true.
-                // Check user password.
-                user.password_check(&req.password)
-                    .map_err(GrpcMethodError::BadRequest)?;
-
-                // Encode revoke token.
+                pattern::user_password_check_lockout(
+                    driver,
+                    audit,
+                    &lockout,
+                    &password_hash,
+                    &user,
+                    &req.password,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                pattern::user_login_success(driver, &user).map_err(GrpcMethodError::BadRequest)?;
                 let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
-                let token = Jwt::encode_revoke(&conn, &service, &user, &key, revoke_token_expires)
-                    .map_err(GrpcMethodError::BadRequest)?;
 
-                // Update user password.
-                let user_update = UserUpdate::new_password(user.id, &req.new_password)
-                    .map_err(GrpcMethodError::BadRequest)?;
+                // Encode revoke token.
+                let token = Jwt::encode_revoke(
+                    &conn,
+                    driver.data_keys(),
+                    &service,
+                    &user,
+                    &key,
+                    revoke_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                // Update user password, if allowed by password policy.
+                pattern::user_password_policy_check(
+                    driver,
+                    &password_policy,
+                    Some(&service),
+                    Some(&user),
+                    "new_password",
+                    &req.new_password,
+                    &password_meta,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                let user_update =
+                    UserUpdate::new_password(user.id, &req.new_password, &password_hash)
+                        .map_err(GrpcMethodError::BadRequest)?;
                 driver
                     .user_update(&user_update)
                     .map_err(GrpcMethodError::BadRequest)?;
@@ -618,7 +1087,7 @@ pub async fn update_password(
                     .map_err(GrpcMethodError::BadRequest)
             },
         )?;
-        email(template)
+        notify(template)
             .map_err::<DriverError, _>(Into::into)
             .map_err(GrpcMethodError::BadRequest)?;
         Ok(password_meta)
@@ -651,6 +1120,61 @@ pub async fn update_password_revoke(
     })
 }
 
+/// Returns login reply for user authenticated by first factor.
+///
+/// Encodes MFA token if service MFA policy applies to user, else user token.
+#[allow(clippy::too_many_arguments)]
+fn login_reply(
+    driver: &Postgres,
+    conn: &PgConnection,
+    audit: &mut AuditBuilder,
+    service: &Service,
+    user: User,
+    key: &KeyWithValue,
+    mfa_token_expires: Duration,
+    access_token_expires: Duration,
+    refresh_token_expires: Duration,
+) -> GrpcMethodResult<pb::AuthLoginReply> {
+    let mfa = pattern::user_login_mfa(driver, service, &user).map_err(|e| match e {
+        DriverError::UserMfaRequired => GrpcMethodError::Forbidden(e),
+        e => GrpcMethodError::BadRequest(e),
+    })?;
+    // Failed logins are reset when tokens are issued, not before second factor is verified.
+    if let Some(mfa_key_types) = mfa {
+        let mfa = Jwt::encode_mfa(
+            conn,
+            driver.data_keys(),
+            service,
+            &user,
+            key,
+            mfa_token_expires,
+        )
+        .map_err(GrpcMethodError::BadRequest)?;
+        return Ok(pb::AuthLoginReply {
+            meta: None,
+            user: Some(user.into()),
+            access: None,
+            refresh: None,
+            mfa: Some(mfa),
+            mfa_key_types: mfa_key_types.into_iter().map(|x| x as i32).collect(),
+        });
+    }
+    pattern::user_login_success(driver, &user).map_err(GrpcMethodError::BadRequest)?;
+
+    Jwt::encode_user(
+        conn,
+        driver.data_keys(),
+        audit.meta(),
+        service,
+        user,
+        key,
+        access_token_expires,
+        refresh_token_expires,
+    )
+    .map_err(GrpcMethodError::BadRequest)
+    .map(Into::into)
+}
+
 fn revoke_inner(
     driver: &Postgres,
     audit: &mut AuditBuilder,
@@ -688,6 +1212,9 @@ fn revoke_inner(
                 is_enabled: Some(false),
                 is_revoked: Some(true),
                 name: None,
+                expires_at: None,
+                scopes: None,
+                role: None,
             },
         )
         .map_err(GrpcMethodError::BadRequest)?;
@@ -18,7 +18,7 @@ pub async fn oauth2_url(
         .map_err(Into::into)
     })
     .await
-    .map(|url| pb::AuthOauth2UrlReply { url })
+    .map(|(url, session)| pb::AuthOauth2UrlReply { url, session })
 }
 
 pub async fn oauth2_callback(
@@ -76,7 +76,7 @@ pub async fn oauth2_callback(
 }
 
 mod provider_microsoft {
-    use crate::{pattern::*, prelude::*};
+    use crate::{grpc::method::auth::Oauth2State, pattern::*, prelude::*};
     use oauth2::{
         basic::BasicClient, reqwest::http_client, AuthType, AuthUrl, AuthorizationCode, ClientId,
         ClientSecret, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
@@ -90,7 +90,7 @@ mod provider_microsoft {
         audit: &mut AuditBuilder,
         auth: &HeaderAuth,
         args: &ServerProviderOauth2Args,
-    ) -> GrpcMethodResult<String> {
+    ) -> GrpcMethodResult<(String, String)> {
         let service =
             key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
 
@@ -108,20 +108,20 @@ mod provider_microsoft {
             .set_pkce_challenge(pkce_code_challenge)
             .url();
 
-        // Save the state and code verifier secrets as a CSRF key, value.
-        let csrf_key = csrf_state.secret();
-        let csrf_value = pkce_code_verifier.secret();
+        // Save the state as a CSRF key, code verifier and browser session as value.
+        let (state, session) = Oauth2State::new(None, Some(pkce_code_verifier.secret().to_owned()));
+        let csrf_value = state.to_value().map_err(GrpcMethodError::BadRequest)?;
         let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
         CsrfCreate::create(
             &conn,
-            csrf_key,
+            csrf_state.secret(),
             csrf_value,
             args.access_token_expires,
             service.id,
         )
         .map_err(GrpcMethodError::BadRequest)?;
 
-        Ok(authorize_url.to_string())
+        Ok((authorize_url.to_string(), session))
     }
 
     pub(crate) fn oauth2_callback(
@@ -134,20 +134,24 @@ mod provider_microsoft {
         let service =
             key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
 
-        // Read the CSRF key using state value, rebuild code verifier from value.
+        // Read the CSRF key using state value, check browser session matches URL request.
         let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
         let csrf = CsrfRead::read(&conn, &request.state)
             .map_err(GrpcMethodError::BadRequest)?
             .ok_or_else(|| DriverError::CsrfNotFoundOrUsed)
             .map_err(GrpcMethodError::BadRequest)?;
+        let state =
+            Oauth2State::from_csrf(&csrf, &request.session).map_err(GrpcMethodError::BadRequest)?;
 
-        // Exchange the code with a token.
+        // Exchange the code with a token, rebuild code verifier from state.
         let client = new_client(&service, &args.provider).map_err(GrpcMethodError::BadRequest)?;
         let code = AuthorizationCode::new(request.code.clone());
-        let pkce_code_verifier = PkceCodeVerifier::new(csrf.value().to_owned());
-        let token = client
-            .exchange_code(code)
-            .set_pkce_verifier(pkce_code_verifier)
+        let mut token_request = client.exchange_code(code);
+        if let Some(pkce_code_verifier) = state.pkce_code_verifier {
+            token_request =
+                token_request.set_pkce_verifier(PkceCodeVerifier::new(pkce_code_verifier));
+        }
+        let token = token_request
             .request(http_client)
             .map_err(|e| DriverError::Oauth2Request(e.into()))
             .map_err(GrpcMethodError::BadRequest)?;
@@ -2,9 +2,15 @@ pub mod github;
 pub mod key;
 pub mod local;
 pub mod microsoft;
+pub mod oauth2;
+pub mod saml;
 pub mod token;
+pub mod totp;
+pub mod webauthn;
 
 use crate::prelude::*;
+use libreauth::key::KeyBuilder;
+use openssl::{memcmp, sha::sha256};
 
 impl validator::Validate for pb::AuthTotpRequest {
     fn validate(&self) -> Result<(), validator::ValidationErrors> {
@@ -42,7 +48,7 @@ pub async fn totp_verify(
                     pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Totp)
                         .map_err(GrpcMethodError::BadRequest)?;
                 // Verify TOTP code.
-                pattern::totp_verify(&key.value, &req.totp).map_err(GrpcMethodError::BadRequest)
+                pattern::totp_verify(driver, &key, &req.totp).map_err(GrpcMethodError::BadRequest)
             },
         )
         .map_err(Into::into)
@@ -103,6 +109,52 @@ pub async fn csrf_verify(
     .map(|_| pb::AuthAuditReply { audit: None })
 }
 
+/// OAuth2 state, saved as CSRF value between URL and callback requests.
+#[derive(Debug, Serialize, Deserialize)]
+struct Oauth2State {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provider_id: Option<Uuid>,
+    pkce_code_verifier: Option<String>,
+    session_hash: String,
+}
+
+impl Oauth2State {
+    /// Returns new state and random browser session value it is bound to.
+    fn new(provider_id: Option<Uuid>, pkce_code_verifier: Option<String>) -> (Self, String) {
+        let session = KeyBuilder::new()
+            .size(BYTES_KEY_VALUE)
+            .generate()
+            .as_base32();
+        let state = Self {
+            provider_id,
+            pkce_code_verifier,
+            session_hash: Self::session_hash(&session),
+        };
+        (state, session)
+    }
+
+    /// Returns state serialised as CSRF value.
+    fn to_value(&self) -> DriverResult<String> {
+        serde_json::to_string(self).map_err(DriverError::SerdeJson)
+    }
+
+    /// Returns state deserialised from CSRF value, checks browser session matches.
+    fn from_csrf(csrf: &Csrf, session: &str) -> DriverResult<Self> {
+        let state = serde_json::from_str::<Self>(csrf.value()).map_err(DriverError::SerdeJson)?;
+        let session_hash = Self::session_hash(session);
+        if state.session_hash.len() != session_hash.len()
+            || !memcmp::eq(state.session_hash.as_bytes(), session_hash.as_bytes())
+        {
+            return Err(DriverError::CsrfSessionMismatch);
+        }
+        Ok(state)
+    }
+
+    fn session_hash(session: &str) -> String {
+        base64::encode(sha256(session.as_bytes()))
+    }
+}
+
 fn oauth2_login(
     driver: &Postgres,
     audit: &mut AuditBuilder,
@@ -129,6 +181,8 @@ fn oauth2_login(
     let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
     Jwt::encode_user(
         &conn,
+        driver.data_keys(),
+        audit.meta(),
         &service,
         user,
         &key,
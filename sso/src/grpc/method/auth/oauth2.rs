@@ -0,0 +1,241 @@
+use crate::{grpc::method::auth::oauth2_login, prelude::*};
+
+impl validator::Validate for pb::AuthOauth2UrlRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "provider_id", &self.provider_id);
+        })
+    }
+}
+
+pub async fn oauth2_url(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthOauth2UrlRequest>,
+) -> GrpcMethodResult<pb::AuthOauth2UrlReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthOauth2Url,
+            |driver, audit| {
+                provider_oauth2::oauth2_url(driver, audit, &auth, &req, access_token_expires)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|(url, session)| pb::AuthOauth2UrlReply { url, session })
+}
+
+impl validator::Validate for pb::AuthOauth2ProviderCallbackRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "provider_id", &self.provider_id);
+            validate::oauth2_token(e, "code", &self.code);
+            validate::oauth2_token(e, "state", &self.state);
+            validate::key(e, "session", &self.session);
+        })
+    }
+}
+
+pub async fn oauth2_callback(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthOauth2ProviderCallbackRequest>,
+) -> GrpcMethodResult<pb::AuthTokenReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    let audit_meta1 = audit_meta.clone();
+    let (service, service_id, provider, userinfo_endpoint, access_token) =
+        blocking_method(move || {
+            audit_result_err(
+                driver.as_ref(),
+                audit_meta1,
+                AuditType::AuthOauth2Callback,
+                |driver, audit| provider_oauth2::oauth2_callback(driver, audit, &auth, &req),
+            )
+            .map_err(Into::into)
+        })
+        .await?;
+
+    let client = server.client();
+    let user_email =
+        provider_oauth2::api_user_email(&client, &provider, &userinfo_endpoint, access_token)
+            .await
+            .map_err(GrpcMethodError::BadRequest)?;
+
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+    let refresh_token_expires = server.options().refresh_token_expires();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthOauth2Callback,
+            |driver, audit| {
+                oauth2_login(
+                    driver,
+                    audit,
+                    &service,
+                    service_id,
+                    user_email.clone(),
+                    access_token_expires,
+                    refresh_token_expires,
+                )
+            },
+        )
+    })
+    .await
+    .map(|user_token| pb::AuthTokenReply {
+        user: Some(user_token.user.clone().into()),
+        access: Some(user_token.access_token()),
+        refresh: Some(user_token.refresh_token()),
+        audit: None,
+    })
+}
+
+mod provider_oauth2 {
+    use crate::{grpc::method::auth::Oauth2State, pattern::*, prelude::*};
+    use diesel::PgConnection;
+    use oauth2::{
+        reqwest::http_client, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier,
+        Scope, TokenResponse,
+    };
+    use reqwest::Client;
+    use serde_json::Value;
+
+    pub(crate) fn oauth2_url(
+        driver: &Postgres,
+        audit: &mut AuditBuilder,
+        auth: &HeaderAuth,
+        request: &pb::AuthOauth2UrlRequest,
+        access_token_expires: Duration,
+    ) -> GrpcMethodResult<(String, String)> {
+        let service =
+            key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
+        let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+        let provider = provider_read(&conn, pb::string_to_uuid(request.provider_id.clone()))?;
+
+        // Generate the authorisation URL to redirect, use PKCE if supported by provider.
+        let metadata = provider.metadata().map_err(GrpcMethodError::BadRequest)?;
+        let client = provider
+            .client(&metadata, service_redirect_url(&service)?)
+            .map_err(GrpcMethodError::BadRequest)?;
+        let mut authorize_request = client.authorize_url(CsrfToken::new_random);
+        for scope in &provider.scopes {
+            authorize_request = authorize_request.add_scope(Scope::new(scope.to_owned()));
+        }
+        let mut pkce_code_verifier = None;
+        if metadata.pkce_s256_supported() {
+            let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+            authorize_request = authorize_request.set_pkce_challenge(pkce_challenge);
+            pkce_code_verifier = Some(pkce_verifier.secret().to_owned());
+        }
+        let (authorize_url, csrf_state) = authorize_request.url();
+
+        // Save the state as a CSRF key, provider, code verifier and browser session as value.
+        let (state, session) = Oauth2State::new(Some(provider.id), pkce_code_verifier);
+        let csrf_value = state.to_value().map_err(GrpcMethodError::BadRequest)?;
+        CsrfCreate::create(
+            &conn,
+            csrf_state.secret(),
+            csrf_value,
+            access_token_expires,
+            service.id,
+        )
+        .map_err(GrpcMethodError::BadRequest)?;
+
+        Ok((authorize_url.to_string(), session))
+    }
+
+    pub(crate) fn oauth2_callback(
+        driver: &Postgres,
+        audit: &mut AuditBuilder,
+        auth: &HeaderAuth,
+        request: &pb::AuthOauth2ProviderCallbackRequest,
+    ) -> GrpcMethodResult<(Service, Uuid, Oauth2Provider, String, String)> {
+        let service =
+            key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
+
+        // Read the CSRF key using state value, check provider and browser session match URL request.
+        let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+        let csrf = CsrfRead::read(&conn, &request.state)
+            .map_err(GrpcMethodError::BadRequest)?
+            .ok_or_else(|| DriverError::CsrfNotFoundOrUsed)
+            .map_err(GrpcMethodError::BadRequest)?;
+        let state =
+            Oauth2State::from_csrf(&csrf, &request.session).map_err(GrpcMethodError::BadRequest)?;
+        let provider = provider_read(&conn, pb::string_to_uuid(request.provider_id.clone()))?;
+        if state.provider_id != Some(provider.id) {
+            return Err(GrpcMethodError::BadRequest(
+                DriverError::CsrfProviderMismatch,
+            ));
+        }
+
+        // Exchange the code with a token.
+        let metadata = provider.metadata().map_err(GrpcMethodError::BadRequest)?;
+        let client = provider
+            .client(&metadata, service_redirect_url(&service)?)
+            .map_err(GrpcMethodError::BadRequest)?;
+        let code = AuthorizationCode::new(request.code.clone());
+        let mut token_request = client.exchange_code(code);
+        if let Some(pkce_code_verifier) = state.pkce_code_verifier {
+            token_request =
+                token_request.set_pkce_verifier(PkceCodeVerifier::new(pkce_code_verifier));
+        }
+        let token = token_request
+            .request(http_client)
+            .map_err(|e| DriverError::Oauth2Request(e.into()))
+            .map_err(GrpcMethodError::BadRequest)?;
+
+        // Return access token value and userinfo endpoint.
+        let access_token = token.access_token().secret().to_owned();
+        Ok((
+            service,
+            csrf.service_id(),
+            provider,
+            metadata.userinfo_endpoint,
+            access_token,
+        ))
+    }
+
+    pub(crate) async fn api_user_email(
+        client: &Client,
+        provider: &Oauth2Provider,
+        userinfo_endpoint: &str,
+        access_token: String,
+    ) -> DriverResult<String> {
+        let authorisation = format!("Bearer {}", access_token);
+        let res = client
+            .get(userinfo_endpoint)
+            .header(header::AUTHORISATION, authorisation)
+            .send()
+            .await
+            .map_err(DriverError::Reqwest)?;
+        let res = res.error_for_status().map_err(DriverError::Reqwest)?;
+        let res = res.json::<Value>().await.map_err(DriverError::Reqwest)?;
+        provider.userinfo_email(&res)
+    }
+
+    fn provider_read(conn: &PgConnection, id: Uuid) -> GrpcMethodResult<Oauth2Provider> {
+        Oauth2ProviderRead::id(conn, id)
+            .map_err(GrpcMethodError::BadRequest)?
+            .ok_or_else(|| DriverError::Oauth2ProviderNotFound)
+            .map_err(GrpcMethodError::NotFound)?
+            .check()
+            .map_err(GrpcMethodError::BadRequest)
+    }
+
+    fn service_redirect_url(service: &Service) -> GrpcMethodResult<&str> {
+        service
+            .provider_oauth2_url
+            .as_ref()
+            .map(|x| &**x)
+            .ok_or_else(|| DriverError::ServiceProviderOauth2Disabled)
+            .map_err(GrpcMethodError::BadRequest)
+    }
+}
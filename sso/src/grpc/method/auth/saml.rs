@@ -0,0 +1,131 @@
+use crate::{grpc::method::auth::oauth2_login, prelude::*};
+
+pub async fn saml_url(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<()>,
+) -> GrpcMethodResult<pb::AuthSamlUrlReply> {
+    let (audit_meta, auth, _) = request.into_inner();
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthSamlUrl,
+            |driver, audit| provider_saml::saml_url(driver, audit, &auth, access_token_expires),
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|url| pb::AuthSamlUrlReply { url })
+}
+
+impl validator::Validate for pb::AuthSamlCallbackRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::saml_response(e, "saml_response", &self.saml_response);
+            validate::csrf_token(e, "relay_state", &self.relay_state);
+        })
+    }
+}
+
+pub async fn saml_callback(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthSamlCallbackRequest>,
+) -> GrpcMethodResult<pb::AuthTokenReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+    let refresh_token_expires = server.options().refresh_token_expires();
+
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthSamlCallback,
+            |driver, audit| {
+                let (service, service_id, user_email) =
+                    provider_saml::saml_callback(driver, audit, &auth, &req)?;
+                oauth2_login(
+                    driver,
+                    audit,
+                    &service,
+                    service_id,
+                    user_email,
+                    access_token_expires,
+                    refresh_token_expires,
+                )
+            },
+        )
+    })
+    .await
+    .map(|user_token| pb::AuthTokenReply {
+        user: Some(user_token.user.clone().into()),
+        access: Some(user_token.access_token()),
+        refresh: Some(user_token.refresh_token()),
+        audit: None,
+    })
+}
+
+mod provider_saml {
+    use crate::{pattern::*, prelude::*};
+
+    pub(crate) fn saml_url(
+        driver: &Postgres,
+        audit: &mut AuditBuilder,
+        auth: &HeaderAuth,
+        access_token_expires: Duration,
+    ) -> GrpcMethodResult<String> {
+        let service =
+            key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
+        let (acs_url, metadata) = service
+            .provider_saml()
+            .map_err(GrpcMethodError::BadRequest)?;
+
+        // Save the request ID as CSRF value, key is used as relay state.
+        let request = SamlAuthnRequest::new(&metadata, acs_url, Utc::now())
+            .map_err(GrpcMethodError::BadRequest)?;
+        let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+        let csrf =
+            CsrfCreate::generate_value(&conn, request.id.clone(), access_token_expires, service.id)
+                .map_err(GrpcMethodError::BadRequest)?;
+
+        request.url(csrf.key()).map_err(GrpcMethodError::BadRequest)
+    }
+
+    pub(crate) fn saml_callback(
+        driver: &Postgres,
+        audit: &mut AuditBuilder,
+        auth: &HeaderAuth,
+        request: &pb::AuthSamlCallbackRequest,
+    ) -> GrpcMethodResult<(Service, Uuid, String)> {
+        let service =
+            key_service_authenticate(driver, audit, auth).map_err(GrpcMethodError::Unauthorised)?;
+        let (acs_url, metadata) = service
+            .provider_saml()
+            .map_err(GrpcMethodError::BadRequest)?;
+
+        // Read the CSRF key using relay state, value is the request ID.
+        let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+        let csrf = CsrfRead::read(&conn, &request.relay_state)
+            .map_err(GrpcMethodError::BadRequest)?
+            .ok_or_else(|| DriverError::CsrfNotFoundOrUsed)
+            .map_err(GrpcMethodError::BadRequest)?;
+
+        // Validate response and signed assertion, read user email.
+        let assertion = SamlResponse::validate(
+            &request.saml_response,
+            &metadata,
+            acs_url,
+            csrf.value(),
+            Utc::now(),
+        )
+        .map_err(GrpcMethodError::BadRequest)?;
+        let user_email = assertion
+            .email(service.saml_email_attribute.as_ref().map(|x| &**x))
+            .map_err(GrpcMethodError::BadRequest)?;
+
+        Ok((service, csrf.service_id(), user_email))
+    }
+}
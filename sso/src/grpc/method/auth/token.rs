@@ -37,8 +37,10 @@ pub async fn verify(
                         .map_err(GrpcMethodError::BadRequest)?;
 
                 // Safely decode token with user key.
-                let access_token_expires = Jwt::decode_access(&service, &user, &key, &req.token)
-                    .map_err(GrpcMethodError::BadRequest)?;
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let access_token_expires =
+                    Jwt::decode_access(&conn, &service, &user, &key, &req.token)
+                        .map_err(GrpcMethodError::BadRequest)?;
 
                 // Token verified.
                 let user_token = UserTokenAccess {
@@ -97,17 +99,22 @@ pub async fn refresh(
                     pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
                         .map_err(GrpcMethodError::BadRequest)?;
 
-                // Safely decode token with user key.
-                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
-                Jwt::decode_refresh(&conn, &service, &user, &key, &req.token)
-                    .map_err(GrpcMethodError::BadRequest)?;
+                // Safely decode token with user key, refresh token can only be used once.
+                let family_id = pattern::user_token_refresh_decode(
+                    driver, &service, audit, &user, &key, &req.token,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
 
-                // Encode user token.
-                let user_token = Jwt::encode_user(
+                // Encode user token, refresh token is added to token family.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let user_token = Jwt::encode_user_family(
                     &conn,
+                    driver.data_keys(),
+                    audit.meta(),
                     &service,
                     user,
                     &key,
+                    family_id,
                     access_token_expires,
                     refresh_token_expires,
                 )
@@ -168,20 +175,25 @@ pub async fn revoke(
                 )
                 .map_err(GrpcMethodError::BadRequest)?;
 
-                // Safely decode token with user key.
+                // Safely decode token with user key and revoke it.
                 let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
-                Jwt::decode_csrf(&conn, &service, &user, &key, token_type, &req.token)
+                let revoked = Jwt::revoke(&conn, &service, &user, &key, token_type, &req.token)
                     .map_err(GrpcMethodError::BadRequest)?;
 
-                // Token revoked, disable and revoke linked key.
-                driver
-                    .key_update(&KeyUpdate {
-                        id: key.id,
-                        is_enabled: Some(false),
-                        is_revoked: Some(true),
-                        name: None,
-                    })
-                    .map_err(GrpcMethodError::BadRequest)?;
+                // Token without ID cannot be revoked, disable and revoke linked key.
+                if !revoked {
+                    driver
+                        .key_update(&KeyUpdate {
+                            id: key.id,
+                            is_enabled: Some(false),
+                            is_revoked: Some(true),
+                            name: None,
+                            expires_at: None,
+                            scopes: None,
+                            role: None,
+                        })
+                        .map_err(GrpcMethodError::BadRequest)?;
+                }
 
                 // Optionally create custom audit log.
                 if let Some(x) = &req.audit {
@@ -0,0 +1,210 @@
+use crate::prelude::*;
+
+impl validator::Validate for pb::AuthTotpRegisterBeginRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "user_id", &self.user_id);
+            validate::name(e, "name", &self.name);
+            validate::totp_period_opt(e, "period", self.period);
+            validate::totp_digits_opt(e, "digits", self.digits);
+            validate::totp_algorithm_opt(e, "algorithm", self.algorithm.as_ref().map(|x| &**x));
+            validate::totp_skew_opt(e, "skew", self.skew);
+        })
+    }
+}
+
+pub async fn register_begin(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthTotpRegisterBeginRequest>,
+) -> GrpcMethodResult<pb::AuthTotpRegisterBeginReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthTotpRegisterBegin,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                let user = pattern::user_read_id_checked(
+                    driver,
+                    Some(&service),
+                    audit,
+                    pb::string_to_uuid(req.user_id.clone()),
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                provider_totp::register_begin(driver, &service, &user, &req)
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+}
+
+impl validator::Validate for pb::AuthTotpRegisterFinishRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::csrf_token(e, "csrf", &self.csrf);
+            validate::totp(e, "totp", &self.totp);
+        })
+    }
+}
+
+pub async fn register_finish(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthTotpRegisterFinishRequest>,
+) -> GrpcMethodResult<pb::AuthTotpRegisterFinishReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        let (key, recovery_codes) = audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthTotpRegisterFinish,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                provider_totp::register_finish(driver, audit, &service, &req)
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        )?;
+        Ok(pb::AuthTotpRegisterFinishReply {
+            key: Some(key.into()),
+            recovery_codes,
+        })
+    })
+    .await
+}
+
+impl validator::Validate for pb::AuthTotpRecoveryRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "user_id", &self.user_id);
+            validate::key(e, "code", &self.code);
+        })
+    }
+}
+
+pub async fn recovery_verify(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthTotpRecoveryRequest>,
+) -> GrpcMethodResult<pb::AuthAuditReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthTotpRecovery,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                let user = pattern::user_read_id_checked(
+                    driver,
+                    Some(&service),
+                    audit,
+                    pb::string_to_uuid(req.user_id.clone()),
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                let key =
+                    pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Totp)
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                // Use recovery code for enabled TOTP key.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                TotpRecovery::use_code(&conn, key.id, &req.code)
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        )
+    })
+    .await
+    .map(|_data| pb::AuthAuditReply { audit: None })
+}
+
+mod provider_totp {
+    use crate::{pattern::*, prelude::*};
+    use libreauth::key::KeyBuilder;
+    use qrcode::{render::svg, QrCode};
+
+    /// QR code image minimum size in pixels.
+    const QR_SIZE: u32 = 200;
+
+    /// Registration state saved as CSRF value.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct RegisterState {
+        user_id: Uuid,
+        name: String,
+        secret: String,
+        params: TotpParams,
+    }
+
+    pub(crate) fn register_begin(
+        driver: &Postgres,
+        service: &Service,
+        user: &User,
+        request: &pb::AuthTotpRegisterBeginRequest,
+    ) -> DriverResult<pb::AuthTotpRegisterBeginReply> {
+        let default = TotpParams::default();
+        let params = TotpParams {
+            period: request.period.map(|x| x as i32).unwrap_or(default.period),
+            digits: request.digits.map(|x| x as i32).unwrap_or(default.digits),
+            algorithm: request
+                .algorithm
+                .as_ref()
+                .map(|x| TotpAlgorithm::from_str(x).unwrap())
+                .unwrap_or(default.algorithm),
+            skew: request.skew.map(|x| x as i32).unwrap_or(default.skew),
+        };
+        let secret = KeyBuilder::new()
+            .size(BYTES_KEY_VALUE)
+            .generate()
+            .as_base32();
+        let uri = params.uri(&secret, &service.name, &user.email)?;
+        let qr_svg = QrCode::new(uri.as_bytes())
+            .map_err(DriverError::QrCode)?
+            .render::<svg::Color>()
+            .min_dimensions(QR_SIZE, QR_SIZE)
+            .build();
+
+        let state = RegisterState {
+            user_id: user.id,
+            name: request.name.clone(),
+            secret: secret.clone(),
+            params,
+        };
+        let csrf = csrf_create(driver, service, &state)?;
+        Ok(pb::AuthTotpRegisterBeginReply {
+            csrf,
+            secret,
+            uri,
+            qr_svg,
+        })
+    }
+
+    pub(crate) fn register_finish(
+        driver: &Postgres,
+        audit: &mut AuditBuilder,
+        service: &Service,
+        request: &pb::AuthTotpRegisterFinishRequest,
+    ) -> DriverResult<(KeyWithValue, Vec<String>)> {
+        let state: RegisterState = csrf_read(driver, service, &request.csrf)?;
+        let user = user_read_id_checked(driver, Some(service), audit, state.user_id)?;
+
+        // Key is only created after code from authenticator app is verified.
+        let step = state.params.verify(&state.secret, &request.totp)?;
+
+        // Users may only have one enabled TOTP key, existing key is revoked.
+        let mut create = KeyCreate::user(true, KeyType::Totp, state.name, service.id, user.id);
+        create.value = state.secret;
+        let (key, recovery_codes) = driver.key_totp_create(&create, &state.params, Some(step))?;
+        audit.user_key(Some(&key));
+        Ok((key, recovery_codes))
+    }
+}
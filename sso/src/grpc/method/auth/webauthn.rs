@@ -0,0 +1,400 @@
+use crate::prelude::*;
+
+impl validator::Validate for pb::AuthWebauthnRegisterBeginRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "user_id", &self.user_id);
+            validate::name(e, "name", &self.name);
+        })
+    }
+}
+
+pub async fn register_begin(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthWebauthnRegisterBeginRequest>,
+) -> GrpcMethodResult<pb::AuthWebauthnChallengeReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthWebauthnRegisterBegin,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                let user = pattern::user_read_id_checked(
+                    driver,
+                    Some(&service),
+                    audit,
+                    pb::string_to_uuid(req.user_id.clone()),
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                provider_webauthn::register_begin(driver, &service, &user, &req.name)
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|(csrf, challenge)| pb::AuthWebauthnChallengeReply {
+        csrf,
+        challenge: pb::value_to_struct_opt(challenge),
+    })
+}
+
+impl validator::Validate for pb::AuthWebauthnFinishRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::csrf_token(e, "csrf", &self.csrf);
+            validate::webauthn_credential(e, "credential", self.credential.as_ref());
+        })
+    }
+}
+
+pub async fn register_finish(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthWebauthnFinishRequest>,
+) -> GrpcMethodResult<pb::AuthWebauthnRegisterFinishReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_subject(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthWebauthnRegisterFinish,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                provider_webauthn::register_finish(driver, audit, &service, &req)
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|key| pb::AuthWebauthnRegisterFinishReply {
+        key: Some(key.into()),
+        audit: None,
+    })
+}
+
+impl validator::Validate for pb::AuthWebauthnLoginBeginRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::email(e, "email", &self.email);
+        })
+    }
+}
+
+pub async fn login_begin(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthWebauthnLoginBeginRequest>,
+) -> GrpcMethodResult<pb::AuthWebauthnChallengeReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthWebauthnLoginBegin,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                let user =
+                    pattern::user_read_email_checked(driver, Some(&service), audit, &req.email)
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                provider_webauthn::login_begin(driver, &service, &user)
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|(csrf, challenge)| pb::AuthWebauthnChallengeReply {
+        csrf,
+        challenge: pb::value_to_struct_opt(challenge),
+    })
+}
+
+pub async fn login_finish(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::AuthWebauthnFinishRequest>,
+) -> GrpcMethodResult<pb::AuthTokenReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    let access_token_expires = server.options().access_token_expires();
+    let refresh_token_expires = server.options().refresh_token_expires();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthWebauthnLoginFinish,
+            |driver, audit| {
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Verify assertion, user must also have a token key to login.
+                let user = provider_webauthn::login_finish(driver, audit, &service, &req)
+                    .map_err(GrpcMethodError::BadRequest)?;
+                let key =
+                    pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                // Encode user token.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                Jwt::encode_user(
+                    &conn,
+                    driver.data_keys(),
+                    audit.meta(),
+                    &service,
+                    user,
+                    &key,
+                    access_token_expires,
+                    refresh_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|user_token| pb::AuthTokenReply {
+        user: Some(user_token.user.clone().into()),
+        access: Some(user_token.access_token()),
+        refresh: Some(user_token.refresh_token()),
+        audit: None,
+    })
+}
+
+pub(crate) mod provider_webauthn {
+    use crate::{pattern::*, prelude::*};
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use url::Url;
+    use webauthn_rs::{
+        base64_data::Base64UrlSafeData,
+        proto::{Credential, PublicKeyCredential, RegisterPublicKeyCredential},
+        AuthenticationState, RegistrationState, Webauthn, WebauthnConfig,
+    };
+
+    /// Byte offset of AAGUID in attested credential authenticator data.
+    const AUTH_DATA_AAGUID_OFFSET: usize = 37;
+
+    /// Relying party configuration, derived from service URL.
+    struct ServiceConfig {
+        name: String,
+        origin: Url,
+        id: String,
+    }
+
+    impl WebauthnConfig for ServiceConfig {
+        fn get_relying_party_name(&self) -> &str {
+            &self.name
+        }
+
+        fn get_origin(&self) -> &Url {
+            &self.origin
+        }
+
+        fn get_relying_party_id(&self) -> &str {
+            &self.id
+        }
+    }
+
+    /// Registration ceremony state saved as CSRF value.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct RegisterState {
+        user_id: Uuid,
+        name: String,
+        state: RegistrationState,
+    }
+
+    /// Authentication ceremony state saved as CSRF value.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct LoginState {
+        user_id: Uuid,
+        state: AuthenticationState,
+    }
+
+    pub(crate) fn register_begin(
+        driver: &Postgres,
+        service: &Service,
+        user: &User,
+        name: &str,
+    ) -> DriverResult<(String, Value)> {
+        let webauthn = new_webauthn(service)?;
+
+        // Exclude credentials already registered by user for service.
+        let exclude_credentials = credential_list(driver, service, user)?
+            .into_iter()
+            .map(|(_, credential)| credential.cred_id)
+            .collect();
+        let (challenge, state) = webauthn
+            .generate_challenge_register_options(
+                user.id.as_bytes().to_vec(),
+                user.email.clone(),
+                user.name.clone(),
+                Some(exclude_credentials),
+                None,
+                None,
+            )
+            .map_err(DriverError::Webauthn)?;
+
+        let state = RegisterState {
+            user_id: user.id,
+            name: name.to_owned(),
+            state,
+        };
+        let csrf = csrf_create(driver, service, &state)?;
+        let challenge = serde_json::to_value(&challenge).map_err(DriverError::SerdeJson)?;
+        Ok((csrf, challenge))
+    }
+
+    pub(crate) fn register_finish(
+        driver: &Postgres,
+        audit: &mut AuditBuilder,
+        service: &Service,
+        request: &pb::AuthWebauthnFinishRequest,
+    ) -> DriverResult<Key> {
+        let webauthn = new_webauthn(service)?;
+        let state: RegisterState = csrf_read(driver, service, &request.csrf)?;
+        let user = user_read_id_checked(driver, Some(service), audit, state.user_id)?;
+
+        let register: RegisterPublicKeyCredential =
+            credential_from_request(request.credential.clone())?;
+        // Credential ID uniqueness is enforced by key value constraint.
+        let (credential, auth_data) = webauthn
+            .register_credential(&register, &state.state, |_| Ok(false))
+            .map_err(DriverError::Webauthn)?;
+        let aaguid = aaguid_from_attestation(&register.response.attestation_object.0)?;
+
+        driver.key_webauthn_create(&KeyWebauthnCreate::user(
+            state.name,
+            Base64UrlSafeData(credential.cred_id.clone()).to_string(),
+            service.id,
+            user.id,
+            serde_json::to_value(&credential).map_err(DriverError::SerdeJson)?,
+            auth_data.counter.into(),
+            aaguid,
+        ))
+    }
+
+    pub(crate) fn login_begin(
+        driver: &Postgres,
+        service: &Service,
+        user: &User,
+    ) -> DriverResult<(String, Value)> {
+        let webauthn = new_webauthn(service)?;
+
+        let credentials: Vec<Credential> = credential_list(driver, service, user)?
+            .into_iter()
+            .map(|(_, credential)| credential)
+            .collect();
+        if credentials.is_empty() {
+            return Err(DriverError::KeyUserWebauthnNotFound);
+        }
+        let (challenge, state) = webauthn
+            .generate_challenge_authenticate(credentials)
+            .map_err(DriverError::Webauthn)?;
+
+        let state = LoginState {
+            user_id: user.id,
+            state,
+        };
+        let csrf = csrf_create(driver, service, &state)?;
+        let challenge = serde_json::to_value(&challenge).map_err(DriverError::SerdeJson)?;
+        Ok((csrf, challenge))
+    }
+
+    pub(crate) fn login_finish(
+        driver: &Postgres,
+        audit: &mut AuditBuilder,
+        service: &Service,
+        request: &pb::AuthWebauthnFinishRequest,
+    ) -> DriverResult<User> {
+        let webauthn = new_webauthn(service)?;
+        let state: LoginState = csrf_read(driver, service, &request.csrf)?;
+        let user = user_read_id_checked(driver, Some(service), audit, state.user_id)?;
+
+        let assertion: PublicKeyCredential = credential_from_request(request.credential.clone())?;
+        let (credential_id, auth_data) = webauthn
+            .authenticate_credential(&assertion, &state.state)
+            .map_err(DriverError::Webauthn)?;
+
+        // Credential must still belong to an enabled key, save updated signature counter.
+        let (key_id, mut credential) = credential_list(driver, service, &user)?
+            .into_iter()
+            .find(|(_, credential)| &credential.cred_id == credential_id)
+            .ok_or(DriverError::KeyUserWebauthnNotFound)?;
+        credential.counter = auth_data.counter;
+        driver.key_webauthn_update(&KeyWebauthnUpdate {
+            key_id,
+            credential: serde_json::to_value(&credential).map_err(DriverError::SerdeJson)?,
+            counter: auth_data.counter.into(),
+        })?;
+
+        Ok(user)
+    }
+
+    fn new_webauthn(service: &Service) -> DriverResult<Webauthn<ServiceConfig>> {
+        let origin = Url::parse(&service.url).map_err(DriverError::UrlParse)?;
+        let id = origin
+            .host_str()
+            .ok_or(DriverError::UrlParse(url::ParseError::EmptyHost))?
+            .to_owned();
+        Ok(Webauthn::new(ServiceConfig {
+            name: service.name.clone(),
+            origin,
+            id,
+        }))
+    }
+
+    fn credential_list(
+        driver: &Postgres,
+        service: &Service,
+        user: &User,
+    ) -> DriverResult<Vec<(Uuid, Credential)>> {
+        driver
+            .key_webauthn_list(&KeyWebauthnList {
+                service_id: service.id,
+                user_id: user.id,
+            })?
+            .into_iter()
+            .map(|key| {
+                let key_id = key.key_id;
+                serde_json::from_value(key.credential)
+                    .map(|credential| (key_id, credential))
+                    .map_err(DriverError::SerdeJson)
+            })
+            .collect()
+    }
+
+    fn credential_from_request<T>(credential: Option<prost_types::Struct>) -> DriverResult<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let credential = pb::struct_opt_to_value_opt(credential).unwrap_or(Value::Null);
+        serde_json::from_value(credential).map_err(DriverError::SerdeJson)
+    }
+
+    /// Read AAGUID from attestation object authenticator data.
+    fn aaguid_from_attestation(attestation_object: &[u8]) -> DriverResult<Uuid> {
+        let attestation: BTreeMap<String, serde_cbor::Value> =
+            serde_cbor::from_slice(attestation_object).map_err(DriverError::SerdeCbor)?;
+        match attestation.get("authData") {
+            Some(serde_cbor::Value::Bytes(auth_data)) => auth_data
+                .get(AUTH_DATA_AAGUID_OFFSET..AUTH_DATA_AAGUID_OFFSET + 16)
+                .and_then(|aaguid| Uuid::from_slice(aaguid).ok()),
+            _ => None,
+        }
+        .ok_or(DriverError::KeyUserWebauthnAaguidInvalid)
+    }
+}
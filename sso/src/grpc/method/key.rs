@@ -10,6 +10,8 @@ impl validator::Validate for pb::KeyListRequest {
             validate::key_type_vec(e, "type", &self.r#type);
             validate::uuid_vec(e, "service_id", &self.service_id);
             validate::uuid_vec(e, "user_id", &self.user_id);
+            validate::key_list_days_opt(e, "expires_within_days", self.expires_within_days);
+            validate::key_list_days_opt(e, "unused_for_days", self.unused_for_days);
         })
     }
 }
@@ -51,6 +53,8 @@ impl validator::Validate for pb::KeyCreateRequest {
             validate::name(e, "name", &self.name);
             validate::uuid_opt(e, "service_id", self.service_id.as_ref().map(|x| &**x));
             validate::uuid_opt(e, "user_id", self.user_id.as_ref().map(|x| &**x));
+            validate::key_scope_vec(e, "scopes", &self.scopes);
+            validate::name_opt(e, "role", self.role.as_ref().map(|x| &**x));
         })
     }
 }
@@ -74,21 +78,33 @@ pub async fn create(
                         pattern::key_root_authenticate(driver, audit, &auth)
                             .map_err(GrpcMethodError::Unauthorised)
                             .and_then(|_| {
+                                let (role, scopes) = role_assign(
+                                    driver,
+                                    audit,
+                                    req.role.clone(),
+                                    req.scopes.clone(),
+                                )?;
                                 match req.user_id {
                                     // User ID is defined, creating user key for service.
-                                    Some(user_id) => driver.key_create(&KeyCreate::user(
-                                        req.is_enabled,
-                                        req.type_,
-                                        &req.name,
-                                        service_id,
-                                        user_id,
-                                    )),
+                                    Some(user_id) => driver.key_create(
+                                        &KeyCreate::user(
+                                            req.is_enabled,
+                                            req.type_,
+                                            &req.name,
+                                            service_id,
+                                            user_id,
+                                        )
+                                        .expires_at(req.expires_at)
+                                        .scopes(scopes.clone())
+                                        .role(role),
+                                    ),
                                     // Creating service key.
-                                    None => driver.key_create(&KeyCreate::service(
-                                        req.is_enabled,
-                                        &req.name,
-                                        service_id,
-                                    )),
+                                    None => driver.key_create(
+                                        &KeyCreate::service(req.is_enabled, &req.name, service_id)
+                                            .expires_at(req.expires_at)
+                                            .scopes(scopes.clone())
+                                            .role(role),
+                                    ),
                                 }
                                 .map_err(GrpcMethodError::BadRequest)
                             })
@@ -97,15 +113,26 @@ pub async fn create(
                         pattern::key_service_authenticate(driver, audit, &auth)
                             .map_err(GrpcMethodError::Unauthorised)
                             .and_then(|service| {
+                                let (role, scopes) = role_assign(
+                                    driver,
+                                    audit,
+                                    req.role.clone(),
+                                    req.scopes.clone(),
+                                )?;
                                 match req.user_id {
                                     // User ID is defined, creating user key for service.
-                                    Some(user_id) => driver.key_create(&KeyCreate::user(
-                                        req.is_enabled,
-                                        req.type_,
-                                        &req.name,
-                                        service.id,
-                                        user_id,
-                                    )),
+                                    Some(user_id) => driver.key_create(
+                                        &KeyCreate::user(
+                                            req.is_enabled,
+                                            req.type_,
+                                            &req.name,
+                                            service.id,
+                                            user_id,
+                                        )
+                                        .expires_at(req.expires_at)
+                                        .scopes(scopes.clone())
+                                        .role(role),
+                                    ),
                                     // Service cannot create service keys.
                                     None => Err(DriverError::ServiceCannotCreateServiceKey),
                                 }
@@ -164,6 +191,8 @@ impl validator::Validate for pb::KeyUpdateRequest {
         validate::wrap(|e| {
             validate::uuid(e, "id", &self.id);
             validate::name_opt(e, "name", self.name.as_ref().map(|x| &**x));
+            validate::key_scope_vec(e, "scopes", &self.scopes);
+            validate::name_opt(e, "role", self.role.as_ref().map(|x| &**x));
         })
     }
 }
@@ -186,12 +215,31 @@ pub async fn update(
 
                 let read = KeyRead::IdUser(req.id, None);
                 let previous_key = read_inner(driver, &read, service.as_ref())?;
+                role_check(driver, audit, &previous_key)?;
+                // Role and scopes are assigned together so the updated key cannot
+                // have permissions the authenticated key does not have.
+                let (role, scopes) = if req.role.is_some() || req.scopes.is_some() {
+                    let (role, scopes) = role_assign(
+                        driver,
+                        audit,
+                        req.role.clone().or_else(|| previous_key.role.clone()),
+                        req.scopes
+                            .clone()
+                            .unwrap_or_else(|| previous_key.scopes.clone()),
+                    )?;
+                    (role, Some(scopes))
+                } else {
+                    (None, None)
+                };
                 let key = driver
                     .key_update(&KeyUpdate {
                         id: req.id,
                         is_enabled: req.is_enabled,
                         is_revoked: None,
                         name: req.name.clone(),
+                        expires_at: req.expires_at,
+                        scopes,
+                        role,
                     })
                     .map_err(GrpcMethodError::BadRequest)?;
                 Ok((previous_key, key))
@@ -205,6 +253,60 @@ pub async fn update(
     })
 }
 
+impl validator::Validate for pb::KeyRotateRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "id", &self.id);
+            validate::key_grace_period_hours_opt(e, "grace_period_hours", self.grace_period_hours);
+        })
+    }
+}
+
+pub async fn rotate(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<KeyRotate>,
+) -> GrpcMethodResult<pb::KeyRotateReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::KeyRotate,
+            |driver, audit| {
+                let service = pattern::key_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                let read = KeyRead::IdUser(req.id, None);
+                let previous = read_inner(driver, &read, service.as_ref())?;
+                role_check(driver, audit, &previous)?;
+
+                let (previous, key) =
+                    driver
+                        .key_rotate(&req, service.map(|x| x.id))
+                        .map_err(|e| match e {
+                            DriverError::KeyNotFound => GrpcMethodError::NotFound(e),
+                            e => GrpcMethodError::BadRequest(e),
+                        })?;
+
+                // Audit log records rotation lineage.
+                let lineage = KeyRotateLineage::new(&previous, &key);
+                audit
+                    .create_data(driver, 0, Some(key.subject()), Some(lineage))
+                    .map_err(GrpcMethodError::InternalServerError)?;
+                Ok((previous, key))
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|(previous, key)| pb::KeyRotateReply {
+        data: Some(key.into()),
+        previous: Some(previous.into()),
+    })
+}
+
 pub async fn delete(
     server: &GrpcServer,
     request: GrpcMethodRequest<KeyRead>,
@@ -222,6 +324,7 @@ pub async fn delete(
                     .map_err(GrpcMethodError::Unauthorised)?;
 
                 let key = read_inner(driver, &req, service.as_ref())?;
+                role_check(driver, audit, &key)?;
                 driver
                     .key_delete(&key.id)
                     .map_err(GrpcMethodError::BadRequest)
@@ -245,3 +348,22 @@ fn read_inner(
         .ok_or_else(|| GrpcMethodError::NotFound(DriverError::KeyNotFound))
         .map(|x| x.into())
 }
+
+fn role_assign(
+    driver: &Postgres,
+    audit: &AuditBuilder,
+    role: Option<String>,
+    scopes: Vec<String>,
+) -> GrpcMethodResult<(Option<String>, Vec<String>)> {
+    pattern::key_role_assign(driver, audit, role, scopes).map_err(|e| match e {
+        DriverError::KeyPermissionDenied => GrpcMethodError::Forbidden(e),
+        e => GrpcMethodError::BadRequest(e),
+    })
+}
+
+fn role_check(driver: &Postgres, audit: &AuditBuilder, key: &Key) -> GrpcMethodResult<()> {
+    pattern::key_role_check(driver, audit, key.role.as_deref(), &key.scopes).map_err(|e| match e {
+        DriverError::KeyPermissionDenied => GrpcMethodError::Forbidden(e),
+        e => GrpcMethodError::BadRequest(e),
+    })
+}
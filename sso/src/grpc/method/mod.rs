@@ -1,5 +1,6 @@
 pub mod audit;
 pub mod auth;
 pub mod key;
+pub mod oauth2_provider;
 pub mod service;
 pub mod user;
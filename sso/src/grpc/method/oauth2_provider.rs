@@ -0,0 +1,197 @@
+use crate::prelude::*;
+
+pub async fn list(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<()>,
+) -> GrpcMethodResult<pb::Oauth2ProviderListReply> {
+    let (audit_meta, auth, _) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::Oauth2ProviderList,
+            |driver, audit| {
+                pattern::key_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                Oauth2ProviderRead::list(&conn).map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|data| pb::Oauth2ProviderListReply {
+        data: data
+            .into_iter()
+            .map::<pb::Oauth2Provider, _>(|x| x.into())
+            .collect(),
+    })
+}
+
+impl validator::Validate for pb::Oauth2ProviderCreateRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::name(e, "name", &self.name);
+            validate::url(e, "discovery_url", &self.discovery_url);
+            validate::oauth2_client(e, "client_id", &self.client_id);
+            validate::oauth2_client(e, "client_secret", &self.client_secret);
+            validate::oauth2_scope_vec(e, "scopes", &self.scopes);
+            validate::name_opt(e, "email_claim", self.email_claim.as_ref().map(|x| &**x));
+        })
+    }
+}
+
+pub async fn create(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<Oauth2ProviderCreate>,
+) -> GrpcMethodResult<pb::Oauth2ProviderReadReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_subject(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::Oauth2ProviderCreate,
+            |driver, audit| {
+                pattern::key_root_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                req.create(&conn).map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|data| pb::Oauth2ProviderReadReply {
+        data: Some(data.into()),
+    })
+}
+
+impl validator::Validate for pb::Oauth2ProviderReadRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "id", &self.id);
+        })
+    }
+}
+
+pub async fn read(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::Oauth2ProviderReadRequest>,
+) -> GrpcMethodResult<pb::Oauth2ProviderReadReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::Oauth2ProviderRead,
+            |driver, audit| {
+                pattern::key_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                read_inner(driver, pb::string_to_uuid(req.id.clone()))
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|data| pb::Oauth2ProviderReadReply {
+        data: Some(data.into()),
+    })
+}
+
+impl validator::Validate for pb::Oauth2ProviderUpdateRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "id", &self.id);
+            validate::name_opt(e, "name", self.name.as_ref().map(|x| &**x));
+            validate::url_opt(
+                e,
+                "discovery_url",
+                self.discovery_url.as_ref().map(|x| &**x),
+            );
+            validate::oauth2_client_opt(e, "client_id", self.client_id.as_ref().map(|x| &**x));
+            validate::oauth2_client_opt(
+                e,
+                "client_secret",
+                self.client_secret.as_ref().map(|x| &**x),
+            );
+            validate::oauth2_scope_vec(e, "scopes", &self.scopes);
+            validate::name_opt(e, "email_claim", self.email_claim.as_ref().map(|x| &**x));
+        })
+    }
+}
+
+pub async fn update(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<Oauth2ProviderUpdate>,
+) -> GrpcMethodResult<pb::Oauth2ProviderReadReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_diff(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::Oauth2ProviderUpdate,
+            |driver, audit| {
+                pattern::key_root_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                let previous_provider = read_inner(driver, req.id)?;
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let provider = req.update(&conn).map_err(GrpcMethodError::BadRequest)?;
+                Ok((previous_provider, provider))
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|data| pb::Oauth2ProviderReadReply {
+        data: Some(data.into()),
+    })
+}
+
+pub async fn delete(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::Oauth2ProviderReadRequest>,
+) -> GrpcMethodResult<()> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_subject(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::Oauth2ProviderDelete,
+            |driver, audit| {
+                pattern::key_root_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                let provider = read_inner(driver, pb::string_to_uuid(req.id.clone()))?;
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                Oauth2ProviderRead::delete(&conn, provider.id)
+                    .map_err(GrpcMethodError::BadRequest)
+                    .map(|_| provider)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|_data| ())
+}
+
+fn read_inner(driver: &Postgres, id: Uuid) -> GrpcMethodResult<Oauth2Provider> {
+    let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+    Oauth2ProviderRead::id(&conn, id)
+        .map_err(GrpcMethodError::BadRequest)?
+        .ok_or_else(|| DriverError::Oauth2ProviderNotFound)
+        .map_err(GrpcMethodError::NotFound)
+}
@@ -69,6 +69,44 @@ impl validator::Validate for pb::ServiceCreateRequest {
                 "provider_microsoft_oauth2_url",
                 self.provider_microsoft_oauth2_url.as_ref().map(|x| &**x),
             );
+            validate::url_vec(e, "oidc_redirect_uris", &self.oidc_redirect_uris);
+            validate::jwt_algorithm_opt(
+                e,
+                "jwt_algorithm",
+                self.jwt_algorithm.as_ref().map(|x| &**x),
+            );
+            validate::url_opt(
+                e,
+                "provider_oauth2_url",
+                self.provider_oauth2_url.as_ref().map(|x| &**x),
+            );
+            validate::url_opt(
+                e,
+                "provider_saml_url",
+                self.provider_saml_url.as_ref().map(|x| &**x),
+            );
+            validate::saml_idp_metadata_opt(
+                e,
+                "saml_idp_metadata",
+                self.saml_idp_metadata.as_ref().map(|x| &**x),
+            );
+            validate::name_opt(
+                e,
+                "saml_email_attribute",
+                self.saml_email_attribute.as_ref().map(|x| &**x),
+            );
+            validate::mfa_policy_opt(e, "mfa_policy", self.mfa_policy.as_ref().map(|x| &**x));
+            validate::password_min_strength_opt(
+                e,
+                "password_min_strength",
+                self.password_min_strength,
+            );
+            validate::password_history_opt(e, "password_history", self.password_history);
+            validate::password_max_age_days_opt(
+                e,
+                "password_max_age_days",
+                self.password_max_age_days,
+            );
         })
     }
 }
@@ -163,6 +201,44 @@ impl validator::Validate for pb::ServiceUpdateRequest {
                 "provider_microsoft_oauth2_url",
                 self.provider_microsoft_oauth2_url.as_ref().map(|x| &**x),
             );
+            validate::url_vec(e, "oidc_redirect_uris", &self.oidc_redirect_uris);
+            validate::jwt_algorithm_opt(
+                e,
+                "jwt_algorithm",
+                self.jwt_algorithm.as_ref().map(|x| &**x),
+            );
+            validate::url_opt(
+                e,
+                "provider_oauth2_url",
+                self.provider_oauth2_url.as_ref().map(|x| &**x),
+            );
+            validate::url_opt(
+                e,
+                "provider_saml_url",
+                self.provider_saml_url.as_ref().map(|x| &**x),
+            );
+            validate::saml_idp_metadata_opt(
+                e,
+                "saml_idp_metadata",
+                self.saml_idp_metadata.as_ref().map(|x| &**x),
+            );
+            validate::name_opt(
+                e,
+                "saml_email_attribute",
+                self.saml_email_attribute.as_ref().map(|x| &**x),
+            );
+            validate::mfa_policy_opt(e, "mfa_policy", self.mfa_policy.as_ref().map(|x| &**x));
+            validate::password_min_strength_opt(
+                e,
+                "password_min_strength",
+                self.password_min_strength,
+            );
+            validate::password_history_opt(e, "password_history", self.password_history);
+            validate::password_max_age_days_opt(
+                e,
+                "password_max_age_days",
+                self.password_max_age_days,
+            );
         })
     }
 }
@@ -50,6 +50,7 @@ impl validator::Validate for pb::UserCreateRequest {
             validate::email(e, "email", &self.email);
             validate::locale_opt(e, "locale", self.locale.as_ref().map(|x| &**x));
             validate::timezone_opt(e, "timezone", self.timezone.as_ref().map(|x| &**x));
+            validate::phone_opt(e, "phone", self.phone.as_ref().map(|x| &**x));
             validate::password_opt(e, "password", self.password.as_ref().map(|x| &**x));
         })
     }
@@ -61,24 +62,53 @@ pub async fn create(
 ) -> GrpcMethodResult<pb::UserCreateReply> {
     let (audit_meta, auth, req) = request.into_inner();
     let password = req.password.clone();
+    let password_allow_reset = req.password_allow_reset.unwrap_or(false);
+    let password_require_update = req.password_require_update.unwrap_or(false);
     let req: UserCreate = req.into();
 
     let client = server.client();
-    let pwned_passwords = server.options().pwned_passwords_enabled();
-    let password_meta = pattern::password_meta(client.as_ref(), pwned_passwords, password)
+    let pwned_passwords = server.options().pwned_passwords_options();
+    let password_meta = pattern::password_meta(client.as_ref(), pwned_passwords, password.clone())
         .await
         .map_err(GrpcMethodError::BadRequest)?;
 
     let driver = server.driver();
+    let password_policy = server.options().password_policy_options();
+    let password_hash = server.options().password_hash_options();
     blocking_method(move || {
         let data = audit_result_subject(
             driver.as_ref(),
             audit_meta,
             AuditType::UserCreate,
             |driver, audit| {
-                let _service = pattern::key_authenticate(driver, audit, &auth)
+                let service = pattern::key_authenticate(driver, audit, &auth)
                     .map_err(GrpcMethodError::Unauthorised)?;
 
+                // Check password against password policy, then hash password.
+                let req = match &password {
+                    Some(password) => {
+                        pattern::user_password_policy_check(
+                            driver,
+                            &password_policy,
+                            service.as_ref(),
+                            None,
+                            "password",
+                            password,
+                            &password_meta,
+                        )
+                        .map_err(GrpcMethodError::BadRequest)?;
+                        req.clone()
+                            .with_password(
+                                password_allow_reset,
+                                password_require_update,
+                                password,
+                                &password_hash,
+                            )
+                            .map_err(GrpcMethodError::BadRequest)?
+                    }
+                    None => req.clone(),
+                };
+
                 driver
                     .user_create(&req)
                     .map_err(GrpcMethodError::BadRequest)
@@ -135,6 +165,11 @@ impl validator::Validate for pb::UserUpdateRequest {
             validate::name_opt(e, "name", self.name.as_ref().map(|x| &**x));
             validate::locale_opt(e, "locale", self.locale.as_ref().map(|x| &**x));
             validate::timezone_opt(e, "timezone", self.timezone.as_ref().map(|x| &**x));
+            validate::phone_opt(
+                e,
+                "phone",
+                self.phone.as_ref().map(|x| &**x).filter(|x| !x.is_empty()),
+            );
         })
     }
 }
@@ -201,6 +236,138 @@ pub async fn delete(
     .map(|_data| ())
 }
 
+impl validator::Validate for pb::UserSessionListRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "user_id", &self.user_id);
+        })
+    }
+}
+
+pub async fn session_list(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::UserSessionListRequest>,
+) -> GrpcMethodResult<pb::UserSessionListReply> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::UserSessionList,
+            |driver, audit| {
+                let service = pattern::key_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Service keys can only list sessions created by that service.
+                let read = UserRead::Id(pb::string_to_uuid(req.user_id.clone()));
+                let user = read_inner(driver, &read)?;
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                UserSession::list(&conn, user.id, service.map(|x| x.id))
+                    .map_err(GrpcMethodError::BadRequest)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|data| pb::UserSessionListReply {
+        data: data
+            .into_iter()
+            .map::<pb::UserSession, _>(|x| x.into())
+            .collect(),
+    })
+}
+
+impl validator::Validate for pb::UserSessionRevokeRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "user_id", &self.user_id);
+            validate::uuid(e, "id", &self.id);
+        })
+    }
+}
+
+pub async fn session_revoke(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::UserSessionRevokeRequest>,
+) -> GrpcMethodResult<()> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_subject(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::UserSessionRevoke,
+            |driver, audit| {
+                let service = pattern::key_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Service keys can only revoke sessions created by that service.
+                let read = UserRead::Id(pb::string_to_uuid(req.user_id.clone()));
+                let user = read_inner(driver, &read)?;
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let session = UserSession::read(
+                    &conn,
+                    pb::string_to_uuid(req.id.clone()),
+                    user.id,
+                    service.map(|x| x.id),
+                )
+                .map_err(GrpcMethodError::BadRequest)?
+                .ok_or_else(|| DriverError::UserSessionNotFound)
+                .map_err(GrpcMethodError::NotFound)?;
+
+                UserSession::revoke(&conn, session.id())
+                    .map_err(GrpcMethodError::BadRequest)
+                    .map(|_| session)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|_data| ())
+}
+
+impl validator::Validate for pb::UserLoginUnlockRequest {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        validate::wrap(|e| {
+            validate::uuid(e, "user_id", &self.user_id);
+            validate::text_opt(e, "remote", self.remote.as_ref().map(|x| &**x));
+        })
+    }
+}
+
+pub async fn login_unlock(
+    server: &GrpcServer,
+    request: GrpcMethodRequest<pb::UserLoginUnlockRequest>,
+) -> GrpcMethodResult<()> {
+    let (audit_meta, auth, req) = request.into_inner();
+
+    let driver = server.driver();
+    blocking_method(move || {
+        audit_result_subject(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::UserLoginUnlock,
+            |driver, audit| {
+                pattern::key_root_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                let read = UserRead::Id(pb::string_to_uuid(req.user_id.clone()));
+                let user = read_inner(driver, &read)?;
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                LoginLockout::unlock(&conn, &user.email, req.remote.as_ref().map(|x| &**x))
+                    .map_err(GrpcMethodError::BadRequest)
+                    .map(|_| user)
+            },
+        )
+        .map_err(Into::into)
+    })
+    .await
+    .map(|_data| ())
+}
+
 fn read_inner(driver: &Postgres, read: &UserRead) -> GrpcMethodResult<User> {
     driver
         .user_read(read)
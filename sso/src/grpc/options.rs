@@ -6,7 +6,7 @@ use lettre::{
 };
 use native_tls::{Protocol, TlsConnector};
 use reqwest::Client;
-use std::fs;
+use std::{fs, sync::Arc};
 use tonic::transport::{Certificate, Identity, ServerTlsConfig};
 
 /// gRPC server authentication provider options.
@@ -63,6 +63,65 @@ impl Default for GrpcServerOptionsTls {
     }
 }
 
+/// gRPC server login lockout options.
+#[derive(Debug, Clone, Copy)]
+pub struct GrpcServerOptionsLockout {
+    user_attempts: i32,
+    remote_attempts: i32,
+    delay: Duration,
+    max_delay: Duration,
+}
+
+impl GrpcServerOptionsLockout {
+    /// Create new login lockout options.
+    ///
+    /// Zero attempts disables lockout for that counter.
+    pub fn new(user_attempts: i32, remote_attempts: i32, delay: i64, max_delay: i64) -> Self {
+        Self {
+            user_attempts,
+            remote_attempts,
+            delay: Duration::seconds(delay),
+            max_delay: Duration::seconds(max_delay),
+        }
+    }
+
+    /// Returns failed login attempts per user email before lockout.
+    pub fn user_attempts(&self) -> i32 {
+        self.user_attempts
+    }
+
+    /// Returns failed login attempts per remote IP address before lockout.
+    pub fn remote_attempts(&self) -> i32 {
+        self.remote_attempts
+    }
+
+    /// Returns lockout duration for failure count, doubled for each
+    /// failure after attempts up to maximum delay.
+    pub fn lockout_delay(&self, attempts: i32, failure_count: i32) -> Option<Duration> {
+        if attempts <= 0 || failure_count < attempts {
+            return None;
+        }
+        let exponent = (failure_count - attempts).min(30) as u32;
+        let delay = self
+            .delay
+            .num_seconds()
+            .saturating_mul(2_i64.saturating_pow(exponent));
+        Some(Duration::seconds(delay).min(self.max_delay))
+    }
+
+    /// Returns maximum lockout duration, failure counts are reset
+    /// after this duration without failures.
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+}
+
+impl Default for GrpcServerOptionsLockout {
+    fn default() -> Self {
+        Self::new(5, 0, 60, 3_600)
+    }
+}
+
 /// gRPC server options.
 #[derive(Debug, Clone)]
 pub struct GrpcServerOptions {
@@ -73,6 +132,8 @@ pub struct GrpcServerOptions {
     /// Enable Pwned Passwords API to check passwords.
     /// API keys may be required in the future to use this API.
     pwned_passwords_enabled: bool,
+    /// Pwned Passwords backend, local files can be used instead of API.
+    pwned_passwords: PwnedPasswords,
     /// Enabled Traefik forward authentication.
     traefik_enabled: bool,
     /// Access token expiry time duration.
@@ -81,6 +142,12 @@ pub struct GrpcServerOptions {
     refresh_token_expires: Duration,
     /// Revoke token expiry time duration.
     revoke_token_expires: Duration,
+    /// Login lockout after failed attempts.
+    lockout: GrpcServerOptionsLockout,
+    /// User password policy, services may define stricter policies.
+    password_policy: UserPasswordPolicy,
+    /// User password hash algorithm.
+    password_hash: UserPasswordHash,
     /// SMTP transport.
     smtp_transport: Option<GrpcServerOptionsSmtp>,
     /// SMTP file transport.
@@ -88,10 +155,18 @@ pub struct GrpcServerOptions {
     /// Writes emails to files in directory, if server settings
     /// are defined this is ignored.
     smtp_file_transport: Option<String>,
+    /// Notification HTTP webhook URL.
+    notify_webhook: Option<String>,
+    /// Additional notification channels.
+    notify_channels: Vec<Arc<dyn NotifyChannel>>,
     /// Github provider.
     github: Option<GrpcServerOptionsProvider>,
     /// Microsoft provider.
     microsoft: Option<GrpcServerOptionsProvider>,
+    /// OpenID Connect issuer URL.
+    ///
+    /// OpenID Connect provider endpoints are disabled if undefined.
+    oidc_issuer: Option<String>,
 }
 
 impl GrpcServerOptions {
@@ -104,14 +179,21 @@ impl GrpcServerOptions {
             tls: GrpcServerOptionsTls::default(),
             user_agent: user_agent.into(),
             pwned_passwords_enabled,
+            pwned_passwords: PwnedPasswords::default(),
             traefik_enabled,
             access_token_expires: Duration::seconds(3_600),
             refresh_token_expires: Duration::seconds(86_400),
             revoke_token_expires: Duration::seconds(604_800),
+            lockout: GrpcServerOptionsLockout::default(),
+            password_policy: UserPasswordPolicy::default(),
+            password_hash: UserPasswordHash::default(),
             smtp_transport: None,
             smtp_file_transport: None,
+            notify_webhook: None,
+            notify_channels: Vec::new(),
             github: None,
             microsoft: None,
+            oidc_issuer: None,
         }
     }
 
@@ -164,6 +246,152 @@ impl GrpcServerOptions {
         self
     }
 
+    /// Set login lockout options.
+    pub fn lockout(mut self, lockout: GrpcServerOptionsLockout) -> Self {
+        self.lockout = lockout;
+        self
+    }
+
+    /// Read login lockout environment variables into options.
+    ///
+    /// Undefined variables use default values.
+    pub fn lockout_from_env<T: AsRef<str>>(
+        self,
+        user_attempts_name: T,
+        remote_attempts_name: T,
+        delay_name: T,
+        max_delay_name: T,
+    ) -> Self {
+        let default = GrpcServerOptionsLockout::default();
+        let user_attempts = env::value_opt::<i32>(user_attempts_name.as_ref())
+            .expect("Failed to read lockout user attempts environment variable.")
+            .unwrap_or(default.user_attempts);
+        let remote_attempts = env::value_opt::<i32>(remote_attempts_name.as_ref())
+            .expect("Failed to read lockout remote attempts environment variable.")
+            .unwrap_or(default.remote_attempts);
+        let delay = env::value_opt::<i64>(delay_name.as_ref())
+            .expect("Failed to read lockout delay environment variable.")
+            .unwrap_or_else(|| default.delay.num_seconds());
+        let max_delay = env::value_opt::<i64>(max_delay_name.as_ref())
+            .expect("Failed to read lockout maximum delay environment variable.")
+            .unwrap_or_else(|| default.max_delay.num_seconds());
+        self.lockout(GrpcServerOptionsLockout::new(
+            user_attempts,
+            remote_attempts,
+            delay,
+            max_delay,
+        ))
+    }
+
+    /// Set Pwned Passwords backend.
+    pub fn pwned_passwords(mut self, pwned_passwords: PwnedPasswords) -> Self {
+        self.pwned_passwords = pwned_passwords;
+        self
+    }
+
+    /// Read Pwned Passwords backend environment variables into options.
+    ///
+    /// If no variables are defined, API is used. Bloom filter file is read into memory.
+    pub fn pwned_passwords_from_env<T: AsRef<str>>(self, file_name: T, bloom_name: T) -> Self {
+        let file = env::string_opt(file_name.as_ref());
+        let bloom = env::string_opt(bloom_name.as_ref());
+        let pwned_passwords = match (file, bloom) {
+            (Some(_), Some(_)) => {
+                panic!(
+                    "Pwned Passwords file and bloom filter environment variables are both defined."
+                )
+            }
+            (Some(file), None) => PwnedPasswords::File(
+                PwnedPasswordsFile::open(file).expect("Failed to open Pwned Passwords file."),
+            ),
+            (None, Some(bloom)) => PwnedPasswords::Bloom(Arc::new(
+                PwnedPasswordsBloom::read(bloom)
+                    .expect("Failed to read Pwned Passwords bloom filter."),
+            )),
+            (None, None) => PwnedPasswords::Api,
+        };
+        self.pwned_passwords(pwned_passwords)
+    }
+
+    /// Set user password policy.
+    pub fn password_policy(mut self, password_policy: UserPasswordPolicy) -> Self {
+        self.password_policy = password_policy;
+        self
+    }
+
+    /// Read user password policy environment variables into options.
+    ///
+    /// Undefined variables disable that check.
+    pub fn password_policy_from_env<T: AsRef<str>>(
+        self,
+        min_strength_name: T,
+        reject_pwned_name: T,
+        history_name: T,
+        max_age_days_name: T,
+    ) -> Self {
+        let min_strength = env::value_opt::<i32>(min_strength_name.as_ref())
+            .expect("Failed to read password minimum strength environment variable.")
+            .unwrap_or(0);
+        let reject_pwned = env::value_opt::<bool>(reject_pwned_name.as_ref())
+            .expect("Failed to read password reject pwned environment variable.")
+            .unwrap_or(false);
+        let history = env::value_opt::<i32>(history_name.as_ref())
+            .expect("Failed to read password history environment variable.")
+            .unwrap_or(0);
+        let max_age_days = env::value_opt::<i32>(max_age_days_name.as_ref())
+            .expect("Failed to read password maximum age environment variable.")
+            .unwrap_or(0);
+        self.password_policy(UserPasswordPolicy {
+            min_strength,
+            reject_pwned,
+            history,
+            max_age_days,
+        })
+    }
+
+    /// Set user password hash algorithm.
+    pub fn password_hash(mut self, password_hash: UserPasswordHash) -> Self {
+        self.password_hash = password_hash;
+        self
+    }
+
+    /// Read user password hash environment variables into options.
+    ///
+    /// If Argon2id is not enabled, parameters are ignored. Undefined parameters use defaults.
+    pub fn password_hash_from_env<T: AsRef<str>>(
+        self,
+        argon2id_name: T,
+        memory_name: T,
+        iterations_name: T,
+        parallelism_name: T,
+    ) -> Self {
+        let argon2id = env::value_opt::<bool>(argon2id_name.as_ref())
+            .expect("Failed to read password Argon2id enabled environment variable.")
+            .unwrap_or(false);
+        if !argon2id {
+            return self.password_hash(UserPasswordHash::Libreauth);
+        }
+
+        let memory = env::value_opt::<u32>(memory_name.as_ref())
+            .expect("Failed to read password Argon2id memory environment variable.")
+            .unwrap_or(DEFAULT_USER_PASSWORD_ARGON2ID_MEMORY);
+        let iterations = env::value_opt::<u32>(iterations_name.as_ref())
+            .expect("Failed to read password Argon2id iterations environment variable.")
+            .unwrap_or(DEFAULT_USER_PASSWORD_ARGON2ID_ITERATIONS);
+        let parallelism = env::value_opt::<u32>(parallelism_name.as_ref())
+            .expect("Failed to read password Argon2id parallelism environment variable.")
+            .unwrap_or(DEFAULT_USER_PASSWORD_ARGON2ID_PARALLELISM);
+        let password_hash = UserPasswordHash::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        };
+        password_hash
+            .hash("password-hash-check")
+            .expect("Failed to hash password with Argon2id parameters.");
+        self.password_hash(password_hash)
+    }
+
     /// Set SMTP transport options.
     pub fn smtp_transport(mut self, smtp_transport: Option<GrpcServerOptionsSmtp>) -> Self {
         self.smtp_transport = smtp_transport;
@@ -216,6 +444,24 @@ impl GrpcServerOptions {
         self.smtp_file_transport(Some(transport))
     }
 
+    /// Set notification HTTP webhook URL.
+    pub fn notify_webhook(mut self, notify_webhook: Option<String>) -> Self {
+        self.notify_webhook = notify_webhook;
+        self
+    }
+
+    /// Read notification HTTP webhook environment variable into options.
+    pub fn notify_webhook_from_env<T: AsRef<str>>(self, url_name: T) -> Self {
+        let url = env::string_opt(url_name.as_ref());
+        self.notify_webhook(url)
+    }
+
+    /// Add notification channel.
+    pub fn notify_channel(mut self, channel: Arc<dyn NotifyChannel>) -> Self {
+        self.notify_channels.push(channel);
+        self
+    }
+
     /// Set Github provider.
     pub fn github(mut self, github: Option<GrpcServerOptionsProvider>) -> Self {
         self.github = github;
@@ -270,6 +516,18 @@ impl GrpcServerOptions {
         self.microsoft(provider)
     }
 
+    /// Set OpenID Connect issuer URL.
+    pub fn oidc_issuer(mut self, oidc_issuer: Option<String>) -> Self {
+        self.oidc_issuer = oidc_issuer.map(|x| x.trim_end_matches('/').to_owned());
+        self
+    }
+
+    /// Read OpenID Connect issuer environment variable into options.
+    pub fn oidc_issuer_from_env<T: AsRef<str>>(self, issuer_name: T) -> Self {
+        let issuer = env::string_opt(issuer_name.as_ref());
+        self.oidc_issuer(issuer)
+    }
+
     /// Return server TLS configuration if any TLS settings are defined.
     pub fn tls_config(&self) -> Option<ServerTlsConfig> {
         let mut x = ServerTlsConfig::new();
@@ -305,11 +563,25 @@ impl GrpcServerOptions {
         self.pwned_passwords_enabled
     }
 
+    /// Returns Pwned Passwords backend if integration is enabled.
+    pub fn pwned_passwords_options(&self) -> Option<PwnedPasswords> {
+        if self.pwned_passwords_enabled {
+            Some(self.pwned_passwords.clone())
+        } else {
+            None
+        }
+    }
+
     /// Returns Traefik integration enabled flag.
     pub fn traefik_enabled(&self) -> bool {
         self.traefik_enabled
     }
 
+    /// Returns OpenID Connect issuer URL if provider endpoints are enabled.
+    pub fn oidc_issuer_url(&self) -> Option<&str> {
+        self.oidc_issuer.as_ref().map(|x| &**x)
+    }
+
     /// Returns access token expiry value.
     pub fn access_token_expires(&self) -> Duration {
         self.access_token_expires
@@ -325,6 +597,21 @@ impl GrpcServerOptions {
         self.revoke_token_expires
     }
 
+    /// Returns login lockout options.
+    pub fn lockout_options(&self) -> GrpcServerOptionsLockout {
+        self.lockout
+    }
+
+    /// Returns user password policy.
+    pub fn password_policy_options(&self) -> UserPasswordPolicy {
+        self.password_policy
+    }
+
+    /// Returns user password hash algorithm.
+    pub fn password_hash_options(&self) -> UserPasswordHash {
+        self.password_hash
+    }
+
     /// Returns `SmtpClient` built from options.
     pub fn smtp_client(&self) -> DriverResult<Option<SmtpClient>> {
         if let Some(smtp) = self.smtp_transport.as_ref() {
@@ -361,6 +648,31 @@ impl GrpcServerOptions {
         self.smtp_file_transport.as_ref().map(|x| x.to_owned())
     }
 
+    /// Returns notification channels built from options.
+    ///
+    /// SMTP file transport is used if SMTP transport is undefined.
+    pub fn notify_channels(
+        &self,
+        client: Arc<Client>,
+    ) -> DriverResult<Vec<Arc<dyn NotifyChannel>>> {
+        let mut channels: Vec<Arc<dyn NotifyChannel>> = Vec::new();
+        match (self.smtp_client()?, self.smtp_file()) {
+            (Some(smtp_client), _) => {
+                let from_email = self.smtp_from_email().unwrap();
+                channels.push(Arc::new(NotifySmtp::new(smtp_client, from_email)));
+            }
+            (None, Some(smtp_file)) => {
+                channels.push(Arc::new(NotifyFile::new(smtp_file)));
+            }
+            (None, None) => {}
+        }
+        if let Some(url) = self.notify_webhook.as_ref() {
+            channels.push(Arc::new(NotifyWebhook::new(client, url)));
+        }
+        channels.extend(self.notify_channels.iter().cloned());
+        Ok(channels)
+    }
+
     /// Returns provider GitHub OAuth2 common arguments.
     pub(crate) fn github_oauth2_args(&self) -> ServerProviderOauth2Args {
         ServerProviderOauth2Args::new(
@@ -401,3 +713,23 @@ impl ServerProviderOauth2Args {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockout_delay_disabled() {
+        let x = GrpcServerOptionsLockout::new(0, 0, 60, 3_600);
+        assert_eq!(x.lockout_delay(x.user_attempts(), 100), None);
+    }
+
+    #[test]
+    fn lockout_delay_doubles_up_to_max_delay() {
+        let x = GrpcServerOptionsLockout::new(5, 0, 60, 3_600);
+        assert_eq!(x.lockout_delay(5, 4), None);
+        assert_eq!(x.lockout_delay(5, 5), Some(Duration::seconds(60)));
+        assert_eq!(x.lockout_delay(5, 6), Some(Duration::seconds(120)));
+        assert_eq!(x.lockout_delay(5, 100), Some(Duration::seconds(3_600)));
+    }
+}
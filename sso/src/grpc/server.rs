@@ -1,8 +1,6 @@
 use crate::{grpc::method, prelude::*};
-use lettre::{file::FileTransport, SmtpClient, Transport};
-use lettre_email::Email;
 use prometheus::{HistogramTimer, HistogramVec, IntCounterVec};
-use std::{fmt, path::PathBuf, sync::Arc};
+use std::{fmt, sync::Arc};
 
 /// gRPC server metrics.
 pub struct GrpcServerMetrics {
@@ -43,7 +41,7 @@ pub struct GrpcServer {
     options: GrpcServerOptions,
     driver: Arc<Postgres>,
     client: Arc<reqwest::Client>,
-    smtp_client: Arc<Option<SmtpClient>>,
+    notify_channels: Arc<Vec<Arc<dyn NotifyChannel>>>,
     count: IntCounterVec,
     latency: HistogramVec,
 }
@@ -57,14 +55,14 @@ impl fmt::Debug for GrpcServer {
 impl GrpcServer {
     /// Returns new server.
     pub fn new(driver: Postgres, options: GrpcServerOptions) -> Self {
-        let client = options.client().unwrap();
-        let smtp_client = options.smtp_client().unwrap();
+        let client = Arc::new(options.client().unwrap());
+        let notify_channels = options.notify_channels(client.clone()).unwrap();
         let (count, latency) = Metrics::grpc_metrics();
         Self {
             options,
             driver: Arc::new(driver),
-            client: Arc::new(client),
-            smtp_client: Arc::new(smtp_client),
+            client,
+            notify_channels: Arc::new(notify_channels),
             count,
             latency,
         }
@@ -90,46 +88,11 @@ impl GrpcServer {
         self.client.clone()
     }
 
-    /// Build email callback function. Must be called from blocking context.
-    /// If client is None and file directory path is provided, file transport is used.
-    pub(crate) fn smtp_email(&self) -> Box<dyn FnOnce(TemplateEmail) -> DriverResult<()> + Send> {
-        let client = self.smtp_client.clone();
-        let from_email = self.options.smtp_from_email();
-        let smtp_file = self.options.smtp_file();
-
-        Box::new(move |email| {
-            let email_builder = Email::builder()
-                .to((email.to_email, email.to_name))
-                .subject(email.subject)
-                .text(email.text);
-
-            match (client.as_ref(), smtp_file) {
-                (Some(client), _) => {
-                    let email = email_builder
-                        .from((from_email.unwrap(), email.from_name))
-                        .build()
-                        .map_err(DriverError::LettreEmail)?;
-
-                    let mut transport = client.clone().transport();
-                    transport.send(email.into()).map_err(DriverError::Lettre)?;
-                    Ok(())
-                }
-                (_, Some(smtp_file)) => {
-                    let email = email_builder
-                        .from(("file@localhost", email.from_name))
-                        .build()
-                        .map_err(DriverError::LettreEmail)?;
-
-                    let path = PathBuf::from(smtp_file);
-                    let mut transport = FileTransport::new(path);
-                    transport
-                        .send(email.into())
-                        .map_err(DriverError::LettreFile)?;
-                    Ok(())
-                }
-                (None, None) => Err(DriverError::SmtpDisabled),
-            }
-        })
+    /// Build notification callback function. Must be called from blocking context.
+    /// Notification is sent to all channels, error is returned if none delivered it.
+    pub(crate) fn notify(&self) -> Box<dyn FnOnce(TemplateEmail) -> DriverResult<()> + Send> {
+        let channels = self.notify_channels.clone();
+        Box::new(move |message| notify_send(&channels, &message))
     }
 
     fn pre(
@@ -264,6 +227,13 @@ impl pb::sso_server::Sso for GrpcServer {
         let (metrics, request) = self.pre_validate("key_update", request)?;
         self.post(metrics, method::key::update(self, request).await)
     }
+    async fn key_rotate(
+        &self,
+        request: tonic::Request<pb::KeyRotateRequest>,
+    ) -> Result<tonic::Response<pb::KeyRotateReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("key_rotate", request)?;
+        self.post(metrics, method::key::rotate(self, request).await)
+    }
     async fn key_delete(
         &self,
         request: tonic::Request<pb::KeyReadRequest>,
@@ -306,6 +276,50 @@ impl pb::sso_server::Sso for GrpcServer {
         let (metrics, request) = self.pre_validate("service_delete", request)?;
         self.post(metrics, method::service::delete(self, request).await)
     }
+    async fn oauth2_provider_list(
+        &self,
+        request: tonic::Request<()>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderListReply>, tonic::Status> {
+        let (metrics, request) = self.pre("oauth2_provider_list", request)?;
+        self.post(metrics, method::oauth2_provider::list(self, request).await)
+    }
+    async fn oauth2_provider_create(
+        &self,
+        request: tonic::Request<pb::Oauth2ProviderCreateRequest>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderReadReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("oauth2_provider_create", request)?;
+        self.post(
+            metrics,
+            method::oauth2_provider::create(self, request).await,
+        )
+    }
+    async fn oauth2_provider_read(
+        &self,
+        request: tonic::Request<pb::Oauth2ProviderReadRequest>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderReadReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("oauth2_provider_read", request)?;
+        self.post(metrics, method::oauth2_provider::read(self, request).await)
+    }
+    async fn oauth2_provider_update(
+        &self,
+        request: tonic::Request<pb::Oauth2ProviderUpdateRequest>,
+    ) -> Result<tonic::Response<pb::Oauth2ProviderReadReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("oauth2_provider_update", request)?;
+        self.post(
+            metrics,
+            method::oauth2_provider::update(self, request).await,
+        )
+    }
+    async fn oauth2_provider_delete(
+        &self,
+        request: tonic::Request<pb::Oauth2ProviderReadRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("oauth2_provider_delete", request)?;
+        self.post(
+            metrics,
+            method::oauth2_provider::delete(self, request).await,
+        )
+    }
     async fn user_list(
         &self,
         request: tonic::Request<pb::UserListRequest>,
@@ -341,13 +355,34 @@ impl pb::sso_server::Sso for GrpcServer {
         let (metrics, request) = self.pre_validate("user_delete", request)?;
         self.post(metrics, method::user::delete(self, request).await)
     }
+    async fn user_session_list(
+        &self,
+        request: tonic::Request<pb::UserSessionListRequest>,
+    ) -> Result<tonic::Response<pb::UserSessionListReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("user_session_list", request)?;
+        self.post(metrics, method::user::session_list(self, request).await)
+    }
+    async fn user_session_revoke(
+        &self,
+        request: tonic::Request<pb::UserSessionRevokeRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("user_session_revoke", request)?;
+        self.post(metrics, method::user::session_revoke(self, request).await)
+    }
+    async fn user_login_unlock(
+        &self,
+        request: tonic::Request<pb::UserLoginUnlockRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("user_login_unlock", request)?;
+        self.post(metrics, method::user::login_unlock(self, request).await)
+    }
     async fn auth_key_verify(
         &self,
         request: tonic::Request<pb::AuthKeyRequest>,
     ) -> Result<tonic::Response<pb::AuthKeyReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_key_verify", request)?;
         self.post(metrics, method::auth::key::verify(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_key_revoke(
         &self,
@@ -355,7 +390,7 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthAuditReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_key_revoke", request)?;
         self.post(metrics, method::auth::key::revoke(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_token_verify(
         &self,
@@ -363,7 +398,7 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthTokenVerifyReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_token_verify", request)?;
         self.post(metrics, method::auth::token::verify(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_token_refresh(
         &self,
@@ -371,7 +406,7 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthTokenReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_token_refresh", request)?;
         self.post(metrics, method::auth::token::refresh(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_token_revoke(
         &self,
@@ -379,7 +414,7 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthAuditReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_token_revoke", request)?;
         self.post(metrics, method::auth::token::revoke(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_totp_verify(
         &self,
@@ -387,7 +422,40 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthAuditReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_totp_verify", request)?;
         self.post(metrics, method::auth::totp_verify(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
+    }
+    async fn auth_totp_register_begin(
+        &self,
+        request: tonic::Request<pb::AuthTotpRegisterBeginRequest>,
+    ) -> Result<tonic::Response<pb::AuthTotpRegisterBeginReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_totp_register_begin", request)?;
+        self.post(
+            metrics,
+            method::auth::totp::register_begin(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_totp_register_finish(
+        &self,
+        request: tonic::Request<pb::AuthTotpRegisterFinishRequest>,
+    ) -> Result<tonic::Response<pb::AuthTotpRegisterFinishReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_totp_register_finish", request)?;
+        self.post(
+            metrics,
+            method::auth::totp::register_finish(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_totp_recovery_verify(
+        &self,
+        request: tonic::Request<pb::AuthTotpRecoveryRequest>,
+    ) -> Result<tonic::Response<pb::AuthAuditReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_totp_recovery_verify", request)?;
+        self.post(
+            metrics,
+            method::auth::totp::recovery_verify(self, request).await,
+        )
+        .map_err(status_redact)
     }
     async fn auth_csrf_create(
         &self,
@@ -395,7 +463,7 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthCsrfCreateReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_csrf_create", request)?;
         self.post(metrics, method::auth::csrf_create(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_csrf_verify(
         &self,
@@ -403,7 +471,7 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthAuditReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_csrf_verify", request)?;
         self.post(metrics, method::auth::csrf_verify(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_local_login(
         &self,
@@ -411,7 +479,59 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_local_login", request)?;
         self.post(metrics, method::auth::local::login(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
+    }
+    async fn auth_local_login_mfa(
+        &self,
+        request: tonic::Request<pb::AuthLoginMfaRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_local_login_mfa", request)?;
+        self.post(metrics, method::auth::local::login_mfa(self, request).await)
+            .map_err(status_redact)
+    }
+    async fn auth_local_magic_link(
+        &self,
+        request: tonic::Request<pb::AuthMagicLinkRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_local_magic_link", request)?;
+        self.post(
+            metrics,
+            method::auth::local::magic_link(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_local_magic_link_confirm(
+        &self,
+        request: tonic::Request<pb::AuthMagicLinkConfirmRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_local_magic_link_confirm", request)?;
+        self.post(
+            metrics,
+            method::auth::local::magic_link_confirm(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_local_email_code(
+        &self,
+        request: tonic::Request<pb::AuthEmailCodeRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_local_email_code", request)?;
+        self.post(
+            metrics,
+            method::auth::local::email_code(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_local_email_code_confirm(
+        &self,
+        request: tonic::Request<pb::AuthEmailCodeConfirmRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_local_email_code_confirm", request)?;
+        self.post(
+            metrics,
+            method::auth::local::email_code_confirm(self, request).await,
+        )
+        .map_err(status_redact)
     }
     async fn auth_local_register(
         &self,
@@ -419,7 +539,7 @@ impl pb::sso_server::Sso for GrpcServer {
     ) -> Result<tonic::Response<()>, tonic::Status> {
         let (metrics, request) = self.pre_validate("auth_local_register", request)?;
         self.post(metrics, method::auth::local::register(self, request).await)
-            .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+            .map_err(status_redact)
     }
     async fn auth_local_register_confirm(
         &self,
@@ -430,7 +550,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::register_confirm(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_register_revoke(
         &self,
@@ -441,7 +561,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::register_revoke(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_reset_password(
         &self,
@@ -452,7 +572,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::reset_password(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_reset_password_confirm(
         &self,
@@ -463,7 +583,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::reset_password_confirm(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_reset_password_revoke(
         &self,
@@ -474,7 +594,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::reset_password_revoke(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_update_email(
         &self,
@@ -485,7 +605,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::update_email(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_update_email_revoke(
         &self,
@@ -496,7 +616,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::update_email_revoke(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_update_password(
         &self,
@@ -507,7 +627,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::update_password(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_local_update_password_revoke(
         &self,
@@ -518,7 +638,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::local::update_password_revoke(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_github_oauth2_url(
         &self,
@@ -529,7 +649,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::github::oauth2_url(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_github_oauth2_callback(
         &self,
@@ -540,7 +660,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::github::oauth2_callback(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_microsoft_oauth2_url(
         &self,
@@ -551,7 +671,7 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::microsoft::oauth2_url(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
     }
     async fn auth_microsoft_oauth2_callback(
         &self,
@@ -562,6 +682,91 @@ impl pb::sso_server::Sso for GrpcServer {
             metrics,
             method::auth::microsoft::oauth2_callback(self, request).await,
         )
-        .map_err(|e| tonic::Status::new(e.code(), ERR_REDACTED))
+        .map_err(status_redact)
+    }
+    async fn auth_oauth2_url(
+        &self,
+        request: tonic::Request<pb::AuthOauth2UrlRequest>,
+    ) -> Result<tonic::Response<pb::AuthOauth2UrlReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_oauth2_url", request)?;
+        self.post(
+            metrics,
+            method::auth::oauth2::oauth2_url(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_oauth2_callback(
+        &self,
+        request: tonic::Request<pb::AuthOauth2ProviderCallbackRequest>,
+    ) -> Result<tonic::Response<pb::AuthTokenReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_oauth2_callback", request)?;
+        self.post(
+            metrics,
+            method::auth::oauth2::oauth2_callback(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_saml_url(
+        &self,
+        request: tonic::Request<()>,
+    ) -> Result<tonic::Response<pb::AuthSamlUrlReply>, tonic::Status> {
+        let (metrics, request) = self.pre("auth_saml_url", request)?;
+        self.post(metrics, method::auth::saml::saml_url(self, request).await)
+            .map_err(status_redact)
+    }
+    async fn auth_saml_callback(
+        &self,
+        request: tonic::Request<pb::AuthSamlCallbackRequest>,
+    ) -> Result<tonic::Response<pb::AuthTokenReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_saml_callback", request)?;
+        self.post(
+            metrics,
+            method::auth::saml::saml_callback(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_webauthn_register_begin(
+        &self,
+        request: tonic::Request<pb::AuthWebauthnRegisterBeginRequest>,
+    ) -> Result<tonic::Response<pb::AuthWebauthnChallengeReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_webauthn_register_begin", request)?;
+        self.post(
+            metrics,
+            method::auth::webauthn::register_begin(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_webauthn_register_finish(
+        &self,
+        request: tonic::Request<pb::AuthWebauthnFinishRequest>,
+    ) -> Result<tonic::Response<pb::AuthWebauthnRegisterFinishReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_webauthn_register_finish", request)?;
+        self.post(
+            metrics,
+            method::auth::webauthn::register_finish(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_webauthn_login_begin(
+        &self,
+        request: tonic::Request<pb::AuthWebauthnLoginBeginRequest>,
+    ) -> Result<tonic::Response<pb::AuthWebauthnChallengeReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_webauthn_login_begin", request)?;
+        self.post(
+            metrics,
+            method::auth::webauthn::login_begin(self, request).await,
+        )
+        .map_err(status_redact)
+    }
+    async fn auth_webauthn_login_finish(
+        &self,
+        request: tonic::Request<pb::AuthWebauthnFinishRequest>,
+    ) -> Result<tonic::Response<pb::AuthTokenReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("auth_webauthn_login_finish", request)?;
+        self.post(
+            metrics,
+            method::auth::webauthn::login_finish(self, request).await,
+        )
+        .map_err(status_redact)
     }
 }
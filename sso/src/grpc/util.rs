@@ -1,7 +1,7 @@
 use crate::prelude::*;
 use std::net::SocketAddr;
 use tokio::task;
-use tonic::{metadata::MetadataMap, Request, Status};
+use tonic::{metadata::MetadataMap, Code, Request, Status};
 use uuid::Uuid;
 
 /// Not found error message.
@@ -17,6 +17,15 @@ pub const ERR_REDACTED: &str = "RedactedError";
 /// Invalid metadata error message.
 pub const ERR_INVALID_METADATA: &str = "InvalidMetadata";
 
+/// Redact status message, validation errors are not redacted.
+pub fn status_redact(e: Status) -> Status {
+    if e.message() == ERR_VALIDATION {
+        e
+    } else {
+        Status::new(e.code(), ERR_REDACTED)
+    }
+}
+
 /// Run a blocking closure on threadpool.
 pub async fn blocking<T, E, F>(f: F) -> Result<T, E>
 where
@@ -76,9 +85,23 @@ struct GrpcMethodErrorData {
 }
 
 impl GrpcMethodError {
+    /// Returns gRPC status, validation errors are serialised as JSON in status details.
     pub fn get_status(&self) -> Status {
         match self {
+            GrpcMethodError::BadRequest(DriverError::Validation(e)) => Status::with_details(
+                Code::InvalidArgument,
+                ERR_VALIDATION,
+                serde_json::to_vec(e).unwrap_or_default().into(),
+            ),
+            // Email or remote is locked out by failed logins.
+            GrpcMethodError::BadRequest(e @ DriverError::UserLoginLocked) => {
+                Status::permission_denied(self.driver_string(e))
+            }
             GrpcMethodError::BadRequest(e) => Status::invalid_argument(self.driver_string(e)),
+            // Key is authenticated but key role does not have permission.
+            GrpcMethodError::Unauthorised(e @ DriverError::KeyPermissionDenied) => {
+                Status::permission_denied(self.driver_string(e))
+            }
             GrpcMethodError::Unauthorised(e) => Status::unauthenticated(self.driver_string(e)),
             GrpcMethodError::Forbidden(e) => Status::permission_denied(self.driver_string(e)),
             GrpcMethodError::NotFound(e) => Status::not_found(self.driver_string(e)),
@@ -347,6 +370,8 @@ impl From<pb::KeyListRequest> for KeyList {
             type_: pb::i32_vec_to_key_type_vec_opt(r.r#type),
             service_id: pb::string_vec_to_uuid_vec_opt(r.service_id),
             user_id: pb::string_vec_to_uuid_vec_opt(r.user_id),
+            expires_within_days: r.expires_within_days,
+            unused_for_days: r.unused_for_days,
             limit,
         };
         KeyList { query, filter }
@@ -361,6 +386,8 @@ impl From<KeyList> for pb::KeyListRequest {
         let type_ = pb::key_type_vec_opt_to_i32_vec(l.filter.type_);
         let service_id = pb::uuid_vec_opt_to_string_vec(l.filter.service_id);
         let user_id = pb::uuid_vec_opt_to_string_vec(l.filter.user_id);
+        let expires_within_days = l.filter.expires_within_days;
+        let unused_for_days = l.filter.unused_for_days;
         let limit = l.filter.limit;
         match l.query {
             KeyListQuery::Limit => Self {
@@ -373,6 +400,8 @@ impl From<KeyList> for pb::KeyListRequest {
                 r#type: type_,
                 service_id,
                 user_id,
+                expires_within_days,
+                unused_for_days,
             },
             KeyListQuery::IdGt(gt) => Self {
                 gt: Some(pb::uuid_to_string(gt)),
@@ -384,6 +413,8 @@ impl From<KeyList> for pb::KeyListRequest {
                 r#type: type_,
                 service_id,
                 user_id,
+                expires_within_days,
+                unused_for_days,
             },
             KeyListQuery::IdLt(lt) => Self {
                 gt: None,
@@ -395,6 +426,8 @@ impl From<KeyList> for pb::KeyListRequest {
                 r#type: type_,
                 service_id,
                 user_id,
+                expires_within_days,
+                unused_for_days,
             },
         }
     }
@@ -412,6 +445,11 @@ impl From<Key> for pb::Key {
             name: r.name,
             service_id: pb::uuid_opt_to_string_opt(r.service_id),
             user_id: pb::uuid_opt_to_string_opt(r.user_id),
+            expires_at: pb::datetime_opt_to_timestamp_opt(r.expires_at),
+            scopes: r.scopes,
+            last_used_at: pb::datetime_opt_to_timestamp_opt(r.last_used_at),
+            last_used_remote: r.last_used_remote,
+            role: r.role,
         }
     }
 }
@@ -428,6 +466,11 @@ impl From<KeyWithValue> for pb::Key {
             name: r.name,
             service_id: pb::uuid_opt_to_string_opt(r.service_id),
             user_id: pb::uuid_opt_to_string_opt(r.user_id),
+            expires_at: pb::datetime_opt_to_timestamp_opt(r.expires_at),
+            scopes: r.scopes,
+            last_used_at: pb::datetime_opt_to_timestamp_opt(r.last_used_at),
+            last_used_remote: r.last_used_remote,
+            role: r.role,
         }
     }
 }
@@ -442,6 +485,9 @@ impl From<pb::KeyCreateRequest> for KeyCreate {
             value: "".to_owned(),
             service_id: pb::string_opt_to_uuid_opt(r.service_id),
             user_id: pb::string_opt_to_uuid_opt(r.user_id),
+            expires_at: pb::timestamp_opt_to_datetime_opt(r.expires_at),
+            scopes: r.scopes,
+            role: r.role,
         }
     }
 }
@@ -472,6 +518,24 @@ impl From<pb::KeyUpdateRequest> for KeyUpdate {
             is_enabled: r.is_enabled,
             is_revoked: None,
             name: r.name,
+            expires_at: pb::timestamp_opt_to_datetime_opt(r.expires_at),
+            scopes: if r.scopes.is_empty() {
+                None
+            } else {
+                Some(r.scopes)
+            },
+            role: r.role,
+        }
+    }
+}
+
+impl From<pb::KeyRotateRequest> for KeyRotate {
+    fn from(r: pb::KeyRotateRequest) -> Self {
+        Self {
+            id: pb::string_to_uuid(r.id),
+            grace_period_hours: r
+                .grace_period_hours
+                .unwrap_or(DEFAULT_KEY_ROTATE_GRACE_PERIOD_HOURS),
         }
     }
 }
@@ -507,6 +571,23 @@ impl From<pb::ServiceCreateRequest> for ServiceCreate {
             provider_local_url: r.provider_local_url,
             provider_github_oauth2_url: r.provider_github_oauth2_url,
             provider_microsoft_oauth2_url: r.provider_microsoft_oauth2_url,
+            oidc_redirect_uris: r.oidc_redirect_uris,
+            jwt_algorithm: r
+                .jwt_algorithm
+                .map(|x| JwtAlgorithm::from_str(&x).unwrap())
+                .unwrap_or(JwtAlgorithm::Hs256),
+            provider_oauth2_url: r.provider_oauth2_url,
+            provider_saml_url: r.provider_saml_url,
+            saml_idp_metadata: r.saml_idp_metadata,
+            saml_email_attribute: r.saml_email_attribute,
+            mfa_policy: r
+                .mfa_policy
+                .map(|x| ServiceMfaPolicy::from_str(&x).unwrap())
+                .unwrap_or(ServiceMfaPolicy::Off),
+            password_min_strength: r.password_min_strength.unwrap_or(0) as i32,
+            password_reject_pwned: r.password_reject_pwned.unwrap_or(false),
+            password_history: r.password_history.unwrap_or(0) as i32,
+            password_max_age_days: r.password_max_age_days.unwrap_or(0) as i32,
         }
     }
 }
@@ -531,6 +612,23 @@ impl From<pb::ServiceUpdateRequest> for ServiceUpdate {
             provider_local_url: r.provider_local_url,
             provider_github_oauth2_url: r.provider_github_oauth2_url,
             provider_microsoft_oauth2_url: r.provider_microsoft_oauth2_url,
+            oidc_redirect_uris: if r.oidc_redirect_uris.is_empty() {
+                None
+            } else {
+                Some(r.oidc_redirect_uris)
+            },
+            jwt_algorithm: r.jwt_algorithm.map(|x| JwtAlgorithm::from_str(&x).unwrap()),
+            provider_oauth2_url: r.provider_oauth2_url,
+            provider_saml_url: r.provider_saml_url,
+            saml_idp_metadata: r.saml_idp_metadata,
+            saml_email_attribute: r.saml_email_attribute,
+            mfa_policy: r
+                .mfa_policy
+                .map(|x| ServiceMfaPolicy::from_str(&x).unwrap()),
+            password_min_strength: r.password_min_strength.map(|x| x as i32),
+            password_reject_pwned: r.password_reject_pwned,
+            password_history: r.password_history.map(|x| x as i32),
+            password_max_age_days: r.password_max_age_days.map(|x| x as i32),
         }
     }
 }
@@ -580,6 +678,17 @@ impl From<Service> for pb::Service {
             provider_local_url: r.provider_local_url,
             provider_github_oauth2_url: r.provider_github_oauth2_url,
             provider_microsoft_oauth2_url: r.provider_microsoft_oauth2_url,
+            oidc_redirect_uris: r.oidc_redirect_uris,
+            jwt_algorithm: r.jwt_algorithm.to_string(),
+            provider_oauth2_url: r.provider_oauth2_url,
+            provider_saml_url: r.provider_saml_url,
+            saml_idp_metadata: r.saml_idp_metadata,
+            saml_email_attribute: r.saml_email_attribute,
+            mfa_policy: r.mfa_policy.to_string(),
+            password_min_strength: r.password_min_strength as u32,
+            password_reject_pwned: r.password_reject_pwned,
+            password_history: r.password_history as u32,
+            password_max_age_days: r.password_max_age_days as u32,
         }
     }
 }
@@ -615,6 +724,7 @@ impl From<pb::UserListRequest> for UserList {
     }
 }
 
+/// Password is not converted, it must be hashed with `UserCreate::with_password`.
 impl From<pb::UserCreateRequest> for UserCreate {
     fn from(r: pb::UserCreateRequest) -> Self {
         let mut create = UserCreate::new(r.is_enabled.unwrap_or(true), r.name, r.email);
@@ -624,14 +734,8 @@ impl From<pb::UserCreateRequest> for UserCreate {
         if let Some(timezone) = r.timezone {
             create = create.timezone(timezone);
         }
-        if let Some(password) = r.password {
-            create = create
-                .with_password(
-                    r.password_allow_reset.unwrap_or(false),
-                    r.password_require_update.unwrap_or(false),
-                    password,
-                )
-                .unwrap();
+        if let Some(phone) = r.phone {
+            create = create.phone(phone);
         }
         create
     }
@@ -653,7 +757,7 @@ impl From<pb::UserReadRequest> for UserRead {
 
 impl From<pb::UserUpdateRequest> for UserUpdate {
     fn from(r: pb::UserUpdateRequest) -> Self {
-        Self::new(
+        let update = Self::new(
             pb::string_to_uuid(r.id),
             r.is_enabled,
             r.name,
@@ -661,7 +765,11 @@ impl From<pb::UserUpdateRequest> for UserUpdate {
             r.timezone,
             r.password_allow_reset,
             r.password_require_update,
-        )
+        );
+        match r.phone {
+            Some(phone) => update.set_phone(phone),
+            None => update,
+        }
     }
 }
 
@@ -725,8 +833,10 @@ impl From<User> for pb::User {
             email: r.email,
             locale: r.locale,
             timezone: r.timezone,
+            phone: r.phone,
             password_allow_reset: r.password_allow_reset,
             password_require_update: r.password_require_update,
+            password_updated_at: pb::datetime_opt_to_timestamp_opt(r.password_updated_at),
         }
     }
 }
@@ -742,9 +852,11 @@ impl From<pb::User> for User {
             email: r.email,
             locale: r.locale,
             timezone: r.timezone,
+            phone: r.phone,
             password_allow_reset: r.password_allow_reset,
             password_require_update: r.password_require_update,
             password_hash: None,
+            password_updated_at: pb::timestamp_opt_to_datetime_opt(r.password_updated_at),
         }
     }
 }
@@ -774,6 +886,19 @@ impl UserToken {
     }
 }
 
+impl From<UserToken> for pb::AuthLoginReply {
+    fn from(r: UserToken) -> Self {
+        Self {
+            meta: None,
+            access: Some(r.access_token()),
+            refresh: Some(r.refresh_token()),
+            user: Some(r.user.into()),
+            mfa: None,
+            mfa_key_types: Vec::new(),
+        }
+    }
+}
+
 impl From<pb::AuditReadRequest> for AuditRead {
     fn from(r: pb::AuditReadRequest) -> Self {
         Self::new(pb::string_to_uuid(r.id)).subject(r.subject)
@@ -872,6 +997,17 @@ impl pb::ServiceCreateRequest {
             provider_local_url: None,
             provider_github_oauth2_url: None,
             provider_microsoft_oauth2_url: None,
+            oidc_redirect_uris: Vec::new(),
+            jwt_algorithm: None,
+            provider_oauth2_url: None,
+            provider_saml_url: None,
+            saml_idp_metadata: None,
+            saml_email_attribute: None,
+            mfa_policy: None,
+            password_min_strength: None,
+            password_reject_pwned: None,
+            password_history: None,
+            password_max_age_days: None,
         }
     }
 
@@ -905,6 +1041,90 @@ impl pb::ServiceCreateRequest {
         self.provider_microsoft_oauth2_url = Some(provider_microsoft_oauth2_url.into());
         self
     }
+
+    pub fn provider_oauth2_url<S: Into<String>>(mut self, provider_oauth2_url: S) -> Self {
+        self.provider_oauth2_url = Some(provider_oauth2_url.into());
+        self
+    }
+
+    pub fn provider_saml<U, M>(mut self, provider_saml_url: U, saml_idp_metadata: M) -> Self
+    where
+        U: Into<String>,
+        M: Into<String>,
+    {
+        self.provider_saml_url = Some(provider_saml_url.into());
+        self.saml_idp_metadata = Some(saml_idp_metadata.into());
+        self
+    }
+
+    pub fn saml_email_attribute<S: Into<String>>(mut self, saml_email_attribute: S) -> Self {
+        self.saml_email_attribute = Some(saml_email_attribute.into());
+        self
+    }
+
+    pub fn oidc_redirect_uri<S: Into<String>>(mut self, oidc_redirect_uri: S) -> Self {
+        self.oidc_redirect_uris.push(oidc_redirect_uri.into());
+        self
+    }
+
+    pub fn jwt_algorithm(mut self, jwt_algorithm: JwtAlgorithm) -> Self {
+        self.jwt_algorithm = Some(jwt_algorithm.to_string());
+        self
+    }
+
+    pub fn mfa_policy(mut self, mfa_policy: ServiceMfaPolicy) -> Self {
+        self.mfa_policy = Some(mfa_policy.to_string());
+        self
+    }
+
+    pub fn password_policy(mut self, password_policy: UserPasswordPolicy) -> Self {
+        self.password_min_strength = Some(password_policy.min_strength as u32);
+        self.password_reject_pwned = Some(password_policy.reject_pwned);
+        self.password_history = Some(password_policy.history as u32);
+        self.password_max_age_days = Some(password_policy.max_age_days as u32);
+        self
+    }
+}
+
+impl pb::Oauth2ProviderCreateRequest {
+    pub fn new<N, D, I, S>(name: N, discovery_url: D, client_id: I, client_secret: S) -> Self
+    where
+        N: Into<String>,
+        D: Into<String>,
+        I: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            discovery_url: discovery_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            is_enabled: None,
+            scopes: Vec::new(),
+            email_claim: None,
+            trust_email: None,
+        }
+    }
+
+    pub fn is_enabled(mut self, is_enabled: bool) -> Self {
+        self.is_enabled = Some(is_enabled);
+        self
+    }
+
+    pub fn scope<S: Into<String>>(mut self, scope: S) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    pub fn email_claim<S: Into<String>>(mut self, email_claim: S) -> Self {
+        self.email_claim = Some(email_claim.into());
+        self
+    }
+
+    pub fn trust_email(mut self, trust_email: bool) -> Self {
+        self.trust_email = Some(trust_email);
+        self
+    }
 }
 
 impl pb::KeyCreateRequest {
@@ -918,6 +1138,9 @@ impl pb::KeyCreateRequest {
             is_enabled: Some(is_enabled),
             service_id: None,
             user_id: None,
+            expires_at: None,
+            scopes: Vec::new(),
+            role: None,
         }
     }
 
@@ -931,6 +1154,9 @@ impl pb::KeyCreateRequest {
             is_enabled: Some(is_enabled),
             service_id: Some(service_id),
             user_id: None,
+            expires_at: None,
+            scopes: Vec::new(),
+            role: None,
         }
     }
 
@@ -944,6 +1170,9 @@ impl pb::KeyCreateRequest {
             is_enabled: Some(is_enabled),
             service_id: None,
             user_id: Some(user_id),
+            expires_at: None,
+            scopes: Vec::new(),
+            role: None,
         }
     }
 }
@@ -960,6 +1189,8 @@ impl pb::KeyListRequest {
             r#type: Vec::new(),
             service_id: Vec::new(),
             user_id: Vec::new(),
+            expires_within_days: None,
+            unused_for_days: None,
         }
     }
 
@@ -997,6 +1228,7 @@ impl pb::UserCreateRequest {
             password_allow_reset: None,
             password_require_update: None,
             password: None,
+            phone: None,
         }
     }
 
@@ -1016,6 +1248,14 @@ impl pb::UserCreateRequest {
         self
     }
 
+    pub fn phone<P>(mut self, phone: P) -> Self
+    where
+        P: Into<String>,
+    {
+        self.phone = Some(phone.into());
+        self
+    }
+
     pub fn with_password<P>(
         mut self,
         password_allow_reset: bool,
@@ -1197,6 +1437,7 @@ impl pb::UserUpdateRequest {
             timezone: None,
             password_allow_reset: None,
             password_require_update: None,
+            phone: None,
         }
     }
 
@@ -1212,6 +1453,14 @@ impl pb::UserUpdateRequest {
         self.name = Some(name.into());
         self
     }
+
+    pub fn phone<P>(mut self, phone: P) -> Self
+    where
+        P: Into<String>,
+    {
+        self.phone = Some(phone.into());
+        self
+    }
 }
 
 impl pb::UserListRequest {
@@ -1290,6 +1539,28 @@ impl pb::AuthLoginRequest {
     }
 }
 
+impl pb::AuthMagicLinkRequest {
+    pub fn new<E>(email: E) -> Self
+    where
+        E: Into<String>,
+    {
+        Self {
+            email: email.into(),
+        }
+    }
+}
+
+impl pb::AuthEmailCodeRequest {
+    pub fn new<E>(email: E) -> Self
+    where
+        E: Into<String>,
+    {
+        Self {
+            email: email.into(),
+        }
+    }
+}
+
 impl pb::AuthResetPasswordRequest {
     pub fn new<E>(email: E) -> Self
     where
@@ -1326,6 +1597,59 @@ impl pb::AuthTotpRequest {
     }
 }
 
+impl pb::AuthTotpRegisterBeginRequest {
+    pub fn new<U, N>(user_id: U, name: N) -> Self
+    where
+        U: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            user_id: user_id.into(),
+            name: name.into(),
+            period: None,
+            digits: None,
+            algorithm: None,
+            skew: None,
+        }
+    }
+}
+
+impl pb::AuthWebauthnRegisterBeginRequest {
+    pub fn new<U, N>(user_id: U, name: N) -> Self
+    where
+        U: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            user_id: user_id.into(),
+            name: name.into(),
+        }
+    }
+}
+
+impl pb::AuthWebauthnLoginBeginRequest {
+    pub fn new<E>(email: E) -> Self
+    where
+        E: Into<String>,
+    {
+        Self {
+            email: email.into(),
+        }
+    }
+}
+
+impl pb::AuthWebauthnFinishRequest {
+    pub fn new<C>(csrf: C, credential: serde_json::Value) -> Self
+    where
+        C: Into<String>,
+    {
+        Self {
+            csrf: csrf.into(),
+            credential: pb::value_to_struct_opt(credential),
+        }
+    }
+}
+
 impl pb::AuthRegisterRequest {
     pub fn new<N, E>(name: N, email: E) -> Self
     where
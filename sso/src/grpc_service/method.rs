@@ -13,6 +13,71 @@ pub async fn local_login(
     Ok(res.into())
 }
 
+pub async fn local_login_mfa(
+    server: &GrpcServiceServer,
+    request: GrpcMethodRequest<pb::AuthLoginMfaRequest>,
+) -> GrpcMethodResult<pb::AuthLoginReply> {
+    let (audit_meta, _auth, req) = request.into_inner();
+    let res = server
+        .client(&audit_meta)
+        .auth_local_login_mfa(req)
+        .await?
+        .into_inner();
+    Ok(res.into())
+}
+
+pub async fn local_magic_link(
+    server: &GrpcServiceServer,
+    request: GrpcMethodRequest<pb::AuthMagicLinkRequest>,
+) -> GrpcMethodResult<()> {
+    let (audit_meta, _auth, req) = request.into_inner();
+    let res = server
+        .client(&audit_meta)
+        .auth_local_magic_link(req)
+        .await?
+        .into_inner();
+    Ok(res.into())
+}
+
+pub async fn local_magic_link_confirm(
+    server: &GrpcServiceServer,
+    request: GrpcMethodRequest<pb::AuthMagicLinkConfirmRequest>,
+) -> GrpcMethodResult<pb::AuthLoginReply> {
+    let (audit_meta, _auth, req) = request.into_inner();
+    let res = server
+        .client(&audit_meta)
+        .auth_local_magic_link_confirm(req)
+        .await?
+        .into_inner();
+    Ok(res.into())
+}
+
+pub async fn local_email_code(
+    server: &GrpcServiceServer,
+    request: GrpcMethodRequest<pb::AuthEmailCodeRequest>,
+) -> GrpcMethodResult<()> {
+    let (audit_meta, _auth, req) = request.into_inner();
+    let res = server
+        .client(&audit_meta)
+        .auth_local_email_code(req)
+        .await?
+        .into_inner();
+    Ok(res.into())
+}
+
+pub async fn local_email_code_confirm(
+    server: &GrpcServiceServer,
+    request: GrpcMethodRequest<pb::AuthEmailCodeConfirmRequest>,
+) -> GrpcMethodResult<pb::AuthLoginReply> {
+    let (audit_meta, _auth, req) = request.into_inner();
+    let res = server
+        .client(&audit_meta)
+        .auth_local_email_code_confirm(req)
+        .await?
+        .into_inner();
+    Ok(res.into())
+}
+
 pub async fn local_register(
     server: &GrpcServiceServer,
     request: GrpcMethodRequest<pb::AuthRegisterRequest>,
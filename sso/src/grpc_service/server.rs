@@ -114,6 +114,52 @@ impl pb::sso_service_server::SsoService for GrpcServiceServer {
         self.post(metrics, method::local_login(self, request).await)
     }
 
+    async fn auth_local_login_mfa(
+        &self,
+        request: tonic::Request<pb::AuthLoginMfaRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("local_login_mfa", request)?;
+        self.post(metrics, method::local_login_mfa(self, request).await)
+    }
+
+    async fn auth_local_magic_link(
+        &self,
+        request: tonic::Request<pb::AuthMagicLinkRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("local_magic_link", request)?;
+        self.post(metrics, method::local_magic_link(self, request).await)
+    }
+
+    async fn auth_local_magic_link_confirm(
+        &self,
+        request: tonic::Request<pb::AuthMagicLinkConfirmRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("local_magic_link_confirm", request)?;
+        self.post(
+            metrics,
+            method::local_magic_link_confirm(self, request).await,
+        )
+    }
+
+    async fn auth_local_email_code(
+        &self,
+        request: tonic::Request<pb::AuthEmailCodeRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("local_email_code", request)?;
+        self.post(metrics, method::local_email_code(self, request).await)
+    }
+
+    async fn auth_local_email_code_confirm(
+        &self,
+        request: tonic::Request<pb::AuthEmailCodeConfirmRequest>,
+    ) -> Result<tonic::Response<pb::AuthLoginReply>, tonic::Status> {
+        let (metrics, request) = self.pre_validate("local_email_code_confirm", request)?;
+        self.post(
+            metrics,
+            method::local_email_code_confirm(self, request).await,
+        )
+    }
+
     async fn auth_local_register(
         &self,
         request: tonic::Request<pb::AuthRegisterRequest>,
@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::{oidc, prelude::*};
 use hyper::{Body, Method, Request, Response, StatusCode};
 use std::{net::SocketAddr, sync::Arc};
 
@@ -19,7 +19,7 @@ impl HttpServer {
     ) -> Result<Response<Body>, hyper::Error> {
         match (req.method(), req.uri().path()) {
             (&Method::GET, "/ping") => ping(req).await,
-            (&Method::GET, "/metrics") => metrics(driver, req).await,
+            (&Method::GET, "/metrics") => metrics(options, driver, req).await,
             (&Method::GET, "/hook/traefik/self") => {
                 if options.traefik_enabled() {
                     traefik_self(driver, req, remote).await
@@ -36,12 +36,40 @@ impl HttpServer {
                     Ok(response_unauthorised())
                 }
             }
+            (&Method::GET, "/.well-known/openid-configuration") => {
+                match options.oidc_issuer_url() {
+                    Some(issuer) => oidc::discovery(issuer).await,
+                    None => Ok(response_not_found()),
+                }
+            }
+            (&Method::GET, "/authorize") => match options.oidc_issuer_url() {
+                Some(issuer) => oidc::authorize(issuer.to_owned(), driver, req, remote).await,
+                None => Ok(response_not_found()),
+            },
+            (&Method::POST, "/authorize") => match options.oidc_issuer_url() {
+                Some(issuer) => {
+                    oidc::authorize_login(issuer.to_owned(), options, driver, req, remote).await
+                }
+                None => Ok(response_not_found()),
+            },
+            (&Method::POST, "/token") => {
+                if options.oidc_issuer_url().is_some() {
+                    oidc::token(options, driver, req, remote).await
+                } else {
+                    Ok(response_not_found())
+                }
+            }
+            (&Method::GET, "/userinfo") | (&Method::POST, "/userinfo") => {
+                if options.oidc_issuer_url().is_some() {
+                    oidc::userinfo(driver, req, remote).await
+                } else {
+                    Ok(response_not_found())
+                }
+            }
+            (&Method::GET, "/jwks.json") => oidc::jwks(options, driver).await,
             _ => {
                 // Return 404 not found response.
-                Ok(Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .body(NOT_FOUND.into())
-                    .unwrap())
+                Ok(response_not_found())
             }
         }
     }
@@ -52,11 +80,14 @@ async fn ping(_req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
 }
 
 async fn metrics(
+    options: Arc<GrpcServerOptions>,
     driver: Arc<Postgres>,
     _req: Request<Body>,
 ) -> Result<Response<Body>, hyper::Error> {
     let driver = driver.clone();
-    let s = blocking_hyper(move || Ok(Metrics::read(driver.as_ref()).unwrap())).await?;
+    let password_hash = options.password_hash_options();
+    let s =
+        blocking_hyper(move || Ok(Metrics::read(driver.as_ref(), &password_hash).unwrap())).await?;
     Ok(Response::new(Body::from(s)))
 }
 
@@ -125,6 +156,13 @@ async fn traefik_service(
     })
 }
 
+fn response_not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(NOT_FOUND.into())
+        .unwrap()
+}
+
 fn response_unauthorised() -> Response<Body> {
     let grpc_status = format!("{}", tonic::Code::Unauthenticated as u8);
     Response::builder()
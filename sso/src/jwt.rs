@@ -1,6 +1,39 @@
 use crate::prelude::*;
+use chrono::TimeZone;
 use diesel::PgConnection;
-use jsonwebtoken::{dangerous_insecure_decode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::de::DeserializeOwned;
+
+/// JSON web token signing algorithms.
+#[derive(Debug, Copy, PartialEq, Clone, Serialize, Deserialize)]
+pub enum JwtAlgorithm {
+    /// HMAC using SHA-256, tokens are signed with user token key.
+    #[serde(rename = "HS256")]
+    Hs256,
+    /// RSASSA-PKCS1-v1_5 using SHA-256, tokens are signed with server key.
+    #[serde(rename = "RS256")]
+    Rs256,
+    /// ECDSA using P-256 and SHA-256, tokens are signed with server key.
+    #[serde(rename = "ES256")]
+    Es256,
+    /// EdDSA using Ed25519, tokens are signed with server key.
+    #[serde(rename = "EdDSA")]
+    EdDsa,
+}
+
+impl_enum_to_from_string!(JwtAlgorithm, "");
+
+impl JwtAlgorithm {
+    /// Returns jsonwebtoken algorithm.
+    pub fn to_algorithm(self) -> Algorithm {
+        match self {
+            JwtAlgorithm::Hs256 => Algorithm::HS256,
+            JwtAlgorithm::Rs256 => Algorithm::RS256,
+            JwtAlgorithm::Es256 => Algorithm::ES256,
+            JwtAlgorithm::EdDsa => Algorithm::EdDSA,
+        }
+    }
+}
 
 /// JSON web token types.
 #[derive(Debug)]
@@ -15,6 +48,10 @@ pub enum JwtType {
     ResetPasswordToken,
     /// Revoke tokens used to revoke user tokens and keys.
     RevokeToken,
+    /// MFA tokens used to verify second factor of user login.
+    MfaToken,
+    /// Magic link tokens used to verify passwordless user login.
+    MagicLinkToken,
 }
 
 impl JwtType {
@@ -26,6 +63,8 @@ impl JwtType {
             JwtType::RegisterToken => 2,
             JwtType::ResetPasswordToken => 3,
             JwtType::RevokeToken => 4,
+            JwtType::MfaToken => 5,
+            JwtType::MagicLinkToken => 6,
         }
     }
 
@@ -37,6 +76,8 @@ impl JwtType {
             2 => Ok(JwtType::RegisterToken),
             3 => Ok(JwtType::ResetPasswordToken),
             4 => Ok(JwtType::RevokeToken),
+            5 => Ok(JwtType::MfaToken),
+            6 => Ok(JwtType::MagicLinkToken),
             _ => Err(DriverError::JwtTypeInvalid),
         }
     }
@@ -53,10 +94,16 @@ struct JwtClaims {
     #[serde(rename = "x-csrf")]
     #[serde(skip_serializing_if = "Option::is_none")]
     x_csrf: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sid: Option<Uuid>,
 }
 
 impl JwtClaims {
-    /// Returns new token of type without CSRF code.
+    /// Returns new token of type without CSRF code, token has a random ID.
     fn new<IS, SU>(iss: IS, sub: SU, exp: Duration, x_type: JwtType) -> Self
     where
         IS: Into<String>,
@@ -69,6 +116,8 @@ impl JwtClaims {
             exp: dt.timestamp(),
             x_type: x_type.to_i64(),
             x_csrf: None,
+            jti: Some(Uuid::new_v4().to_string()),
+            sid: None,
         }
     }
 
@@ -84,20 +133,34 @@ impl JwtClaims {
         claims
     }
 
-    /// Returns validation rules for decoding a token with issuer and subject.
-    fn validation<IS, SU>(iss: IS, sub: SU) -> Validation
+    /// Returns validation rules for decoding a token with algorithm, issuer and subject.
+    fn validation<IS, SU>(algorithm: Algorithm, iss: IS, sub: SU) -> Validation
     where
-        IS: Into<String>,
+        IS: ToString,
         SU: Into<String>,
     {
-        Validation {
-            iss: Some(iss.into()),
-            sub: Some(sub.into()),
-            ..Validation::default()
-        }
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = 0;
+        validation.set_issuer(&[iss]);
+        validation.sub = Some(sub.into());
+        validation
     }
 }
 
+/// OpenID Connect ID token claims.
+#[derive(Debug, Serialize, Deserialize)]
+struct JwtIdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    exp: i64,
+    iat: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    email: String,
+    name: String,
+}
+
 /// JSON web tokens.
 #[derive(Debug)]
 pub struct Jwt;
@@ -107,9 +170,7 @@ impl Jwt {
     /// If matched, returns the `sub` claim, which may be a user ID and the token type.
     /// The user ID must be used to read a key that can safely decode the token.
     pub fn decode_unsafe_user(token: &str, service_id: Uuid) -> DriverResult<(Uuid, JwtType)> {
-        let claims: JwtClaims = dangerous_insecure_decode(token)
-            .map_err(DriverError::Jsonwebtoken)?
-            .claims;
+        let claims: JwtClaims = Self::decode_unsafe(token)?;
 
         let iss = Uuid::parse_str(&claims.iss).map_err(DriverError::UuidParse)?;
         if service_id != iss {
@@ -121,27 +182,101 @@ impl Jwt {
         Ok((sub, x_type))
     }
 
+    /// Unsafely decodes a token, returns the `iss` claim as a service ID.
+    /// The service ID must be used to read a service and user key that can safely decode the token.
+    pub fn decode_unsafe_service(token: &str) -> DriverResult<Uuid> {
+        let claims: JwtClaims = Self::decode_unsafe(token)?;
+
+        Uuid::parse_str(&claims.iss).map_err(DriverError::UuidParse)
+    }
+
+    /// Encode and return OpenID Connect ID token for user.
+    /// Token is signed with client secret, which is a service key value, if service
+    /// algorithm is `HS256`, otherwise it is signed with a server key.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_id_token(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        issuer: &str,
+        service: &Service,
+        user: &User,
+        client_secret: &str,
+        nonce: Option<String>,
+        token_expires: Duration,
+    ) -> DriverResult<String> {
+        let now = Utc::now();
+        let claims = JwtIdTokenClaims {
+            iss: issuer.to_owned(),
+            sub: user.id.to_string(),
+            aud: service.id.to_string(),
+            exp: (now + token_expires).timestamp(),
+            iat: now.timestamp(),
+            nonce,
+            email: user.email.to_owned(),
+            name: user.name.to_owned(),
+        };
+        let (header, key) = Self::encoding_key(conn, data_keys, service, client_secret)?;
+        jsonwebtoken::encode(&header, &claims, &key).map_err(DriverError::Jsonwebtoken)
+    }
+
     /// Encode and return access and refresh tokens for a user with key.
+    /// Refresh token is the first in a new token family, which is a new user session.
+    #[allow(clippy::too_many_arguments)]
     pub fn encode_user(
         conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        meta: &AuditMeta,
         service: &Service,
         user: User,
         key: &KeyWithValue,
         access_token_expires: Duration,
         refresh_token_expires: Duration,
     ) -> DriverResult<UserToken> {
-        let (access_token, access_token_expires) = Self::encode(
-            service.id,
-            user.id,
-            JwtType::AccessToken,
-            &key.value,
+        Self::encode_user_family(
+            conn,
+            data_keys,
+            meta,
+            service,
+            user,
+            key,
+            Uuid::new_v4(),
             access_token_expires,
-        )?;
-        let (refresh_token, refresh_token_expires) = Self::encode_csrf(
+            refresh_token_expires,
+        )
+    }
+
+    /// Encode and return access and refresh tokens for a user with key.
+    /// Refresh token is added to existing token family, user session is updated.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_user_family(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        meta: &AuditMeta,
+        service: &Service,
+        user: User,
+        key: &KeyWithValue,
+        family_id: Uuid,
+        access_token_expires: Duration,
+        refresh_token_expires: Duration,
+    ) -> DriverResult<UserToken> {
+        let session_expires = std::cmp::max(access_token_expires, refresh_token_expires);
+        let session =
+            UserSession::upsert(conn, meta, family_id, session_expires, service.id, user.id)?;
+        let mut claims = JwtClaims::new(
+            service.id.to_string(),
+            user.id.to_string(),
+            access_token_expires,
+            JwtType::AccessToken,
+        );
+        claims.sid = Some(session.id());
+        let (access_token, access_token_expires) =
+            Self::encode_claims(conn, data_keys, service, &key.value, claims)?;
+        let (refresh_token, refresh_token_expires) = Self::encode_refresh(
             conn,
-            service.id,
+            data_keys,
+            service,
             user.id,
-            JwtType::RefreshToken,
+            family_id,
             &key.value,
             refresh_token_expires,
         )?;
@@ -157,13 +292,15 @@ impl Jwt {
     /// Safely decode access token for user with key.
     /// Returns expiry time.
     pub fn decode_access<T: AsRef<str>>(
+        conn: &PgConnection,
         service: &Service,
         user: &User,
         key: &KeyWithValue,
         token: T,
     ) -> DriverResult<i64> {
         let (exp, _) = Self::decode(
-            service.id,
+            conn,
+            service,
             user.id,
             JwtType::AccessToken,
             &key.value,
@@ -172,28 +309,30 @@ impl Jwt {
         Ok(exp)
     }
 
-    /// Safely decode refresh token for user with key and verify CSRF key.
+    /// Safely decode refresh token for user with key and use refresh token key.
+    /// Returns token family ID, if refresh token was already used its family is revoked.
     pub fn decode_refresh<T: AsRef<str>>(
         conn: &PgConnection,
         service: &Service,
         user: &User,
         key: &KeyWithValue,
         token: T,
-    ) -> DriverResult<()> {
-        let (_, csrf_key) = Self::decode(
-            service.id,
+    ) -> DriverResult<Uuid> {
+        let (_, refresh_key) = Self::decode(
+            conn,
+            service,
             user.id,
             JwtType::RefreshToken,
             &key.value,
             token.as_ref(),
         )?;
-        CsrfVerify::verify(conn, service.id, csrf_key)?;
-        Ok(())
+        RefreshToken::use_key(conn, service.id, user.id, refresh_key)
     }
 
     /// Encode and return register token for user with key.
     pub fn encode_register(
         conn: &PgConnection,
+        data_keys: &KeyDataKeys,
         service: &Service,
         user: &User,
         key: &KeyWithValue,
@@ -201,7 +340,8 @@ impl Jwt {
     ) -> DriverResult<String> {
         let (token, _) = Self::encode_csrf(
             conn,
-            service.id,
+            data_keys,
+            service,
             user.id,
             JwtType::RegisterToken,
             &key.value,
@@ -219,7 +359,8 @@ impl Jwt {
         token: T,
     ) -> DriverResult<()> {
         let (_, csrf_key) = Self::decode(
-            service.id,
+            conn,
+            service,
             user.id,
             JwtType::RegisterToken,
             &key.value,
@@ -232,6 +373,7 @@ impl Jwt {
     /// Encode and return reset password token for user with key.
     pub fn encode_reset_password(
         conn: &PgConnection,
+        data_keys: &KeyDataKeys,
         service: &Service,
         user: &User,
         key: &KeyWithValue,
@@ -239,7 +381,8 @@ impl Jwt {
     ) -> DriverResult<String> {
         let (token, _) = Self::encode_csrf(
             conn,
-            service.id,
+            data_keys,
+            service,
             user.id,
             JwtType::ResetPasswordToken,
             &key.value,
@@ -257,7 +400,8 @@ impl Jwt {
         token: T,
     ) -> DriverResult<()> {
         let (_, csrf_key) = Self::decode(
-            service.id,
+            conn,
+            service,
             user.id,
             JwtType::ResetPasswordToken,
             &key.value,
@@ -270,6 +414,7 @@ impl Jwt {
     /// Encode and return revoke token for user with key.
     pub fn encode_revoke(
         conn: &PgConnection,
+        data_keys: &KeyDataKeys,
         service: &Service,
         user: &User,
         key: &KeyWithValue,
@@ -277,7 +422,8 @@ impl Jwt {
     ) -> DriverResult<String> {
         let (token, _) = Self::encode_csrf(
             conn,
-            service.id,
+            data_keys,
+            service,
             user.id,
             JwtType::RevokeToken,
             &key.value,
@@ -295,7 +441,8 @@ impl Jwt {
         token: T,
     ) -> DriverResult<()> {
         let (_, csrf_key) = Self::decode(
-            service.id,
+            conn,
+            service,
             user.id,
             JwtType::RevokeToken,
             &key.value,
@@ -305,87 +452,362 @@ impl Jwt {
         Ok(())
     }
 
-    /// Safely decode token of type for user with key, read CSRF to prevent verification.
-    pub fn decode_csrf<T: AsRef<str>>(
+    /// Encode and return MFA token for user with key.
+    pub fn encode_mfa(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        service: &Service,
+        user: &User,
+        key: &KeyWithValue,
+        token_expires: Duration,
+    ) -> DriverResult<String> {
+        let (token, _) = Self::encode_csrf(
+            conn,
+            data_keys,
+            service,
+            user.id,
+            JwtType::MfaToken,
+            &key.value,
+            token_expires,
+        )?;
+        Ok(token)
+    }
+
+    /// Safely decode MFA token for user with key and verify CSRF key.
+    pub fn decode_mfa<T: AsRef<str>>(
         conn: &PgConnection,
         service: &Service,
         user: &User,
         key: &KeyWithValue,
-        token_type: JwtType,
         token: T,
     ) -> DriverResult<()> {
-        let (_, csrf_key) =
-            Self::decode(service.id, user.id, token_type, &key.value, token.as_ref())?;
-        if let Some(csrf_key) = csrf_key {
-            CsrfRead::read(conn, &csrf_key)?;
-        }
+        let (_, csrf_key) = Self::decode(
+            conn,
+            service,
+            user.id,
+            JwtType::MfaToken,
+            &key.value,
+            token.as_ref(),
+        )?;
+        CsrfVerify::verify(conn, service.id, csrf_key)?;
         Ok(())
     }
 
-    /// Encode a token with key of type without a CSRF code, returns token and expiry time.
-    fn encode(
-        service_id: Uuid,
-        user_id: Uuid,
-        x_type: JwtType,
-        key_value: &str,
-        exp: Duration,
-    ) -> DriverResult<(String, i64)> {
-        let claims = JwtClaims::new(service_id.to_string(), user_id.to_string(), exp, x_type);
-        let token = jsonwebtoken::encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(key_value.as_bytes()),
-        )
-        .map_err(DriverError::Jsonwebtoken)?;
-        Ok((token, claims.exp))
+    /// Encode and return magic link token for user with key.
+    pub fn encode_magic_link(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        service: &Service,
+        user: &User,
+        key: &KeyWithValue,
+        token_expires: Duration,
+    ) -> DriverResult<String> {
+        let (token, _) = Self::encode_csrf(
+            conn,
+            data_keys,
+            service,
+            user.id,
+            JwtType::MagicLinkToken,
+            &key.value,
+            token_expires,
+        )?;
+        Ok(token)
+    }
+
+    /// Safely decode magic link token for user with key and verify CSRF key.
+    pub fn decode_magic_link<T: AsRef<str>>(
+        conn: &PgConnection,
+        service: &Service,
+        user: &User,
+        key: &KeyWithValue,
+        token: T,
+    ) -> DriverResult<()> {
+        let (_, csrf_key) = Self::decode(
+            conn,
+            service,
+            user.id,
+            JwtType::MagicLinkToken,
+            &key.value,
+            token.as_ref(),
+        )?;
+        CsrfVerify::verify(conn, service.id, csrf_key)?;
+        Ok(())
+    }
+
+    /// Safely decode token of type for user with key and revoke it.
+    /// Token ID is added to denylist until token expires, CSRF key is read to prevent
+    /// verification and refresh tokens also revoke their token family.
+    /// Returns false if token does not have an ID, which is true of tokens issued by
+    /// earlier versions.
+    pub fn revoke<T: AsRef<str>>(
+        conn: &PgConnection,
+        service: &Service,
+        user: &User,
+        key: &KeyWithValue,
+        token_type: JwtType,
+        token: T,
+    ) -> DriverResult<bool> {
+        let is_refresh = matches!(token_type, JwtType::RefreshToken);
+        let claims = Self::decode_claims(
+            conn,
+            service,
+            user.id,
+            token_type,
+            &key.value,
+            token.as_ref(),
+        )?;
+        match claims.x_csrf {
+            Some(refresh_key) if is_refresh => RefreshToken::revoke_key(conn, &refresh_key)?,
+            Some(csrf_key) => {
+                CsrfRead::read(conn, &csrf_key)?;
+            }
+            None => {}
+        }
+        match claims.jti {
+            Some(jti) => {
+                let exp = Utc.timestamp_opt(claims.exp, 0).unwrap();
+                JwtDenylist::create(conn, jti, exp, service.id, user.id)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 
     /// Encode a token with key of type with a CSRF code, returns token and expiry time.
     fn encode_csrf(
         conn: &PgConnection,
-        service_id: Uuid,
+        data_keys: &KeyDataKeys,
+        service: &Service,
         user_id: Uuid,
         x_type: JwtType,
         key_value: &str,
         exp: Duration,
     ) -> DriverResult<(String, i64)> {
-        let csrf = CsrfCreate::generate(conn, exp, service_id)?;
+        let csrf = CsrfCreate::generate(conn, exp, service.id)?;
         let claims = JwtClaims::new_csrf(
-            service_id.to_string(),
+            service.id.to_string(),
             user_id.to_string(),
             exp,
             x_type,
             csrf.value(),
         );
-        let token = jsonwebtoken::encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(key_value.as_bytes()),
-        )
-        .map_err(DriverError::Jsonwebtoken)?;
+        Self::encode_claims(conn, data_keys, service, key_value, claims)
+    }
+
+    /// Encode a refresh token with key in token family, returns token and expiry time.
+    fn encode_refresh(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        service: &Service,
+        user_id: Uuid,
+        family_id: Uuid,
+        key_value: &str,
+        exp: Duration,
+    ) -> DriverResult<(String, i64)> {
+        let refresh = RefreshToken::generate(conn, family_id, exp, service.id, user_id)?;
+        let mut claims = JwtClaims::new_csrf(
+            service.id.to_string(),
+            user_id.to_string(),
+            exp,
+            JwtType::RefreshToken,
+            refresh.key(),
+        );
+        claims.sid = Some(family_id);
+        Self::encode_claims(conn, data_keys, service, key_value, claims)
+    }
+
+    /// Encode a token with claims, returns token and expiry time.
+    fn encode_claims(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        service: &Service,
+        key_value: &str,
+        claims: JwtClaims,
+    ) -> DriverResult<(String, i64)> {
+        let (header, key) = Self::encoding_key(conn, data_keys, service, key_value)?;
+        let token =
+            jsonwebtoken::encode(&header, &claims, &key).map_err(DriverError::Jsonwebtoken)?;
         Ok((token, claims.exp))
     }
 
     /// Safely decodes a token with key, returns expiry time and optional CSRF key.
-    /// This will return an error if the subject or issuer claims do not match the server
-    /// and user ID, if the token is expired, or if the type is unexpected.
     fn decode(
-        service_id: Uuid,
+        conn: &PgConnection,
+        service: &Service,
         user_id: Uuid,
         x_type: JwtType,
         key_value: &str,
         token: &str,
     ) -> DriverResult<(i64, Option<String>)> {
-        let validation = JwtClaims::validation(service_id.to_string(), user_id.to_string());
-        let data = jsonwebtoken::decode::<JwtClaims>(
-            token,
-            &DecodingKey::from_secret(key_value.as_bytes()),
-            &validation,
-        )
-        .map_err(DriverError::Jsonwebtoken)?;
+        let claims = Self::decode_claims(conn, service, user_id, x_type, key_value, token)?;
+        Ok((claims.exp, claims.x_csrf))
+    }
+
+    /// Safely decodes a token with key, returns claims.
+    /// This will return an error if the subject or issuer claims do not match the server
+    /// and user ID, if the token is expired, if the type is unexpected, if the token
+    /// ID is in the denylist, or if the token session is revoked.
+    fn decode_claims(
+        conn: &PgConnection,
+        service: &Service,
+        user_id: Uuid,
+        x_type: JwtType,
+        key_value: &str,
+        token: &str,
+    ) -> DriverResult<JwtClaims> {
+        let (algorithm, key) = Self::decoding_key(conn, service, key_value, token)?;
+        let validation =
+            JwtClaims::validation(algorithm, service.id.to_string(), user_id.to_string());
+        let data = jsonwebtoken::decode::<JwtClaims>(token, &key, &validation)
+            .map_err(DriverError::Jsonwebtoken)?;
         if data.claims.x_type != x_type.to_i64() {
             return Err(DriverError::JwtTypeMismatch);
         }
-        Ok((data.claims.exp, data.claims.x_csrf))
+        if let Some(jti) = &data.claims.jti {
+            if JwtDenylist::contains(conn, jti)? {
+                return Err(DriverError::JwtRevoked);
+            }
+        }
+        if let Some(sid) = data.claims.sid {
+            if UserSession::is_revoked(conn, sid)? {
+                return Err(DriverError::JwtRevoked);
+            }
+        }
+        Ok(data.claims)
+    }
+
+    /// Unsafely decodes a token without verifying signature or expiry time.
+    fn decode_unsafe<T: DeserializeOwned>(token: &str) -> DriverResult<T> {
+        let mut validation = Validation::default();
+        validation.insecure_disable_signature_validation();
+        validation.validate_exp = false;
+        validation.required_spec_claims.clear();
+        jsonwebtoken::decode::<T>(token, &DecodingKey::from_secret(&[]), &validation)
+            .map(|x| x.claims)
+            .map_err(DriverError::Jsonwebtoken)
+    }
+
+    /// Returns header and key used to sign tokens for service.
+    /// Tokens are signed with key value if service algorithm is `HS256`, otherwise
+    /// they are signed with a server key which is identified by the `kid` header.
+    fn encoding_key(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        service: &Service,
+        key_value: &str,
+    ) -> DriverResult<(Header, EncodingKey)> {
+        match service.jwt_algorithm {
+            JwtAlgorithm::Hs256 => Ok((
+                Header::default(),
+                EncodingKey::from_secret(key_value.as_bytes()),
+            )),
+            algorithm => {
+                let jwt_key = JwtKeyRead::signing(conn, data_keys, algorithm)?;
+                let mut header = Header::new(algorithm.to_algorithm());
+                header.kid = Some(jwt_key.id().to_string());
+                Ok((header, jwt_key.encoding_key(data_keys)?))
+            }
+        }
+    }
+
+    /// Returns algorithm and key used to verify token.
+    /// Tokens must be signed with the service algorithm. Server keys are read using
+    /// the `kid` header, which allows tokens signed by expired keys to be verified
+    /// until the tokens expire.
+    fn decoding_key(
+        conn: &PgConnection,
+        service: &Service,
+        key_value: &str,
+        token: &str,
+    ) -> DriverResult<(Algorithm, DecodingKey)> {
+        let header = jsonwebtoken::decode_header(token).map_err(DriverError::Jsonwebtoken)?;
+        let algorithm = Self::decoding_algorithm(service, &header)?;
+        match service.jwt_algorithm {
+            JwtAlgorithm::Hs256 => Ok((algorithm, DecodingKey::from_secret(key_value.as_bytes()))),
+            _ => {
+                let kid = header.kid.ok_or(DriverError::JwtKeyNotFound)?;
+                let id = Uuid::parse_str(&kid).map_err(|_| DriverError::JwtKeyNotFound)?;
+                let jwt_key = JwtKeyRead::id(conn, id)?.ok_or(DriverError::JwtKeyNotFound)?;
+                Self::decoding_jwt_key_check(service, jwt_key.algorithm())?;
+                Ok((algorithm, jwt_key.decoding_key()?))
+            }
+        }
+    }
+
+    /// Returns service algorithm, error if token header algorithm does not match.
+    fn decoding_algorithm(service: &Service, header: &Header) -> DriverResult<Algorithm> {
+        let algorithm = service.jwt_algorithm.to_algorithm();
+        if header.alg != algorithm {
+            return Err(DriverError::JwtAlgorithmMismatch);
+        }
+        Ok(algorithm)
+    }
+
+    /// Returns error if server key is not a signing key of service algorithm,
+    /// server keys are shared by services with the same algorithm.
+    fn decoding_jwt_key_check(service: &Service, algorithm: JwtAlgorithm) -> DriverResult<()> {
+        if algorithm != service.jwt_algorithm {
+            return Err(DriverError::JwtKeyNotFound);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service_new(jwt_algorithm: JwtAlgorithm) -> Service {
+        Service {
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            id: Uuid::new_v4(),
+            is_enabled: true,
+            name: "Service Name".to_owned(),
+            url: "http://localhost:9000".to_owned(),
+            user_allow_register: true,
+            user_email_text: "".to_owned(),
+            provider_local_url: None,
+            provider_github_oauth2_url: None,
+            provider_microsoft_oauth2_url: None,
+            oidc_redirect_uris: Vec::new(),
+            jwt_algorithm,
+            provider_oauth2_url: None,
+            provider_saml_url: None,
+            saml_idp_metadata: None,
+            saml_email_attribute: None,
+            mfa_policy: ServiceMfaPolicy::Off,
+            password_min_strength: 0,
+            password_reject_pwned: false,
+            password_history: 0,
+            password_max_age_days: 0,
+        }
+    }
+
+    #[test]
+    fn jwt_decoding_algorithm_mismatch() {
+        let service = service_new(JwtAlgorithm::Rs256);
+        let header = Header::new(Algorithm::RS256);
+        assert_eq!(
+            Jwt::decoding_algorithm(&service, &header).unwrap(),
+            Algorithm::RS256
+        );
+
+        let header = Header::new(Algorithm::HS256);
+        let res = Jwt::decoding_algorithm(&service, &header).unwrap_err();
+        assert!(matches!(res, DriverError::JwtAlgorithmMismatch));
+
+        let service = service_new(JwtAlgorithm::Hs256);
+        let header = Header::new(Algorithm::ES256);
+        let res = Jwt::decoding_algorithm(&service, &header).unwrap_err();
+        assert!(matches!(res, DriverError::JwtAlgorithmMismatch));
+    }
+
+    #[test]
+    fn jwt_decoding_jwt_key_not_found() {
+        let service = service_new(JwtAlgorithm::Es256);
+        Jwt::decoding_jwt_key_check(&service, JwtAlgorithm::Es256).unwrap();
+        let res = Jwt::decoding_jwt_key_check(&service, JwtAlgorithm::EdDsa).unwrap_err();
+        assert!(matches!(res, DriverError::JwtKeyNotFound));
     }
 }
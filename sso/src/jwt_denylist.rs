@@ -0,0 +1,65 @@
+use crate::{prelude::*, schema::sso_jwt_denylist};
+use diesel::{dsl::exists, prelude::*, PgConnection};
+
+/// JSON web token denylist entry create.
+///
+/// Revoked token IDs are denied until the token would have expired.
+#[derive(Debug, Insertable)]
+#[table_name = "sso_jwt_denylist"]
+pub struct JwtDenylist {
+    created_at: DateTime<Utc>,
+    jti: String,
+    exp: DateTime<Utc>,
+    service_id: Uuid,
+    user_id: Uuid,
+}
+
+impl JwtDenylist {
+    /// Add token ID to denylist until expiry time for service user.
+    pub fn create<J>(
+        conn: &PgConnection,
+        jti: J,
+        exp: DateTime<Utc>,
+        service_id: Uuid,
+        user_id: Uuid,
+    ) -> DriverResult<()>
+    where
+        J: Into<String>,
+    {
+        Self::delete_by_exp(conn)?;
+
+        let value = Self {
+            created_at: Utc::now(),
+            jti: jti.into(),
+            exp,
+            service_id,
+            user_id,
+        };
+        diesel::insert_into(sso_jwt_denylist::table)
+            .values(&value)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map_err(Into::into)
+            .map(|_| ())
+    }
+
+    /// Returns true if token ID is in denylist and has not expired.
+    pub fn contains<J: AsRef<str>>(conn: &PgConnection, jti: J) -> DriverResult<bool> {
+        let now = Utc::now();
+        diesel::select(exists(
+            sso_jwt_denylist::table
+                .filter(sso_jwt_denylist::dsl::jti.eq(jti.as_ref()))
+                .filter(sso_jwt_denylist::dsl::exp.gt(now)),
+        ))
+        .get_result::<bool>(conn)
+        .map_err(Into::into)
+    }
+
+    fn delete_by_exp(conn: &PgConnection) -> DriverResult<()> {
+        let now = Utc::now();
+        diesel::delete(sso_jwt_denylist::table.filter(sso_jwt_denylist::dsl::exp.le(now)))
+            .execute(conn)
+            .map_err(Into::into)
+            .map(|_| ())
+    }
+}
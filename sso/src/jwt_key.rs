@@ -0,0 +1,286 @@
+use crate::{prelude::*, schema::sso_jwt_key};
+use diesel::{prelude::*, PgConnection};
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey},
+    nid::Nid,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+};
+use serde_json::Value;
+use std::fmt;
+
+/// RSA signing key size in bits.
+const JWT_KEY_RSA_BITS: u32 = 2048;
+
+/// JSON web token signing key.
+#[derive(Clone, Identifiable, Queryable)]
+#[table_name = "sso_jwt_key"]
+pub struct JwtKey {
+    created_at: DateTime<Utc>,
+    id: Uuid,
+    algorithm: String,
+    private_key: String,
+    public_key: String,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// JSON web token signing key create.
+#[derive(Debug, Insertable)]
+#[table_name = "sso_jwt_key"]
+pub struct JwtKeyCreate {
+    created_at: DateTime<Utc>,
+    id: Uuid,
+    algorithm: String,
+    private_key: String,
+    public_key: String,
+}
+
+/// JSON web token signing key read.
+#[derive(Debug)]
+pub struct JwtKeyRead;
+
+impl JwtKey {
+    /// Returns key ID, used as `kid` header of tokens.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns signing algorithm.
+    pub fn algorithm(&self) -> JwtAlgorithm {
+        JwtAlgorithm::from_str(&self.algorithm).unwrap()
+    }
+
+    /// Returns expires at time, key is not used to sign tokens after this time.
+    pub fn expires_at(&self) -> Option<&DateTime<Utc>> {
+        self.expires_at.as_ref()
+    }
+
+    /// Returns key used to sign tokens, private key is decrypted by data keys.
+    pub fn encoding_key(&self, data_keys: &KeyDataKeys) -> DriverResult<EncodingKey> {
+        let private_key = data_keys.jwt_key_decrypt(self.id, &self.private_key)?;
+        let private_key = private_key.as_bytes();
+        match self.algorithm() {
+            JwtAlgorithm::Rs256 => EncodingKey::from_rsa_pem(private_key),
+            JwtAlgorithm::Es256 => EncodingKey::from_ec_pem(private_key),
+            JwtAlgorithm::EdDsa => EncodingKey::from_ed_pem(private_key),
+            JwtAlgorithm::Hs256 => return Err(DriverError::JwtAlgorithmInvalid),
+        }
+        .map_err(DriverError::Jsonwebtoken)
+    }
+
+    /// Returns key used to verify tokens.
+    pub fn decoding_key(&self) -> DriverResult<DecodingKey> {
+        let public_key = self.public_key.as_bytes();
+        match self.algorithm() {
+            JwtAlgorithm::Rs256 => DecodingKey::from_rsa_pem(public_key),
+            JwtAlgorithm::Es256 => DecodingKey::from_ec_pem(public_key),
+            JwtAlgorithm::EdDsa => DecodingKey::from_ed_pem(public_key),
+            JwtAlgorithm::Hs256 => return Err(DriverError::JwtAlgorithmInvalid),
+        }
+        .map_err(DriverError::Jsonwebtoken)
+    }
+
+    /// Returns public key as JSON web key.
+    pub fn jwk(&self) -> DriverResult<Value> {
+        let pkey =
+            PKey::public_key_from_pem(self.public_key.as_bytes()).map_err(DriverError::Openssl)?;
+        let mut jwk = match self.algorithm() {
+            JwtAlgorithm::Rs256 => {
+                let rsa = pkey.rsa().map_err(DriverError::Openssl)?;
+                json!({
+                    "kty": "RSA",
+                    "n": base64_url(&rsa.n().to_vec()),
+                    "e": base64_url(&rsa.e().to_vec()),
+                })
+            }
+            JwtAlgorithm::Es256 => {
+                let ec = pkey.ec_key().map_err(DriverError::Openssl)?;
+                let mut ctx = BigNumContext::new().map_err(DriverError::Openssl)?;
+                let mut x = BigNum::new().map_err(DriverError::Openssl)?;
+                let mut y = BigNum::new().map_err(DriverError::Openssl)?;
+                ec.public_key()
+                    .affine_coordinates(ec.group(), &mut x, &mut y, &mut ctx)
+                    .map_err(DriverError::Openssl)?;
+                json!({
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "x": base64_url(&x.to_vec_padded(32).map_err(DriverError::Openssl)?),
+                    "y": base64_url(&y.to_vec_padded(32).map_err(DriverError::Openssl)?),
+                })
+            }
+            JwtAlgorithm::EdDsa => {
+                let x = pkey.raw_public_key().map_err(DriverError::Openssl)?;
+                json!({
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": base64_url(&x),
+                })
+            }
+            JwtAlgorithm::Hs256 => return Err(DriverError::JwtAlgorithmInvalid),
+        };
+        jwk["use"] = json!("sig");
+        jwk["alg"] = json!(self.algorithm);
+        jwk["kid"] = json!(self.id.to_string());
+        Ok(jwk)
+    }
+}
+
+impl JwtKey {
+    /// Encrypt plaintext private keys of signing keys created before they were
+    /// encrypted at rest, returns number of signing keys updated.
+    pub fn private_key_migrate(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+    ) -> DriverResult<usize> {
+        conn.transaction(|| {
+            let keys = sso_jwt_key::table
+                .filter(sso_jwt_key::dsl::private_key.not_like("$%"))
+                .for_update()
+                .load::<JwtKey>(conn)?;
+
+            for key in keys.iter() {
+                let private_key = data_keys.jwt_key_encrypt(key.id, &key.private_key)?;
+                diesel::update(sso_jwt_key::table.filter(sso_jwt_key::dsl::id.eq(key.id)))
+                    .set(sso_jwt_key::dsl::private_key.eq(private_key))
+                    .execute(conn)?;
+            }
+            Ok(keys.len())
+        })
+    }
+}
+
+impl fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtKey")
+            .field("created_at", &self.created_at)
+            .field("id", &self.id)
+            .field("algorithm", &self.algorithm)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+impl fmt::Display for JwtKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JwtKey {}", self.id)?;
+        write!(f, "\n\tcreated_at {}", self.created_at)?;
+        write!(f, "\n\talgorithm {}", self.algorithm)?;
+        if let Some(expires_at) = &self.expires_at {
+            write!(f, "\n\texpires_at {}", expires_at)?;
+        }
+        Ok(())
+    }
+}
+
+impl JwtKeyCreate {
+    /// Generate signing key for algorithm, existing keys for algorithm are expired.
+    /// Expired keys continue to verify tokens they signed until those tokens expire.
+    /// Private key is encrypted by data keys before it is stored.
+    pub fn generate(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        algorithm: JwtAlgorithm,
+    ) -> DriverResult<JwtKey> {
+        let pkey = Self::generate_pkey(algorithm)?;
+        let private_key = pkey
+            .private_key_to_pem_pkcs8()
+            .map_err(DriverError::Openssl)?;
+        let public_key = pkey.public_key_to_pem().map_err(DriverError::Openssl)?;
+
+        let now = Utc::now();
+        let id = Uuid::new_v4();
+        let private_key = String::from_utf8(private_key).unwrap();
+        let value = Self {
+            created_at: now,
+            id,
+            algorithm: algorithm.to_string(),
+            private_key: data_keys.jwt_key_encrypt(id, &private_key)?,
+            public_key: String::from_utf8(public_key).unwrap(),
+        };
+        conn.transaction(|| {
+            diesel::update(
+                sso_jwt_key::table.filter(
+                    sso_jwt_key::dsl::algorithm
+                        .eq(&value.algorithm)
+                        .and(sso_jwt_key::dsl::expires_at.is_null()),
+                ),
+            )
+            .set(sso_jwt_key::dsl::expires_at.eq(now))
+            .execute(conn)?;
+
+            diesel::insert_into(sso_jwt_key::table)
+                .values(&value)
+                .get_result::<JwtKey>(conn)
+        })
+        .map_err(Into::into)
+    }
+
+    fn generate_pkey(algorithm: JwtAlgorithm) -> DriverResult<PKey<Private>> {
+        match algorithm {
+            JwtAlgorithm::Rs256 => Rsa::generate(JWT_KEY_RSA_BITS).and_then(PKey::from_rsa),
+            JwtAlgorithm::Es256 => EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)
+                .and_then(|group| EcKey::generate(&group))
+                .and_then(PKey::from_ec_key),
+            JwtAlgorithm::EdDsa => PKey::generate_ed25519(),
+            JwtAlgorithm::Hs256 => return Err(DriverError::JwtAlgorithmInvalid),
+        }
+        .map_err(DriverError::Openssl)
+    }
+}
+
+impl JwtKeyRead {
+    /// Read signing key for algorithm, a key is generated if none exist.
+    pub fn signing(
+        conn: &PgConnection,
+        data_keys: &KeyDataKeys,
+        algorithm: JwtAlgorithm,
+    ) -> DriverResult<JwtKey> {
+        let key = sso_jwt_key::table
+            .filter(
+                sso_jwt_key::dsl::algorithm
+                    .eq(algorithm.to_string())
+                    .and(sso_jwt_key::dsl::expires_at.is_null()),
+            )
+            .order(sso_jwt_key::dsl::created_at.desc())
+            .first::<JwtKey>(conn)
+            .optional()
+            .map_err(DriverError::DieselResult)?;
+
+        match key {
+            Some(key) => Ok(key),
+            None => JwtKeyCreate::generate(conn, data_keys, algorithm),
+        }
+    }
+
+    /// Read key by ID, including expired keys.
+    pub fn id(conn: &PgConnection, id: Uuid) -> DriverResult<Option<JwtKey>> {
+        sso_jwt_key::table
+            .filter(sso_jwt_key::dsl::id.eq(id))
+            .get_result::<JwtKey>(conn)
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Read published keys, which are unexpired keys and keys that expired after time.
+    pub fn published(
+        conn: &PgConnection,
+        expired_after: DateTime<Utc>,
+    ) -> DriverResult<Vec<JwtKey>> {
+        sso_jwt_key::table
+            .filter(
+                sso_jwt_key::dsl::expires_at
+                    .is_null()
+                    .or(sso_jwt_key::dsl::expires_at.gt(expired_after)),
+            )
+            .order(sso_jwt_key::dsl::created_at.desc())
+            .load::<JwtKey>(conn)
+            .map_err(Into::into)
+    }
+}
+
+/// Returns URL safe base64 encoding without padding.
+fn base64_url(value: &[u8]) -> String {
+    base64::encode_config(value, base64::URL_SAFE_NO_PAD)
+}
@@ -0,0 +1,181 @@
+use crate::{impl_enum_to_from_string, prelude::*, schema::sso_key_totp};
+use diesel::{prelude::*, PgConnection};
+use libreauth::{
+    hash::HashFunction,
+    oath::{HOTPBuilder, TOTPBuilder},
+};
+
+/// TOTP hash algorithms.
+#[derive(Debug, Copy, PartialEq, Clone, Serialize, Deserialize)]
+pub enum TotpAlgorithm {
+    #[serde(rename = "SHA1")]
+    Sha1,
+    #[serde(rename = "SHA256")]
+    Sha256,
+    #[serde(rename = "SHA512")]
+    Sha512,
+}
+
+impl_enum_to_from_string!(TotpAlgorithm, "");
+
+impl TotpAlgorithm {
+    /// Returns libreauth hash function.
+    pub fn to_hash_function(self) -> HashFunction {
+        match self {
+            TotpAlgorithm::Sha1 => HashFunction::Sha1,
+            TotpAlgorithm::Sha256 => HashFunction::Sha256,
+            TotpAlgorithm::Sha512 => HashFunction::Sha512,
+        }
+    }
+}
+
+/// TOTP key parameters.
+///
+/// Skew is the number of time steps before or after the current time step
+/// in which codes are accepted.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TotpParams {
+    pub period: i32,
+    pub digits: i32,
+    pub algorithm: TotpAlgorithm,
+    pub skew: i32,
+}
+
+impl Default for TotpParams {
+    fn default() -> Self {
+        Self {
+            period: 30,
+            digits: 6,
+            algorithm: TotpAlgorithm::Sha1,
+            skew: 1,
+        }
+    }
+}
+
+impl TotpParams {
+    /// Returns otpauth URI for key secret, non-default parameters are included.
+    pub fn uri(&self, secret: &str, issuer: &str, account_name: &str) -> DriverResult<String> {
+        let totp = TOTPBuilder::new()
+            .base32_key(secret)
+            .period(self.period as u32)
+            .output_len(self.digits as usize)
+            .hash_function(self.algorithm.to_hash_function())
+            .finalize()
+            .map_err::<DriverError, _>(Into::into)?;
+        Ok(totp.key_uri_format(issuer, account_name).finalize())
+    }
+
+    /// Verify code for key secret, returns accepted time step.
+    pub fn verify(&self, secret: &str, code: &str) -> DriverResult<i64> {
+        let step = Utc::now().timestamp() / i64::from(self.period);
+        for step in (step - i64::from(self.skew))..=(step + i64::from(self.skew)) {
+            let hotp = HOTPBuilder::new()
+                .base32_key(secret)
+                .counter(step as u64)
+                .output_len(self.digits as usize)
+                .hash_function(self.algorithm.to_hash_function())
+                .finalize()
+                .map_err::<DriverError, _>(Into::into)?;
+            if hotp.is_valid(code) {
+                return Ok(step);
+            }
+        }
+        Err(DriverError::TotpInvalid)
+    }
+}
+
+/// TOTP key.
+///
+/// Stored alongside a user key of type `Totp`, where the key value is the
+/// base32 encoded secret. Keys without a row use default parameters.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "sso_key_totp"]
+pub struct KeyTotp {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    key_id: Uuid,
+    period: i32,
+    digits: i32,
+    algorithm: String,
+    skew: i32,
+    last_step: Option<i64>,
+}
+
+impl KeyTotp {
+    /// Create TOTP key parameters with optional last accepted time step.
+    pub fn create(
+        conn: &PgConnection,
+        key_id: Uuid,
+        params: &TotpParams,
+        last_step: Option<i64>,
+    ) -> DriverResult<()> {
+        diesel::insert_into(sso_key_totp::table)
+            .values(&Self::new(key_id, params, last_step))
+            .execute(conn)
+            .map_err(Into::into)
+            .map(|_| ())
+    }
+
+    /// Read TOTP key parameters, returns defaults if not stored.
+    pub fn read(conn: &PgConnection, key_id: Uuid) -> DriverResult<TotpParams> {
+        let key = sso_key_totp::table
+            .filter(sso_key_totp::dsl::key_id.eq(key_id))
+            .get_result::<KeyTotp>(conn)
+            .optional()?;
+        Ok(key.map(|x| x.params()).unwrap_or_default())
+    }
+
+    /// Verify code for TOTP key, returns error if code is invalid or its
+    /// time step is not after the last accepted time step.
+    pub fn verify(conn: &PgConnection, key_id: Uuid, secret: &str, code: &str) -> DriverResult<()> {
+        let params = Self::read(conn, key_id)?;
+        let step = params.verify(secret, code)?;
+
+        diesel::insert_into(sso_key_totp::table)
+            .values(&Self::new(key_id, &params, None))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        let count = diesel::update(
+            sso_key_totp::table
+                .filter(sso_key_totp::dsl::key_id.eq(key_id))
+                .filter(
+                    sso_key_totp::dsl::last_step
+                        .is_null()
+                        .or(sso_key_totp::dsl::last_step.lt(step)),
+                ),
+        )
+        .set((
+            sso_key_totp::dsl::updated_at.eq(Utc::now()),
+            sso_key_totp::dsl::last_step.eq(step),
+        ))
+        .execute(conn)?;
+        if count == 1 {
+            Ok(())
+        } else {
+            Err(DriverError::TotpReused)
+        }
+    }
+
+    fn new(key_id: Uuid, params: &TotpParams, last_step: Option<i64>) -> Self {
+        let now = Utc::now();
+        Self {
+            created_at: now,
+            updated_at: now,
+            key_id,
+            period: params.period,
+            digits: params.digits,
+            algorithm: params.algorithm.to_string(),
+            skew: params.skew,
+            last_step,
+        }
+    }
+
+    fn params(&self) -> TotpParams {
+        TotpParams {
+            period: self.period,
+            digits: self.digits,
+            algorithm: TotpAlgorithm::from_str(&self.algorithm).unwrap(),
+            skew: self.skew,
+        }
+    }
+}
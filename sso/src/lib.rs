@@ -19,18 +19,36 @@ extern crate serde_json;
 
 mod csrf;
 mod driver;
+mod email_code;
 pub mod env;
 mod grpc;
 mod grpc_service;
 pub mod header;
 mod http_server;
 mod jwt;
+mod jwt_denylist;
+mod jwt_key;
+mod key_totp;
+mod login_lockout;
+mod notify;
+mod oauth2_provider;
+mod oidc;
 mod prelude;
+mod pwned_passwords;
+mod refresh_token;
+mod saml;
 mod schema;
+mod totp_recovery;
+mod user_password_history;
+mod user_session;
 pub mod validate;
 
 pub use crate::driver::*;
-pub use crate::{csrf::*, grpc::*, grpc_service::*, http_server::*, jwt::*};
+pub use crate::{
+    csrf::*, email_code::*, grpc::*, grpc_service::*, http_server::*, jwt::*, jwt_denylist::*,
+    jwt_key::*, key_totp::*, login_lockout::*, notify::*, oauth2_provider::*, pwned_passwords::*,
+    refresh_token::*, saml::*, totp_recovery::*, user_password_history::*, user_session::*,
+};
 
 use std::io::Write;
 
@@ -0,0 +1,169 @@
+use crate::{prelude::*, schema::sso_login_lockout};
+use diesel::{dsl::exists, prelude::*, PgConnection};
+use std::net::SocketAddr;
+
+/// Login lockout counter.
+///
+/// Failed logins are counted per user email, whether or not a user
+/// exists for that email, and per remote IP address.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "sso_login_lockout"]
+pub struct LoginLockout {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    key: String,
+    failure_count: i32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+impl LoginLockout {
+    /// Returns error if login is locked out for user email or remote.
+    pub fn check(conn: &PgConnection, email: &str, remote: &str) -> DriverResult<()> {
+        let now = Utc::now();
+        let keys = vec![Self::user_key(email), Self::remote_key(remote)];
+        let locked = diesel::select(exists(
+            sso_login_lockout::table
+                .filter(sso_login_lockout::dsl::key.eq_any(keys))
+                .filter(sso_login_lockout::dsl::locked_until.gt(now)),
+        ))
+        .get_result::<bool>(conn)?;
+        if locked {
+            Err(DriverError::UserLoginLocked)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Count failed login for user email and remote, returns true if
+    /// login is now locked out.
+    pub fn failure(
+        conn: &PgConnection,
+        options: &GrpcServerOptionsLockout,
+        email: &str,
+        remote: &str,
+    ) -> DriverResult<bool> {
+        Self::delete_by_expired(conn, options)?;
+
+        let user = Self::failure_inner(
+            conn,
+            options,
+            options.user_attempts(),
+            Self::user_key(email),
+        )?;
+        let remote = Self::failure_inner(
+            conn,
+            options,
+            options.remote_attempts(),
+            Self::remote_key(remote),
+        )?;
+        Ok(user || remote)
+    }
+
+    /// Reset failed login count for user email after successful login.
+    pub fn success(conn: &PgConnection, email: &str) -> DriverResult<()> {
+        Self::delete_keys(conn, vec![Self::user_key(email)]).map(|_| ())
+    }
+
+    /// Unlock login for user email and optional remote, returns true if
+    /// any failed login counts were reset.
+    pub fn unlock(conn: &PgConnection, email: &str, remote: Option<&str>) -> DriverResult<bool> {
+        let mut keys = vec![Self::user_key(email)];
+        if let Some(remote) = remote {
+            keys.push(Self::remote_key(remote));
+        }
+        Self::delete_keys(conn, keys).map(|count| count > 0)
+    }
+
+    fn failure_inner(
+        conn: &PgConnection,
+        options: &GrpcServerOptionsLockout,
+        attempts: i32,
+        key: String,
+    ) -> DriverResult<bool> {
+        if attempts <= 0 {
+            return Ok(false);
+        }
+
+        conn.transaction::<_, DriverError, _>(|| {
+            let now = Utc::now();
+            let current = sso_login_lockout::table
+                .filter(sso_login_lockout::dsl::key.eq(&key))
+                .for_update()
+                .get_result::<LoginLockout>(conn)
+                .optional()?;
+
+            // Failure count is reset if no failures or lockout within maximum delay.
+            let failure_count = match current {
+                Some(current) if !current.is_expired(now, options.max_delay()) => {
+                    current.failure_count + 1
+                }
+                _ => 1,
+            };
+            let delay = options.lockout_delay(attempts, failure_count);
+            let value = Self {
+                created_at: now,
+                updated_at: now,
+                key,
+                failure_count,
+                locked_until: delay.map(|delay| now + delay),
+            };
+
+            diesel::insert_into(sso_login_lockout::table)
+                .values(&value)
+                .on_conflict(sso_login_lockout::dsl::key)
+                .do_update()
+                .set((
+                    sso_login_lockout::dsl::updated_at.eq(value.updated_at),
+                    sso_login_lockout::dsl::failure_count.eq(value.failure_count),
+                    sso_login_lockout::dsl::locked_until.eq(value.locked_until),
+                ))
+                .execute(conn)?;
+            Ok(delay.is_some())
+        })
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>, max_delay: Duration) -> bool {
+        let last = match self.locked_until {
+            Some(locked_until) => locked_until.max(self.updated_at),
+            None => self.updated_at,
+        };
+        last + max_delay <= now
+    }
+
+    fn delete_keys(conn: &PgConnection, keys: Vec<String>) -> DriverResult<usize> {
+        diesel::delete(sso_login_lockout::table.filter(sso_login_lockout::dsl::key.eq_any(keys)))
+            .execute(conn)
+            .map_err(Into::into)
+    }
+
+    fn delete_by_expired(
+        conn: &PgConnection,
+        options: &GrpcServerOptionsLockout,
+    ) -> DriverResult<()> {
+        let expired = Utc::now() - options.max_delay();
+        diesel::delete(
+            sso_login_lockout::table
+                .filter(sso_login_lockout::dsl::updated_at.le(expired))
+                .filter(
+                    sso_login_lockout::dsl::locked_until
+                        .is_null()
+                        .or(sso_login_lockout::dsl::locked_until.le(expired)),
+                ),
+        )
+        .execute(conn)
+        .map_err(Into::into)
+        .map(|_| ())
+    }
+
+    fn user_key(email: &str) -> String {
+        format!("user:{}", email.trim().to_lowercase())
+    }
+
+    /// Remote addresses include a port, which is ignored.
+    fn remote_key(remote: &str) -> String {
+        match remote.parse::<SocketAddr>() {
+            Ok(addr) => format!("remote:{}", addr.ip()),
+            Err(_e) => format!("remote:{}", remote),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::prelude::*;
+use lettre::{file::FileTransport, SmtpClient, Transport};
+use lettre_email::Email;
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// Notification channel.
+///
+/// Channels deliver rendered templates to users out of band, for example
+/// OTP codes, password reset links and security alerts.
+pub trait NotifyChannel: fmt::Debug + Send + Sync {
+    /// Channel name.
+    fn name(&self) -> &'static str;
+
+    /// Send notification. Must be called from blocking context.
+    /// Returns false if channel cannot deliver to recipient.
+    fn send(&self, message: &TemplateEmail) -> DriverResult<bool>;
+}
+
+/// SMTP notification channel.
+#[derive(Clone)]
+pub struct NotifySmtp {
+    client: SmtpClient,
+    from_email: String,
+}
+
+impl fmt::Debug for NotifySmtp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NotifySmtp {{ from_email: {}, ... }}", self.from_email)
+    }
+}
+
+impl NotifySmtp {
+    pub fn new<F: Into<String>>(client: SmtpClient, from_email: F) -> Self {
+        Self {
+            client,
+            from_email: from_email.into(),
+        }
+    }
+}
+
+impl NotifyChannel for NotifySmtp {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    fn send(&self, message: &TemplateEmail) -> DriverResult<bool> {
+        let email = email_build(message, &self.from_email)?;
+        let mut transport = self.client.clone().transport();
+        transport.send(email.into()).map_err(DriverError::Lettre)?;
+        Ok(true)
+    }
+}
+
+/// File notification channel.
+///
+/// Writes emails to files in directory.
+#[derive(Debug, Clone)]
+pub struct NotifyFile {
+    path: PathBuf,
+}
+
+impl NotifyFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl NotifyChannel for NotifyFile {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn send(&self, message: &TemplateEmail) -> DriverResult<bool> {
+        let email = email_build(message, "file@localhost")?;
+        let mut transport = FileTransport::new(self.path.clone());
+        transport
+            .send(email.into())
+            .map_err(DriverError::LettreFile)?;
+        Ok(true)
+    }
+}
+
+/// HTTP webhook notification channel.
+///
+/// Sends notifications as JSON POST requests to URL, which can forward
+/// them to an SMS gateway or be a mock HTTP sink for local testing.
+#[derive(Debug, Clone)]
+pub struct NotifyWebhook {
+    client: Arc<reqwest::Client>,
+    url: String,
+}
+
+impl NotifyWebhook {
+    pub fn new<U: Into<String>>(client: Arc<reqwest::Client>, url: U) -> Self {
+        Self {
+            client,
+            url: url.into(),
+        }
+    }
+}
+
+impl NotifyChannel for NotifyWebhook {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn send(&self, message: &TemplateEmail) -> DriverResult<bool> {
+        let req = self.client.post(&self.url).json(message).send();
+        tokio::runtime::Handle::current()
+            .block_on(req)
+            .and_then(|res| res.error_for_status())
+            .map_err(DriverError::Reqwest)?;
+        Ok(true)
+    }
+}
+
+/// SMS message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotifySms {
+    pub to_phone: String,
+    pub text: String,
+}
+
+/// Stub SMS gateway notification channel.
+///
+/// Records messages sent to users with a phone number, for tests.
+#[derive(Debug, Clone, Default)]
+pub struct NotifySmsStub {
+    messages: Arc<Mutex<Vec<NotifySms>>>,
+}
+
+impl NotifySmsStub {
+    /// Returns messages sent by channel.
+    pub fn messages(&self) -> Vec<NotifySms> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+impl NotifyChannel for NotifySmsStub {
+    fn name(&self) -> &'static str {
+        "sms_stub"
+    }
+
+    fn send(&self, message: &TemplateEmail) -> DriverResult<bool> {
+        match &message.to_phone {
+            Some(to_phone) => {
+                self.messages.lock().unwrap().push(NotifySms {
+                    to_phone: to_phone.to_owned(),
+                    text: message.text.to_owned(),
+                });
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Send notification to all channels.
+///
+/// Returns error if no channel delivered notification.
+pub fn notify_send(
+    channels: &[Arc<dyn NotifyChannel>],
+    message: &TemplateEmail,
+) -> DriverResult<()> {
+    let mut delivered = false;
+    let mut error = None;
+    for channel in channels {
+        match channel.send(message) {
+            Ok(x) => delivered |= x,
+            Err(e) => {
+                warn!("Notify channel {} failed: {}", channel.name(), e);
+                error.get_or_insert(e);
+            }
+        }
+    }
+    match (delivered, error) {
+        (true, _) => Ok(()),
+        (false, Some(e)) => Err(e),
+        (false, None) => Err(DriverError::NotifyDisabled),
+    }
+}
+
+fn email_build(message: &TemplateEmail, from_email: &str) -> DriverResult<Email> {
+    Email::builder()
+        .to((message.to_email.as_str(), message.to_name.as_str()))
+        .from((from_email, message.from_name.as_str()))
+        .subject(message.subject.as_str())
+        .text(message.text.as_str())
+        .build()
+        .map_err(DriverError::LettreEmail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(to_phone: Option<&str>) -> TemplateEmail {
+        TemplateEmail {
+            to_email: "user@test.com".to_owned(),
+            to_name: "user".to_owned(),
+            to_phone: to_phone.map(|x| x.to_owned()),
+            from_name: "service".to_owned(),
+            subject: "subject".to_owned(),
+            text: "text".to_owned(),
+        }
+    }
+
+    #[test]
+    fn notify_send_sms_stub() {
+        let sms = NotifySmsStub::default();
+        let channels: Vec<Arc<dyn NotifyChannel>> = vec![Arc::new(sms.clone())];
+
+        notify_send(&channels, &message(Some("+447700900000"))).unwrap();
+        assert_eq!(
+            sms.messages(),
+            vec![NotifySms {
+                to_phone: "+447700900000".to_owned(),
+                text: "text".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn notify_send_undelivered() {
+        let sms = NotifySmsStub::default();
+        let channels: Vec<Arc<dyn NotifyChannel>> = vec![Arc::new(sms.clone())];
+
+        let res = notify_send(&channels, &message(None));
+        assert!(matches!(res, Err(DriverError::NotifyDisabled)));
+        assert!(sms.messages().is_empty());
+
+        let res = notify_send(&[], &message(None));
+        assert!(matches!(res, Err(DriverError::NotifyDisabled)));
+    }
+}
@@ -0,0 +1,447 @@
+use crate::{prelude::*, schema::sso_oauth2_provider};
+use diesel::{prelude::*, PgConnection};
+use oauth2::{
+    basic::BasicClient,
+    http::{header, HeaderMap, HeaderValue, Method},
+    reqwest::http_client,
+    AuthUrl, ClientId, ClientSecret, HttpRequest, RedirectUrl, TokenUrl,
+};
+use serde_json::Value;
+use std::fmt;
+use url::Url;
+
+/// OAuth2 provider default scopes.
+const OAUTH2_PROVIDER_SCOPES: [&str; 2] = ["openid", "email"];
+
+/// OAuth2 provider default email claim.
+const OAUTH2_PROVIDER_EMAIL_CLAIM: &str = "email";
+
+/// OAuth2 provider.
+#[derive(Clone, Identifiable, Queryable)]
+#[table_name = "sso_oauth2_provider"]
+pub struct Oauth2Provider {
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub id: Uuid,
+    pub is_enabled: bool,
+    pub name: String,
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    pub email_claim: String,
+    pub trust_email: bool,
+}
+
+/// OAuth2 provider metadata, read from OpenID Connect discovery document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Oauth2ProviderMetadata {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    #[serde(default)]
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+/// OAuth2 provider create.
+#[derive(Debug)]
+pub struct Oauth2ProviderCreate {
+    pub is_enabled: bool,
+    pub name: String,
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    pub email_claim: String,
+    pub trust_email: bool,
+}
+
+/// OAuth2 provider update.
+#[derive(Debug)]
+pub struct Oauth2ProviderUpdate {
+    pub id: Uuid,
+    pub is_enabled: Option<bool>,
+    pub name: Option<String>,
+    pub discovery_url: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scopes: Option<Vec<String>>,
+    pub email_claim: Option<String>,
+    pub trust_email: Option<bool>,
+}
+
+/// OAuth2 provider read.
+#[derive(Debug)]
+pub struct Oauth2ProviderRead;
+
+#[derive(Insertable)]
+#[table_name = "sso_oauth2_provider"]
+struct Oauth2ProviderInsert<'a> {
+    created_at: &'a DateTime<Utc>,
+    updated_at: &'a DateTime<Utc>,
+    id: &'a Uuid,
+    is_enabled: bool,
+    name: &'a str,
+    discovery_url: &'a str,
+    client_id: &'a str,
+    client_secret: &'a str,
+    scopes: &'a [String],
+    email_claim: &'a str,
+    trust_email: bool,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "sso_oauth2_provider"]
+struct Oauth2ProviderChangeset<'a> {
+    updated_at: &'a DateTime<Utc>,
+    is_enabled: Option<bool>,
+    name: Option<&'a str>,
+    discovery_url: Option<&'a str>,
+    client_id: Option<&'a str>,
+    client_secret: Option<&'a str>,
+    scopes: Option<&'a [String]>,
+    email_claim: Option<&'a str>,
+    trust_email: Option<bool>,
+}
+
+impl Oauth2Provider {
+    /// Check provider is enabled.
+    pub fn check(self) -> DriverResult<Self> {
+        if !self.is_enabled {
+            Err(DriverError::Oauth2ProviderDisabled)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Read provider metadata from discovery URL.
+    pub fn metadata(&self) -> DriverResult<Oauth2ProviderMetadata> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        let request = HttpRequest {
+            url: Url::parse(&self.discovery_url).map_err(DriverError::UrlParse)?,
+            method: Method::GET,
+            headers,
+            body: Vec::new(),
+        };
+        let response = http_client(request).map_err(|e| DriverError::Oauth2Request(e.into()))?;
+        if !response.status_code.is_success() {
+            return Err(DriverError::Oauth2ProviderDiscovery(
+                response.status_code.as_u16(),
+            ));
+        }
+        serde_json::from_slice(&response.body).map_err(DriverError::SerdeJson)
+    }
+
+    /// Returns client for provider metadata with redirect URL.
+    pub fn client(
+        &self,
+        metadata: &Oauth2ProviderMetadata,
+        redirect_url: &str,
+    ) -> DriverResult<BasicClient> {
+        let auth_url = AuthUrl::new(metadata.authorization_endpoint.to_owned())
+            .map_err(DriverError::UrlParse)?;
+        let token_url =
+            TokenUrl::new(metadata.token_endpoint.to_owned()).map_err(DriverError::UrlParse)?;
+        let redirect_url =
+            RedirectUrl::new(redirect_url.to_owned()).map_err(DriverError::UrlParse)?;
+
+        Ok(BasicClient::new(
+            ClientId::new(self.client_id.to_owned()),
+            Some(ClientSecret::new(self.client_secret.to_owned())),
+            auth_url,
+            Some(token_url),
+        )
+        .set_redirect_url(redirect_url))
+    }
+
+    /// Returns email address from userinfo response using email claim.
+    ///
+    /// Fails unless the provider reports the email address is verified, or the
+    /// provider is trusted to only return verified email addresses.
+    pub fn userinfo_email(&self, userinfo: &Value) -> DriverResult<String> {
+        let email_verified = match &userinfo["email_verified"] {
+            Value::Bool(x) => *x,
+            Value::String(x) => x == "true",
+            _ => false,
+        };
+        if !email_verified && !self.trust_email {
+            return Err(DriverError::Oauth2ProviderEmailUnverified);
+        }
+        userinfo[&self.email_claim]
+            .as_str()
+            .map(|x| x.to_owned())
+            .ok_or_else(|| DriverError::Oauth2ProviderEmailClaimNotFound)
+    }
+}
+
+impl Oauth2ProviderMetadata {
+    /// Returns true if provider supports PKCE with SHA-256 code challenge.
+    pub fn pkce_s256_supported(&self) -> bool {
+        self.code_challenge_methods_supported
+            .iter()
+            .any(|x| x == "S256")
+    }
+}
+
+impl fmt::Debug for Oauth2Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Oauth2Provider")
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .field("id", &self.id)
+            .field("is_enabled", &self.is_enabled)
+            .field("name", &self.name)
+            .field("discovery_url", &self.discovery_url)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .field("email_claim", &self.email_claim)
+            .field("trust_email", &self.trust_email)
+            .finish()
+    }
+}
+
+impl fmt::Display for Oauth2Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Oauth2Provider {}", self.id)?;
+        write!(f, "\n\tcreated_at {}", self.created_at)?;
+        write!(f, "\n\tupdated_at {}", self.updated_at)?;
+        write!(f, "\n\tis_enabled {}", self.is_enabled)?;
+        write!(f, "\n\tname {}", self.name)?;
+        write!(f, "\n\tdiscovery_url {}", self.discovery_url)?;
+        write!(f, "\n\tclient_id {}", self.client_id)?;
+        write!(f, "\n\tscopes {}", self.scopes.join(" "))?;
+        write!(f, "\n\temail_claim {}", self.email_claim)?;
+        write!(f, "\n\ttrust_email {}", self.trust_email)
+    }
+}
+
+impl AuditSubject for Oauth2Provider {
+    fn subject(&self) -> String {
+        format!("{}", self.id)
+    }
+}
+
+impl AuditDiff for Oauth2Provider {
+    fn diff(&self, previous: &Self) -> Value {
+        let c_client_secret = self.client_secret != previous.client_secret;
+
+        AuditDiffBuilder::default()
+            .compare("is_enabled", &self.is_enabled, &previous.is_enabled)
+            .compare("name", &self.name, &previous.name)
+            .compare(
+                "discovery_url",
+                &self.discovery_url,
+                &previous.discovery_url,
+            )
+            .compare("client_id", &self.client_id, &previous.client_id)
+            .compare("client_secret", &c_client_secret, &false)
+            .compare_vec("scopes", &self.scopes, &previous.scopes)
+            .compare("email_claim", &self.email_claim, &previous.email_claim)
+            .compare("trust_email", &self.trust_email, &previous.trust_email)
+            .into_value()
+    }
+}
+
+impl From<Oauth2Provider> for pb::Oauth2Provider {
+    fn from(r: Oauth2Provider) -> Self {
+        Self {
+            created_at: pb::datetime_to_timestamp_opt(r.created_at),
+            updated_at: pb::datetime_to_timestamp_opt(r.updated_at),
+            id: pb::uuid_to_string(r.id),
+            is_enabled: r.is_enabled,
+            name: r.name,
+            discovery_url: r.discovery_url,
+            client_id: r.client_id,
+            scopes: r.scopes,
+            email_claim: r.email_claim,
+            trust_email: r.trust_email,
+        }
+    }
+}
+
+impl From<pb::Oauth2ProviderCreateRequest> for Oauth2ProviderCreate {
+    fn from(r: pb::Oauth2ProviderCreateRequest) -> Self {
+        Self {
+            is_enabled: r.is_enabled.unwrap_or(true),
+            name: r.name,
+            discovery_url: r.discovery_url,
+            client_id: r.client_id,
+            client_secret: r.client_secret,
+            scopes: if r.scopes.is_empty() {
+                OAUTH2_PROVIDER_SCOPES
+                    .iter()
+                    .map(|x| (*x).to_owned())
+                    .collect()
+            } else {
+                r.scopes
+            },
+            email_claim: r
+                .email_claim
+                .unwrap_or_else(|| OAUTH2_PROVIDER_EMAIL_CLAIM.to_owned()),
+            trust_email: r.trust_email.unwrap_or(false),
+        }
+    }
+}
+
+impl From<pb::Oauth2ProviderUpdateRequest> for Oauth2ProviderUpdate {
+    fn from(r: pb::Oauth2ProviderUpdateRequest) -> Self {
+        Self {
+            id: pb::string_to_uuid(r.id),
+            is_enabled: r.is_enabled,
+            name: r.name,
+            discovery_url: r.discovery_url,
+            client_id: r.client_id,
+            client_secret: r.client_secret,
+            scopes: if r.scopes.is_empty() {
+                None
+            } else {
+                Some(r.scopes)
+            },
+            email_claim: r.email_claim,
+            trust_email: r.trust_email,
+        }
+    }
+}
+
+impl Oauth2ProviderCreate {
+    /// Create provider.
+    pub fn create(&self, conn: &PgConnection) -> DriverResult<Oauth2Provider> {
+        let now = Utc::now();
+        let id = Uuid::new_v4();
+        let value = Oauth2ProviderInsert {
+            created_at: &now,
+            updated_at: &now,
+            id: &id,
+            is_enabled: self.is_enabled,
+            name: &self.name,
+            discovery_url: &self.discovery_url,
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+            scopes: &self.scopes,
+            email_claim: &self.email_claim,
+            trust_email: self.trust_email,
+        };
+        diesel::insert_into(sso_oauth2_provider::table)
+            .values(value)
+            .get_result::<Oauth2Provider>(conn)
+            .map_err(Into::into)
+    }
+}
+
+impl Oauth2ProviderUpdate {
+    /// Update provider.
+    pub fn update(&self, conn: &PgConnection) -> DriverResult<Oauth2Provider> {
+        let now = Utc::now();
+        let value = Oauth2ProviderChangeset {
+            updated_at: &now,
+            is_enabled: self.is_enabled,
+            name: self.name.as_ref().map(|x| &**x),
+            discovery_url: self.discovery_url.as_ref().map(|x| &**x),
+            client_id: self.client_id.as_ref().map(|x| &**x),
+            client_secret: self.client_secret.as_ref().map(|x| &**x),
+            scopes: self.scopes.as_ref().map(|x| &**x),
+            email_claim: self.email_claim.as_ref().map(|x| &**x),
+            trust_email: self.trust_email,
+        };
+        diesel::update(sso_oauth2_provider::table.filter(sso_oauth2_provider::dsl::id.eq(self.id)))
+            .set(value)
+            .get_result::<Oauth2Provider>(conn)
+            .map_err(Into::into)
+    }
+}
+
+impl Oauth2ProviderRead {
+    /// Read all providers ordered by name.
+    pub fn list(conn: &PgConnection) -> DriverResult<Vec<Oauth2Provider>> {
+        sso_oauth2_provider::table
+            .order(sso_oauth2_provider::dsl::name.asc())
+            .load::<Oauth2Provider>(conn)
+            .map_err(Into::into)
+    }
+
+    /// Read provider by ID.
+    pub fn id(conn: &PgConnection, id: Uuid) -> DriverResult<Option<Oauth2Provider>> {
+        sso_oauth2_provider::table
+            .filter(sso_oauth2_provider::dsl::id.eq(id))
+            .get_result::<Oauth2Provider>(conn)
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Delete provider by ID.
+    pub fn delete(conn: &PgConnection, id: Uuid) -> DriverResult<usize> {
+        diesel::delete(sso_oauth2_provider::table.filter(sso_oauth2_provider::dsl::id.eq(id)))
+            .execute(conn)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider_new(email_claim: &str, trust_email: bool) -> Oauth2Provider {
+        Oauth2Provider {
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            id: Uuid::new_v4(),
+            is_enabled: true,
+            name: "Provider Name".to_owned(),
+            discovery_url: "http://localhost/.well-known/openid-configuration".to_owned(),
+            client_id: "client_id".to_owned(),
+            client_secret: "client_secret".to_owned(),
+            scopes: vec!["openid".to_owned(), "email".to_owned()],
+            email_claim: email_claim.to_owned(),
+            trust_email,
+        }
+    }
+
+    #[test]
+    fn oauth2_provider_userinfo_email() {
+        let provider = provider_new("email", false);
+        let email = provider
+            .userinfo_email(&json!({ "email": "user@test.com", "email_verified": true }))
+            .unwrap();
+        assert_eq!(email, "user@test.com");
+        provider
+            .userinfo_email(&json!({ "email": "user@test.com", "email_verified": false }))
+            .unwrap_err();
+        provider
+            .userinfo_email(&json!({ "mail": "user@test.com", "email_verified": true }))
+            .unwrap_err();
+
+        let provider = provider_new("upn", false);
+        let email = provider
+            .userinfo_email(&json!({ "upn": "user@test.com", "email_verified": "true" }))
+            .unwrap();
+        assert_eq!(email, "user@test.com");
+    }
+
+    #[test]
+    fn oauth2_provider_userinfo_email_unverified() {
+        let provider = provider_new("email", false);
+        for email_verified in &[json!(false), json!("false"), json!(1), json!(null)] {
+            let res = provider
+                .userinfo_email(
+                    &json!({ "email": "user@test.com", "email_verified": email_verified }),
+                )
+                .unwrap_err();
+            assert!(matches!(res, DriverError::Oauth2ProviderEmailUnverified));
+        }
+        // Missing claim is not verified.
+        let res = provider
+            .userinfo_email(&json!({ "email": "user@test.com" }))
+            .unwrap_err();
+        assert!(matches!(res, DriverError::Oauth2ProviderEmailUnverified));
+
+        let provider = provider_new("email", true);
+        let email = provider
+            .userinfo_email(&json!({ "email": "user@test.com" }))
+            .unwrap();
+        assert_eq!(email, "user@test.com");
+        provider
+            .userinfo_email(&json!({ "email": "user@test.com", "email_verified": false }))
+            .unwrap();
+    }
+}
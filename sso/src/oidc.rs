@@ -0,0 +1,663 @@
+//! OpenID Connect provider endpoints.
+//!
+//! Each service is an OpenID Connect client, the client ID is the service ID and
+//! the client secret is a service key value. Users authenticate with the local
+//! provider, access and refresh tokens are issued with the user token key.
+use crate::prelude::*;
+use http::header::{self as http_header, HeaderMap, HeaderValue};
+use hyper::{Body, Request, Response, StatusCode};
+use std::{net::SocketAddr, sync::Arc};
+use url::Url;
+
+static BAD_REQUEST: &[u8] = b"BadRequest";
+
+/// Authorization code expiry time in seconds.
+const OIDC_CODE_EXPIRES_S: i64 = 600;
+
+/// Scope required for OpenID Connect requests.
+const OIDC_SCOPE: &str = "openid";
+
+/// Authorize login form error message.
+const OIDC_LOGIN_ERROR: &str = "Email or password is incorrect.";
+
+/// Authorize request query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OidcAuthorizeQuery {
+    client_id: String,
+    redirect_uri: String,
+    response_type: String,
+    scope: String,
+    state: Option<String>,
+    nonce: Option<String>,
+}
+
+/// Authorize login form.
+#[derive(Debug, Deserialize)]
+struct OidcAuthorizeForm {
+    request: String,
+    email: String,
+    password: String,
+}
+
+/// Authorization code data, stored as CSRF value.
+#[derive(Debug, Serialize, Deserialize)]
+struct OidcCode {
+    user_id: Uuid,
+    redirect_uri: String,
+    scope: String,
+    nonce: Option<String>,
+}
+
+/// Token request form.
+#[derive(Debug, Deserialize)]
+struct OidcTokenForm {
+    grant_type: String,
+    code: Option<String>,
+    redirect_uri: Option<String>,
+    refresh_token: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+/// Token response.
+#[derive(Debug, Serialize)]
+struct OidcTokenReply {
+    access_token: String,
+    token_type: &'static str,
+    expires_in: i64,
+    refresh_token: String,
+    id_token: String,
+}
+
+/// Authorize request result.
+#[derive(Debug)]
+enum OidcAuthorize {
+    /// Render login form with request key.
+    Form(Box<Service>, String),
+    /// Redirect to client.
+    Redirect(Url),
+}
+
+/// Discovery document.
+pub async fn discovery(issuer: &str) -> Result<Response<Body>, hyper::Error> {
+    let data = json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "userinfo_endpoint": format!("{}/userinfo", issuer),
+        "jwks_uri": format!("{}/jwks.json", issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["HS256", "RS256", "ES256", "EdDSA"],
+        "scopes_supported": [OIDC_SCOPE, "email", "profile"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post"],
+        "grant_types_supported": ["authorization_code", "refresh_token"],
+        "claims_supported": ["iss", "sub", "aud", "exp", "iat", "nonce", "email", "name"],
+    });
+    Ok(response_json(StatusCode::OK, &data))
+}
+
+/// JSON web key set.
+///
+/// Public keys of server signing keys used by services with an asymmetric algorithm.
+/// Expired keys are published until access and ID tokens they signed have expired.
+/// Tokens signed with `HS256` use user keys or client secrets, which are never published.
+pub async fn jwks(
+    options: Arc<GrpcServerOptions>,
+    driver: Arc<Postgres>,
+) -> Result<Response<Body>, hyper::Error> {
+    let expired_after = Utc::now() - options.access_token_expires();
+    let keys = blocking::<_, DriverError, _>(move || {
+        let conn = driver.conn()?;
+        JwtKeyRead::published(&conn, expired_after)?
+            .iter()
+            .map(JwtKey::jwk)
+            .collect::<DriverResult<Vec<_>>>()
+    })
+    .await;
+
+    Ok(match keys {
+        Ok(keys) => response_json(StatusCode::OK, &json!({ "keys": keys })),
+        Err(e) => {
+            warn!("{}", e);
+            response_token_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error")
+        }
+    })
+}
+
+/// Authorize request, renders login form.
+pub async fn authorize(
+    issuer: String,
+    driver: Arc<Postgres>,
+    req: Request<Body>,
+    remote: SocketAddr,
+) -> Result<Response<Body>, hyper::Error> {
+    let audit_meta = AuditMeta::from_header_map(req.headers(), format!("{}", remote));
+    let query: OidcAuthorizeQuery =
+        match serde_urlencoded::from_str(req.uri().query().unwrap_or("")) {
+            Ok(query) => query,
+            Err(_e) => return Ok(response_bad_request()),
+        };
+
+    let authorize = blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthOidcAuthorize,
+            |driver, audit| {
+                let client_id = Uuid::parse_str(&query.client_id)
+                    .map_err(|_e| GrpcMethodError::BadRequest(DriverError::OidcClientInvalid))?;
+                let service = pattern::service_read_id_checked(driver, audit, client_id)
+                    .map_err(GrpcMethodError::BadRequest)?;
+                service
+                    .oidc_redirect_uri_check(&query.redirect_uri)
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                // Redirect URI is registered, other errors are returned to client.
+                if query.response_type != "code" {
+                    return redirect_error(&query, "unsupported_response_type")
+                        .map(OidcAuthorize::Redirect);
+                }
+                if !query.scope.split_whitespace().any(|x| x == OIDC_SCOPE) {
+                    return redirect_error(&query, "invalid_scope").map(OidcAuthorize::Redirect);
+                }
+
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let csrf = request_create(&conn, &query, service.id)
+                    .map_err(GrpcMethodError::BadRequest)?;
+                Ok(OidcAuthorize::Form(Box::new(service), csrf))
+            },
+        )
+    })
+    .await;
+
+    Ok(match authorize {
+        Ok(OidcAuthorize::Form(service, csrf)) => {
+            response_authorize_form(&issuer, &service, &csrf, None)
+        }
+        Ok(OidcAuthorize::Redirect(url)) => response_redirect(&url),
+        Err(_e) => response_bad_request(),
+    })
+}
+
+/// Authorize login form submitted, redirects to client with code if successful.
+pub async fn authorize_login(
+    issuer: String,
+    options: Arc<GrpcServerOptions>,
+    driver: Arc<Postgres>,
+    req: Request<Body>,
+    remote: SocketAddr,
+) -> Result<Response<Body>, hyper::Error> {
+    let audit_meta = AuditMeta::from_header_map(req.headers(), format!("{}", remote));
+    let body = hyper::body::to_bytes(req.into_body()).await?;
+    let form: OidcAuthorizeForm = match serde_urlencoded::from_bytes(&body) {
+        Ok(form) => form,
+        Err(_e) => return Ok(response_bad_request()),
+    };
+
+    // Request is single use, create another in case login fails.
+    let driver_request = driver.clone();
+    let request_key = form.request.clone();
+    let request = blocking_method(move || {
+        let driver = driver_request.as_ref();
+        let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+        let csrf = CsrfRead::read(&conn, &request_key)
+            .map_err(GrpcMethodError::BadRequest)?
+            .ok_or_else(|| GrpcMethodError::BadRequest(DriverError::CsrfNotFoundOrUsed))?;
+        let query: OidcAuthorizeQuery = serde_json::from_str(csrf.value())
+            .map_err(|e| GrpcMethodError::BadRequest(DriverError::SerdeJson(e)))?;
+        let service = driver
+            .service_read(&ServiceRead::new(csrf.service_id()), None)
+            .map_err(GrpcMethodError::BadRequest)?
+            .ok_or_else(|| GrpcMethodError::BadRequest(DriverError::ServiceNotFound))?;
+        let csrf =
+            request_create(&conn, &query, service.id).map_err(GrpcMethodError::BadRequest)?;
+        Ok((query, service, csrf))
+    })
+    .await;
+    let (query, service, csrf) = match request {
+        Ok(request) => request,
+        Err(_e) => return Ok(response_bad_request()),
+    };
+
+    let login_service = service.clone();
+    let login_csrf = csrf.clone();
+    let password_policy = options.password_policy_options();
+    let password_hash = options.password_hash_options();
+    let lockout = options.lockout_options();
+    let url = blocking_method(move || {
+        audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthOidcAuthorize,
+            |driver, audit| {
+                audit.service(Some(&login_service));
+                login_service
+                    .clone()
+                    .check()
+                    .map_err(GrpcMethodError::BadRequest)?;
+
+                // Forbidden if email or remote locked out by failed logins.
+                // Login requires token key type.
+                // Unknown emails are counted as failed logins.
+                let user = pattern::user_read_email_lockout(
+                    driver,
+                    audit,
+                    &lockout,
+                    &login_service,
+                    &form.email,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                pattern::key_read_user_checked(
+                    driver,
+                    &login_service,
+                    audit,
+                    &user,
+                    KeyType::Token,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                // Forbidden if user password update required.
+                if user.password_require_update {
+                    return Err(GrpcMethodError::Forbidden(
+                        DriverError::UserPasswordUpdateRequired,
+                    ));
+                }
+
+                // Check user password.
+                pattern::user_password_check_lockout(
+                    driver,
+                    audit,
+                    &lockout,
+                    &password_hash,
+                    &user,
+                    &form.password,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                // Forbidden if user password is older than password policy maximum age.
+                pattern::user_password_age_check(driver, &password_policy, &login_service, &user)
+                    .map_err(GrpcMethodError::Forbidden)?;
+
+                // Forbidden if service MFA policy applies to user, form has no second factor.
+                if pattern::user_login_mfa(driver, &login_service, &user)
+                    .map_err(GrpcMethodError::Forbidden)?
+                    .is_some()
+                {
+                    return Err(GrpcMethodError::Forbidden(DriverError::UserMfaRequired));
+                }
+                pattern::user_login_success(driver, &user).map_err(GrpcMethodError::BadRequest)?;
+
+                // Consume retry request and create authorization code.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                CsrfRead::read(&conn, &login_csrf).map_err(GrpcMethodError::BadRequest)?;
+                let code = OidcCode {
+                    user_id: user.id,
+                    redirect_uri: query.redirect_uri.clone(),
+                    scope: query.scope.clone(),
+                    nonce: query.nonce.clone(),
+                };
+                let code = serde_json::to_string(&code)
+                    .map_err(|e| GrpcMethodError::BadRequest(DriverError::SerdeJson(e)))?;
+                let code = CsrfCreate::generate_value(
+                    &conn,
+                    code,
+                    Duration::seconds(OIDC_CODE_EXPIRES_S),
+                    login_service.id,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                redirect_query(
+                    &query.redirect_uri,
+                    &[("code", code.key())],
+                    query.state.as_ref().map(|x| &**x),
+                )
+            },
+        )
+    })
+    .await;
+
+    Ok(match url {
+        Ok(url) => response_redirect(&url),
+        Err(_e) => response_authorize_form(&issuer, &service, &csrf, Some(OIDC_LOGIN_ERROR)),
+    })
+}
+
+/// Token request, exchanges authorization code or refresh token.
+pub async fn token(
+    options: Arc<GrpcServerOptions>,
+    driver: Arc<Postgres>,
+    req: Request<Body>,
+    remote: SocketAddr,
+) -> Result<Response<Body>, hyper::Error> {
+    let issuer = options.oidc_issuer_url().unwrap_or("").to_owned();
+    let access_token_expires = options.access_token_expires();
+    let refresh_token_expires = options.refresh_token_expires();
+
+    let audit_meta = AuditMeta::from_header_map(req.headers(), format!("{}", remote));
+    let basic = client_basic_authorisation(req.headers());
+    let body = hyper::body::to_bytes(req.into_body()).await?;
+    let form: OidcTokenForm = match serde_urlencoded::from_bytes(&body) {
+        Ok(form) => form,
+        Err(_e) => {
+            return Ok(response_token_error(
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+            ))
+        }
+    };
+
+    let reply = blocking_method(move || {
+        audit_result(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthOidcToken,
+            |driver, audit| {
+                // Client authenticates with service key as client secret.
+                let (client_id, client_secret) = basic
+                    .clone()
+                    .or_else(|| match (&form.client_id, &form.client_secret) {
+                        (Some(client_id), Some(client_secret)) => {
+                            Some((client_id.to_owned(), client_secret.to_owned()))
+                        }
+                        _ => None,
+                    })
+                    .ok_or_else(|| GrpcMethodError::Unauthorised(DriverError::KeyUndefined))?;
+                let auth = HeaderAuth::Header(HeaderAuthType::Key(client_secret.clone()));
+                let service = pattern::key_service_authenticate(driver, audit, &auth)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                if Uuid::parse_str(&client_id).ok() != Some(service.id) {
+                    return Err(GrpcMethodError::Unauthorised(
+                        DriverError::OidcClientInvalid,
+                    ));
+                }
+
+                // Authorization code and refresh token are single use, requires token key type.
+                let conn = driver.conn().map_err(GrpcMethodError::BadRequest)?;
+                let (user, key, nonce, family_id) = match form.grant_type.as_ref() {
+                    "authorization_code" => {
+                        let csrf = CsrfVerify::verify(&conn, service.id, form.code.clone())
+                            .map_err(GrpcMethodError::BadRequest)?;
+                        let code: OidcCode = serde_json::from_str(csrf.value())
+                            .map_err(|e| GrpcMethodError::BadRequest(DriverError::SerdeJson(e)))?;
+                        if form.redirect_uri.as_ref() != Some(&code.redirect_uri) {
+                            return Err(GrpcMethodError::BadRequest(
+                                DriverError::ServiceOidcRedirectUriInvalid,
+                            ));
+                        }
+                        let user = pattern::user_read_id_checked(
+                            driver,
+                            Some(&service),
+                            audit,
+                            code.user_id,
+                        )
+                        .map_err(GrpcMethodError::BadRequest)?;
+                        let key = pattern::key_read_user_checked(
+                            driver,
+                            &service,
+                            audit,
+                            &user,
+                            KeyType::Token,
+                        )
+                        .map_err(GrpcMethodError::BadRequest)?;
+                        (user, key, code.nonce, Uuid::new_v4())
+                    }
+                    "refresh_token" => {
+                        let token = form.refresh_token.clone().unwrap_or_default();
+
+                        // Unsafely decode token to get user identifier, used to read key for safe token decode.
+                        let (user_id, _) = Jwt::decode_unsafe_user(&token, service.id)
+                            .map_err(GrpcMethodError::BadRequest)?;
+
+                        // Token refresh requires token key type.
+                        let user =
+                            pattern::user_read_id_checked(driver, Some(&service), audit, user_id)
+                                .map_err(GrpcMethodError::BadRequest)?;
+                        let key = pattern::key_read_user_checked(
+                            driver,
+                            &service,
+                            audit,
+                            &user,
+                            KeyType::Token,
+                        )
+                        .map_err(GrpcMethodError::BadRequest)?;
+
+                        // Safely decode token with user key, refresh token is added to token family.
+                        let family_id = pattern::user_token_refresh_decode(
+                            driver, &service, audit, &user, &key, &token,
+                        )
+                        .map_err(GrpcMethodError::BadRequest)?;
+                        (user, key, None, family_id)
+                    }
+                    _ => {
+                        return Err(GrpcMethodError::BadRequest(
+                            DriverError::OidcGrantTypeUnsupported,
+                        ))
+                    }
+                };
+
+                // Encode ID token and user token, requires token key type.
+                let id_token = Jwt::encode_id_token(
+                    &conn,
+                    driver.data_keys(),
+                    &issuer,
+                    &service,
+                    &user,
+                    &client_secret,
+                    nonce,
+                    access_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+                let user_token = Jwt::encode_user_family(
+                    &conn,
+                    driver.data_keys(),
+                    audit.meta(),
+                    &service,
+                    user,
+                    &key,
+                    family_id,
+                    access_token_expires,
+                    refresh_token_expires,
+                )
+                .map_err(GrpcMethodError::BadRequest)?;
+
+                Ok(OidcTokenReply {
+                    access_token: user_token.access_token,
+                    token_type: "Bearer",
+                    expires_in: access_token_expires.num_seconds(),
+                    refresh_token: user_token.refresh_token,
+                    id_token,
+                })
+            },
+        )
+    })
+    .await;
+
+    Ok(match reply {
+        Ok(reply) => response_json(StatusCode::OK, &json!(reply)),
+        Err(GrpcMethodError::Unauthorised(_e)) => {
+            let mut res = response_token_error(StatusCode::UNAUTHORIZED, "invalid_client");
+            res.headers_mut().insert(
+                http_header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic"),
+            );
+            res
+        }
+        Err(GrpcMethodError::BadRequest(DriverError::OidcGrantTypeUnsupported)) => {
+            response_token_error(StatusCode::BAD_REQUEST, "unsupported_grant_type")
+        }
+        Err(GrpcMethodError::InternalServerError(_e)) => {
+            response_token_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error")
+        }
+        Err(_e) => response_token_error(StatusCode::BAD_REQUEST, "invalid_grant"),
+    })
+}
+
+/// User information request, authenticated by access token.
+pub async fn userinfo(
+    driver: Arc<Postgres>,
+    req: Request<Body>,
+    remote: SocketAddr,
+) -> Result<Response<Body>, hyper::Error> {
+    let audit_meta = AuditMeta::from_header_map(req.headers(), format!("{}", remote));
+    let token = header::authorisation(req.headers());
+
+    let user = blocking_method(move || {
+        audit_result_err(
+            driver.as_ref(),
+            audit_meta,
+            AuditType::AuthOidcUserinfo,
+            |driver, audit| {
+                let token = token
+                    .clone()
+                    .ok_or_else(|| GrpcMethodError::Unauthorised(DriverError::KeyUndefined))?;
+
+                // Unsafely decode token to get service and user identifiers, used to read key for safe token decode.
+                let service_id =
+                    Jwt::decode_unsafe_service(&token).map_err(GrpcMethodError::Unauthorised)?;
+                let service = pattern::service_read_id_checked(driver, audit, service_id)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                let (user_id, _) = Jwt::decode_unsafe_user(&token, service.id)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Token verify requires token key type.
+                let user = pattern::user_read_id_checked(driver, Some(&service), audit, user_id)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                let key =
+                    pattern::key_read_user_checked(driver, &service, audit, &user, KeyType::Token)
+                        .map_err(GrpcMethodError::Unauthorised)?;
+
+                // Safely decode token with user key.
+                let conn = driver.conn().map_err(GrpcMethodError::Unauthorised)?;
+                Jwt::decode_access(&conn, &service, &user, &key, &token)
+                    .map_err(GrpcMethodError::Unauthorised)?;
+                Ok(user)
+            },
+        )
+    })
+    .await;
+
+    Ok(match user {
+        Ok(user) => response_json(
+            StatusCode::OK,
+            &json!({
+                "sub": user.id.to_string(),
+                "email": user.email,
+                "name": user.name,
+            }),
+        ),
+        Err(_e) => Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header(
+                http_header::WWW_AUTHENTICATE,
+                "Bearer error=\"invalid_token\"",
+            )
+            .body(Body::empty())
+            .unwrap(),
+    })
+}
+
+/// Returns client ID and secret from basic Authorization header.
+fn client_basic_authorisation(map: &HeaderMap<HeaderValue>) -> Option<(String, String)> {
+    let value = map.get(header::AUTHORISATION)?.to_str().ok()?;
+    let value = base64::decode(value.strip_prefix("Basic ")?.trim()).ok()?;
+    let value = String::from_utf8(value).ok()?;
+    let mut parts = value.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(client_id), Some(client_secret)) => {
+            Some((client_id.to_owned(), client_secret.to_owned()))
+        }
+        _ => None,
+    }
+}
+
+/// Create authorize request CSRF, returns key.
+fn request_create(
+    conn: &diesel::PgConnection,
+    query: &OidcAuthorizeQuery,
+    service_id: Uuid,
+) -> DriverResult<String> {
+    let value = serde_json::to_string(query).map_err(DriverError::SerdeJson)?;
+    let csrf = CsrfCreate::generate_value(
+        conn,
+        value,
+        Duration::seconds(DEFAULT_CSRF_EXPIRES_S),
+        service_id,
+    )?;
+    Ok(csrf.key().to_owned())
+}
+
+/// Returns client redirect URL with error code.
+fn redirect_error(query: &OidcAuthorizeQuery, error: &str) -> GrpcMethodResult<Url> {
+    redirect_query(
+        &query.redirect_uri,
+        &[("error", error)],
+        query.state.as_ref().map(|x| &**x),
+    )
+}
+
+/// Returns client redirect URL with query parameters and optional state.
+fn redirect_query(
+    redirect_uri: &str,
+    pairs: &[(&str, &str)],
+    state: Option<&str>,
+) -> GrpcMethodResult<Url> {
+    let mut url = Url::parse(redirect_uri)
+        .map_err(|e| GrpcMethodError::BadRequest(DriverError::UrlParse(e)))?;
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in pairs {
+            query.append_pair(key, value);
+        }
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+    Ok(url)
+}
+
+fn response_authorize_form(
+    issuer: &str,
+    service: &Service,
+    request: &str,
+    error: Option<&str>,
+) -> Response<Body> {
+    let action = format!("{}/authorize", issuer);
+    match TemplateHtml::oidc_authorize(service, &action, request, error) {
+        Ok(html) => Response::builder()
+            .status(StatusCode::OK)
+            .header(http_header::CONTENT_TYPE, "text/html; charset=utf-8")
+            .header(http_header::CACHE_CONTROL, "no-store")
+            .body(Body::from(html))
+            .unwrap(),
+        Err(_e) => response_bad_request(),
+    }
+}
+
+fn response_redirect(url: &Url) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::FOUND)
+        .header(http_header::LOCATION, url.as_str())
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn response_json(status: StatusCode, data: &serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(http_header::CONTENT_TYPE, "application/json")
+        .header(http_header::CACHE_CONTROL, "no-store")
+        .body(Body::from(data.to_string()))
+        .unwrap()
+}
+
+fn response_token_error(status: StatusCode, error: &str) -> Response<Body> {
+    response_json(status, &json!({ "error": error }))
+}
+
+fn response_bad_request() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(BAD_REQUEST.into())
+        .unwrap()
+}
@@ -0,0 +1,289 @@
+use crate::prelude::*;
+use sha1::{Digest, Sha1};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Length of hexadecimal SHA-1 hash.
+const SHA1_HEX_LEN: usize = 40;
+
+/// Binary search window size in bytes, lines in window are read sequentially.
+const FILE_SEARCH_WINDOW: u64 = 4096;
+
+/// Bloom filter file header.
+const BLOOM_MAGIC: &[u8; 8] = b"SSOBLOOM";
+
+/// Pwned Passwords backend.
+#[derive(Debug, Clone)]
+pub enum PwnedPasswords {
+    /// Pwned Passwords range API, requires network access.
+    Api,
+    /// Local sorted SHA-1 hash file.
+    File(PwnedPasswordsFile),
+    /// Local bloom filter built from sorted SHA-1 hash file.
+    Bloom(Arc<PwnedPasswordsBloom>),
+}
+
+impl Default for PwnedPasswords {
+    fn default() -> Self {
+        Self::Api
+    }
+}
+
+impl PwnedPasswords {
+    /// Returns uppercase hexadecimal SHA-1 hash of password.
+    pub fn hash(password: &str) -> String {
+        let mut hash = Sha1::new();
+        hash.update(password);
+        format!("{:X}", hash.finalize())
+    }
+}
+
+/// Local sorted SHA-1 hash file.
+///
+/// Lines begin with uppercase hexadecimal SHA-1 hash and are ordered by hash,
+/// for example the downloadable Pwned Passwords `HASH:COUNT` file.
+#[derive(Debug, Clone)]
+pub struct PwnedPasswordsFile {
+    path: PathBuf,
+}
+
+impl PwnedPasswordsFile {
+    /// Returns file backend if path is a readable file.
+    pub fn open<P: Into<PathBuf>>(path: P) -> DriverResult<Self> {
+        let path = path.into();
+        File::open(&path).map_err(DriverError::StdIo)?;
+        Ok(Self { path })
+    }
+
+    /// Returns true if hash is present in file. Must be called from blocking context.
+    pub fn contains(&self, hash: &str) -> DriverResult<bool> {
+        let hash = hash.to_uppercase();
+        let file = File::open(&self.path).map_err(DriverError::StdIo)?;
+        let len = file.metadata().map_err(DriverError::StdIo)?.len();
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+
+        // Narrow search to window starting at a line which sorts before hash.
+        let (mut lo, mut hi) = (0, len);
+        while hi - lo > FILE_SEARCH_WINDOW {
+            let mid = lo + (hi - lo) / 2;
+            reader
+                .seek(SeekFrom::Start(mid))
+                .map_err(DriverError::StdIo)?;
+            line.clear();
+            let skip = reader.read_line(&mut line).map_err(DriverError::StdIo)?;
+            line.clear();
+            reader.read_line(&mut line).map_err(DriverError::StdIo)?;
+            match file_line_hash(&line) {
+                Some(x) if x < hash.as_str() => lo = mid + skip as u64,
+                _ => hi = mid,
+            }
+        }
+
+        reader
+            .seek(SeekFrom::Start(lo))
+            .map_err(DriverError::StdIo)?;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).map_err(DriverError::StdIo)? == 0 {
+                return Ok(false);
+            }
+            match file_line_hash(&line) {
+                Some(x) if x < hash.as_str() => continue,
+                Some(x) => return Ok(x == hash),
+                None => continue,
+            }
+        }
+    }
+}
+
+/// Local bloom filter of SHA-1 hashes.
+///
+/// False positives are possible at the rate the filter was built with,
+/// false negatives are not.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PwnedPasswordsBloom {
+    hashes: u32,
+    bits: u64,
+    data: Vec<u8>,
+}
+
+impl PwnedPasswordsBloom {
+    /// Returns empty bloom filter sized for number of items and false positive rate.
+    pub fn new(items: u64, false_positive_rate: f64) -> Self {
+        let items = items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bytes = (-items * false_positive_rate.ln() / (ln2 * ln2 * 8.0))
+            .ceil()
+            .max(1.0);
+        let hashes = ((bytes * 8.0 / items) * ln2).round().max(1.0);
+        Self {
+            hashes: hashes as u32,
+            bits: bytes as u64 * 8,
+            data: vec![0; bytes as usize],
+        }
+    }
+
+    /// Returns bloom filter built from sorted SHA-1 hash file.
+    pub fn from_file<P: AsRef<Path>>(path: P, false_positive_rate: f64) -> DriverResult<Self> {
+        let file = File::open(path.as_ref()).map_err(DriverError::StdIo)?;
+        let items = BufReader::new(file).lines().count() as u64;
+        let mut bloom = Self::new(items, false_positive_rate);
+
+        let file = File::open(path.as_ref()).map_err(DriverError::StdIo)?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(DriverError::StdIo)?;
+            if let Some(hash) = file_line_hash(&line) {
+                bloom.insert(hash)?;
+            }
+        }
+        Ok(bloom)
+    }
+
+    /// Read bloom filter from file.
+    pub fn read<P: AsRef<Path>>(path: P) -> DriverResult<Self> {
+        let file = File::open(path).map_err(DriverError::StdIo)?;
+        let mut reader = BufReader::new(file);
+        let mut header = [0u8; 20];
+        reader.read_exact(&mut header).map_err(DriverError::StdIo)?;
+        if &header[..8] != BLOOM_MAGIC {
+            return Err(DriverError::PwnedPasswordsBloomInvalid);
+        }
+        let mut hashes = [0u8; 4];
+        hashes.copy_from_slice(&header[8..12]);
+        let mut bits = [0u8; 8];
+        bits.copy_from_slice(&header[12..20]);
+        let hashes = u32::from_le_bytes(hashes);
+        let bits = u64::from_le_bytes(bits);
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(DriverError::StdIo)?;
+        if hashes == 0 || bits == 0 || data.len() as u64 * 8 != bits {
+            return Err(DriverError::PwnedPasswordsBloomInvalid);
+        }
+        Ok(Self { hashes, bits, data })
+    }
+
+    /// Write bloom filter to file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> DriverResult<()> {
+        let file = File::create(path).map_err(DriverError::StdIo)?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(BLOOM_MAGIC)
+            .and_then(|_| writer.write_all(&self.hashes.to_le_bytes()))
+            .and_then(|_| writer.write_all(&self.bits.to_le_bytes()))
+            .and_then(|_| writer.write_all(&self.data))
+            .and_then(|_| writer.flush())
+            .map_err(DriverError::StdIo)
+    }
+
+    /// Insert hexadecimal SHA-1 hash into filter.
+    pub fn insert(&mut self, hash: &str) -> DriverResult<()> {
+        for bit in self.bit_indices(hash)? {
+            self.data[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+        Ok(())
+    }
+
+    /// Returns true if hexadecimal SHA-1 hash may be present in filter.
+    pub fn contains(&self, hash: &str) -> DriverResult<bool> {
+        Ok(self
+            .bit_indices(hash)?
+            .iter()
+            .all(|bit| self.data[(*bit / 8) as usize] & (1 << (*bit % 8)) != 0))
+    }
+
+    /// Returns bit indices for hash, SHA-1 output is uniformly distributed so
+    /// it is split into two values for double hashing.
+    fn bit_indices(&self, hash: &str) -> DriverResult<Vec<u64>> {
+        let h1 = hash
+            .get(0..16)
+            .and_then(|x| u64::from_str_radix(x, 16).ok())
+            .ok_or(DriverError::PwnedPasswordsBloomInvalid)?;
+        let h2 = hash
+            .get(16..32)
+            .and_then(|x| u64::from_str_radix(x, 16).ok())
+            .ok_or(DriverError::PwnedPasswordsBloomInvalid)?
+            | 1;
+        Ok((0..self.hashes as u64)
+            .map(|i| h1.wrapping_add(i.wrapping_mul(h2)) % self.bits)
+            .collect())
+    }
+}
+
+/// Returns SHA-1 hash at start of file line, if present.
+fn file_line_hash(line: &str) -> Option<&str> {
+    line.get(0..SHA1_HEX_LEN)
+        .filter(|x| x.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_file(name: &str, passwords: &[String]) -> PathBuf {
+        let mut hashes: Vec<String> = passwords.iter().map(|x| PwnedPasswords::hash(x)).collect();
+        hashes.sort();
+        let path = std::env::temp_dir().join(format!("sso-{}-{}", name, Uuid::new_v4()));
+        let mut file = File::create(&path).unwrap();
+        for (i, hash) in hashes.iter().enumerate() {
+            write!(file, "{}:{}\r\n", hash, i + 1).unwrap();
+        }
+        path
+    }
+
+    fn passwords(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("password{}", i)).collect()
+    }
+
+    #[test]
+    fn pwned_passwords_file_contains() {
+        let passwords = passwords(2000);
+        let path = hash_file("file", &passwords);
+        let file = PwnedPasswordsFile::open(&path).unwrap();
+
+        for password in passwords.iter().step_by(97) {
+            let hash = PwnedPasswords::hash(password);
+            assert!(file.contains(&hash).unwrap());
+            assert!(file.contains(&hash.to_lowercase()).unwrap());
+        }
+        let hash = PwnedPasswords::hash("not-a-pwned-password");
+        assert!(!file.contains(&hash).unwrap());
+        assert!(!file
+            .contains("0000000000000000000000000000000000000000")
+            .unwrap());
+        assert!(!file
+            .contains("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF")
+            .unwrap());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn pwned_passwords_bloom_contains() {
+        let passwords = passwords(1000);
+        let path = hash_file("bloom", &passwords);
+        let bloom = PwnedPasswordsBloom::from_file(&path, 0.001).unwrap();
+
+        let bloom_path = path.with_extension("bloom");
+        bloom.write(&bloom_path).unwrap();
+        let bloom_read = PwnedPasswordsBloom::read(&bloom_path).unwrap();
+        assert_eq!(bloom, bloom_read);
+
+        for password in passwords.iter() {
+            let hash = PwnedPasswords::hash(password);
+            assert!(bloom_read.contains(&hash).unwrap());
+        }
+        let hash = PwnedPasswords::hash("not-a-pwned-password");
+        assert!(!bloom_read.contains(&hash).unwrap());
+        assert!(bloom_read.contains("invalid").is_err());
+        assert!(PwnedPasswordsBloom::read(&path).is_err());
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(bloom_path).unwrap();
+    }
+}
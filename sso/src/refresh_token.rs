@@ -0,0 +1,173 @@
+use crate::{prelude::*, schema::sso_refresh_token};
+use diesel::{prelude::*, PgConnection};
+use libreauth::key::KeyBuilder;
+use std::fmt;
+
+/// Refresh token key size in bytes.
+const REFRESH_TOKEN_KEY_BYTES: usize = 11;
+
+/// Refresh token key.
+///
+/// Refresh tokens created by a login belong to a family, each refresh uses the
+/// presented key and creates a new key in the same family.
+#[derive(Debug, Clone, Identifiable, Queryable)]
+#[table_name = "sso_refresh_token"]
+#[primary_key(key)]
+pub struct RefreshToken {
+    created_at: DateTime<Utc>,
+    key: String,
+    family_id: Uuid,
+    ttl: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    is_revoked: bool,
+    service_id: Uuid,
+    user_id: Uuid,
+}
+
+/// Refresh token create.
+#[derive(Debug, Insertable)]
+#[table_name = "sso_refresh_token"]
+struct RefreshTokenCreate {
+    created_at: DateTime<Utc>,
+    key: String,
+    family_id: Uuid,
+    ttl: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    is_revoked: bool,
+    service_id: Uuid,
+    user_id: Uuid,
+}
+
+impl fmt::Display for RefreshToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RefreshToken {}", self.key)?;
+        write!(f, "\n\tcreated_at {}", self.created_at)?;
+        write!(f, "\n\tfamily_id {}", self.family_id)?;
+        write!(f, "\n\tttl {}", self.ttl)?;
+        if let Some(used_at) = &self.used_at {
+            write!(f, "\n\tused_at {}", used_at)?;
+        }
+        write!(f, "\n\tis_revoked {}", self.is_revoked)?;
+        write!(f, "\n\tservice_id {}", self.service_id)?;
+        write!(f, "\n\tuser_id {}", self.user_id)
+    }
+}
+
+impl RefreshToken {
+    /// Returns reference to key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns family ID.
+    pub fn family_id(&self) -> Uuid {
+        self.family_id
+    }
+
+    /// Generate random refresh token key in family with time to live for service user.
+    pub fn generate(
+        conn: &PgConnection,
+        family_id: Uuid,
+        ttl: Duration,
+        service_id: Uuid,
+        user_id: Uuid,
+    ) -> DriverResult<Self> {
+        Self::delete_by_ttl(conn)?;
+
+        let now = Utc::now();
+        let key = KeyBuilder::new()
+            .size(REFRESH_TOKEN_KEY_BYTES)
+            .generate()
+            .as_base32();
+        diesel::insert_into(sso_refresh_token::table)
+            .values(&RefreshTokenCreate {
+                created_at: now,
+                key,
+                family_id,
+                ttl: now + ttl,
+                used_at: None,
+                is_revoked: false,
+                service_id,
+                user_id,
+            })
+            .get_result::<Self>(conn)
+            .map_err(Into::into)
+    }
+
+    /// Use refresh token key for service user, returns family ID.
+    /// If key was already used, all keys in family are revoked.
+    pub fn use_key(
+        conn: &PgConnection,
+        service_id: Uuid,
+        user_id: Uuid,
+        key: Option<String>,
+    ) -> DriverResult<Uuid> {
+        let key = key.ok_or_else(|| DriverError::CsrfNotFoundOrUsed)?;
+        let now = Utc::now();
+
+        // Conditional update so concurrent requests cannot use a key twice.
+        let used = diesel::update(
+            sso_refresh_token::table
+                .filter(sso_refresh_token::dsl::key.eq(&key))
+                .filter(sso_refresh_token::dsl::service_id.eq(service_id))
+                .filter(sso_refresh_token::dsl::user_id.eq(user_id))
+                .filter(sso_refresh_token::dsl::ttl.gt(now))
+                .filter(sso_refresh_token::dsl::used_at.is_null())
+                .filter(sso_refresh_token::dsl::is_revoked.eq(false)),
+        )
+        .set(sso_refresh_token::dsl::used_at.eq(now))
+        .get_result::<Self>(conn)
+        .optional()
+        .map_err(DriverError::DieselResult)?;
+        if let Some(used) = used {
+            return Ok(used.family_id);
+        }
+
+        let token = Self::read(conn, &key)?
+            .filter(|x| x.ttl > now)
+            .ok_or_else(|| DriverError::CsrfNotFoundOrUsed)?;
+        if token.service_id != service_id || token.user_id != user_id {
+            Err(DriverError::CsrfServiceMismatch)
+        } else if token.is_revoked {
+            Err(DriverError::JwtRefreshTokenRevoked)
+        } else {
+            UserSession::revoke(conn, token.family_id)?;
+            Err(DriverError::JwtRefreshTokenReused(token.family_id))
+        }
+    }
+
+    /// Revoke session and all keys in family of refresh token key.
+    pub fn revoke_key<T: AsRef<str>>(conn: &PgConnection, key: T) -> DriverResult<()> {
+        if let Some(token) = Self::read(conn, key)? {
+            UserSession::revoke(conn, token.family_id)?;
+        }
+        Ok(())
+    }
+
+    fn read<T: AsRef<str>>(conn: &PgConnection, key: T) -> DriverResult<Option<Self>> {
+        sso_refresh_token::table
+            .filter(sso_refresh_token::dsl::key.eq(key.as_ref()))
+            .get_result::<Self>(conn)
+            .optional()
+            .map_err(DriverError::DieselResult)
+    }
+
+    /// Revoke all keys in family.
+    pub fn revoke_family(conn: &PgConnection, family_id: Uuid) -> DriverResult<()> {
+        diesel::update(
+            sso_refresh_token::table.filter(sso_refresh_token::dsl::family_id.eq(family_id)),
+        )
+        .set(sso_refresh_token::dsl::is_revoked.eq(true))
+        .execute(conn)
+        .map_err(Into::into)
+        .map(|_| ())
+    }
+
+    fn delete_by_ttl(conn: &PgConnection) -> DriverResult<()> {
+        let now = Utc::now();
+        diesel::delete(sso_refresh_token::table.filter(sso_refresh_token::dsl::ttl.le(now)))
+            .execute(conn)
+            .map_err(Into::into)
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,753 @@
+//! SAML 2.0 service provider.
+//!
+//! Services are service providers, identity provider metadata is configured per service.
+//! Authentication requests use the HTTP-Redirect binding, responses are received by the
+//! service using the HTTP-POST binding and must contain a single signed assertion.
+use crate::prelude::*;
+use chrono::SecondsFormat;
+use flate2::{write::DeflateEncoder, Compression};
+use openssl::{hash::MessageDigest, memcmp, sign::Verifier, x509::X509};
+use roxmltree::{Document, Node, NodeId};
+use std::{collections::HashMap, fmt, io::Write};
+use url::Url;
+
+const SAML_NS_METADATA: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+const SAML_NS_ASSERTION: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const SAML_NS_PROTOCOL: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const SAML_NS_DSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+const SAML_NS_EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+const SAML_BINDING_REDIRECT: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+const SAML_BINDING_POST: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const SAML_STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const SAML_CM_BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const SAML_TRANSFORM_ENVELOPED: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+
+/// Allowed clock skew between service provider and identity provider in seconds.
+const SAML_CLOCK_SKEW_S: i64 = 60;
+
+/// SAML identity provider metadata.
+#[derive(Clone)]
+pub struct SamlIdpMetadata {
+    pub entity_id: String,
+    pub sso_url: String,
+    certificates: Vec<X509>,
+}
+
+/// SAML authentication request.
+#[derive(Debug)]
+pub struct SamlAuthnRequest {
+    pub id: String,
+    sso_url: String,
+    request: String,
+}
+
+/// SAML assertion, returned after response is validated.
+#[derive(Debug)]
+pub struct SamlAssertion {
+    pub name_id: String,
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+/// SAML response.
+#[derive(Debug)]
+pub struct SamlResponse;
+
+impl fmt::Debug for SamlIdpMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SamlIdpMetadata")
+            .field("entity_id", &self.entity_id)
+            .field("sso_url", &self.sso_url)
+            .field("certificates", &self.certificates.len())
+            .finish()
+    }
+}
+
+impl SamlIdpMetadata {
+    /// Parse identity provider metadata XML, entity must have a HTTP-Redirect binding
+    /// single sign-on service and at least one signing certificate.
+    pub fn parse(xml: &str) -> DriverResult<Self> {
+        let doc = Document::parse(xml).map_err(|_e| DriverError::SamlMetadataInvalid)?;
+        let entity = doc
+            .descendants()
+            .filter(|x| x.has_tag_name((SAML_NS_METADATA, "EntityDescriptor")))
+            .find(|x| child(*x, SAML_NS_METADATA, "IDPSSODescriptor").is_some())
+            .ok_or_else(|| DriverError::SamlMetadataInvalid)?;
+        let entity_id = entity
+            .attribute("entityID")
+            .ok_or_else(|| DriverError::SamlMetadataInvalid)?
+            .to_owned();
+        let idp = child(entity, SAML_NS_METADATA, "IDPSSODescriptor").unwrap();
+
+        let sso_url = children(idp, SAML_NS_METADATA, "SingleSignOnService")
+            .find(|x| x.attribute("Binding") == Some(SAML_BINDING_REDIRECT))
+            .and_then(|x| x.attribute("Location"))
+            .ok_or_else(|| DriverError::SamlMetadataInvalid)?
+            .to_owned();
+        Url::parse(&sso_url).map_err(|_e| DriverError::SamlMetadataInvalid)?;
+
+        let mut certificates = Vec::new();
+        for key in children(idp, SAML_NS_METADATA, "KeyDescriptor") {
+            if key.attribute("use").unwrap_or("signing") != "signing" {
+                continue;
+            }
+            for cert in key
+                .descendants()
+                .filter(|x| x.has_tag_name((SAML_NS_DSIG, "X509Certificate")))
+            {
+                let der = base64_decode(cert.text().unwrap_or(""))
+                    .ok_or_else(|| DriverError::SamlMetadataInvalid)?;
+                certificates.push(X509::from_der(&der).map_err(DriverError::Openssl)?);
+            }
+        }
+        if certificates.is_empty() {
+            return Err(DriverError::SamlMetadataInvalid);
+        }
+
+        Ok(Self {
+            entity_id,
+            sso_url,
+            certificates,
+        })
+    }
+}
+
+impl SamlAuthnRequest {
+    /// Create authentication request for identity provider.
+    ///
+    /// Service provider entity ID is the assertion consumer service URL.
+    pub fn new(
+        metadata: &SamlIdpMetadata,
+        acs_url: &str,
+        now: DateTime<Utc>,
+    ) -> DriverResult<Self> {
+        let id = format!("_{}", Uuid::new_v4().to_simple());
+        let xml = format!(
+            concat!(
+                r#"<samlp:AuthnRequest xmlns:samlp="{}" xmlns:saml="{}" "#,
+                r#"ID="{}" Version="2.0" IssueInstant="{}" Destination="{}" "#,
+                r#"AssertionConsumerServiceURL="{}" ProtocolBinding="{}">"#,
+                r#"<saml:Issuer>{}</saml:Issuer>"#,
+                r#"<samlp:NameIDPolicy AllowCreate="false"/>"#,
+                r#"</samlp:AuthnRequest>"#
+            ),
+            SAML_NS_PROTOCOL,
+            SAML_NS_ASSERTION,
+            id,
+            now.to_rfc3339_opts(SecondsFormat::Secs, true),
+            escape(&metadata.sso_url, true),
+            escape(acs_url, true),
+            SAML_BINDING_POST,
+            escape(acs_url, false),
+        );
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(xml.as_bytes())
+            .map_err(DriverError::StdIo)?;
+        let request = base64::encode(&encoder.finish().map_err(DriverError::StdIo)?);
+        Ok(Self {
+            id,
+            sso_url: metadata.sso_url.clone(),
+            request,
+        })
+    }
+
+    /// Returns identity provider URL using HTTP-Redirect binding with relay state.
+    pub fn url(&self, relay_state: &str) -> DriverResult<String> {
+        let mut url = Url::parse(&self.sso_url).map_err(DriverError::UrlParse)?;
+        url.query_pairs_mut()
+            .append_pair("SAMLRequest", &self.request)
+            .append_pair("RelayState", relay_state);
+        Ok(url.to_string())
+    }
+}
+
+impl SamlAssertion {
+    /// Returns user email address from named attribute, or subject name ID if none.
+    pub fn email(&self, attribute: Option<&str>) -> DriverResult<String> {
+        let email = match attribute {
+            Some(attribute) => self
+                .attributes
+                .get(attribute)
+                .and_then(|x| x.first())
+                .map(|x| x.trim()),
+            None => Some(self.name_id.trim()),
+        };
+        match email {
+            Some(email) if !email.is_empty() => Ok(email.to_owned()),
+            _ => Err(DriverError::SamlEmailNotFound),
+        }
+    }
+}
+
+impl SamlResponse {
+    /// Validate base64 encoded response received by assertion consumer service.
+    ///
+    /// Response must be successful, in response to request ID and contain exactly one
+    /// assertion. Either the assertion or the response must be signed by a certificate
+    /// in identity provider metadata.
+    pub fn validate(
+        response: &str,
+        metadata: &SamlIdpMetadata,
+        acs_url: &str,
+        request_id: &str,
+        now: DateTime<Utc>,
+    ) -> DriverResult<SamlAssertion> {
+        let xml = base64_decode(response).ok_or_else(|| DriverError::SamlResponseInvalid)?;
+        let xml = String::from_utf8(xml).map_err(|_e| DriverError::SamlResponseInvalid)?;
+        let doc = Document::parse(&xml).map_err(|_e| DriverError::SamlResponseInvalid)?;
+
+        let res = doc.root_element();
+        if !res.has_tag_name((SAML_NS_PROTOCOL, "Response")) {
+            return Err(DriverError::SamlResponseInvalid);
+        }
+        if !attribute_opt_eq(res, "Destination", acs_url) {
+            return Err(DriverError::SamlRecipientMismatch);
+        }
+        if !attribute_opt_eq(res, "InResponseTo", request_id) {
+            return Err(DriverError::SamlInResponseToMismatch);
+        }
+        if let Some(issuer) = child(res, SAML_NS_ASSERTION, "Issuer") {
+            if issuer.text().map(|x| x.trim()) != Some(&metadata.entity_id) {
+                return Err(DriverError::SamlIssuerMismatch);
+            }
+        }
+        let status = child(res, SAML_NS_PROTOCOL, "Status")
+            .and_then(|x| child(x, SAML_NS_PROTOCOL, "StatusCode"))
+            .and_then(|x| x.attribute("Value"));
+        if status != Some(SAML_STATUS_SUCCESS) {
+            return Err(DriverError::SamlStatusUnsuccessful);
+        }
+
+        // Exactly one assertion in document, child of response, to prevent signature
+        // wrapping. IDs must be unique so references resolve to a single element.
+        let mut assertions = doc
+            .descendants()
+            .filter(|x| x.has_tag_name((SAML_NS_ASSERTION, "Assertion")));
+        let assertion = match (assertions.next(), assertions.next()) {
+            (Some(assertion), None) if assertion.parent() == Some(res) => assertion,
+            _ => return Err(DriverError::SamlResponseInvalid),
+        };
+        let mut ids = Vec::new();
+        for id in doc.descendants().filter_map(|x| x.attribute("ID")) {
+            if ids.contains(&id) {
+                return Err(DriverError::SamlResponseInvalid);
+            }
+            ids.push(id);
+        }
+
+        let assertion_signed = signature_verify(&xml, assertion, &metadata.certificates)?;
+        let response_signed = signature_verify(&xml, res, &metadata.certificates)?;
+        if !assertion_signed && !response_signed {
+            return Err(DriverError::SamlSignatureInvalid);
+        }
+
+        assertion_validate(assertion, metadata, acs_url, request_id, now)
+    }
+}
+
+/// Validate assertion issuer, conditions and subject, returns name ID and attributes.
+fn assertion_validate(
+    assertion: Node,
+    metadata: &SamlIdpMetadata,
+    acs_url: &str,
+    request_id: &str,
+    now: DateTime<Utc>,
+) -> DriverResult<SamlAssertion> {
+    let issuer = match child(assertion, SAML_NS_ASSERTION, "Issuer") {
+        Some(x) => Some(text(x)?),
+        None => None,
+    };
+    if issuer.map(|x| x.trim()) != Some(&metadata.entity_id) {
+        return Err(DriverError::SamlIssuerMismatch);
+    }
+
+    let conditions = child(assertion, SAML_NS_ASSERTION, "Conditions")
+        .ok_or_else(|| DriverError::SamlResponseInvalid)?;
+    time_validate(conditions, now)?;
+    let mut audience = false;
+    for x in children(conditions, SAML_NS_ASSERTION, "AudienceRestriction")
+        .flat_map(|x| children(x, SAML_NS_ASSERTION, "Audience"))
+    {
+        audience |= text(x)?.trim() == acs_url;
+    }
+    if !audience {
+        return Err(DriverError::SamlAudienceMismatch);
+    }
+
+    let subject = child(assertion, SAML_NS_ASSERTION, "Subject")
+        .ok_or_else(|| DriverError::SamlResponseInvalid)?;
+    let name_id = match child(subject, SAML_NS_ASSERTION, "NameID") {
+        Some(x) => text(x)?.to_owned(),
+        None => String::new(),
+    };
+    let confirmation = children(subject, SAML_NS_ASSERTION, "SubjectConfirmation")
+        .filter(|x| x.attribute("Method") == Some(SAML_CM_BEARER))
+        .filter_map(|x| child(x, SAML_NS_ASSERTION, "SubjectConfirmationData"))
+        .find(|x| x.attribute("Recipient") == Some(acs_url))
+        .ok_or_else(|| DriverError::SamlRecipientMismatch)?;
+    if confirmation.attribute("InResponseTo") != Some(request_id) {
+        return Err(DriverError::SamlInResponseToMismatch);
+    }
+    if confirmation.attribute("NotOnOrAfter").is_none() {
+        return Err(DriverError::SamlResponseInvalid);
+    }
+    time_validate(confirmation, now)?;
+
+    let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+    for statement in children(assertion, SAML_NS_ASSERTION, "AttributeStatement") {
+        for attribute in children(statement, SAML_NS_ASSERTION, "Attribute") {
+            let name = attribute.attribute("Name").unwrap_or("");
+            let values = attributes.entry(name.to_owned()).or_default();
+            for value in children(attribute, SAML_NS_ASSERTION, "AttributeValue") {
+                values.push(text(value)?.to_owned());
+            }
+        }
+    }
+
+    Ok(SamlAssertion {
+        name_id,
+        attributes,
+    })
+}
+
+/// Check `NotBefore` and `NotOnOrAfter` attributes of node if present.
+fn time_validate(node: Node, now: DateTime<Utc>) -> DriverResult<()> {
+    let skew = Duration::seconds(SAML_CLOCK_SKEW_S);
+    if let Some(not_before) = node.attribute("NotBefore") {
+        if now + skew < time_parse(not_before)? {
+            return Err(DriverError::SamlExpired);
+        }
+    }
+    if let Some(not_on_or_after) = node.attribute("NotOnOrAfter") {
+        if now - skew >= time_parse(not_on_or_after)? {
+            return Err(DriverError::SamlExpired);
+        }
+    }
+    Ok(())
+}
+
+fn time_parse(value: &str) -> DriverResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|x| x.with_timezone(&Utc))
+        .map_err(|_e| DriverError::SamlResponseInvalid)
+}
+
+/// Verify enveloped signature of element, returns false if element is not signed.
+///
+/// Signature must reference element ID, only exclusive canonicalisation and RSA with
+/// SHA-256 or SHA-512 are supported.
+fn signature_verify(xml: &str, element: Node, certificates: &[X509]) -> DriverResult<bool> {
+    let signature = match child(element, SAML_NS_DSIG, "Signature") {
+        Some(signature) => signature,
+        None => return Ok(false),
+    };
+    let id = element
+        .attribute("ID")
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+    let signed_info = child(signature, SAML_NS_DSIG, "SignedInfo")
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+
+    let c14n_method = child(signed_info, SAML_NS_DSIG, "CanonicalizationMethod")
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+    let c14n_prefixes = c14n_method_prefixes(c14n_method)?;
+    let signature_digest = child(signed_info, SAML_NS_DSIG, "SignatureMethod")
+        .and_then(|x| x.attribute("Algorithm"))
+        .and_then(signature_method_digest)
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+
+    let mut references = children(signed_info, SAML_NS_DSIG, "Reference");
+    let reference = match (references.next(), references.next()) {
+        (Some(reference), None) => reference,
+        _ => return Err(DriverError::SamlSignatureInvalid),
+    };
+    if reference.attribute("URI") != Some(&format!("#{}", id)) {
+        return Err(DriverError::SamlSignatureInvalid);
+    }
+    let mut reference_prefixes = None;
+    let transforms = child(reference, SAML_NS_DSIG, "Transforms")
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+    for transform in children(transforms, SAML_NS_DSIG, "Transform") {
+        match transform.attribute("Algorithm") {
+            Some(SAML_TRANSFORM_ENVELOPED) => {}
+            Some(SAML_NS_EXC_C14N) => reference_prefixes = Some(c14n_method_prefixes(transform)?),
+            _ => return Err(DriverError::SamlSignatureInvalid),
+        }
+    }
+    let reference_prefixes = reference_prefixes.ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+    let reference_digest = child(reference, SAML_NS_DSIG, "DigestMethod")
+        .and_then(|x| x.attribute("Algorithm"))
+        .and_then(digest_method)
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+    let digest_value = child(reference, SAML_NS_DSIG, "DigestValue")
+        .and_then(|x| x.text())
+        .and_then(base64_decode)
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+
+    // Digest of element with signature removed must match reference.
+    let element_c14n = c14n(xml, element, Some(signature.id()), &reference_prefixes);
+    let digest = openssl::hash::hash(reference_digest, element_c14n.as_bytes())
+        .map_err(DriverError::Openssl)?;
+    if digest.len() != digest_value.len() || !memcmp::eq(&digest, &digest_value) {
+        return Err(DriverError::SamlSignatureInvalid);
+    }
+
+    // Signature of signed info must be verified by an identity provider certificate.
+    let signature_value = child(signature, SAML_NS_DSIG, "SignatureValue")
+        .and_then(|x| x.text())
+        .and_then(base64_decode)
+        .ok_or_else(|| DriverError::SamlSignatureInvalid)?;
+    let signed_info_c14n = c14n(xml, signed_info, None, &c14n_prefixes);
+    for certificate in certificates {
+        let public_key = certificate.public_key().map_err(DriverError::Openssl)?;
+        let mut verifier =
+            Verifier::new(signature_digest, &public_key).map_err(DriverError::Openssl)?;
+        verifier
+            .update(signed_info_c14n.as_bytes())
+            .map_err(DriverError::Openssl)?;
+        if verifier.verify(&signature_value).unwrap_or(false) {
+            return Ok(true);
+        }
+    }
+    Err(DriverError::SamlSignatureInvalid)
+}
+
+/// Returns inclusive namespace prefixes of exclusive canonicalisation method node.
+fn c14n_method_prefixes(method: Node) -> DriverResult<Vec<String>> {
+    if method.attribute("Algorithm") != Some(SAML_NS_EXC_C14N) {
+        return Err(DriverError::SamlSignatureInvalid);
+    }
+    let prefixes = method
+        .children()
+        .find(|x| x.has_tag_name((SAML_NS_EXC_C14N, "InclusiveNamespaces")))
+        .and_then(|x| x.attribute("PrefixList"))
+        .unwrap_or("");
+    Ok(prefixes
+        .split_whitespace()
+        .map(|x| if x == "#default" { "" } else { x }.to_owned())
+        .collect())
+}
+
+fn signature_method_digest(algorithm: &str) -> Option<MessageDigest> {
+    match algorithm {
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Some(MessageDigest::sha256()),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => Some(MessageDigest::sha512()),
+        _ => None,
+    }
+}
+
+fn digest_method(algorithm: &str) -> Option<MessageDigest> {
+    match algorithm {
+        "http://www.w3.org/2001/04/xmlenc#sha256" => Some(MessageDigest::sha256()),
+        "http://www.w3.org/2001/04/xmlenc#sha512" => Some(MessageDigest::sha512()),
+        _ => None,
+    }
+}
+
+/// Exclusive XML canonicalisation without comments of element, optionally excluding a
+/// descendant element (enveloped signature transform).
+fn c14n(xml: &str, element: Node, exclude: Option<NodeId>, prefixes: &[String]) -> String {
+    let mut out = String::new();
+    c14n_element(xml, element, exclude, prefixes, &[], &mut out);
+    out
+}
+
+fn c14n_element(
+    xml: &str,
+    element: Node,
+    exclude: Option<NodeId>,
+    prefixes: &[String],
+    rendered: &[(String, String)],
+    out: &mut String,
+) {
+    let name = source_name(xml, element.range().start + 1);
+    let attributes: Vec<(&str, &roxmltree::Attribute)> = element
+        .attributes()
+        .iter()
+        .map(|x| (source_name(xml, x.range().start), x))
+        .collect();
+
+    // Namespaces visibly utilised by element and attributes, or in inclusive prefix list.
+    let mut utilised: Vec<&str> = vec![name_prefix(name)];
+    for (name, _) in &attributes {
+        let prefix = name_prefix(name);
+        if !prefix.is_empty() {
+            utilised.push(prefix);
+        }
+    }
+    utilised.extend(prefixes.iter().map(|x| &**x));
+    utilised.sort_unstable();
+    utilised.dedup();
+
+    let mut rendered = rendered.to_vec();
+    let mut namespaces = Vec::new();
+    for prefix in utilised {
+        if prefix == "xml" {
+            continue;
+        }
+        let uri = element
+            .namespaces()
+            .iter()
+            .find(|x| x.name().unwrap_or("") == prefix)
+            .map(|x| x.uri())
+            .unwrap_or("");
+        let previous = rendered
+            .iter()
+            .rev()
+            .find(|(x, _)| x == prefix)
+            .map(|(_, x)| &**x)
+            .unwrap_or("");
+        if uri != previous {
+            namespaces.push((prefix, uri));
+            rendered.push((prefix.to_owned(), uri.to_owned()));
+        }
+    }
+
+    let mut attributes: Vec<(&str, &str, &str, &str)> = attributes
+        .iter()
+        .map(|(name, x)| (x.namespace().unwrap_or(""), x.name(), *name, x.value()))
+        .collect();
+    attributes.sort_unstable();
+
+    out.push('<');
+    out.push_str(name);
+    for (prefix, uri) in namespaces {
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        out.push_str(&escape(uri, true));
+        out.push('"');
+    }
+    for (_, _, name, value) in attributes {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        out.push_str(&escape(value, true));
+        out.push('"');
+    }
+    out.push('>');
+    for node in element.children() {
+        if node.is_element() {
+            if Some(node.id()) != exclude {
+                c14n_element(xml, node, exclude, prefixes, &rendered, out);
+            }
+        } else if node.is_text() {
+            out.push_str(&escape(node.text().unwrap_or(""), false));
+        } else if let Some(pi) = node.pi() {
+            out.push_str("<?");
+            out.push_str(pi.target);
+            if let Some(value) = pi.value {
+                out.push(' ');
+                out.push_str(value);
+            }
+            out.push_str("?>");
+        }
+    }
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+/// Returns qualified name in source document starting at position.
+fn source_name(xml: &str, start: usize) -> &str {
+    let name = &xml[start..];
+    let end = name
+        .find(|c: char| c.is_whitespace() || c == '=' || c == '/' || c == '>')
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+fn name_prefix(name: &str) -> &str {
+    match name.find(':') {
+        Some(i) => &name[..i],
+        None => "",
+    }
+}
+
+/// Escape text or attribute value for canonical XML.
+fn escape(value: &str, attribute: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' if !attribute => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            '\t' if attribute => out.push_str("&#x9;"),
+            '\n' if attribute => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn base64_decode(value: &str) -> Option<Vec<u8>> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    base64::decode(&value).ok()
+}
+
+fn attribute_opt_eq(node: Node, name: &str, value: &str) -> bool {
+    node.attribute(name).map(|x| x == value).unwrap_or(true)
+}
+
+/// Returns text content of node, content other than a single text node is rejected.
+///
+/// Canonicalisation excludes comments, text split by a comment would be truncated
+/// by `Node::text` without invalidating the signature.
+fn text<'a>(node: Node<'a, '_>) -> DriverResult<&'a str> {
+    let mut children = node.children();
+    match (children.next(), children.next()) {
+        (None, _) => Ok(""),
+        (Some(x), None) if x.is_text() => Ok(x.text().unwrap_or("")),
+        _ => Err(DriverError::SamlResponseInvalid),
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, ns: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|x| x.has_tag_name((ns, name)))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    ns: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |x| x.has_tag_name((ns, name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = include_str!("../tests/saml/idp_metadata.xml");
+    const METADATA_OTHER: &str = include_str!("../tests/saml/idp_metadata_other.xml");
+    const RESPONSE: &str = include_str!("../tests/saml/response.xml");
+    const ACS_URL: &str = "http://localhost/saml/acs";
+    const REQUEST_ID: &str = "_request";
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2021-05-29T12:01:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn validate(response: &str, metadata: &str) -> DriverResult<SamlAssertion> {
+        let metadata = SamlIdpMetadata::parse(metadata).unwrap();
+        SamlResponse::validate(
+            &base64::encode(response),
+            &metadata,
+            ACS_URL,
+            REQUEST_ID,
+            now(),
+        )
+    }
+
+    #[test]
+    fn saml_idp_metadata_parse() {
+        let metadata = SamlIdpMetadata::parse(METADATA).unwrap();
+        assert_eq!(metadata.entity_id, "http://localhost/idp");
+        assert_eq!(metadata.sso_url, "http://localhost/idp/sso");
+        assert_eq!(metadata.certificates.len(), 1);
+        assert!(SamlIdpMetadata::parse("<md:EntityDescriptor/>").is_err());
+    }
+
+    #[test]
+    fn saml_authn_request_new() {
+        let metadata = SamlIdpMetadata::parse(METADATA).unwrap();
+        let request = SamlAuthnRequest::new(&metadata, ACS_URL, now()).unwrap();
+        assert!(request.id.starts_with('_'));
+        let url = Url::parse(&request.url("state").unwrap()).unwrap();
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert!(query.contains_key("SAMLRequest"));
+        assert_eq!(query.get("RelayState").unwrap(), "state");
+    }
+
+    #[test]
+    fn saml_response_validate_ok() {
+        let assertion = validate(RESPONSE, METADATA).unwrap();
+        assert_eq!(assertion.email(None).unwrap(), "user&1@example.com");
+        assert_eq!(assertion.email(Some("mail")).unwrap(), "user@example.com");
+        assert!(assertion.email(Some("upn")).is_err());
+    }
+
+    #[test]
+    fn saml_response_validate_signature_invalid() {
+        let res = validate(RESPONSE, METADATA_OTHER).unwrap_err();
+        assert!(matches!(res, DriverError::SamlSignatureInvalid));
+
+        let response = RESPONSE.replace(
+            "<saml:AttributeValue>user@example.com",
+            "<saml:AttributeValue>admin@example.com",
+        );
+        let res = validate(&response, METADATA).unwrap_err();
+        assert!(matches!(res, DriverError::SamlSignatureInvalid));
+
+        let start = RESPONSE.find("<ds:Signature").unwrap();
+        let end = RESPONSE.find("</ds:Signature>").unwrap() + "</ds:Signature>".len();
+        let response = format!("{}{}", &RESPONSE[..start], &RESPONSE[end..]);
+        let res = validate(&response, METADATA).unwrap_err();
+        assert!(matches!(res, DriverError::SamlSignatureInvalid));
+    }
+
+    #[test]
+    fn saml_response_validate_comment_text_invalid() {
+        let response = RESPONSE.replace(
+            "<saml:AttributeValue>user@example.com",
+            "<saml:AttributeValue>user@exa<!---->mple.com",
+        );
+        assert_ne!(response, RESPONSE);
+        let res = validate(&response, METADATA).unwrap_err();
+        assert!(matches!(res, DriverError::SamlResponseInvalid));
+
+        let response = RESPONSE.replace("</saml:NameID>", "<!----></saml:NameID>");
+        assert_ne!(response, RESPONSE);
+        let res = validate(&response, METADATA).unwrap_err();
+        assert!(matches!(res, DriverError::SamlResponseInvalid));
+    }
+
+    #[test]
+    fn saml_response_validate_wrapped_assertion_invalid() {
+        let response = RESPONSE.replace(
+            "<samlp:Status>",
+            concat!(
+                r#"<saml:Assertion ID="_wrapped" Version="2.0"><saml:Issuer>http://localhost/idp</saml:Issuer>"#,
+                r#"</saml:Assertion><samlp:Status>"#
+            ),
+        );
+        let res = validate(&response, METADATA).unwrap_err();
+        assert!(matches!(res, DriverError::SamlResponseInvalid));
+    }
+
+    #[test]
+    fn saml_response_validate_request_expired_mismatch() {
+        let metadata = SamlIdpMetadata::parse(METADATA).unwrap();
+        let response = base64::encode(RESPONSE);
+        let res = SamlResponse::validate(&response, &metadata, ACS_URL, "_other", now());
+        assert!(matches!(
+            res.unwrap_err(),
+            DriverError::SamlInResponseToMismatch
+        ));
+
+        let res = SamlResponse::validate(
+            &response,
+            &metadata,
+            "http://localhost/other",
+            REQUEST_ID,
+            now(),
+        );
+        assert!(matches!(
+            res.unwrap_err(),
+            DriverError::SamlRecipientMismatch
+        ));
+
+        let expired = now() + Duration::minutes(10);
+        let res = SamlResponse::validate(&response, &metadata, ACS_URL, REQUEST_ID, expired);
+        assert!(matches!(res.unwrap_err(), DriverError::SamlExpired));
+    }
+}
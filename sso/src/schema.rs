@@ -28,6 +28,27 @@ table! {
     }
 }
 
+table! {
+    sso_jwt_denylist (jti) {
+        created_at -> Timestamptz,
+        jti -> Varchar,
+        exp -> Timestamptz,
+        service_id -> Uuid,
+        user_id -> Uuid,
+    }
+}
+
+table! {
+    sso_jwt_key (id) {
+        created_at -> Timestamptz,
+        id -> Uuid,
+        algorithm -> Varchar,
+        private_key -> Varchar,
+        public_key -> Varchar,
+        expires_at -> Nullable<Timestamptz>,
+    }
+}
+
 table! {
     sso_key (id) {
         created_at -> Timestamptz,
@@ -41,6 +62,93 @@ table! {
         value -> Varchar,
         service_id -> Nullable<Uuid>,
         user_id -> Nullable<Uuid>,
+        value_prefix -> Nullable<Varchar>,
+        expires_at -> Nullable<Timestamptz>,
+        scopes -> Array<Varchar>,
+        last_used_at -> Nullable<Timestamptz>,
+        last_used_remote -> Nullable<Varchar>,
+        role -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    sso_key_data_key (id) {
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        id -> Uuid,
+        wrapped -> Varchar,
+    }
+}
+
+table! {
+    sso_key_totp (key_id) {
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        key_id -> Uuid,
+        period -> Int4,
+        digits -> Int4,
+        algorithm -> Varchar,
+        skew -> Int4,
+        last_step -> Nullable<Int8>,
+    }
+}
+
+table! {
+    sso_key_webauthn (key_id) {
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        key_id -> Uuid,
+        credential -> Jsonb,
+        counter -> Int8,
+        aaguid -> Uuid,
+    }
+}
+
+table! {
+    sso_login_lockout (key) {
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        key -> Varchar,
+        failure_count -> Int4,
+        locked_until -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    sso_oauth2_provider (id) {
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        id -> Uuid,
+        is_enabled -> Bool,
+        name -> Varchar,
+        discovery_url -> Varchar,
+        client_id -> Varchar,
+        client_secret -> Varchar,
+        scopes -> Array<Varchar>,
+        email_claim -> Varchar,
+        trust_email -> Bool,
+    }
+}
+
+table! {
+    sso_refresh_token (key) {
+        created_at -> Timestamptz,
+        key -> Varchar,
+        family_id -> Uuid,
+        ttl -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        is_revoked -> Bool,
+        service_id -> Uuid,
+        user_id -> Uuid,
+    }
+}
+
+table! {
+    sso_role (name) {
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        name -> Varchar,
+        permissions -> Array<Varchar>,
     }
 }
 
@@ -57,6 +165,26 @@ table! {
         provider_local_url -> Nullable<Varchar>,
         provider_github_oauth2_url -> Nullable<Varchar>,
         provider_microsoft_oauth2_url -> Nullable<Varchar>,
+        oidc_redirect_uris -> Array<Varchar>,
+        jwt_algorithm -> Varchar,
+        provider_oauth2_url -> Nullable<Varchar>,
+        provider_saml_url -> Nullable<Varchar>,
+        saml_idp_metadata -> Nullable<Varchar>,
+        saml_email_attribute -> Nullable<Varchar>,
+        mfa_policy -> Varchar,
+        password_min_strength -> Int4,
+        password_reject_pwned -> Bool,
+        password_history -> Int4,
+        password_max_age_days -> Int4,
+    }
+}
+
+table! {
+    sso_totp_recovery (hash) {
+        created_at -> Timestamptz,
+        hash -> Varchar,
+        used_at -> Nullable<Timestamptz>,
+        key_id -> Uuid,
     }
 }
 
@@ -73,13 +201,68 @@ table! {
         password_allow_reset -> Bool,
         password_require_update -> Bool,
         password_hash -> Nullable<Varchar>,
+        phone -> Nullable<Varchar>,
+        password_updated_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    sso_user_password_history (id) {
+        created_at -> Timestamptz,
+        id -> Uuid,
+        user_id -> Uuid,
+        password_hash -> Varchar,
+    }
+}
+
+table! {
+    sso_user_session (id) {
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        id -> Uuid,
+        user_agent -> Varchar,
+        remote -> Varchar,
+        forwarded -> Nullable<Varchar>,
+        expires_at -> Timestamptz,
+        is_revoked -> Bool,
+        service_id -> Uuid,
+        user_id -> Uuid,
     }
 }
 
 joinable!(sso_audit -> sso_service (service_id));
 joinable!(sso_audit -> sso_user (user_id));
 joinable!(sso_csrf -> sso_service (service_id));
+joinable!(sso_jwt_denylist -> sso_service (service_id));
+joinable!(sso_jwt_denylist -> sso_user (user_id));
+joinable!(sso_key -> sso_role (role));
 joinable!(sso_key -> sso_service (service_id));
 joinable!(sso_key -> sso_user (user_id));
+joinable!(sso_key_totp -> sso_key (key_id));
+joinable!(sso_key_webauthn -> sso_key (key_id));
+joinable!(sso_refresh_token -> sso_service (service_id));
+joinable!(sso_refresh_token -> sso_user (user_id));
+joinable!(sso_totp_recovery -> sso_key (key_id));
+joinable!(sso_user_password_history -> sso_user (user_id));
+joinable!(sso_user_session -> sso_service (service_id));
+joinable!(sso_user_session -> sso_user (user_id));
 
-allow_tables_to_appear_in_same_query!(sso_audit, sso_csrf, sso_key, sso_service, sso_user,);
+allow_tables_to_appear_in_same_query!(
+    sso_audit,
+    sso_csrf,
+    sso_jwt_denylist,
+    sso_jwt_key,
+    sso_key,
+    sso_key_data_key,
+    sso_key_totp,
+    sso_key_webauthn,
+    sso_login_lockout,
+    sso_oauth2_provider,
+    sso_refresh_token,
+    sso_role,
+    sso_service,
+    sso_totp_recovery,
+    sso_user,
+    sso_user_password_history,
+    sso_user_session,
+);
@@ -0,0 +1,77 @@
+use crate::{prelude::*, schema::sso_totp_recovery};
+use diesel::{prelude::*, PgConnection};
+use libreauth::key::KeyBuilder;
+use openssl::sha::sha256;
+
+/// TOTP recovery codes generated per key.
+pub const TOTP_RECOVERY_CODES: usize = 10;
+
+/// TOTP recovery code size in bytes.
+pub const BYTES_TOTP_RECOVERY_CODE: usize = 10;
+
+/// TOTP recovery code.
+///
+/// Single use codes generated when TOTP key is registered, usable if
+/// the user has lost their device. Only a hash of each code is stored.
+#[derive(Debug, Insertable)]
+#[table_name = "sso_totp_recovery"]
+pub struct TotpRecovery {
+    created_at: DateTime<Utc>,
+    hash: String,
+    used_at: Option<DateTime<Utc>>,
+    key_id: Uuid,
+}
+
+impl TotpRecovery {
+    /// Generate recovery codes for TOTP key, replacing any existing codes.
+    pub fn generate(conn: &PgConnection, key_id: Uuid) -> DriverResult<Vec<String>> {
+        diesel::delete(sso_totp_recovery::table.filter(sso_totp_recovery::dsl::key_id.eq(key_id)))
+            .execute(conn)?;
+
+        let now = Utc::now();
+        let codes: Vec<String> = (0..TOTP_RECOVERY_CODES)
+            .map(|_| {
+                KeyBuilder::new()
+                    .size(BYTES_TOTP_RECOVERY_CODE)
+                    .generate()
+                    .as_base32()
+            })
+            .collect();
+        let values: Vec<Self> = codes
+            .iter()
+            .map(|code| Self {
+                created_at: now,
+                hash: Self::hash(code),
+                used_at: None,
+                key_id,
+            })
+            .collect();
+        diesel::insert_into(sso_totp_recovery::table)
+            .values(&values)
+            .execute(conn)?;
+        Ok(codes)
+    }
+
+    /// Use recovery code for TOTP key, returns error if code is invalid or used.
+    pub fn use_code(conn: &PgConnection, key_id: Uuid, code: &str) -> DriverResult<()> {
+        let now = Utc::now();
+        let count = diesel::update(
+            sso_totp_recovery::table
+                .filter(sso_totp_recovery::dsl::hash.eq(Self::hash(code)))
+                .filter(sso_totp_recovery::dsl::key_id.eq(key_id))
+                .filter(sso_totp_recovery::dsl::used_at.is_null()),
+        )
+        .set(sso_totp_recovery::dsl::used_at.eq(now))
+        .execute(conn)?;
+        if count == 1 {
+            Ok(())
+        } else {
+            Err(DriverError::TotpRecoveryInvalid)
+        }
+    }
+
+    fn hash(code: &str) -> String {
+        let code = code.trim().to_uppercase();
+        base64::encode(sha256(code.as_bytes()))
+    }
+}
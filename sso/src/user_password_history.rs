@@ -0,0 +1,71 @@
+use crate::{prelude::*, schema::sso_user_password_history};
+use diesel::{prelude::*, PgConnection};
+
+/// User password history.
+///
+/// Previous password hashes of user, written when user password is updated
+/// and used to reject reuse of previous passwords.
+#[derive(Debug, Insertable, Queryable)]
+#[table_name = "sso_user_password_history"]
+pub struct UserPasswordHistory {
+    created_at: DateTime<Utc>,
+    id: Uuid,
+    user_id: Uuid,
+    password_hash: String,
+}
+
+impl UserPasswordHistory {
+    /// Create user password history entry for previous password hash.
+    ///
+    /// Entries older than the maximum password history are deleted.
+    pub fn create(conn: &PgConnection, user_id: Uuid, password_hash: &str) -> DriverResult<()> {
+        let value = Self {
+            created_at: Utc::now(),
+            id: Uuid::new_v4(),
+            user_id,
+            password_hash: password_hash.to_owned(),
+        };
+        diesel::insert_into(sso_user_password_history::table)
+            .values(&value)
+            .execute(conn)?;
+
+        let keep = Self::list(conn, user_id, MAX_USER_PASSWORD_HISTORY)?
+            .into_iter()
+            .map(|x| x.id)
+            .collect::<Vec<_>>();
+        diesel::delete(
+            sso_user_password_history::table
+                .filter(sso_user_password_history::dsl::user_id.eq(user_id))
+                .filter(sso_user_password_history::dsl::id.ne_all(keep)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns true if password matches any of the last history entries of user.
+    pub fn reused(
+        conn: &PgConnection,
+        user_id: Uuid,
+        password: &str,
+        history: i32,
+    ) -> DriverResult<bool> {
+        if history <= 0 {
+            return Ok(false);
+        }
+        for entry in Self::list(conn, user_id, history)? {
+            if password_hash_verify(&entry.password_hash, password)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn list(conn: &PgConnection, user_id: Uuid, limit: i32) -> DriverResult<Vec<Self>> {
+        sso_user_password_history::table
+            .filter(sso_user_password_history::dsl::user_id.eq(user_id))
+            .order(sso_user_password_history::dsl::created_at.desc())
+            .limit(i64::from(limit))
+            .load::<Self>(conn)
+            .map_err(Into::into)
+    }
+}
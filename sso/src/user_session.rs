@@ -0,0 +1,186 @@
+use crate::{prelude::*, schema::sso_user_session};
+use diesel::{dsl::exists, prelude::*, PgConnection};
+use std::fmt;
+
+/// User session.
+///
+/// Sessions are created by login and updated by refresh, session ID is the
+/// refresh token family ID.
+#[derive(Debug, Clone, Identifiable, Queryable)]
+#[table_name = "sso_user_session"]
+pub struct UserSession {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    id: Uuid,
+    user_agent: String,
+    remote: String,
+    forwarded: Option<String>,
+    expires_at: DateTime<Utc>,
+    is_revoked: bool,
+    service_id: Uuid,
+    user_id: Uuid,
+}
+
+/// User session create.
+#[derive(Debug, Insertable)]
+#[table_name = "sso_user_session"]
+struct UserSessionCreate {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    id: Uuid,
+    user_agent: String,
+    remote: String,
+    forwarded: Option<String>,
+    expires_at: DateTime<Utc>,
+    is_revoked: bool,
+    service_id: Uuid,
+    user_id: Uuid,
+}
+
+impl fmt::Display for UserSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UserSession {}", self.id)?;
+        write!(f, "\n\tcreated_at {}", self.created_at)?;
+        write!(f, "\n\tupdated_at {}", self.updated_at)?;
+        write!(f, "\n\tuser_agent {}", self.user_agent)?;
+        write!(f, "\n\tremote {}", self.remote)?;
+        if let Some(forwarded) = &self.forwarded {
+            write!(f, "\n\tforwarded {}", forwarded)?;
+        }
+        write!(f, "\n\texpires_at {}", self.expires_at)?;
+        write!(f, "\n\tis_revoked {}", self.is_revoked)?;
+        write!(f, "\n\tservice_id {}", self.service_id)?;
+        write!(f, "\n\tuser_id {}", self.user_id)
+    }
+}
+
+impl AuditSubject for UserSession {
+    fn subject(&self) -> String {
+        format!("{}", self.id)
+    }
+}
+
+impl From<UserSession> for pb::UserSession {
+    fn from(r: UserSession) -> Self {
+        Self {
+            created_at: pb::datetime_to_timestamp_opt(r.created_at),
+            updated_at: pb::datetime_to_timestamp_opt(r.updated_at),
+            id: pb::uuid_to_string(r.id),
+            user_agent: r.user_agent,
+            remote: r.remote,
+            forwarded: r.forwarded,
+            expires_at: pb::datetime_to_timestamp_opt(r.expires_at),
+            service_id: pb::uuid_to_string(r.service_id),
+            user_id: pb::uuid_to_string(r.user_id),
+        }
+    }
+}
+
+impl UserSession {
+    /// Returns ID.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Create or update session for service user with request metadata.
+    /// Session is kept until expiry time, unless updated by a refresh.
+    pub fn upsert(
+        conn: &PgConnection,
+        meta: &AuditMeta,
+        id: Uuid,
+        expires: Duration,
+        service_id: Uuid,
+        user_id: Uuid,
+    ) -> DriverResult<Self> {
+        Self::delete_by_expires(conn)?;
+
+        let now = Utc::now();
+        let value = UserSessionCreate {
+            created_at: now,
+            updated_at: now,
+            id,
+            user_agent: meta.user_agent().to_owned(),
+            remote: meta.remote().to_owned(),
+            forwarded: meta.forwarded().map(|x| x.to_owned()),
+            expires_at: now + expires,
+            is_revoked: false,
+            service_id,
+            user_id,
+        };
+        diesel::insert_into(sso_user_session::table)
+            .values(&value)
+            .on_conflict(sso_user_session::dsl::id)
+            .do_update()
+            .set((
+                sso_user_session::dsl::updated_at.eq(value.updated_at),
+                sso_user_session::dsl::user_agent.eq(&value.user_agent),
+                sso_user_session::dsl::remote.eq(&value.remote),
+                sso_user_session::dsl::forwarded.eq(&value.forwarded),
+                sso_user_session::dsl::expires_at.eq(value.expires_at),
+            ))
+            .get_result::<Self>(conn)
+            .map_err(Into::into)
+    }
+
+    /// List active sessions of user, optionally masked by service.
+    pub fn list(
+        conn: &PgConnection,
+        user_id: Uuid,
+        service_id: Option<Uuid>,
+    ) -> DriverResult<Vec<Self>> {
+        let now = Utc::now();
+        let mut query = sso_user_session::table
+            .filter(sso_user_session::dsl::user_id.eq(user_id))
+            .filter(sso_user_session::dsl::expires_at.gt(now))
+            .filter(sso_user_session::dsl::is_revoked.eq(false))
+            .into_boxed();
+        if let Some(service_id) = service_id {
+            query = query.filter(sso_user_session::dsl::service_id.eq(service_id));
+        }
+        query
+            .order(sso_user_session::dsl::updated_at.desc())
+            .load::<Self>(conn)
+            .map_err(Into::into)
+    }
+
+    /// Read active session of user by ID, optionally masked by service.
+    pub fn read(
+        conn: &PgConnection,
+        id: Uuid,
+        user_id: Uuid,
+        service_id: Option<Uuid>,
+    ) -> DriverResult<Option<Self>> {
+        let session = Self::list(conn, user_id, service_id)?
+            .into_iter()
+            .find(|x| x.id == id);
+        Ok(session)
+    }
+
+    /// Revoke session, also revokes refresh token family.
+    pub fn revoke(conn: &PgConnection, id: Uuid) -> DriverResult<()> {
+        diesel::update(sso_user_session::table.filter(sso_user_session::dsl::id.eq(id)))
+            .set(sso_user_session::dsl::is_revoked.eq(true))
+            .execute(conn)
+            .map_err(DriverError::DieselResult)?;
+        RefreshToken::revoke_family(conn, id)
+    }
+
+    /// Returns true if session is revoked.
+    pub fn is_revoked(conn: &PgConnection, id: Uuid) -> DriverResult<bool> {
+        diesel::select(exists(
+            sso_user_session::table
+                .filter(sso_user_session::dsl::id.eq(id))
+                .filter(sso_user_session::dsl::is_revoked.eq(true)),
+        ))
+        .get_result::<bool>(conn)
+        .map_err(Into::into)
+    }
+
+    fn delete_by_expires(conn: &PgConnection) -> DriverResult<()> {
+        let now = Utc::now();
+        diesel::delete(sso_user_session::table.filter(sso_user_session::dsl::expires_at.le(now)))
+            .execute(conn)
+            .map_err(Into::into)
+            .map(|_| ())
+    }
+}
@@ -26,6 +26,12 @@ pub fn url_opt(errors: &mut ValidationErrors, field: &'static str, value: Option
     }
 }
 
+pub fn url_vec(errors: &mut ValidationErrors, field: &'static str, value: &[String]) {
+    for v in value {
+        url(errors, field, v);
+    }
+}
+
 pub fn password(errors: &mut ValidationErrors, field: &'static str, value: &str) {
     if value.len() < MIN_USER_PASSWORD || value.len() > MAX_USER_PASSWORD {
         errors.add(field, ValidationError::new("password_invalid"));
@@ -38,6 +44,42 @@ pub fn password_opt(errors: &mut ValidationErrors, field: &'static str, value: O
     }
 }
 
+pub fn password_min_strength_opt(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    value: Option<u32>,
+) {
+    if let Some(value) = value {
+        if value > MAX_USER_PASSWORD_STRENGTH as u32 {
+            errors.add(field, ValidationError::new("password_min_strength_invalid"));
+        }
+    }
+}
+
+pub fn password_history_opt(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    value: Option<u32>,
+) {
+    if let Some(value) = value {
+        if value > MAX_USER_PASSWORD_HISTORY as u32 {
+            errors.add(field, ValidationError::new("password_history_invalid"));
+        }
+    }
+}
+
+pub fn password_max_age_days_opt(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    value: Option<u32>,
+) {
+    if let Some(value) = value {
+        if value > MAX_USER_PASSWORD_MAX_AGE_DAYS as u32 {
+            errors.add(field, ValidationError::new("password_max_age_days_invalid"));
+        }
+    }
+}
+
 pub fn name(errors: &mut ValidationErrors, field: &'static str, value: &str) {
     if value.is_empty() || value.len() > MAX_NAME {
         errors.add(field, ValidationError::new("name_invalid"));
@@ -76,6 +118,23 @@ pub fn timezone(errors: &mut ValidationErrors, field: &'static str, value: &str)
     }
 }
 
+pub fn phone(errors: &mut ValidationErrors, field: &'static str, value: &str) {
+    let digits = value.strip_prefix('+').unwrap_or("");
+    if digits.len() < 7
+        || value.len() > MAX_USER_PHONE
+        || digits.starts_with('0')
+        || !digits.chars().all(|c| c.is_ascii_digit())
+    {
+        errors.add(field, ValidationError::new("phone_invalid"));
+    }
+}
+
+pub fn phone_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<&str>) {
+    if let Some(value) = value {
+        phone(errors, field, value);
+    }
+}
+
 pub fn timezone_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<&str>) {
     if let Some(value) = value {
         timezone(errors, field, value);
@@ -173,6 +232,30 @@ pub fn key_type_vec(errors: &mut ValidationErrors, field: &'static str, value: &
     }
 }
 
+pub fn jwt_algorithm(errors: &mut ValidationErrors, field: &'static str, value: &str) {
+    if JwtAlgorithm::from_str(value).is_err() {
+        errors.add(field, ValidationError::new("jwt_algorithm_invalid"));
+    }
+}
+
+pub fn jwt_algorithm_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<&str>) {
+    if let Some(value) = value {
+        jwt_algorithm(errors, field, value);
+    }
+}
+
+pub fn mfa_policy(errors: &mut ValidationErrors, field: &'static str, value: &str) {
+    if ServiceMfaPolicy::from_str(value).is_err() {
+        errors.add(field, ValidationError::new("mfa_policy_invalid"));
+    }
+}
+
+pub fn mfa_policy_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<&str>) {
+    if let Some(value) = value {
+        mfa_policy(errors, field, value);
+    }
+}
+
 pub fn text(errors: &mut ValidationErrors, field: &'static str, value: &str) {
     if value.len() > MAX_TEXT {
         errors.add(field, ValidationError::new("text_invalid"));
@@ -191,12 +274,125 @@ pub fn totp(errors: &mut ValidationErrors, field: &'static str, value: &str) {
     }
 }
 
+pub fn totp_period_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<u32>) {
+    if let Some(value) = value {
+        if value < 1 || value > MAX_TOTP_PERIOD {
+            errors.add(field, ValidationError::new("totp_period_invalid"));
+        }
+    }
+}
+
+pub fn totp_digits_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<u32>) {
+    if let Some(value) = value {
+        if value < 6 || value > 8 {
+            errors.add(field, ValidationError::new("totp_digits_invalid"));
+        }
+    }
+}
+
+pub fn totp_algorithm_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<&str>) {
+    if let Some(value) = value {
+        if TotpAlgorithm::from_str(value).is_err() {
+            errors.add(field, ValidationError::new("totp_algorithm_invalid"));
+        }
+    }
+}
+
+pub fn totp_skew_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<u32>) {
+    if let Some(value) = value {
+        if value > MAX_TOTP_SKEW {
+            errors.add(field, ValidationError::new("totp_skew_invalid"));
+        }
+    }
+}
+
+pub fn totp_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<&str>) {
+    if let Some(value) = value {
+        totp(errors, field, value);
+    }
+}
+
+pub fn email_code(errors: &mut ValidationErrors, field: &'static str, value: &str) {
+    if value.len() != EMAIL_CODE_DIGITS || !value.chars().all(|c| c.is_ascii_digit()) {
+        errors.add(field, ValidationError::new("email_code_invalid"));
+    }
+}
+
 pub fn oauth2_token(errors: &mut ValidationErrors, field: &'static str, value: &str) {
     if value.is_empty() || value.len() > MAX_OAUTH2 {
         errors.add(field, ValidationError::new("oauth2_token_invalid"));
     }
 }
 
+pub fn oauth2_client(errors: &mut ValidationErrors, field: &'static str, value: &str) {
+    if value.is_empty() || value.len() > MAX_OAUTH2 {
+        errors.add(field, ValidationError::new("oauth2_client_invalid"));
+    }
+}
+
+pub fn oauth2_client_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<&str>) {
+    if let Some(value) = value {
+        oauth2_client(errors, field, value);
+    }
+}
+
+pub fn oauth2_scope_vec(errors: &mut ValidationErrors, field: &'static str, value: &[String]) {
+    for v in value {
+        if v.is_empty() || v.len() > MAX_NAME || v.contains(char::is_whitespace) {
+            errors.add(field, ValidationError::new("oauth2_scope_invalid"));
+        }
+    }
+}
+
+pub fn key_scope_vec(errors: &mut ValidationErrors, field: &'static str, value: &[String]) {
+    for v in value {
+        if RolePermission::from_str(v).is_err() {
+            errors.add(field, ValidationError::new("key_scope_invalid"));
+        }
+    }
+}
+
+pub fn key_list_days_opt(errors: &mut ValidationErrors, field: &'static str, value: Option<u32>) {
+    if let Some(value) = value {
+        if value > MAX_KEY_LIST_DAYS {
+            errors.add(field, ValidationError::new("key_list_days_invalid"));
+        }
+    }
+}
+
+pub fn key_grace_period_hours_opt(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    value: Option<u32>,
+) {
+    if let Some(value) = value {
+        if value > MAX_KEY_ROTATE_GRACE_PERIOD_HOURS {
+            errors.add(
+                field,
+                ValidationError::new("key_grace_period_hours_invalid"),
+            );
+        }
+    }
+}
+
+pub fn saml_response(errors: &mut ValidationErrors, field: &'static str, value: &str) {
+    if value.is_empty() || value.len() > MAX_SAML {
+        errors.add(field, ValidationError::new("saml_response_invalid"));
+    }
+}
+
+pub fn saml_idp_metadata_opt(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    value: Option<&str>,
+) {
+    if let Some(value) = value {
+        if value.len() > MAX_SAML || SamlIdpMetadata::parse(value).is_err() {
+            errors.add(field, ValidationError::new("saml_idp_metadata_invalid"));
+        }
+    }
+}
+
 pub fn csrf_token(errors: &mut ValidationErrors, field: &'static str, value: &str) {
     key(errors, field, value);
 }
@@ -213,6 +409,16 @@ pub fn csrf_expires_s_opt(errors: &mut ValidationErrors, field: &'static str, va
     }
 }
 
+pub fn webauthn_credential(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    value: Option<&prost_types::Struct>,
+) {
+    if value.is_none() {
+        errors.add(field, ValidationError::new("webauthn_credential_required"));
+    }
+}
+
 pub fn wrap<F>(f: F) -> Result<(), ValidationErrors>
 where
     F: FnOnce(&mut ValidationErrors),
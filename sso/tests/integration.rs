@@ -9,10 +9,14 @@ audit_integration_test!();
 auth_csrf_integration_test!();
 auth_key_integration_test!();
 auth_local_integration_test!();
+auth_saml_integration_test!();
 auth_token_integration_test!();
 auth_totp_integration_test!();
+auth_webauthn_integration_test!();
 guide_integration_test!();
 key_integration_test!();
+oauth2_provider_integration_test!();
+oidc_integration_test!();
 service_integration_test!();
 user_integration_test!();
 
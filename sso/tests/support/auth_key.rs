@@ -24,6 +24,33 @@ macro_rules! auth_key_integration_test {
             assert_eq!(res.message(), ERR_REDACTED);
         }
 
+        #[test]
+        #[ignore]
+        fn auth_key_verify_bad_request_user_key_prefix() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (_user, user_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Key, service.id, user);
+
+            let key = format!("{}{}", &user_key.value[..8], &INVALID_KEY[8..]);
+            let body = pb::AuthKeyRequest::new(&key, None);
+            let res = client.auth_key_verify(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
         #[test]
         #[ignore]
         fn auth_key_verify_bad_request_unknown_user_key_for_service() {
@@ -160,6 +160,91 @@ macro_rules! auth_local_integration_test {
             assert_eq!(res.message(), ERR_REDACTED);
         }
 
+        #[test]
+        #[ignore]
+        fn auth_local_login_forbidden_locked_after_incorrect_passwords() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client_service = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client_service,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let user_id = user.id.clone();
+            let _user_key = user_key_create(
+                &mut client_service,
+                KEY_NAME,
+                KeyType::Token,
+                service.id,
+                user,
+            );
+
+            // Default lockout after 5 failed login attempts.
+            for _ in 0..5 {
+                let body = pb::AuthLoginRequest::new(&user_email, USER_WRONG_PASSWORD);
+                let res = client_service.auth_local_login(body).unwrap_err();
+                assert_eq!(res.code(), tonic::Code::InvalidArgument);
+                assert_eq!(res.message(), ERR_REDACTED);
+            }
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client_service.auth_local_login(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            assert_eq!(res.message(), ERR_REDACTED);
+
+            let lockout_type = AuditType::AuthLocalLoginLockout.to_string();
+            let audit_list = client
+                .audit_list(pb::AuditListRequest::type_subject(
+                    vec![lockout_type],
+                    vec![user_email.clone()],
+                ))
+                .unwrap()
+                .into_inner()
+                .data;
+            assert_eq!(audit_list.len(), 1);
+
+            let res = client_service.user_login_unlock(pb::UserLoginUnlockRequest {
+                user_id: user_id.clone(),
+                remote: None,
+            });
+            assert_eq!(res.unwrap_err().code(), tonic::Code::Unauthenticated);
+
+            client
+                .user_login_unlock(pb::UserLoginUnlockRequest {
+                    user_id,
+                    remote: None,
+                })
+                .unwrap();
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            client_service.auth_local_login(body).unwrap();
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_login_forbidden_locked_unknown_email() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            for _ in 0..5 {
+                let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+                let res = client.auth_local_login(body).unwrap_err();
+                assert_eq!(res.code(), tonic::Code::InvalidArgument);
+                assert_eq!(res.message(), ERR_REDACTED);
+            }
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client.auth_local_login(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
         #[test]
         #[ignore]
         fn auth_local_login_bad_request_unknown_user_key_for_service() {
@@ -264,6 +349,385 @@ macro_rules! auth_local_integration_test {
             assert_eq!(res.user.unwrap().id, user.id);
         }
 
+        #[test]
+        #[ignore]
+        fn auth_local_login_forbidden_mfa_required_without_key() {
+            let mut client = client_create(None);
+            let (service, service_key) =
+                service_key_create_with_mfa_policy(&mut client, ServiceMfaPolicy::Required);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let _user_key =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client.auth_local_login(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_login_mfa_bad_request_incorrect_totp() {
+            let mut client = client_create(None);
+            let (service, service_key) =
+                service_key_create_with_mfa_policy(&mut client, ServiceMfaPolicy::Optional);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) = user_key_create(
+                &mut client,
+                KEY_NAME,
+                KeyType::Token,
+                service.id.clone(),
+                user,
+            );
+            let (_user, user_totp_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Totp, service.id, user);
+
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client.auth_local_login(body).unwrap().into_inner();
+            let mfa = res.mfa.unwrap();
+
+            let totp = libreauth::oath::TOTPBuilder::new()
+                .base32_key(&user_totp_key.value)
+                .finalize()
+                .unwrap()
+                .generate();
+            let totp = format!("{:06}", (totp.parse::<u32>().unwrap() + 1) % 1_000_000);
+            let res = client
+                .auth_local_login_mfa(pb::AuthLoginMfaRequest {
+                    token: mfa,
+                    totp: Some(totp),
+                    webauthn: None,
+                    recovery_code: None,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_login_mfa_totp_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) =
+                service_key_create_with_mfa_policy(&mut client, ServiceMfaPolicy::Optional);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) = user_key_create(
+                &mut client,
+                KEY_NAME,
+                KeyType::Token,
+                service.id.clone(),
+                user,
+            );
+            let (user, user_totp_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Totp, service.id, user);
+
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client.auth_local_login(body).unwrap().into_inner();
+            assert_eq!(res.user.unwrap().id, user.id);
+            assert!(res.access.is_none());
+            assert!(res.refresh.is_none());
+            assert_eq!(res.mfa_key_types, vec![KeyType::Totp as i32]);
+            let mfa = res.mfa.unwrap();
+
+            let totp = libreauth::oath::TOTPBuilder::new()
+                .base32_key(&user_totp_key.value)
+                .finalize()
+                .unwrap();
+            let body = pb::AuthLoginMfaRequest {
+                token: mfa.clone(),
+                totp: Some(totp.generate()),
+                webauthn: None,
+                recovery_code: None,
+            };
+            let res = client.auth_local_login_mfa(body).unwrap().into_inner();
+            assert_eq!(res.user.unwrap().id, user.id);
+            assert!(res.access.is_some());
+            assert!(res.refresh.is_some());
+            assert!(res.mfa.is_none());
+
+            // MFA token can only be used once.
+            let body = pb::AuthLoginMfaRequest {
+                token: mfa,
+                totp: Some(totp.generate()),
+                webauthn: None,
+                recovery_code: None,
+            };
+            let res = client.auth_local_login_mfa(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_login_mfa_forbidden_locked_after_incorrect_totp() {
+            let mut client = client_create(None);
+            let (service, service_key) =
+                service_key_create_with_mfa_policy(&mut client, ServiceMfaPolicy::Optional);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) = user_key_create(
+                &mut client,
+                KEY_NAME,
+                KeyType::Token,
+                service.id.clone(),
+                user,
+            );
+            let (_user, user_totp_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Totp, service.id, user);
+            let totp = libreauth::oath::TOTPBuilder::new()
+                .base32_key(&user_totp_key.value)
+                .finalize()
+                .unwrap()
+                .generate();
+            let totp = format!("{:06}", (totp.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+            // Default lockout after 5 failed logins, correct password does not reset count.
+            for _ in 0..5 {
+                let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+                let res = client.auth_local_login(body).unwrap().into_inner();
+                let res = client
+                    .auth_local_login_mfa(pb::AuthLoginMfaRequest {
+                        token: res.mfa.unwrap(),
+                        totp: Some(totp.clone()),
+                        webauthn: None,
+                        recovery_code: None,
+                    })
+                    .unwrap_err();
+                assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            }
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client.auth_local_login(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_login_mfa_recovery_code_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) =
+                service_key_create_with_mfa_policy(&mut client, ServiceMfaPolicy::Required);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+            let res = client
+                .auth_totp_register_begin(pb::AuthTotpRegisterBeginRequest::new(
+                    user.id.clone(),
+                    KEY_NAME,
+                ))
+                .unwrap()
+                .into_inner();
+            let totp = libreauth::oath::TOTPBuilder::new()
+                .base32_key(&res.secret)
+                .finalize()
+                .unwrap();
+            let recovery_codes = client
+                .auth_totp_register_finish(pb::AuthTotpRegisterFinishRequest {
+                    csrf: res.csrf,
+                    totp: totp.generate(),
+                })
+                .unwrap()
+                .into_inner()
+                .recovery_codes;
+
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client.auth_local_login(body.clone()).unwrap().into_inner();
+            let res = client
+                .auth_local_login_mfa(pb::AuthLoginMfaRequest {
+                    token: res.mfa.unwrap(),
+                    totp: None,
+                    webauthn: None,
+                    recovery_code: Some(recovery_codes[0].clone()),
+                })
+                .unwrap()
+                .into_inner();
+            assert_eq!(res.user.unwrap().id, user.id);
+            assert!(res.access.is_some());
+            assert!(res.refresh.is_some());
+
+            // Recovery codes can only be used once.
+            let res = client.auth_local_login(body).unwrap().into_inner();
+            let res = client
+                .auth_local_login_mfa(pb::AuthLoginMfaRequest {
+                    token: res.mfa.unwrap(),
+                    totp: None,
+                    webauthn: None,
+                    recovery_code: Some(recovery_codes[0].clone()),
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_magic_link_bad_request_invalid_email() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let body = pb::AuthMagicLinkRequest::new(INVALID_EMAIL);
+            let res = client.auth_local_magic_link(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_magic_link_ok_unknown_email() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            // Endpoint should not infer users existence.
+            let mut client = client_create(Some(&service_key.value));
+            let body = pb::AuthMagicLinkRequest::new(&user_email);
+            client.auth_local_magic_link(body).unwrap();
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_magic_link_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            // User without password.
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let _user_key =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            let body = pb::AuthMagicLinkRequest::new(&user_email);
+            client.auth_local_magic_link(body).unwrap();
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_magic_link_confirm_bad_request_invalid_token() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let body = pb::AuthMagicLinkConfirmRequest {
+                token: INVALID_KEY.to_owned(),
+            };
+            let res = client.auth_local_magic_link_confirm(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_email_code_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            // User without password.
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let _user_key =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            let body = pb::AuthEmailCodeRequest::new(&user_email);
+            client.auth_local_email_code(body).unwrap();
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_email_code_confirm_bad_request_invalid_code() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let body = pb::AuthEmailCodeConfirmRequest {
+                email: user_email,
+                code: "12345a".to_owned(),
+            };
+            let res = client.auth_local_email_code_confirm(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_email_code_confirm_bad_request_incorrect_code() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let _user_key =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            // Code is sent by email, incorrect code is rejected.
+            let body = pb::AuthEmailCodeRequest::new(&user_email);
+            client.auth_local_email_code(body).unwrap();
+            let body = pb::AuthEmailCodeConfirmRequest {
+                email: user_email,
+                code: "000000".to_owned(),
+            };
+            let res = client.auth_local_email_code_confirm(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
         #[test]
         #[ignore]
         fn auth_local_register_unauthorised() {
@@ -608,5 +1072,181 @@ macro_rules! auth_local_integration_test {
             assert_eq!(res.code(), tonic::Code::InvalidArgument);
             assert_eq!(res.message(), ERR_VALIDATION);
         }
+
+        #[test]
+        #[ignore]
+        fn auth_local_update_password_bad_request_password_reused() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_password_policy(
+                &mut client,
+                UserPasswordPolicy {
+                    history: 1,
+                    ..Default::default()
+                },
+            );
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            let body = pb::AuthUpdatePasswordRequest {
+                email: user_email.clone(),
+                password: String::from(USER_PASSWORD),
+                new_password: String::from(USER_PASSWORD),
+            };
+            let res = client.auth_local_update_password(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+            assert_eq!(
+                status_validation_codes(&res, "new_password"),
+                vec!["password_reused_invalid"]
+            );
+
+            let body = pb::AuthUpdatePasswordRequest {
+                email: user_email,
+                password: String::from(USER_PASSWORD),
+                new_password: String::from(USER_WRONG_PASSWORD),
+            };
+            client.auth_local_update_password(body).unwrap();
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_update_password_bad_request_password_current() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            let body = pb::AuthUpdatePasswordRequest {
+                email: user_email,
+                password: String::from(USER_PASSWORD),
+                new_password: String::from(USER_PASSWORD),
+            };
+            let res = client.auth_local_update_password(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(
+                status_validation_codes(&res, "new_password"),
+                vec!["password_reused_invalid"]
+            );
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_update_password_forbidden_locked() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            // Default lockout after 5 failed password checks.
+            for _ in 0..5 {
+                let body = pb::AuthUpdatePasswordRequest {
+                    email: user_email.clone(),
+                    password: String::from(USER_WRONG_PASSWORD),
+                    new_password: String::from(USER_PASSWORD),
+                };
+                let res = client.auth_local_update_password(body).unwrap_err();
+                assert_eq!(res.code(), tonic::Code::InvalidArgument);
+                assert_eq!(res.message(), ERR_REDACTED);
+            }
+            let body = pb::AuthUpdatePasswordRequest {
+                email: user_email.clone(),
+                password: String::from(USER_PASSWORD),
+                new_password: String::from(USER_WRONG_PASSWORD),
+            };
+            let res = client.auth_local_update_password(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            let body = pb::AuthUpdateEmailRequest {
+                email: user_email.clone(),
+                password: USER_PASSWORD.to_owned(),
+                new_email: email_create(),
+            };
+            let res = client.auth_local_update_email(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            let body = pb::AuthLoginRequest::new(&user_email, USER_PASSWORD);
+            let res = client.auth_local_login(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_local_update_password_bad_request_password_history() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_password_policy(
+                &mut client,
+                UserPasswordPolicy {
+                    history: 1,
+                    ..Default::default()
+                },
+            );
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                "password-a",
+            );
+            user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            let body = |password: &str, new_password: &str| pb::AuthUpdatePasswordRequest {
+                email: user_email.clone(),
+                password: password.to_owned(),
+                new_password: new_password.to_owned(),
+            };
+            client
+                .auth_local_update_password(body("password-a", "password-b"))
+                .unwrap();
+            let res = client
+                .auth_local_update_password(body("password-b", "password-a"))
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(
+                status_validation_codes(&res, "new_password"),
+                vec!["password_reused_invalid"]
+            );
+            client
+                .auth_local_update_password(body("password-b", "password-c"))
+                .unwrap();
+            client
+                .auth_local_update_password(body("password-c", "password-a"))
+                .unwrap();
+        }
     };
 }
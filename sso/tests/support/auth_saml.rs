@@ -0,0 +1,85 @@
+#[macro_export]
+macro_rules! auth_saml_integration_test {
+    () => {
+        #[test]
+        #[ignore]
+        fn service_create_bad_request_invalid_saml_idp_metadata() {
+            let mut client = client_create(None);
+            let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
+                .provider_saml("http://localhost/saml/acs", "<md:EntityDescriptor/>");
+            let res = client.service_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_saml_url_ok() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create_with_saml(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client.auth_saml_url(()).unwrap().into_inner();
+            let url = url::Url::parse(&res.url).unwrap();
+            assert_eq!(url.path(), "/idp/sso");
+            let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+            assert!(query.iter().any(|(k, _)| k == "SAMLRequest"));
+            assert!(query.iter().any(|(k, _)| k == "RelayState"));
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_saml_url_bad_request_service_disabled() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client.auth_saml_url(()).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_saml_callback_bad_request_invalid_relay_state() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create_with_saml(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client
+                .auth_saml_callback(pb::AuthSamlCallbackRequest {
+                    saml_response: base64::encode(SAML_RESPONSE),
+                    relay_state: INVALID_KEY.to_owned(),
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_saml_callback_bad_request_response_not_in_response_to_request() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create_with_saml(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client.auth_saml_url(()).unwrap().into_inner();
+            let url = url::Url::parse(&res.url).unwrap();
+            let (_, relay_state) = url
+                .query_pairs()
+                .into_owned()
+                .find(|(k, _)| k == "RelayState")
+                .unwrap();
+
+            let body = pb::AuthSamlCallbackRequest {
+                saml_response: base64::encode(SAML_RESPONSE),
+                relay_state,
+            };
+            let res = client.auth_saml_callback(body.clone()).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+
+            // Relay state is single use.
+            let res = client.auth_saml_callback(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+    };
+}
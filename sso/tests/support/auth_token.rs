@@ -162,6 +162,40 @@ macro_rules! auth_token_integration_test {
             assert_eq!(res.message(), ERR_REDACTED);
         }
 
+        #[test]
+        #[ignore]
+        fn auth_token_refresh_bad_request_reused_refresh_token_revokes_family() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+            let user_token = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+            let user_token2 = user_token_refresh(&mut client, &user_token);
+
+            let body = pb::AuthTokenRequest::new(&user_token.refresh.unwrap().token, None);
+            let res = client.auth_token_refresh(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+
+            let body = pb::AuthTokenRequest::new(&user_token2.refresh.unwrap().token, None);
+            let res = client.auth_token_refresh(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+
+            let user_token3 = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+            user_token_refresh(&mut client, &user_token3);
+        }
+
         #[test]
         #[ignore]
         fn auth_token_refresh_ok() {
@@ -189,6 +223,48 @@ macro_rules! auth_token_integration_test {
             client.auth_token_verify(body).unwrap();
         }
 
+        #[test]
+        #[ignore]
+        fn auth_token_asymmetric_jwt_algorithm_ok() {
+            let jwt_algorithms = [
+                JwtAlgorithm::Rs256,
+                JwtAlgorithm::Es256,
+                JwtAlgorithm::EdDsa,
+            ];
+            for jwt_algorithm in jwt_algorithms.iter() {
+                let mut client = client_create(None);
+                let (service, service_key) =
+                    service_key_create_with_jwt_algorithm(&mut client, *jwt_algorithm);
+                let user_email = email_create();
+
+                let mut client = client_create(Some(&service_key.value));
+                let user = user_create_with_password(
+                    &mut client,
+                    true,
+                    USER_NAME,
+                    &user_email,
+                    false,
+                    false,
+                    USER_PASSWORD,
+                );
+                let (user, _user_key) =
+                    user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+                let user_token =
+                    auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+
+                // Tokens are signed with server key identified by kid header.
+                let token = &user_token.access.as_ref().unwrap().token;
+                let header = jsonwebtoken::decode_header(token).unwrap();
+                assert_eq!(header.alg, jwt_algorithm.to_algorithm());
+                assert!(header.kid.is_some());
+
+                user_token_verify(&mut client, &user_token);
+                let user_token = user_token_refresh(&mut client, &user_token);
+                let body = pb::AuthTokenRequest::new(&user_token.access.unwrap().token, None);
+                client.auth_token_verify(body).unwrap();
+            }
+        }
+
         #[test]
         #[ignore]
         fn auth_token_revoke_unauthorised() {
@@ -262,6 +338,7 @@ macro_rules! auth_token_integration_test {
                 user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
 
             let user_token = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+            let user_token2 = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
 
             user_token_verify(&mut client, &user_token);
             let token_access = user_token.access.unwrap();
@@ -271,6 +348,42 @@ macro_rules! auth_token_integration_test {
             let res = client.auth_token_verify(body).unwrap_err();
             assert_eq!(res.code(), tonic::Code::InvalidArgument);
             assert_eq!(res.message(), ERR_REDACTED);
+
+            user_token_verify(&mut client, &user_token2);
+            user_token_refresh(&mut client, &user_token2);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_token_revoke_refresh_token_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+            let user_token = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+            let user_token2 = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+
+            let token_refresh = user_token.refresh.unwrap();
+            let body = pb::AuthTokenRequest::new(&token_refresh.token, None);
+            client.auth_token_revoke(body).unwrap();
+            let body = pb::AuthTokenRequest::new(&token_refresh.token, None);
+            let res = client.auth_token_refresh(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+
+            user_token_refresh(&mut client, &user_token2);
         }
     };
 }
@@ -78,8 +78,161 @@ macro_rules! auth_totp_integration_test {
                 .base32_key(&user_key.value)
                 .finalize()
                 .unwrap();
-            let body = pb::AuthTotpRequest::new(user.id, totp.generate());
+            let body = pb::AuthTotpRequest::new(user.id.clone(), totp.generate());
             client.auth_totp_verify(body).unwrap();
+
+            // Code cannot be used again in same time step.
+            let body = pb::AuthTotpRequest::new(user.id, totp.generate());
+            let res = client.auth_totp_verify(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_totp_register_bad_request_incorrect_totp() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+
+            let res = client
+                .auth_totp_register_begin(pb::AuthTotpRegisterBeginRequest::new(
+                    user.id.clone(),
+                    KEY_NAME,
+                ))
+                .unwrap()
+                .into_inner();
+            let totp = libreauth::oath::TOTPBuilder::new()
+                .base32_key(&res.secret)
+                .finalize()
+                .unwrap()
+                .generate();
+            let totp = format!("{:06}", (totp.parse::<u32>().unwrap() + 1) % 1_000_000);
+            let res = client
+                .auth_totp_register_finish(pb::AuthTotpRegisterFinishRequest {
+                    csrf: res.csrf,
+                    totp,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+
+            let body = pb::AuthTotpRequest::new(user.id, "123456");
+            let res = client.auth_totp_verify(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_totp_register_ok() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+
+            let register = |client: &mut GrpcClientBlocking| {
+                let res = client
+                    .auth_totp_register_begin(pb::AuthTotpRegisterBeginRequest::new(
+                        user.id.clone(),
+                        KEY_NAME,
+                    ))
+                    .unwrap()
+                    .into_inner();
+                assert!(res.uri.starts_with("otpauth://totp/"));
+                assert!(res.uri.contains(&res.secret));
+                assert!(res.qr_svg.contains("<svg"));
+
+                let totp = libreauth::oath::TOTPBuilder::new()
+                    .base32_key(&res.secret)
+                    .finalize()
+                    .unwrap();
+                let reply = client
+                    .auth_totp_register_finish(pb::AuthTotpRegisterFinishRequest {
+                        csrf: res.csrf,
+                        totp: totp.generate(),
+                    })
+                    .unwrap()
+                    .into_inner();
+                let key = reply.key.unwrap();
+                assert_eq!(key.r#type, pb::KeyType::Totp as i32);
+                assert_eq!(key.user_id.unwrap(), user.id);
+                assert_eq!(reply.recovery_codes.len(), 10);
+                (res.secret, totp.generate(), reply.recovery_codes)
+            };
+            // Code used to register is rejected, code for next time step is accepted.
+            let verify = |client: &mut GrpcClientBlocking, secret: &str, code: String| {
+                let body = pb::AuthTotpRequest::new(user.id.clone(), code);
+                let res = client.auth_totp_verify(body).unwrap_err();
+                assert_eq!(res.code(), tonic::Code::InvalidArgument);
+                let next = libreauth::oath::TOTPBuilder::new()
+                    .base32_key(secret)
+                    .timestamp(Utc::now().timestamp() + 30)
+                    .finalize()
+                    .unwrap();
+                let body = pb::AuthTotpRequest::new(user.id.clone(), next.generate());
+                client.auth_totp_verify(body).unwrap();
+            };
+
+            let (secret, code, recovery_codes) = register(&mut client);
+            verify(&mut client, &secret, code);
+
+            let body = pb::AuthTotpRecoveryRequest {
+                user_id: user.id.clone(),
+                code: recovery_codes[0].clone(),
+            };
+            client.auth_totp_recovery_verify(body.clone()).unwrap();
+            let res = client.auth_totp_recovery_verify(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+
+            // Registering again replaces key and recovery codes.
+            let (secret2, code2, _recovery_codes2) = register(&mut client);
+            verify(&mut client, &secret2, code2);
+            let body = pb::AuthTotpRecoveryRequest {
+                user_id: user.id.clone(),
+                code: recovery_codes[1].clone(),
+            };
+            let res = client.auth_totp_recovery_verify(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_totp_register_ok_params() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+
+            let mut body = pb::AuthTotpRegisterBeginRequest::new(user.id.clone(), KEY_NAME);
+            body.period = Some(60);
+            body.digits = Some(8);
+            body.algorithm = Some("SHA256".to_owned());
+            let res = client.auth_totp_register_begin(body).unwrap().into_inner();
+            assert!(res.uri.contains("algorithm=SHA256"));
+            assert!(res.uri.contains("digits=8"));
+            assert!(res.uri.contains("period=60"));
+
+            let totp = libreauth::oath::TOTPBuilder::new()
+                .base32_key(&res.secret)
+                .period(60)
+                .output_len(8)
+                .hash_function(libreauth::hash::HashFunction::Sha256)
+                .finalize()
+                .unwrap();
+            client
+                .auth_totp_register_finish(pb::AuthTotpRegisterFinishRequest {
+                    csrf: res.csrf,
+                    totp: totp.generate(),
+                })
+                .unwrap();
         }
     };
 }
@@ -0,0 +1,161 @@
+#[macro_export]
+macro_rules! auth_webauthn_integration_test {
+    () => {
+        #[test]
+        #[ignore]
+        fn auth_webauthn_register_begin_unauthorised() {
+            let mut client = client_create(Some(INVALID_KEY));
+            let body = pb::AuthWebauthnRegisterBeginRequest::new(UUID_NIL, KEY_NAME);
+            let res = client.auth_webauthn_register_begin(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::Unauthenticated);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_webauthn_login_finish_bad_request_invalid_csrf() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let body = pb::AuthWebauthnFinishRequest::new("", json!({}));
+            let res = client.auth_webauthn_login_finish(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_webauthn_login_begin_bad_request_unknown_user_webauthn_key() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+
+            let body = pb::AuthWebauthnLoginBeginRequest::new(&user_email);
+            let res = client.auth_webauthn_login_begin(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_webauthn_key_create_bad_request() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let body =
+                pb::KeyCreateRequest::with_user_id(true, KeyType::WebAuthn, KEY_NAME, user.id);
+            let res = client.key_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), "KeyUserWebauthnCredentialRequired");
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_webauthn_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let (user, _user_key) = user_key_create(
+                &mut client,
+                KEY_NAME,
+                KeyType::Token,
+                service.id.clone(),
+                user,
+            );
+            let mut authenticator = WebauthnAuthenticator::new();
+
+            let body = pb::AuthWebauthnRegisterBeginRequest::new(&user.id, KEY_NAME);
+            let begin = client
+                .auth_webauthn_register_begin(body)
+                .unwrap()
+                .into_inner();
+            let challenge = pb::struct_opt_to_value_opt(begin.challenge).unwrap();
+            let credential = authenticator.register(&challenge);
+            let body = pb::AuthWebauthnFinishRequest::new(&begin.csrf, credential.clone());
+            let key = client
+                .auth_webauthn_register_finish(body)
+                .unwrap()
+                .into_inner()
+                .key
+                .unwrap();
+            assert_eq!(key.r#type, KeyType::WebAuthn as i32);
+            assert_eq!(key.name, KEY_NAME);
+            assert_eq!(key.service_id.unwrap(), service.id);
+            assert_eq!(key.user_id.unwrap(), user.id);
+
+            // CSRF is single use.
+            let body = pb::AuthWebauthnFinishRequest::new(&begin.csrf, credential);
+            let res = client.auth_webauthn_register_finish(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+
+            for _ in 0..2 {
+                let body = pb::AuthWebauthnLoginBeginRequest::new(&user_email);
+                let begin = client.auth_webauthn_login_begin(body).unwrap().into_inner();
+                let challenge = pb::struct_opt_to_value_opt(begin.challenge).unwrap();
+                let credential = authenticator.login(&challenge);
+                let body = pb::AuthWebauthnFinishRequest::new(&begin.csrf, credential);
+                let login = client
+                    .auth_webauthn_login_finish(body)
+                    .unwrap()
+                    .into_inner();
+                assert_eq!(login.user.unwrap().id, user.id);
+
+                let body = pb::AuthTokenRequest::new(&login.access.unwrap().token, None);
+                client.auth_token_verify(body).unwrap();
+            }
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_webauthn_login_finish_bad_request_cloned_authenticator() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let (user, _user_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+            let mut authenticator = WebauthnAuthenticator::new();
+
+            let body = pb::AuthWebauthnRegisterBeginRequest::new(&user.id, KEY_NAME);
+            let begin = client
+                .auth_webauthn_register_begin(body)
+                .unwrap()
+                .into_inner();
+            let challenge = pb::struct_opt_to_value_opt(begin.challenge).unwrap();
+            let credential = authenticator.register(&challenge);
+            let body = pb::AuthWebauthnFinishRequest::new(&begin.csrf, credential);
+            client.auth_webauthn_register_finish(body).unwrap();
+
+            // Signature counter must increase for every login.
+            let body = pb::AuthWebauthnLoginBeginRequest::new(&user_email);
+            let begin = client.auth_webauthn_login_begin(body).unwrap().into_inner();
+            let challenge = pb::struct_opt_to_value_opt(begin.challenge).unwrap();
+            let credential = authenticator.login(&challenge);
+            let body = pb::AuthWebauthnFinishRequest::new(&begin.csrf, credential);
+            client.auth_webauthn_login_finish(body).unwrap();
+
+            authenticator.counter_reset();
+            let body = pb::AuthWebauthnLoginBeginRequest::new(&user_email);
+            let begin = client.auth_webauthn_login_begin(body).unwrap().into_inner();
+            let challenge = pb::struct_opt_to_value_opt(begin.challenge).unwrap();
+            let credential = authenticator.login(&challenge);
+            let body = pb::AuthWebauthnFinishRequest::new(&begin.csrf, credential);
+            let res = client.auth_webauthn_login_finish(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_REDACTED);
+        }
+    };
+}
@@ -0,0 +1,156 @@
+//! Software WebAuthn authenticator for integration tests.
+use openssl::{
+    bn::BigNumContext,
+    ec::{EcGroup, EcKey},
+    ecdsa::EcdsaSig,
+    nid::Nid,
+    pkey::Private,
+    sha::sha256,
+};
+use serde_cbor::Value as CborValue;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use webauthn_rs::base64_data::Base64UrlSafeData;
+
+/// Origin of service created by `service_key_create`.
+const ORIGIN: &str = "http://localhost";
+
+/// Authenticator data flags.
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// Authenticator with a single P-256 credential and "none" attestation.
+pub struct WebauthnAuthenticator {
+    key: EcKey<Private>,
+    credential_id: Vec<u8>,
+    aaguid: Uuid,
+    counter: u32,
+}
+
+impl WebauthnAuthenticator {
+    pub fn new() -> Self {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        Self {
+            key: EcKey::generate(&group).unwrap(),
+            credential_id: Uuid::new_v4().as_bytes().to_vec(),
+            aaguid: Uuid::new_v4(),
+            counter: 0,
+        }
+    }
+
+    /// Reset signature counter, as if authenticator was cloned.
+    pub fn counter_reset(&mut self) {
+        self.counter = 0;
+    }
+
+    /// Returns credential for `navigator.credentials.create()` challenge.
+    pub fn register(&mut self, challenge: &Value) -> Value {
+        let rp_id = challenge["publicKey"]["rp"]["id"].as_str().unwrap();
+        let client_data = client_data("webauthn.create", challenge);
+
+        let mut auth_data =
+            self.auth_data(rp_id, FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA);
+        auth_data.extend_from_slice(self.aaguid.as_bytes());
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        auth_data.extend_from_slice(&self.public_key_cose());
+
+        let mut attestation = BTreeMap::new();
+        attestation.insert(
+            CborValue::Text("fmt".to_owned()),
+            CborValue::Text("none".to_owned()),
+        );
+        attestation.insert(
+            CborValue::Text("attStmt".to_owned()),
+            CborValue::Map(BTreeMap::new()),
+        );
+        attestation.insert(
+            CborValue::Text("authData".to_owned()),
+            CborValue::Bytes(auth_data),
+        );
+        let attestation = serde_cbor::to_vec(&CborValue::Map(attestation)).unwrap();
+
+        json!({
+            "id": encode(&self.credential_id),
+            "rawId": encode(&self.credential_id),
+            "response": {
+                "attestationObject": encode(&attestation),
+                "clientDataJSON": encode(&client_data),
+            },
+            "type": "public-key",
+        })
+    }
+
+    /// Returns credential for `navigator.credentials.get()` challenge.
+    pub fn login(&mut self, challenge: &Value) -> Value {
+        let rp_id = challenge["publicKey"]["rpId"].as_str().unwrap();
+        let client_data = client_data("webauthn.get", challenge);
+
+        self.counter += 1;
+        let auth_data = self.auth_data(rp_id, FLAG_USER_PRESENT);
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&sha256(&client_data));
+        let signature = EcdsaSig::sign(&sha256(&signed), &self.key)
+            .unwrap()
+            .to_der()
+            .unwrap();
+
+        json!({
+            "id": encode(&self.credential_id),
+            "rawId": encode(&self.credential_id),
+            "response": {
+                "authenticatorData": encode(&auth_data),
+                "clientDataJSON": encode(&client_data),
+                "signature": encode(&signature),
+            },
+            "type": "public-key",
+        })
+    }
+
+    fn auth_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+        let mut auth_data = sha256(rp_id.as_bytes()).to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&self.counter.to_be_bytes());
+        auth_data
+    }
+
+    fn public_key_cose(&self) -> Vec<u8> {
+        let mut ctx = BigNumContext::new().unwrap();
+        let mut x = openssl::bn::BigNum::new().unwrap();
+        let mut y = openssl::bn::BigNum::new().unwrap();
+        self.key
+            .public_key()
+            .affine_coordinates_gfp(self.key.group(), &mut x, &mut y, &mut ctx)
+            .unwrap();
+
+        let mut key = BTreeMap::new();
+        // Key type EC2, algorithm ES256, curve P-256.
+        key.insert(CborValue::Integer(1), CborValue::Integer(2));
+        key.insert(CborValue::Integer(3), CborValue::Integer(-7));
+        key.insert(CborValue::Integer(-1), CborValue::Integer(1));
+        key.insert(
+            CborValue::Integer(-2),
+            CborValue::Bytes(x.to_vec_padded(32).unwrap()),
+        );
+        key.insert(
+            CborValue::Integer(-3),
+            CborValue::Bytes(y.to_vec_padded(32).unwrap()),
+        );
+        serde_cbor::to_vec(&CborValue::Map(key)).unwrap()
+    }
+}
+
+fn client_data(type_: &str, challenge: &Value) -> Vec<u8> {
+    let client_data = json!({
+        "type": type_,
+        "challenge": challenge["publicKey"]["challenge"],
+        "origin": ORIGIN,
+        "crossOrigin": false,
+    });
+    serde_json::to_vec(&client_data).unwrap()
+}
+
+fn encode(data: &[u8]) -> String {
+    Base64UrlSafeData(data.to_vec()).to_string()
+}
@@ -96,6 +96,7 @@ macro_rules! guide_integration_test {
 
             let res = client.auth_microsoft_oauth2_url(()).unwrap().into_inner();
             assert!(!res.url.is_empty());
+            assert!(!res.session.is_empty());
         }
     };
 }
@@ -288,5 +288,345 @@ macro_rules! key_integration_test {
                 .unwrap_err();
             assert_eq!(res.code(), tonic::Code::NotFound);
         }
+
+        #[test]
+        #[ignore]
+        fn key_expired_unauthorised() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create(&mut client);
+
+            let mut body = pb::KeyCreateRequest::with_service_id(
+                true,
+                KeyType::Key,
+                KEY_NAME,
+                service.id.clone(),
+            );
+            body.expires_at = pb::datetime_to_timestamp_opt(Utc::now() - chrono::Duration::days(1));
+            let key = client.key_create(body).unwrap().into_inner().data.unwrap();
+
+            let mut client = client_create(Some(&key.value));
+            let res = client.key_list(pb::KeyListRequest::default()).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::Unauthenticated);
+        }
+
+        #[test]
+        #[ignore]
+        fn key_list_expires_within_days_ok() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+
+            let mut body =
+                pb::KeyCreateRequest::with_user_id(true, KeyType::Key, KEY_NAME, user.id);
+            let k1 = client
+                .key_create(body.clone())
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap()
+                .key
+                .unwrap();
+            assert!(k1.expires_at.is_none());
+            body.expires_at = pb::datetime_to_timestamp_opt(Utc::now() + chrono::Duration::days(2));
+            body.scopes = vec!["key:read".to_owned()];
+            let k2 = client
+                .key_create(body)
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap()
+                .key
+                .unwrap();
+            assert!(k2.expires_at.is_some());
+            assert_eq!(k2.scopes, vec!["key:read".to_owned()]);
+
+            let mut list = pb::KeyListRequest::limit_id(10, vec![k1.id, k2.id.clone()]);
+            list.expires_within_days = Some(7);
+            let res = client.key_list(list).unwrap().into_inner();
+            let id: Vec<String> = res.data.into_iter().map(|x| x.id).collect();
+            assert_eq!(id, vec![k2.id]);
+        }
+
+        #[test]
+        #[ignore]
+        fn key_list_unused_for_days_ok() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let service_key_id = service_key.key.unwrap().id;
+
+            let mut list = pb::KeyListRequest::limit_id(10, vec![service_key_id.clone()]);
+            list.unused_for_days = Some(0);
+            let res = client.key_list(list.clone()).unwrap().into_inner();
+            assert_eq!(res.data.len(), 1);
+            assert!(res.data[0].last_used_at.is_none());
+
+            let mut service_client = client_create(Some(&service_key.value));
+            service_client
+                .key_list(pb::KeyListRequest::default())
+                .unwrap();
+
+            let key = client
+                .key_read(pb::KeyReadRequest {
+                    id: service_key_id,
+                    user_id: None,
+                })
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap();
+            assert!(key.last_used_at.is_some());
+            assert!(key.last_used_remote.is_some());
+
+            list.unused_for_days = Some(1);
+            let res = client.key_list(list).unwrap().into_inner();
+            assert!(res.data.is_empty());
+        }
+
+        #[test]
+        #[ignore]
+        fn key_create_bad_request_invalid_scopes() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let mut body =
+                pb::KeyCreateRequest::with_user_id(true, KeyType::Key, KEY_NAME, user.id);
+            body.scopes = vec!["key read".to_owned()];
+            let res = client.key_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(
+                status_validation_codes(&res, "scopes"),
+                vec!["key_scope_invalid".to_owned()]
+            );
+        }
+
+        #[test]
+        #[ignore]
+        fn key_rotate_ok() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let service_key_id = service_key.key.unwrap().id;
+
+            let res = client
+                .key_rotate(pb::KeyRotateRequest {
+                    id: service_key_id.clone(),
+                    grace_period_hours: None,
+                })
+                .unwrap()
+                .into_inner();
+            let previous = res.previous.unwrap();
+            let key = res.data.unwrap();
+            assert_eq!(previous.id, service_key_id);
+            assert!(previous.expires_at.is_some());
+            assert_ne!(key.key.as_ref().unwrap().id, service_key_id);
+            assert_eq!(key.key.as_ref().unwrap().service_id, previous.service_id);
+
+            let mut client = client_create(Some(&service_key.value));
+            client.key_list(pb::KeyListRequest::default()).unwrap();
+            let mut client = client_create(Some(&key.value));
+            client.key_list(pb::KeyListRequest::default()).unwrap();
+        }
+
+        #[test]
+        #[ignore]
+        fn key_rotate_grace_period_ended_unauthorised() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let key = client
+                .key_rotate(pb::KeyRotateRequest {
+                    id: service_key.key.unwrap().id,
+                    grace_period_hours: Some(0),
+                })
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap();
+
+            let res = client.key_list(pb::KeyListRequest::default()).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::Unauthenticated);
+            let mut client = client_create(Some(&key.value));
+            client.key_list(pb::KeyListRequest::default()).unwrap();
+        }
+
+        #[test]
+        #[ignore]
+        fn key_rotate_not_found_service_mask() {
+            let mut client = client_create(None);
+            let (_service1, service1_key) = service_key_create(&mut client);
+            let (_service2, service2_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service1_key.value));
+            let res = client
+                .key_rotate(pb::KeyRotateRequest {
+                    id: service2_key.key.unwrap().id,
+                    grace_period_hours: None,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::NotFound);
+        }
+
+        #[test]
+        #[ignore]
+        fn key_rotate_bad_request_token_key() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let body = pb::KeyCreateRequest::with_user_id(true, KeyType::Token, KEY_NAME, user.id);
+            let key = client.key_create(body).unwrap().into_inner().data.unwrap();
+
+            let res = client
+                .key_rotate(pb::KeyRotateRequest {
+                    id: key.key.unwrap().id,
+                    grace_period_hours: None,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+        #[test]
+        #[ignore]
+        fn key_role_permission_denied() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create(&mut client);
+
+            let mut body =
+                pb::KeyCreateRequest::with_service_id(true, KeyType::Key, KEY_NAME, service.id);
+            body.role = Some("read_only".to_owned());
+            let key = client.key_create(body).unwrap().into_inner().data.unwrap();
+            assert_eq!(key.key.as_ref().unwrap().role, Some("read_only".to_owned()));
+
+            let mut client = client_create(Some(&key.value));
+            client.key_list(pb::KeyListRequest::default()).unwrap();
+            let res = client
+                .key_delete(pb::KeyReadRequest {
+                    id: key.key.unwrap().id,
+                    user_id: None,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            let user_email = email_create();
+            let res = client
+                .user_create(pb::UserCreateRequest::new(true, USER_NAME, &user_email))
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+        }
+
+        #[test]
+        #[ignore]
+        fn key_scope_permission_denied() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+
+            let mut body =
+                pb::KeyCreateRequest::with_service_id(true, KeyType::Key, KEY_NAME, service.id);
+            body.scopes = vec!["key:read".to_owned(), "key:create".to_owned()];
+            let key = client.key_create(body).unwrap().into_inner().data.unwrap();
+
+            let mut client = client_create(Some(&key.value));
+            client.key_list(pb::KeyListRequest::default()).unwrap();
+            let user_email = email_create();
+            let res = client
+                .user_create(pb::UserCreateRequest::new(true, USER_NAME, &user_email))
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+
+            let mut body =
+                pb::KeyCreateRequest::with_user_id(true, KeyType::Key, KEY_NAME, user.id);
+            let user_key = client
+                .key_create(body.clone())
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap()
+                .key
+                .unwrap();
+            assert_eq!(
+                user_key.scopes,
+                vec!["key:read".to_owned(), "key:create".to_owned()]
+            );
+            body.scopes = vec!["user:read".to_owned()];
+            let res = client.key_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+        }
+
+        #[test]
+        #[ignore]
+        fn key_create_bad_request_role_not_found() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create(&mut client);
+
+            let mut body =
+                pb::KeyCreateRequest::with_service_id(true, KeyType::Key, KEY_NAME, service.id);
+            body.role = Some("does_not_exist".to_owned());
+            let res = client.key_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+        #[test]
+        #[ignore]
+        fn key_role_escalation_forbidden() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+
+            let mut body = pb::KeyCreateRequest::with_service_id(
+                true,
+                KeyType::Key,
+                KEY_NAME,
+                service.id.clone(),
+            );
+            body.role = Some("key_manager".to_owned());
+            let manager_key = client.key_create(body).unwrap().into_inner().data.unwrap();
+            let mut body =
+                pb::KeyCreateRequest::with_service_id(true, KeyType::Key, KEY_NAME, service.id);
+            body.user_id = Some(user.id);
+            let user_key = client.key_create(body).unwrap().into_inner().data.unwrap();
+            let user_key_id = user_key.key.unwrap().id;
+
+            let mut client = client_create(Some(&manager_key.value));
+            let res = client
+                .key_rotate(pb::KeyRotateRequest {
+                    id: user_key_id.clone(),
+                    grace_period_hours: None,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            let res = client
+                .key_update(pb::KeyUpdateRequest {
+                    id: user_key_id.clone(),
+                    is_enabled: Some(false),
+                    ..Default::default()
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+            let res = client
+                .key_delete(pb::KeyReadRequest {
+                    id: user_key_id,
+                    user_id: None,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::PermissionDenied);
+
+            let res = client
+                .key_rotate(pb::KeyRotateRequest {
+                    id: manager_key.key.unwrap().id,
+                    grace_period_hours: None,
+                })
+                .unwrap()
+                .into_inner();
+            let key = res.data.unwrap().key.unwrap();
+            assert_eq!(key.role, Some("key_manager".to_owned()));
+        }
     };
 }
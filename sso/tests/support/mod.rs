@@ -2,14 +2,22 @@ mod audit;
 mod auth_csrf;
 mod auth_key;
 mod auth_local;
+mod auth_saml;
 mod auth_token;
 mod auth_totp;
+mod auth_webauthn;
+mod authenticator;
 mod guide;
 mod key;
+mod oauth2_provider;
+mod oidc;
+mod oidc_client;
 mod service;
 mod user;
 
+pub use authenticator::WebauthnAuthenticator;
 pub use chrono::Utc;
+pub use oidc_client::OidcClient;
 pub use serde_json::Value;
 pub use sso::*;
 pub use uuid::Uuid;
@@ -22,6 +30,9 @@ pub const USER_PASSWORD: &str = "guestguest";
 pub const USER_WRONG_PASSWORD: &str = "guestguests";
 pub const KEY_NAME: &str = "key-name";
 pub const UUID_NIL: &str = "00000000-0000-0000-0000-000000000000";
+pub const SAML_IDP_METADATA: &str = include_str!("../saml/idp_metadata.xml");
+pub const SAML_RESPONSE: &str = include_str!("../saml/response.xml");
+pub const OIDC_REDIRECT_URI: &str = "http://localhost/oidc/callback";
 
 fn env_test_sso_url() -> String {
     std::env::var("SSO_TEST_URL").expect("SSO_TEST_URL is undefined, integration test disabled")
@@ -31,6 +42,11 @@ fn env_test_sso_key() -> String {
     std::env::var("SSO_TEST_KEY").expect("SSO_TEST_KEY is undefined, integration test disabled")
 }
 
+fn env_test_sso_oidc_url() -> String {
+    std::env::var("SSO_TEST_OIDC_URL")
+        .expect("SSO_TEST_OIDC_URL is undefined, integration test disabled")
+}
+
 fn channel_tls() -> GrpcClientChannelTls {
     GrpcClientChannelTls::from_env(
         "SSO_TEST_TLS_DOMAIN",
@@ -68,16 +84,120 @@ pub fn client_user_create(key: &str, user_key: &str) -> GrpcClientBlocking {
     .unwrap()
 }
 
+pub fn oidc_client_create() -> OidcClient {
+    OidcClient::new(env_test_sso_oidc_url())
+}
+
+pub fn oidc_user_create(client: &mut GrpcClientBlocking, service: &pb::Service) -> String {
+    let user_email = email_create();
+    let user = user_create_with_password(
+        client,
+        true,
+        USER_NAME,
+        &user_email,
+        false,
+        false,
+        USER_PASSWORD,
+    );
+    user_key_create(client, KEY_NAME, KeyType::Token, service.id.clone(), user);
+    user_email
+}
+
+pub fn oidc_code_create(oidc: &OidcClient, service: &pb::Service, email: &str) -> String {
+    let res = oidc.get(
+        "/authorize",
+        &[
+            ("client_id", &service.id),
+            ("redirect_uri", OIDC_REDIRECT_URI),
+            ("response_type", "code"),
+            ("scope", "openid email"),
+            ("state", "state"),
+            ("nonce", "nonce"),
+        ],
+    );
+    assert_eq!(res.status.as_u16(), 200);
+    let request = res.form_request().unwrap();
+
+    let res = oidc.post_form(
+        "/authorize",
+        &[
+            ("request", &request),
+            ("email", email),
+            ("password", USER_PASSWORD),
+        ],
+        None,
+    );
+    assert_eq!(res.status.as_u16(), 302);
+    assert_eq!(res.location_query("state"), Some("state".to_owned()));
+    res.location_query("code").unwrap()
+}
+
 pub fn email_create() -> String {
     let random = Uuid::new_v4().to_simple().to_string();
     format!("{}@test.com", random)
 }
 
 pub fn service_key_create(client: &mut GrpcClientBlocking) -> (pb::Service, pb::KeyWithValue) {
+    service_key_create_with_jwt_algorithm(client, JwtAlgorithm::Hs256)
+}
+
+pub fn service_key_create_with_jwt_algorithm(
+    client: &mut GrpcClientBlocking,
+    jwt_algorithm: JwtAlgorithm,
+) -> (pb::Service, pb::KeyWithValue) {
     let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
         .provider_local_url("http://localhost")
         .provider_github_oauth2_url("http://localhost")
-        .provider_microsoft_oauth2_url("http://localhost");
+        .provider_microsoft_oauth2_url("http://localhost")
+        .provider_oauth2_url("http://localhost")
+        .jwt_algorithm(jwt_algorithm);
+    let create_service = client
+        .service_create(body)
+        .unwrap()
+        .into_inner()
+        .data
+        .unwrap();
+
+    let body = pb::KeyCreateRequest::with_service_id(
+        true,
+        KeyType::Key,
+        "test",
+        create_service.id.clone(),
+    );
+    let create_key = client.key_create(body).unwrap().into_inner().data.unwrap();
+    (create_service, create_key)
+}
+
+pub fn service_key_create_with_mfa_policy(
+    client: &mut GrpcClientBlocking,
+    mfa_policy: ServiceMfaPolicy,
+) -> (pb::Service, pb::KeyWithValue) {
+    let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
+        .provider_local_url("http://localhost")
+        .mfa_policy(mfa_policy);
+    let create_service = client
+        .service_create(body)
+        .unwrap()
+        .into_inner()
+        .data
+        .unwrap();
+
+    let body = pb::KeyCreateRequest::with_service_id(
+        true,
+        KeyType::Key,
+        "test",
+        create_service.id.clone(),
+    );
+    let create_key = client.key_create(body).unwrap().into_inner().data.unwrap();
+    (create_service, create_key)
+}
+
+pub fn service_key_create_with_oidc(
+    client: &mut GrpcClientBlocking,
+) -> (pb::Service, pb::KeyWithValue) {
+    let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
+        .provider_local_url("http://localhost")
+        .oidc_redirect_uri(OIDC_REDIRECT_URI);
     let create_service = client
         .service_create(body)
         .unwrap()
@@ -95,6 +215,48 @@ pub fn service_key_create(client: &mut GrpcClientBlocking) -> (pb::Service, pb::
     (create_service, create_key)
 }
 
+pub fn service_key_create_with_saml(
+    client: &mut GrpcClientBlocking,
+) -> (pb::Service, pb::KeyWithValue) {
+    let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
+        .provider_saml("http://localhost/saml/acs", SAML_IDP_METADATA)
+        .saml_email_attribute("mail");
+    let create_service = client
+        .service_create(body)
+        .unwrap()
+        .into_inner()
+        .data
+        .unwrap();
+
+    let body = pb::KeyCreateRequest::with_service_id(
+        true,
+        KeyType::Key,
+        "test",
+        create_service.id.clone(),
+    );
+    let create_key = client.key_create(body).unwrap().into_inner().data.unwrap();
+    (create_service, create_key)
+}
+
+pub fn oauth2_provider_create(
+    client: &mut GrpcClientBlocking,
+    is_enabled: bool,
+) -> pb::Oauth2Provider {
+    let body = pb::Oauth2ProviderCreateRequest::new(
+        "test",
+        "http://localhost/.well-known/openid-configuration",
+        "client-id",
+        "client-secret",
+    )
+    .is_enabled(is_enabled);
+    client
+        .oauth2_provider_create(body)
+        .unwrap()
+        .into_inner()
+        .data
+        .unwrap()
+}
+
 pub fn user_create(
     client: &mut GrpcClientBlocking,
     is_enabled: bool,
@@ -113,6 +275,40 @@ pub fn user_create(
     user
 }
 
+pub fn service_key_create_with_password_policy(
+    client: &mut GrpcClientBlocking,
+    password_policy: UserPasswordPolicy,
+) -> (pb::Service, pb::KeyWithValue) {
+    let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
+        .provider_local_url("http://localhost")
+        .password_policy(password_policy);
+    let create_service = client
+        .service_create(body)
+        .unwrap()
+        .into_inner()
+        .data
+        .unwrap();
+
+    let body = pb::KeyCreateRequest::with_service_id(
+        true,
+        KeyType::Key,
+        "test",
+        create_service.id.clone(),
+    );
+    let create_key = client.key_create(body).unwrap().into_inner().data.unwrap();
+    (create_service, create_key)
+}
+
+pub fn status_validation_codes(status: &tonic::Status, field: &str) -> Vec<String> {
+    let details: Value = serde_json::from_slice(status.details()).unwrap();
+    details[field]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["code"].as_str().unwrap().to_owned())
+        .collect()
+}
+
 pub fn user_create_with_password(
     client: &mut GrpcClientBlocking,
     is_enabled: bool,
@@ -0,0 +1,210 @@
+#[macro_export]
+macro_rules! oauth2_provider_integration_test {
+    () => {
+        #[test]
+        #[ignore]
+        fn oauth2_provider_create_ok() {
+            let mut client = client_create(None);
+            let provider = oauth2_provider_create(&mut client, true);
+            assert!(provider.is_enabled);
+            assert_eq!(provider.client_id, "client-id");
+            assert_eq!(provider.scopes, vec!["openid", "email"]);
+            assert_eq!(provider.email_claim, "email");
+            assert!(!provider.trust_email);
+
+            let body = pb::Oauth2ProviderCreateRequest::new(
+                "test",
+                "http://localhost/.well-known/openid-configuration",
+                "client-id",
+                "client-secret",
+            )
+            .scope("openid")
+            .scope("profile")
+            .email_claim("upn")
+            .trust_email(true);
+            let provider = client
+                .oauth2_provider_create(body)
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap();
+            assert_eq!(provider.scopes, vec!["openid", "profile"]);
+            assert_eq!(provider.email_claim, "upn");
+            assert!(provider.trust_email);
+        }
+
+        #[test]
+        #[ignore]
+        fn oauth2_provider_create_bad_request_invalid_discovery_url() {
+            let mut client = client_create(None);
+            let body = pb::Oauth2ProviderCreateRequest::new(
+                "test",
+                "invalid-url",
+                "client-id",
+                "client-secret",
+            );
+            let res = client.oauth2_provider_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn oauth2_provider_create_bad_request_invalid_scope() {
+            let mut client = client_create(None);
+            let body = pb::Oauth2ProviderCreateRequest::new(
+                "test",
+                "http://localhost/.well-known/openid-configuration",
+                "client-id",
+                "client-secret",
+            )
+            .scope("openid email");
+            let res = client.oauth2_provider_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn oauth2_provider_create_unauthorised_service_key() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let body = pb::Oauth2ProviderCreateRequest::new(
+                "test",
+                "http://localhost/.well-known/openid-configuration",
+                "client-id",
+                "client-secret",
+            );
+            let res = client.oauth2_provider_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::Unauthenticated);
+        }
+
+        #[test]
+        #[ignore]
+        fn oauth2_provider_read_list_service_key_ok() {
+            let mut client = client_create(None);
+            let provider = oauth2_provider_create(&mut client, true);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client
+                .oauth2_provider_read(pb::Oauth2ProviderReadRequest {
+                    id: provider.id.clone(),
+                })
+                .unwrap()
+                .into_inner();
+            assert_eq!(res.data.unwrap().id, provider.id);
+
+            let res = client.oauth2_provider_list(()).unwrap().into_inner();
+            assert!(res.data.iter().any(|x| x.id == provider.id));
+        }
+
+        #[test]
+        #[ignore]
+        fn oauth2_provider_update_delete_ok() {
+            let mut client = client_create(None);
+            let provider = oauth2_provider_create(&mut client, true);
+
+            let res = client
+                .oauth2_provider_update(pb::Oauth2ProviderUpdateRequest {
+                    id: provider.id.clone(),
+                    name: Some("updated".to_owned()),
+                    discovery_url: None,
+                    client_id: None,
+                    client_secret: Some("client-secret-updated".to_owned()),
+                    is_enabled: Some(false),
+                    scopes: vec!["openid".to_owned()],
+                    email_claim: None,
+                    trust_email: Some(true),
+                })
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap();
+            assert_eq!(res.name, "updated");
+            assert!(!res.is_enabled);
+            assert_eq!(res.scopes, vec!["openid"]);
+            assert_eq!(res.email_claim, "email");
+            assert!(res.trust_email);
+
+            client
+                .oauth2_provider_delete(pb::Oauth2ProviderReadRequest {
+                    id: provider.id.clone(),
+                })
+                .unwrap();
+            let res = client
+                .oauth2_provider_read(pb::Oauth2ProviderReadRequest { id: provider.id })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::NotFound);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_oauth2_url_not_found() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client
+                .auth_oauth2_url(pb::AuthOauth2UrlRequest {
+                    provider_id: UUID_NIL.to_owned(),
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::NotFound);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_oauth2_url_bad_request_provider_disabled() {
+            let mut client = client_create(None);
+            let provider = oauth2_provider_create(&mut client, false);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client
+                .auth_oauth2_url(pb::AuthOauth2UrlRequest {
+                    provider_id: provider.id,
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_oauth2_callback_bad_request_invalid_state() {
+            let mut client = client_create(None);
+            let provider = oauth2_provider_create(&mut client, true);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client
+                .auth_oauth2_callback(pb::AuthOauth2ProviderCallbackRequest {
+                    provider_id: provider.id,
+                    code: "code".to_owned(),
+                    state: "state".to_owned(),
+                    session: "session".to_owned(),
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn auth_oauth2_callback_bad_request_invalid_session() {
+            let mut client = client_create(None);
+            let provider = oauth2_provider_create(&mut client, true);
+            let (_service, service_key) = service_key_create(&mut client);
+
+            let mut client = client_create(Some(&service_key.value));
+            let res = client
+                .auth_oauth2_callback(pb::AuthOauth2ProviderCallbackRequest {
+                    provider_id: provider.id,
+                    code: "code".to_owned(),
+                    state: "state".to_owned(),
+                    session: "".to_owned(),
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+    };
+}
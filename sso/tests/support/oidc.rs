@@ -0,0 +1,280 @@
+#[macro_export]
+macro_rules! oidc_integration_test {
+    () => {
+        #[test]
+        #[ignore]
+        fn oidc_discovery_ok() {
+            let oidc = oidc_client_create();
+
+            let res = oidc.get("/.well-known/openid-configuration", &[]);
+            assert_eq!(res.status.as_u16(), 200);
+            let data = res.json();
+            let issuer = data["issuer"].as_str().unwrap();
+            assert_eq!(issuer, oidc.url());
+            assert_eq!(data["token_endpoint"], format!("{}/token", issuer));
+            assert_eq!(data["userinfo_endpoint"], format!("{}/userinfo", issuer));
+            assert_eq!(data["jwks_uri"], format!("{}/jwks.json", issuer));
+            assert_eq!(data["response_types_supported"], json!(["code"]));
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_jwks_ok() {
+            let oidc = oidc_client_create();
+
+            let res = oidc.get("/jwks.json", &[]);
+            assert_eq!(res.status.as_u16(), 200);
+            assert!(res.json()["keys"].is_array());
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_authorize_ok() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create_with_oidc(&mut client);
+            let oidc = oidc_client_create();
+
+            let res = oidc.get(
+                "/authorize",
+                &[
+                    ("client_id", &service.id),
+                    ("redirect_uri", OIDC_REDIRECT_URI),
+                    ("response_type", "code"),
+                    ("scope", "openid"),
+                ],
+            );
+            assert_eq!(res.status.as_u16(), 200);
+            assert!(res.form_request().is_some());
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_authorize_bad_request_redirect_uri_invalid() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create_with_oidc(&mut client);
+            let oidc = oidc_client_create();
+
+            let res = oidc.get(
+                "/authorize",
+                &[
+                    ("client_id", &service.id),
+                    ("redirect_uri", "http://localhost/invalid"),
+                    ("response_type", "code"),
+                    ("scope", "openid"),
+                ],
+            );
+            assert_eq!(res.status.as_u16(), 400);
+            assert!(res.location.is_none());
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_authorize_redirect_invalid_scope() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create_with_oidc(&mut client);
+            let oidc = oidc_client_create();
+
+            let res = oidc.get(
+                "/authorize",
+                &[
+                    ("client_id", &service.id),
+                    ("redirect_uri", OIDC_REDIRECT_URI),
+                    ("response_type", "code"),
+                    ("scope", "email"),
+                    ("state", "state"),
+                ],
+            );
+            assert_eq!(res.status.as_u16(), 302);
+            assert_eq!(
+                res.location_query("error"),
+                Some("invalid_scope".to_owned())
+            );
+            assert_eq!(res.location_query("state"), Some("state".to_owned()));
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_authorize_login_wrong_password() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_oidc(&mut client);
+            let mut client = client_create(Some(&service_key.value));
+            let user_email = oidc_user_create(&mut client, &service);
+            let oidc = oidc_client_create();
+
+            let res = oidc.get(
+                "/authorize",
+                &[
+                    ("client_id", &service.id),
+                    ("redirect_uri", OIDC_REDIRECT_URI),
+                    ("response_type", "code"),
+                    ("scope", "openid"),
+                ],
+            );
+            let request = res.form_request().unwrap();
+
+            let res = oidc.post_form(
+                "/authorize",
+                &[
+                    ("request", &request),
+                    ("email", &user_email),
+                    ("password", USER_WRONG_PASSWORD),
+                ],
+                None,
+            );
+            assert_eq!(res.status.as_u16(), 200);
+            assert!(res.location.is_none());
+            assert!(res.body.contains("role=\"alert\""));
+            // Failed login form has a new request key, submitted request is used.
+            let retry = res.form_request().unwrap();
+            assert_ne!(retry, request);
+            let res = oidc.post_form(
+                "/authorize",
+                &[
+                    ("request", &request),
+                    ("email", &user_email),
+                    ("password", USER_PASSWORD),
+                ],
+                None,
+            );
+            assert_eq!(res.status.as_u16(), 400);
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_token_userinfo_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_oidc(&mut client);
+            let mut client = client_create(Some(&service_key.value));
+            let user_email = oidc_user_create(&mut client, &service);
+            let oidc = oidc_client_create();
+            let code = oidc_code_create(&oidc, &service, &user_email);
+
+            let res = oidc.post_form(
+                "/token",
+                &[
+                    ("grant_type", "authorization_code"),
+                    ("code", &code),
+                    ("redirect_uri", OIDC_REDIRECT_URI),
+                ],
+                Some((&service.id, &service_key.value)),
+            );
+            assert_eq!(res.status.as_u16(), 200);
+            let data = res.json();
+            assert_eq!(data["token_type"], "Bearer");
+            assert!(data["id_token"].as_str().is_some());
+            assert!(data["refresh_token"].as_str().is_some());
+            let access_token = data["access_token"].as_str().unwrap();
+
+            let res = oidc.get_bearer("/userinfo", access_token);
+            assert_eq!(res.status.as_u16(), 200);
+            let data = res.json();
+            assert_eq!(data["email"], user_email);
+            assert_eq!(data["name"], USER_NAME);
+            assert!(data["sub"].as_str().is_some());
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_token_client_secret_post_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_oidc(&mut client);
+            let mut client = client_create(Some(&service_key.value));
+            let user_email = oidc_user_create(&mut client, &service);
+            let oidc = oidc_client_create();
+            let code = oidc_code_create(&oidc, &service, &user_email);
+
+            let res = oidc.post_form(
+                "/token",
+                &[
+                    ("grant_type", "authorization_code"),
+                    ("code", &code),
+                    ("redirect_uri", OIDC_REDIRECT_URI),
+                    ("client_id", &service.id),
+                    ("client_secret", &service_key.value),
+                ],
+                None,
+            );
+            assert_eq!(res.status.as_u16(), 200);
+            assert!(res.json()["access_token"].as_str().is_some());
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_token_bad_request_redirect_uri_mismatch() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_oidc(&mut client);
+            let mut client = client_create(Some(&service_key.value));
+            let user_email = oidc_user_create(&mut client, &service);
+            let oidc = oidc_client_create();
+            let code = oidc_code_create(&oidc, &service, &user_email);
+
+            let res = oidc.post_form(
+                "/token",
+                &[
+                    ("grant_type", "authorization_code"),
+                    ("code", &code),
+                    ("redirect_uri", "http://localhost/invalid"),
+                ],
+                Some((&service.id, &service_key.value)),
+            );
+            assert_eq!(res.status.as_u16(), 400);
+            assert_eq!(res.json()["error"], "invalid_grant");
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_token_bad_request_code_reuse() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_oidc(&mut client);
+            let mut client = client_create(Some(&service_key.value));
+            let user_email = oidc_user_create(&mut client, &service);
+            let oidc = oidc_client_create();
+            let code = oidc_code_create(&oidc, &service, &user_email);
+
+            let form = [
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", OIDC_REDIRECT_URI),
+            ];
+            let res = oidc.post_form("/token", &form, Some((&service.id, &service_key.value)));
+            assert_eq!(res.status.as_u16(), 200);
+            let res = oidc.post_form("/token", &form, Some((&service.id, &service_key.value)));
+            assert_eq!(res.status.as_u16(), 400);
+            assert_eq!(res.json()["error"], "invalid_grant");
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_token_unauthorised_client_invalid() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create_with_oidc(&mut client);
+            let mut client = client_create(Some(&service_key.value));
+            let user_email = oidc_user_create(&mut client, &service);
+            let oidc = oidc_client_create();
+            let code = oidc_code_create(&oidc, &service, &user_email);
+
+            let res = oidc.post_form(
+                "/token",
+                &[
+                    ("grant_type", "authorization_code"),
+                    ("code", &code),
+                    ("redirect_uri", OIDC_REDIRECT_URI),
+                ],
+                Some((&service.id, INVALID_KEY)),
+            );
+            assert_eq!(res.status.as_u16(), 401);
+            assert_eq!(res.json()["error"], "invalid_client");
+        }
+
+        #[test]
+        #[ignore]
+        fn oidc_userinfo_unauthorised() {
+            let oidc = oidc_client_create();
+
+            let res = oidc.get("/userinfo", &[]);
+            assert_eq!(res.status.as_u16(), 401);
+            let res = oidc.get_bearer("/userinfo", INVALID_KEY);
+            assert_eq!(res.status.as_u16(), 401);
+        }
+    };
+}
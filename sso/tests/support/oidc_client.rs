@@ -0,0 +1,98 @@
+use reqwest::{redirect::Policy, Client, RequestBuilder, StatusCode};
+use serde_json::Value;
+use tokio::runtime::{Builder, Runtime};
+use url::Url;
+
+/// OpenID Connect HTTP response.
+#[derive(Debug)]
+pub struct OidcResponse {
+    pub status: StatusCode,
+    pub location: Option<String>,
+    pub body: String,
+}
+
+impl OidcResponse {
+    /// Returns body parsed as JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+
+    /// Returns query parameter of redirect location.
+    pub fn location_query(&self, key: &str) -> Option<String> {
+        let location = Url::parse(self.location.as_ref()?).unwrap();
+        location
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    /// Returns request key of authorize login form.
+    pub fn form_request(&self) -> Option<String> {
+        let (_, value) = self.body.split_once("name=\"request\" value=\"")?;
+        value.split('"').next().map(|x| x.to_owned())
+    }
+}
+
+/// OpenID Connect HTTP client, redirects are not followed.
+pub struct OidcClient {
+    rt: Runtime,
+    client: Client,
+    url: String,
+}
+
+impl OidcClient {
+    pub fn new(url: String) -> Self {
+        let rt = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build runtime.");
+        let client = Client::builder().redirect(Policy::none()).build().unwrap();
+        Self { rt, client, url }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn get(&self, path: &str, query: &[(&str, &str)]) -> OidcResponse {
+        self.send(self.client.get(self.path(path)).query(query))
+    }
+
+    pub fn get_bearer(&self, path: &str, token: &str) -> OidcResponse {
+        self.send(self.client.get(self.path(path)).bearer_auth(token))
+    }
+
+    pub fn post_form(
+        &self,
+        path: &str,
+        form: &[(&str, &str)],
+        basic: Option<(&str, &str)>,
+    ) -> OidcResponse {
+        let req = self.client.post(self.path(path)).form(form);
+        match basic {
+            Some((username, password)) => self.send(req.basic_auth(username, Some(password))),
+            None => self.send(req),
+        }
+    }
+
+    fn path(&self, path: &str) -> String {
+        format!("{}{}", self.url, path)
+    }
+
+    fn send(&self, req: RequestBuilder) -> OidcResponse {
+        self.rt.block_on(async move {
+            let res = req.send().await.unwrap();
+            let status = res.status();
+            let location = res
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .map(|x| x.to_str().unwrap().to_owned());
+            let body = res.text().await.unwrap();
+            OidcResponse {
+                status,
+                location,
+                body,
+            }
+        })
+    }
+}
@@ -138,5 +138,101 @@ macro_rules! service_integration_test {
                 .unwrap_err();
             assert_eq!(res.code(), tonic::Code::NotFound);
         }
+
+        #[test]
+        #[ignore]
+        fn service_create_bad_request_invalid_oidc_redirect_uri() {
+            let mut client = client_create(None);
+            let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
+                .oidc_redirect_uri("http://localhost/callback")
+                .oidc_redirect_uri("invalid-uri");
+            let res = client.service_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+        }
+
+        #[test]
+        #[ignore]
+        fn service_create_oidc_redirect_uris_ok() {
+            let mut client = client_create(None);
+            let body = pb::ServiceCreateRequest::new(true, "test", "http://localhost")
+                .oidc_redirect_uri("http://localhost/callback")
+                .oidc_redirect_uri("http://localhost/login/generic_oauth");
+            let service = client
+                .service_create(body)
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap();
+            assert_eq!(
+                service.oidc_redirect_uris,
+                vec![
+                    "http://localhost/callback".to_owned(),
+                    "http://localhost/login/generic_oauth".to_owned(),
+                ]
+            );
+        }
+
+        #[test]
+        #[ignore]
+        fn service_create_bad_request_invalid_jwt_algorithm() {
+            let mut client = client_create(None);
+            let mut body = pb::ServiceCreateRequest::new(true, "test", "http://localhost");
+            body.jwt_algorithm = Some("HS512".to_owned());
+            let res = client.service_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+        }
+
+        #[test]
+        #[ignore]
+        fn service_create_jwt_algorithm_ok() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create(&mut client);
+            assert_eq!(service.jwt_algorithm, "HS256");
+
+            let (service, _service_key) =
+                service_key_create_with_jwt_algorithm(&mut client, JwtAlgorithm::EdDsa);
+            assert_eq!(service.jwt_algorithm, "EdDSA");
+        }
+
+        #[test]
+        #[ignore]
+        fn service_create_bad_request_invalid_password_policy() {
+            let mut client = client_create(None);
+            let mut body = pb::ServiceCreateRequest::new(true, "test", "http://localhost");
+            body.password_min_strength = Some(5);
+            body.password_history = Some(100);
+            let res = client.service_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+            assert_eq!(
+                status_validation_codes(&res, "password_min_strength"),
+                vec!["password_min_strength_invalid"]
+            );
+            assert_eq!(
+                status_validation_codes(&res, "password_history"),
+                vec!["password_history_invalid"]
+            );
+        }
+
+        #[test]
+        #[ignore]
+        fn service_create_password_policy_ok() {
+            let mut client = client_create(None);
+            let (service, _service_key) = service_key_create_with_password_policy(
+                &mut client,
+                UserPasswordPolicy {
+                    min_strength: 3,
+                    reject_pwned: true,
+                    history: 2,
+                    max_age_days: 90,
+                },
+            );
+            assert_eq!(service.password_min_strength, 3);
+            assert!(service.password_reject_pwned);
+            assert_eq!(service.password_history, 2);
+            assert_eq!(service.password_max_age_days, 90);
+        }
     };
 }
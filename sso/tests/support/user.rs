@@ -299,6 +299,55 @@ macro_rules! user_integration_test {
             assert_eq!(res.code(), tonic::Code::InvalidArgument);
         }
 
+        #[test]
+        #[ignore]
+        fn user_create_bad_request_invalid_phone() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let body =
+                pb::UserCreateRequest::new(true, USER_NAME, &user_email).phone("07700900000");
+            let res = client.user_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+        }
+
+        #[test]
+        #[ignore]
+        fn user_create_bad_request_password_policy_strength() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create_with_password_policy(
+                &mut client,
+                UserPasswordPolicy {
+                    min_strength: 4,
+                    ..Default::default()
+                },
+            );
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let body = pb::UserCreateRequest::new(true, USER_NAME, &user_email).with_password(
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let res = client.user_create(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            assert_eq!(res.message(), ERR_VALIDATION);
+            assert_eq!(
+                status_validation_codes(&res, "password"),
+                vec!["password_strength_invalid"]
+            );
+
+            let body = pb::UserCreateRequest::new(true, USER_NAME, &user_email).with_password(
+                false,
+                false,
+                "correct-horse-battery-staple-9",
+            );
+            client.user_create(body).unwrap();
+        }
+
         #[test]
         #[ignore]
         fn user_read_unauthorised() {
@@ -370,6 +419,37 @@ macro_rules! user_integration_test {
             assert_eq!(audit.service_id.clone().unwrap(), service.id);
         }
 
+        #[test]
+        #[ignore]
+        fn user_update_phone_ok() {
+            let mut client = client_create(None);
+            let (_service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let body =
+                pb::UserCreateRequest::new(true, USER_NAME, &user_email).phone("+447700900000");
+            let user1 = client.user_create(body).unwrap().into_inner().data.unwrap();
+            assert_eq!(user1.phone.unwrap(), "+447700900000");
+
+            let user2 = client
+                .user_update(pb::UserUpdateRequest::new(user1.id.clone()).phone("+447700900001"))
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap();
+            assert_eq!(user2.phone.unwrap(), "+447700900001");
+
+            // Empty string removes phone number.
+            let user3 = client
+                .user_update(pb::UserUpdateRequest::new(user1.id.clone()).phone(""))
+                .unwrap()
+                .into_inner()
+                .data
+                .unwrap();
+            assert!(user3.phone.is_none());
+        }
+
         #[test]
         #[ignore]
         fn user_update_user_authorisation_forbidden_user_disabled() {
@@ -448,5 +528,131 @@ macro_rules! user_integration_test {
                 user1_key.key.unwrap().id
             );
         }
+
+        #[test]
+        #[ignore]
+        fn user_session_list_ok() {
+            let mut client = client_create(None);
+            let (service1, service1_key) = service_key_create(&mut client);
+            let (_service2, service2_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service1_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) = user_key_create(
+                &mut client,
+                KEY_NAME,
+                KeyType::Token,
+                service1.id.clone(),
+                user,
+            );
+            let user_token = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+            auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+            user_token_refresh(&mut client, &user_token);
+
+            let res = client
+                .user_session_list(pb::UserSessionListRequest {
+                    user_id: user.id.clone(),
+                })
+                .unwrap()
+                .into_inner();
+            assert_eq!(res.data.len(), 2);
+            assert!(res.data.iter().all(|x| x.service_id == service1.id));
+            assert!(res.data.iter().all(|x| x.user_id == user.id));
+
+            let mut client = client_create(Some(&service2_key.value));
+            let res = client
+                .user_session_list(pb::UserSessionListRequest { user_id: user.id })
+                .unwrap()
+                .into_inner();
+            assert!(res.data.is_empty());
+        }
+
+        #[test]
+        #[ignore]
+        fn user_session_revoke_not_found() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create(&mut client, true, USER_NAME, &user_email);
+            let (user, _user_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+            let res = client
+                .user_session_revoke(pb::UserSessionRevokeRequest {
+                    user_id: user.id,
+                    id: UUID_NIL.to_owned(),
+                })
+                .unwrap_err();
+            assert_eq!(res.code(), tonic::Code::NotFound);
+        }
+
+        #[test]
+        #[ignore]
+        fn user_session_revoke_ok() {
+            let mut client = client_create(None);
+            let (service, service_key) = service_key_create(&mut client);
+            let user_email = email_create();
+
+            let mut client = client_create(Some(&service_key.value));
+            let user = user_create_with_password(
+                &mut client,
+                true,
+                USER_NAME,
+                &user_email,
+                false,
+                false,
+                USER_PASSWORD,
+            );
+            let (user, _user_key) =
+                user_key_create(&mut client, KEY_NAME, KeyType::Token, service.id, user);
+            let user_token1 = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+            let user_token2 = auth_local_login(&mut client, &user.id, &user_email, USER_PASSWORD);
+
+            let res = client
+                .user_session_list(pb::UserSessionListRequest {
+                    user_id: user.id.clone(),
+                })
+                .unwrap()
+                .into_inner();
+            assert_eq!(res.data.len(), 2);
+            let session = res.data[0].clone();
+            client
+                .user_session_revoke(pb::UserSessionRevokeRequest {
+                    user_id: user.id.clone(),
+                    id: session.id.clone(),
+                })
+                .unwrap();
+
+            let res = client
+                .user_session_list(pb::UserSessionListRequest {
+                    user_id: user.id.clone(),
+                })
+                .unwrap()
+                .into_inner();
+            assert_eq!(res.data.len(), 1);
+            assert_ne!(res.data[0].id, session.id);
+
+            // Most recent session is listed first.
+            let (revoked, active) = (user_token2, user_token1);
+            let body = pb::AuthTokenRequest::new(&revoked.access.clone().unwrap().token, None);
+            let res = client.auth_token_verify(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+            let body = pb::AuthTokenRequest::new(&revoked.refresh.unwrap().token, None);
+            let res = client.auth_token_refresh(body).unwrap_err();
+            assert_eq!(res.code(), tonic::Code::InvalidArgument);
+
+            user_token_verify(&mut client, &active);
+            user_token_refresh(&mut client, &active);
+        }
     };
 }